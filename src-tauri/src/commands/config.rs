@@ -7,7 +7,11 @@ use tauri::{AppHandle, State};
 use tracing::{error, info, warn};
 
 use crate::core::{
+    bandwidth::BandwidthLimits,
+    bandwidth_schedule::BandwidthSchedule,
+    http_client::HttpClientOptions,
     models::{AppError, AppResult},
+    platform_registry::{self, PlatformRule},
     AppConfig,
 };
 use crate::AppState;
@@ -121,12 +125,7 @@ async fn update_config_impl(state: &State<'_, AppState>, new_config: AppConfig)
         .save()
         .map_err(|e| AppError::Config(format!("Failed to save configuration: {}", e)))?;
 
-    // Update download manager configuration through runtime
-    state
-        .download_runtime
-        .update_config(new_config.download.clone())
-        .await
-        .map_err(|e| AppError::Config(format!("Failed to update download manager: {}", e)))?;
+    apply_download_config(state, &new_config).await?;
 
     Ok(())
 }
@@ -145,12 +144,7 @@ async fn reset_config_impl(state: &State<'_, AppState>) -> AppResult<AppConfig>
         .save()
         .map_err(|e| AppError::Config(format!("Failed to save default configuration: {}", e)))?;
 
-    // Update download manager configuration through runtime
-    state
-        .download_runtime
-        .update_config(default_config.download.clone())
-        .await
-        .map_err(|e| AppError::Config(format!("Failed to update download manager: {}", e)))?;
+    apply_download_config(state, &default_config).await?;
 
     Ok(default_config)
 }
@@ -205,14 +199,40 @@ async fn import_config_impl(state: &State<'_, AppState>, file_path: &str) -> App
         .save()
         .map_err(|e| AppError::Config(format!("Failed to save imported configuration: {}", e)))?;
 
+    apply_download_config(state, &imported_config).await?;
+
+    Ok(imported_config)
+}
+
+/// Platform rules in match order (custom rules first, then the built-in ones)
+#[tauri::command]
+pub async fn get_platform_rules() -> Result<Vec<PlatformRule>, String> {
+    Ok(platform_registry::current().rules().to_vec())
+}
+
+/// Push the effective download config (including proxy settings) to the running engine
+async fn apply_download_config(state: &State<'_, AppState>, config: &AppConfig) -> AppResult<()> {
+    let download_config = config.effective_download_config();
+
     // Update download manager configuration through runtime
     state
         .download_runtime
-        .update_config(imported_config.download.clone())
+        .update_config(download_config.clone())
         .await
         .map_err(|e| AppError::Config(format!("Failed to update download manager: {}", e)))?;
 
-    Ok(imported_config)
+    let client_options = HttpClientOptions::from_download_config(&download_config)
+        .map_err(|e| AppError::Config(format!("Invalid network configuration: {}", e)))?;
+    state
+        .http_downloader
+        .read()
+        .await
+        .apply_client_options(client_options)
+        .map_err(|e| AppError::Config(format!("Failed to rebuild HTTP client: {}", e)))?;
+
+    platform_registry::install(&config.platforms);
+
+    Ok(())
 }
 
 fn validate_config(config: &AppConfig) -> AppResult<()> {
@@ -243,6 +263,15 @@ fn validate_config(config: &AppConfig) -> AppResult<()> {
         );
     }
 
+    HttpClientOptions::from_download_config(&config.effective_download_config())
+        .map_err(|e| AppError::Config(format!("Invalid network configuration: {}", e)))?;
+    BandwidthSchedule::from_download_config(&config.download)
+        .map_err(|e| AppError::Config(format!("Invalid bandwidth schedule: {}", e)))?;
+    BandwidthLimits::from_download_config(&config.download)
+        .map_err(|e| AppError::Config(format!("Invalid bandwidth limits: {}", e)))?;
+    platform_registry::validate_rules(&config.platforms)
+        .map_err(|e| AppError::Config(format!("Invalid platform rules: {}", e)))?;
+
     if config.download.retry_attempts > 10 {
         warn!(
             "⚠️ Retry attempts set to {} (recommended: 1-5)",
@@ -322,6 +351,26 @@ mod tests {
         assert!(validate_config(&config).is_err());
     }
 
+    #[test]
+    fn test_validate_invalid_proxy() {
+        let mut config = AppConfig::default();
+        config.download.proxy = Some("ftp://proxy.corp:21".to_string());
+        assert!(validate_config(&config).is_err());
+
+        config.download.proxy = Some("socks5h://proxy.corp:1080".to_string());
+        assert!(validate_config(&config).is_ok());
+    }
+
+    #[test]
+    fn test_validate_invalid_header() {
+        let mut config = AppConfig::default();
+        config
+            .download
+            .headers
+            .insert("Bad Header".to_string(), "value".to_string());
+        assert!(validate_config(&config).is_err());
+    }
+
     #[test]
     fn test_validate_config_warnings() {
         let mut config = AppConfig::default();
@@ -0,0 +1,104 @@
+//! Credential store and cookie jar commands
+//!
+//! 密码/令牌/cookie 值只进不出：前端只能看到 [`CredentialSummary`] 与 [`CookieDomainSummary`]。
+
+use tauri::{command, State};
+use tracing::info;
+
+use crate::core::cookie_jar::CookieDomainSummary;
+use crate::core::credentials::{CredentialInput, CredentialSummary};
+use crate::infra::command_error::CommandError;
+use crate::AppState;
+
+#[command]
+pub async fn list_credentials(
+    state: State<'_, AppState>,
+) -> Result<Vec<CredentialSummary>, CommandError> {
+    Ok(state.http_downloader.read().await.credential_store().list())
+}
+
+/// 新建或更新凭据；编辑时 `secret` 留空表示沿用原密码
+#[command]
+pub async fn save_credential(
+    credential: CredentialInput,
+    state: State<'_, AppState>,
+) -> Result<CredentialSummary, CommandError> {
+    let saved = state
+        .http_downloader
+        .read()
+        .await
+        .credential_store()
+        .upsert(credential)
+        .map_err(|error| CommandError::validation(error.to_string()))?;
+    info!(
+        "🔑 Saved {:?} credential for {}",
+        saved.scheme, saved.pattern
+    );
+    Ok(saved)
+}
+
+#[command]
+pub async fn delete_credential(
+    id: String,
+    state: State<'_, AppState>,
+) -> Result<bool, CommandError> {
+    state
+        .http_downloader
+        .read()
+        .await
+        .credential_store()
+        .remove(&id)
+        .map_err(|error| CommandError::internal(format!("Failed to delete credential: {}", error)))
+}
+
+/// 导入浏览器导出的 Netscape `cookies.txt`；指定 `domain` 时只导入并替换该域名的 cookie
+#[command]
+pub async fn import_cookies(
+    file_path: String,
+    domain: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<usize, CommandError> {
+    let content = tokio::fs::read_to_string(&file_path)
+        .await
+        .map_err(|error| {
+            CommandError::validation(format!("Failed to read {}: {}", file_path, error))
+        })?;
+    let imported = state
+        .http_downloader
+        .read()
+        .await
+        .cookie_jar()
+        .import_netscape(&content, domain.as_deref())
+        .map_err(|error| CommandError::validation(error.to_string()))?;
+    info!(
+        "🍪 Imported {} cookies{}",
+        imported,
+        domain
+            .as_deref()
+            .map(|domain| format!(" for {}", domain))
+            .unwrap_or_default()
+    );
+    Ok(imported)
+}
+
+#[command]
+pub async fn list_cookie_domains(
+    state: State<'_, AppState>,
+) -> Result<Vec<CookieDomainSummary>, CommandError> {
+    Ok(state.http_downloader.read().await.cookie_jar().domains())
+}
+
+/// 删除域名（含子域名）下的 cookie，返回删除条数
+#[command]
+pub async fn clear_cookies(
+    domain: String,
+    state: State<'_, AppState>,
+) -> Result<usize, CommandError> {
+    state
+        .http_downloader
+        .read()
+        .await
+        .cookie_jar()
+        .remove_domain(&domain)
+        .map_err(|error| CommandError::internal(format!("Failed to clear cookies: {}", error)))
+}
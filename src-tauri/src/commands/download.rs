@@ -4,6 +4,9 @@ use serde::{Deserialize, Serialize};
 use tauri::{command, State};
 use uuid::Uuid;
 
+use crate::core::bandwidth_schedule::RateLimitStatus;
+use crate::core::hls_variant::VariantPolicy;
+use crate::core::ytdlp_downloader::YtDlpDownloader;
 use crate::infra::command_error::CommandError;
 use crate::{core::models::*, AppState};
 
@@ -36,6 +39,46 @@ pub async fn add_download_tasks(
         .map_err(|error| error.to_string())
 }
 
+/// 展开播放列表/频道/课程，返回条目供预览和勾选；顺序与数量上限取 YouTube 配置
+#[command]
+pub async fn expand_playlist(
+    url: String,
+    state: State<'_, AppState>,
+) -> Result<PlaylistExpansion, CommandError> {
+    let (reverse, max_items) = {
+        let config = state.config.read().await;
+        config
+            .youtube
+            .as_ref()
+            .map(|youtube| (youtube.playlist_reverse, youtube.playlist_max_items))
+            .unwrap_or((false, None))
+    };
+    let downloader = state.http_downloader.read().await.ytdlp_downloader();
+    downloader
+        .expand_playlist(&url, reverse, max_items)
+        .await
+        .map_err(|error| map_runtime_error("Failed to expand playlist", error))
+}
+
+/// 为选中的条目（`selected` 为空时全部）各建一个任务，保持播放列表顺序
+#[command]
+pub async fn add_playlist_tasks(
+    playlist: PlaylistExpansion,
+    selected: Option<Vec<u32>>,
+    output_path: String,
+    state: State<'_, AppState>,
+) -> Result<Vec<VideoTask>, CommandError> {
+    let tasks = YtDlpDownloader::playlist_tasks(&playlist, selected.as_deref(), &output_path);
+    if tasks.is_empty() {
+        return Err(CommandError::validation("No playlist entries selected"));
+    }
+    state
+        .download_runtime
+        .add_tasks(tasks)
+        .await
+        .map_err(|error| map_runtime_error("Failed to add playlist tasks", error))
+}
+
 #[command]
 pub async fn update_task_output_paths(
     task_updates: Vec<TaskOutputPathUpdate>,
@@ -53,6 +96,47 @@ pub async fn update_task_output_paths(
         .map_err(|error| error.to_string())
 }
 
+/// 单个任务的 HLS 档位策略（None 时使用配置中的默认策略），下次开始下载时生效
+#[command]
+pub async fn set_task_variant_policy(
+    task_id: String,
+    policy: Option<VariantPolicy>,
+    state: State<'_, AppState>,
+) -> Result<VideoTask, CommandError> {
+    state
+        .download_runtime
+        .set_task_variant_policy(task_id, policy)
+        .await
+        .map_err(|error| map_runtime_error("Failed to set task variant policy", error))
+}
+
+/// 单个任务的仅音频模式（None 时保留视频），下次开始下载时生效
+#[command]
+pub async fn set_task_audio_extraction(
+    task_id: String,
+    extraction: Option<AudioExtraction>,
+    state: State<'_, AppState>,
+) -> Result<VideoTask, CommandError> {
+    state
+        .download_runtime
+        .set_task_audio_extraction(task_id, extraction)
+        .await
+        .map_err(|error| map_runtime_error("Failed to set task audio extraction", error))
+}
+
+/// 结束直播录制并保留已录制内容；任务不在录制直播流时返回 false
+#[command]
+pub async fn stop_live_recording(
+    task_id: String,
+    state: State<'_, AppState>,
+) -> Result<bool, CommandError> {
+    state
+        .download_runtime
+        .stop_live_recording(task_id)
+        .await
+        .map_err(|error| map_runtime_error("Failed to stop live recording", error))
+}
+
 #[command]
 pub async fn start_download(
     task_id: String,
@@ -333,6 +417,7 @@ pub async fn retry_failed_tasks(state: State<'_, AppState>) -> Result<(), Comman
 #[command]
 pub async fn set_rate_limit(
     bytes_per_second: Option<u64>,
+    duration_minutes: Option<u64>,
     state: State<'_, AppState>,
 ) -> Result<Option<u64>, CommandError> {
     const MIN_LIMIT: u64 = 64 * 1024; // 64KB/s
@@ -347,13 +432,54 @@ pub async fn set_rate_limit(
         }
     }
 
+    if duration_minutes == Some(0) {
+        return Err(CommandError::validation(
+            "Rate limit duration must be at least 1 minute",
+        ));
+    }
+
     state
         .download_runtime
-        .set_rate_limit(bytes_per_second)
+        .set_rate_limit_override(
+            bytes_per_second,
+            duration_minutes.map(|minutes| std::time::Duration::from_secs(minutes * 60)),
+        )
         .await
         .map_err(|error| map_runtime_error("Failed to set rate limit", error))
 }
 
+/// 单个任务的限速上限（None 时使用配置中的每任务默认上限）
+#[command]
+pub async fn set_task_rate_limit(
+    task_id: String,
+    bytes_per_second: Option<u64>,
+    state: State<'_, AppState>,
+) -> Result<VideoTask, CommandError> {
+    state
+        .download_runtime
+        .set_task_rate_limit(task_id, bytes_per_second)
+        .await
+        .map_err(|error| map_runtime_error("Failed to set task rate limit", error))
+}
+
+/// 取消手动限速，恢复按时段限速
+#[command]
+pub async fn clear_rate_limit_override(
+    state: State<'_, AppState>,
+) -> Result<RateLimitStatus, CommandError> {
+    state
+        .download_runtime
+        .clear_rate_limit_override()
+        .await
+        .map_err(|error| map_runtime_error("Failed to clear rate limit override", error))
+}
+
+#[command]
+pub async fn get_rate_limit_status(state: State<'_, AppState>) -> Result<RateLimitStatus, String> {
+    let manager = state.download_manager.read().await;
+    Ok(manager.rate_limit_status().await)
+}
+
 #[command]
 pub async fn get_rate_limit(state: State<'_, AppState>) -> Result<Option<u64>, String> {
     let manager = state.download_manager.read().await;
@@ -4,6 +4,7 @@
 //! Commands are organized into different modules based on their functionality.
 
 pub mod config;
+pub mod credentials;
 pub mod download;
 pub mod import;
 pub mod system;
@@ -12,6 +13,7 @@ pub mod youtube;
 
 // Re-export all command functions for easy access
 pub use config::*;
+pub use credentials::*;
 pub use download::*;
 pub use import::*;
 pub use system::*;
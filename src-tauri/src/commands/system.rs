@@ -9,7 +9,6 @@ use tracing::{error, info};
 
 use crate::core::download_provider::should_probe_with_ytdlp_for_info;
 use crate::core::models::{AppError, AppResult};
-use crate::core::ytdlp_downloader::YtDlpDownloader;
 #[cfg(test)]
 use crate::infra::capability_service::ToolCapabilityService;
 use crate::utils::logging;
@@ -118,10 +117,13 @@ pub async fn read_clipboard_text() -> Result<String, String> {
 
 /// Get video information from URL
 #[tauri::command]
-pub async fn get_video_info(url: String) -> Result<serde_json::Value, String> {
+pub async fn get_video_info(
+    url: String,
+    state: State<'_, AppState>,
+) -> Result<serde_json::Value, String> {
     info!("📹 Getting video info for URL: {}", url);
 
-    match get_video_info_impl(&url).await {
+    match get_video_info_impl(&state, &url).await {
         Ok(info) => {
             info!("✅ Successfully retrieved video information");
             Ok(info)
@@ -375,7 +377,10 @@ async fn validate_url_impl(url: &str) -> AppResult<bool> {
     }
 }
 
-async fn get_video_info_impl(url: &str) -> AppResult<serde_json::Value> {
+async fn get_video_info_impl(
+    state: &State<'_, AppState>,
+    url: &str,
+) -> AppResult<serde_json::Value> {
     use serde_json::json;
 
     // First validate the URL
@@ -386,16 +391,33 @@ async fn get_video_info_impl(url: &str) -> AppResult<serde_json::Value> {
     }
 
     if should_probe_with_ytdlp_for_info(url) {
-        let downloader =
-            YtDlpDownloader::default_with_user_agent("VideoDownloaderPro/1.0.0".to_string());
-        let external_info = downloader
-            .probe_video_info(url)
-            .await
-            .map_err(|err| AppError::System(err.to_string()))?;
+        let (downloader, cookies) = {
+            let http_downloader = state.http_downloader.read().await;
+            (
+                http_downloader.ytdlp_downloader(),
+                http_downloader.cookie_jar(),
+            )
+        };
+        let external_info = match downloader.probe_video_info(url).await {
+            Ok(info) => info,
+            // 需要登录时不算失败：告诉前端提示导入 cookies.txt 或添加凭据
+            Err(err) if err.to_string().starts_with("authentication_required") => {
+                return Ok(json!({
+                    "title": extract_title_from_url_fallback(url),
+                    "url": url,
+                    "available": false,
+                    "requires_auth": true,
+                    "has_cookies": cookies.cookie_header_for(url).is_some(),
+                    "error": err.to_string(),
+                }));
+            }
+            Err(err) => return Err(AppError::System(err.to_string())),
+        };
         return Ok(json!({
             "title": external_info.title.clone().unwrap_or_else(|| extract_title_from_url_fallback(url)),
             "url": url,
             "available": !external_info.requires_auth,
+            "requires_auth": external_info.requires_auth,
             "extractor": external_info.extractor,
             "external_info": external_info,
         }));
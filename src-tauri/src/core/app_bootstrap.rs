@@ -1,11 +1,16 @@
 use anyhow::Result;
 
 use crate::core::config::{AppConfig, SystemConfig, UiConfig};
+use crate::core::cookie_jar::CookieJar;
+use crate::core::credentials::CredentialStore;
 use crate::core::downloader::{DownloaderConfig, HttpDownloader};
 use crate::core::models::DownloadConfig;
+use crate::core::platform_registry;
+use crate::core::proxy::ProxySettings;
+use crate::core::request_headers::HeaderRules;
 
 pub fn load_or_initialize_config() -> AppConfig {
-    match AppConfig::load() {
+    let config = match AppConfig::load() {
         Ok(cfg) => {
             if let Err(err) = cfg.validate() {
                 tracing::warn!(
@@ -24,7 +29,10 @@ pub fn load_or_initialize_config() -> AppConfig {
             );
             persist_default_config()
         }
-    }
+    };
+    // 平台规则是全局的，检测与路由都从这里读取
+    platform_registry::install(&config.platforms);
+    config
 }
 
 pub fn ensure_optional_config_defaults(config: &mut AppConfig) {
@@ -47,6 +55,14 @@ pub fn downloader_config_from_download_config(
         buffer_size: 64 * 1024,
         user_agent: download_config.user_agent.clone(),
         resume_enabled: true,
+        proxy: ProxySettings::from_download_config(download_config).unwrap_or_else(|err| {
+            tracing::warn!("Ignoring invalid proxy configuration: {}", err);
+            None
+        }),
+        header_rules: HeaderRules::from_download_config(download_config).unwrap_or_else(|err| {
+            tracing::warn!("Ignoring invalid header rules: {}", err);
+            HeaderRules::default()
+        }),
     }
 }
 
@@ -59,9 +75,43 @@ pub fn fallback_downloader_config() -> DownloaderConfig {
         buffer_size: 16 * 1024,
         user_agent: "VideoDownloaderPro/1.0.0-fallback".to_string(),
         resume_enabled: false,
+        proxy: None,
+        header_rules: HeaderRules::default(),
     }
 }
 
+fn config_dir() -> Result<std::path::PathBuf> {
+    let path = AppConfig::get_config_path()?;
+    path.parent()
+        .map(std::path::Path::to_path_buf)
+        .ok_or_else(|| anyhow::anyhow!("Config path has no parent directory"))
+}
+
+/// 打开配置目录下的凭据库；失败时退回仅本次运行有效的内存凭据库
+pub fn open_credential_store() -> CredentialStore {
+    let opened = config_dir().and_then(|dir| CredentialStore::open(&dir));
+    opened.unwrap_or_else(|err| {
+        tracing::warn!(
+            "Failed to open credential store ({}), credentials will not be saved",
+            err
+        );
+        CredentialStore::in_memory()
+    })
+}
+
+/// 打开配置目录下的 cookie jar；失败时退回内存 jar
+pub fn open_cookie_jar() -> CookieJar {
+    config_dir()
+        .and_then(|dir| CookieJar::open(&dir))
+        .unwrap_or_else(|err| {
+            tracing::warn!(
+                "Failed to open cookie jar ({}), imported cookies will not be saved",
+                err
+            );
+            CookieJar::in_memory()
+        })
+}
+
 pub fn create_http_downloader(config: DownloaderConfig) -> Result<HttpDownloader> {
     HttpDownloader::new(config)
 }
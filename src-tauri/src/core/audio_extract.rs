@@ -0,0 +1,259 @@
+//! 仅音频模式的提交后处理
+//!
+//! HTTP/HLS/DASH 任务提交完成后，用受管的 ffmpeg
+//! (`external_tools::resolve_tool_path("ffmpeg")`) 把视频转成 `AudioExtraction`
+//! 指定的格式；进度由 `-progress` 输出的 `out_time_us` 和输入时长换算。
+//! yt-dlp 任务由 `-x --audio-format` 直接产出音频，不经过这里。
+
+#![deny(clippy::unwrap_used, clippy::expect_used)]
+
+use crate::core::external_tools::registry::ExternalToolSource;
+use crate::core::external_tools::resolve_tool_path;
+use crate::core::models::{AudioExtraction, AudioFormat};
+use crate::core::remote_filename::unique_filename;
+use crate::utils::process::hidden_command;
+use anyhow::{bail, Result};
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::time::interval;
+
+/// 提取后的音频路径：与视频同目录同名，只换扩展名；同名文件已存在时改用 `name (2).ext`
+pub(crate) fn audio_output_path(video: &Path, format: AudioFormat) -> PathBuf {
+    let candidate = video.with_extension(format.extension());
+    let (Some(dir), Some(name)) = (
+        candidate.parent(),
+        candidate.file_name().and_then(|name| name.to_str()),
+    ) else {
+        return candidate;
+    };
+    dir.join(unique_filename(dir, name))
+}
+
+/// 源文件已经是目标格式（例如 yt-dlp `-x` 的产物）时无需再提取
+pub(crate) fn already_extracted(path: &Path, format: AudioFormat) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| ext.eq_ignore_ascii_case(format.extension()))
+}
+
+pub(crate) fn build_extract_args(
+    input: &Path,
+    output: &Path,
+    extraction: &AudioExtraction,
+) -> Vec<OsString> {
+    // -n：输出已存在时直接失败，绝不覆盖用户文件
+    let mut args: Vec<OsString> = ["-n", "-hide_banner", "-nostdin", "-i"]
+        .into_iter()
+        .map(OsString::from)
+        .collect();
+    args.push(input.into());
+    args.extend(["-vn".into(), "-sn".into(), "-map".into(), "0:a:0".into()]);
+
+    let codec = match extraction.format {
+        AudioFormat::Mp3 => "libmp3lame",
+        AudioFormat::M4a => "aac",
+        AudioFormat::Opus => "libopus",
+        AudioFormat::Flac => "flac",
+        AudioFormat::Wav => "pcm_s16le",
+    };
+    args.extend(["-c:a".into(), codec.into()]);
+    if let Some(kbps) = extraction
+        .bitrate_kbps
+        .filter(|_| !extraction.format.is_lossless())
+    {
+        args.extend(["-b:a".into(), format!("{}k", kbps).into()]);
+    }
+
+    args.extend(["-progress".into(), "pipe:1".into(), "-nostats".into()]);
+    args.push(output.into());
+    args
+}
+
+/// 解析 ffmpeg 输入信息里的 `  Duration: 00:03:25.46, start: ...`（秒）
+pub(crate) fn parse_duration_line(line: &str) -> Option<f64> {
+    let rest = line.trim_start().strip_prefix("Duration:")?;
+    let timestamp = rest.split(',').next()?.trim();
+    let mut seconds = 0.0;
+    for part in timestamp.split(':') {
+        seconds = seconds * 60.0 + part.parse::<f64>().ok()?;
+    }
+    (seconds > 0.0).then_some(seconds)
+}
+
+/// 解析 `-progress` 的 `out_time_us=12345678`（秒）；ffmpeg 的 `out_time_ms` 实际也是微秒
+pub(crate) fn parse_out_time(line: &str) -> Option<f64> {
+    let (key, value) = line.trim().split_once('=')?;
+    if key != "out_time_us" && key != "out_time_ms" {
+        return None;
+    }
+    let micros = value.parse::<i64>().ok()?;
+    (micros >= 0).then(|| micros as f64 / 1_000_000.0)
+}
+
+/// 调用 ffmpeg 把 `input` 的首条音轨转成 `output`，`on_progress` 收到 0.0 - 1.0。
+/// `ffmpeg` 为空时按 external_tools 解析；提交阶段只响应取消，不响应暂停。
+pub(crate) async fn extract_audio(
+    ffmpeg: Option<&Path>,
+    input: &Path,
+    output: &Path,
+    extraction: &AudioExtraction,
+    cancel_flag: &AtomicBool,
+    mut on_progress: impl FnMut(f64),
+) -> Result<()> {
+    let (ffmpeg, source) = match ffmpeg {
+        Some(path) => (path.to_path_buf(), ExternalToolSource::UserOverride),
+        None => resolve_tool_path("ffmpeg"),
+    };
+    tracing::info!(
+        "使用 ffmpeg ({:?}) 提取音频 ({:?}): {}",
+        source,
+        extraction.format,
+        output.display()
+    );
+
+    let mut child = match hidden_command(&ffmpeg)
+        .args(build_extract_args(input, output, extraction))
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+    {
+        Ok(child) => child,
+        Err(e) => bail!("ffmpeg_missing: 无法启动 {}: {}", ffmpeg.display(), e),
+    };
+    let mut stdout = child.stdout.take().map(|out| BufReader::new(out).lines());
+    let mut stderr = child.stderr.take().map(|err| BufReader::new(err).lines());
+
+    let mut duration: Option<f64> = None;
+    let mut stderr_tail: Vec<String> = Vec::new();
+    let mut cancel_check = interval(Duration::from_millis(200));
+    while stdout.is_some() || stderr.is_some() {
+        tokio::select! {
+            line = async { stdout.as_mut()?.next_line().await.ok().flatten() }, if stdout.is_some() => {
+                let Some(line) = line else {
+                    stdout = None;
+                    continue;
+                };
+                if let (Some(done), Some(total)) = (parse_out_time(&line), duration) {
+                    on_progress((done / total).clamp(0.0, 1.0));
+                }
+            }
+            line = async { stderr.as_mut()?.next_line().await.ok().flatten() }, if stderr.is_some() => {
+                let Some(line) = line else {
+                    stderr = None;
+                    continue;
+                };
+                if duration.is_none() {
+                    duration = parse_duration_line(&line);
+                }
+                if stderr_tail.len() == 5 {
+                    stderr_tail.remove(0);
+                }
+                stderr_tail.push(line);
+            }
+            _ = cancel_check.tick() => {
+                if cancel_flag.load(Ordering::Relaxed) {
+                    let _ = child.kill().await;
+                    let _ = tokio::fs::remove_file(output).await;
+                    bail!("download_cancelled");
+                }
+            }
+        }
+    }
+
+    let status = child.wait().await?;
+    if !status.success() {
+        let _ = tokio::fs::remove_file(output).await;
+        bail!(
+            "ffmpeg 提取音频失败 ({}): {}",
+            status,
+            stderr_tail.join(" | ")
+        );
+    }
+    on_progress(1.0);
+    Ok(())
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::expect_used)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builds_extract_args_per_format() {
+        let input = Path::new("/tmp/clip.mp4");
+        let output = input.with_extension("mp3");
+
+        let args = build_extract_args(
+            input,
+            &output,
+            &AudioExtraction {
+                format: AudioFormat::Mp3,
+                bitrate_kbps: Some(192),
+            },
+        );
+        let args: Vec<String> = args
+            .iter()
+            .map(|arg| arg.to_string_lossy().to_string())
+            .collect();
+        let joined = args.join(" ");
+        assert!(joined.starts_with("-n "));
+        assert!(!args.iter().any(|arg| arg == "-y"));
+        assert!(joined.contains("-i /tmp/clip.mp4 -vn -sn -map 0:a:0"));
+        assert!(joined.contains("-c:a libmp3lame -b:a 192k"));
+        assert!(joined.ends_with("-progress pipe:1 -nostats /tmp/clip.mp3"));
+
+        let flac = build_extract_args(
+            input,
+            &input.with_extension("flac"),
+            &AudioExtraction {
+                format: AudioFormat::Flac,
+                bitrate_kbps: Some(320),
+            },
+        );
+        assert!(flac.contains(&OsString::from("flac")));
+        assert!(!flac.contains(&OsString::from("-b:a")));
+
+        assert!(already_extracted(
+            Path::new("/tmp/song.MP3"),
+            AudioFormat::Mp3
+        ));
+        assert!(!already_extracted(input, AudioFormat::M4a));
+    }
+
+    #[test]
+    fn audio_output_path_skips_existing_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let video = dir.path().join("clip.mp4");
+        assert_eq!(
+            audio_output_path(&video, AudioFormat::Mp3),
+            dir.path().join("clip.mp3")
+        );
+
+        std::fs::write(dir.path().join("clip.mp3"), b"unrelated").unwrap();
+        assert_eq!(
+            audio_output_path(&video, AudioFormat::Mp3),
+            dir.path().join("clip (2).mp3")
+        );
+    }
+
+    #[test]
+    fn parses_duration_and_progress_lines() {
+        assert_eq!(
+            parse_duration_line("  Duration: 00:03:25.50, start: 0.000000, bitrate: 128 kb/s"),
+            Some(205.5)
+        );
+        assert_eq!(parse_duration_line("  Duration: N/A, bitrate: N/A"), None);
+        assert_eq!(parse_duration_line("Stream #0:0: Audio: aac"), None);
+
+        assert_eq!(parse_out_time("out_time_us=102750000"), Some(102.75));
+        assert_eq!(parse_out_time("out_time_ms=1000000"), Some(1.0));
+        assert_eq!(parse_out_time("out_time_us=-9223372036854775807"), None);
+        assert_eq!(parse_out_time("progress=continue"), None);
+    }
+}
@@ -0,0 +1,463 @@
+//! 分层限速
+//!
+//! 全局上限（手动或按时段）之下再叠加每任务上限与按主机上限。全局额度按任务优先级加权分配：
+//! 最近仍在传输的任务参与分配，受自身上限约束用不完的额度再分给其他任务，
+//! 所以大批量导入的任务不会把单个高优先级下载饿死。
+
+#![deny(clippy::unwrap_used, clippy::expect_used)]
+
+use anyhow::Result;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::RwLock;
+use tokio::time::sleep;
+use url::Url;
+
+use crate::core::models::DownloadConfig;
+use crate::core::request_headers::{host_matches, normalize_host_pattern};
+
+/// 未登记优先级的任务按默认优先级计算权重
+const DEFAULT_PRIORITY: u8 = 5;
+/// 最近多久内有传输的任务参与全局额度分配
+const ACTIVE_WINDOW: Duration = Duration::from_secs(2);
+/// 空闲后允许的突发量（按时长计）
+const BURST_ALLOWANCE: Duration = Duration::from_millis(200);
+/// 长时间没有传输的任务记录会被清理（暂停/结束的任务由管理器显式注销）
+const STALE_AFTER: Duration = Duration::from_secs(600);
+
+/// 每任务默认上限与按主机上限
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BandwidthLimits {
+    per_task: Option<u64>,
+    /// 按具体程度降序排列，第一条匹配的生效
+    hosts: Vec<(String, u64)>,
+}
+
+impl BandwidthLimits {
+    pub fn from_download_config(config: &DownloadConfig) -> Result<Self> {
+        if config.per_task_rate_limit == Some(0) {
+            anyhow::bail!("Per-task rate limit must be positive");
+        }
+        let mut hosts = Vec::with_capacity(config.host_rate_limits.len());
+        for rule in &config.host_rate_limits {
+            let host = normalize_host_pattern(&rule.host);
+            if host.is_empty() {
+                anyhow::bail!("Host rate limit host must not be empty");
+            }
+            if rule.bytes_per_second == 0 {
+                anyhow::bail!("Rate limit for host {} must be positive", host);
+            }
+            hosts.push((host, rule.bytes_per_second));
+        }
+        // 稳定排序：相同具体程度时保持配置顺序
+        hosts.sort_by_key(|(host, _)| std::cmp::Reverse(host.split('.').count()));
+        Ok(Self {
+            per_task: config.per_task_rate_limit,
+            hosts,
+        })
+    }
+
+    fn host_limit_for(&self, url: &str) -> Option<(&str, u64)> {
+        if self.hosts.is_empty() {
+            return None;
+        }
+        let host = Url::parse(url)
+            .ok()?
+            .host_str()
+            .map(|host| host.to_ascii_lowercase())?;
+        self.hosts
+            .iter()
+            .find(|(pattern, _)| host_matches(&host, pattern))
+            .map(|(pattern, limit)| (pattern.as_str(), *limit))
+    }
+}
+
+/// 按速率记账的令牌桶：记录已预订额度用完的时刻
+#[derive(Debug)]
+struct RateBucket {
+    next_free: Instant,
+}
+
+impl Default for RateBucket {
+    fn default() -> Self {
+        Self {
+            next_free: Instant::now(),
+        }
+    }
+}
+
+impl RateBucket {
+    /// 预订 `bytes` 的额度，返回需要等待的时长
+    fn reserve(&mut self, bytes: u64, rate: f64, now: Instant) -> Duration {
+        let earliest = now.checked_sub(BURST_ALLOWANCE).unwrap_or(now);
+        let start = self.next_free.max(earliest);
+        self.next_free = start + Duration::from_secs_f64(bytes as f64 / rate.max(1.0));
+        self.next_free.saturating_duration_since(now)
+    }
+}
+
+#[derive(Debug)]
+struct TaskEntry {
+    weight: f64,
+    limit: Option<u64>,
+    cap: RateBucket,
+    share: RateBucket,
+    last_active: Option<Instant>,
+}
+
+impl TaskEntry {
+    fn new(priority: u8, limit: Option<u64>) -> Self {
+        Self {
+            weight: priority_weight(priority),
+            limit,
+            cap: RateBucket::default(),
+            share: RateBucket::default(),
+            last_active: None,
+        }
+    }
+}
+
+/// 优先级每高一级权重翻倍（默认优先级 5 = 1.0）
+fn priority_weight(priority: u8) -> f64 {
+    2f64.powi(i32::from(priority.min(10)) - i32::from(DEFAULT_PRIORITY))
+}
+
+/// 把 `limit` 按权重分给各任务；受自身上限约束的任务只拿上限，余量再按权重分给其他任务
+fn weighted_shares(limit: f64, demands: &[(f64, Option<f64>)]) -> Vec<f64> {
+    let mut shares = vec![0.0; demands.len()];
+    let mut open: Vec<usize> = (0..demands.len()).collect();
+    let mut remaining = limit;
+
+    while !open.is_empty() {
+        let total_weight: f64 = open.iter().map(|&index| demands[index].0).sum();
+        if total_weight <= 0.0 {
+            break;
+        }
+        let capped: Vec<usize> = open
+            .iter()
+            .copied()
+            .filter(|&index| {
+                let (weight, cap) = demands[index];
+                cap.is_some_and(|cap| cap <= remaining * weight / total_weight)
+            })
+            .collect();
+        if capped.is_empty() {
+            for &index in &open {
+                shares[index] = remaining * demands[index].0 / total_weight;
+            }
+            break;
+        }
+        for &index in &capped {
+            if let Some(cap) = demands[index].1 {
+                shares[index] = cap;
+                remaining -= cap;
+            }
+        }
+        open.retain(|index| !capped.contains(index));
+    }
+    shares
+}
+
+#[derive(Debug, Default)]
+struct BandwidthState {
+    global: RateBucket,
+    hosts: HashMap<String, RateBucket>,
+    tasks: HashMap<String, TaskEntry>,
+    limits: BandwidthLimits,
+}
+
+impl BandwidthState {
+    /// 当前任务在全局额度中分到的速率
+    fn share_for(&self, task_id: &str, global_limit: u64, now: Instant) -> f64 {
+        let mut position = None;
+        let demands: Vec<(f64, Option<f64>)> = self
+            .tasks
+            .iter()
+            .filter(|(id, entry)| {
+                id.as_str() == task_id
+                    || entry
+                        .last_active
+                        .is_some_and(|last| now.duration_since(last) <= ACTIVE_WINDOW)
+            })
+            .enumerate()
+            .map(|(index, (id, entry))| {
+                if id.as_str() == task_id {
+                    position = Some(index);
+                }
+                let cap = entry.limit.or(self.limits.per_task).map(|cap| cap as f64);
+                (entry.weight, cap)
+            })
+            .collect();
+        let shares = weighted_shares(global_limit as f64, &demands);
+        position
+            .and_then(|index| shares.get(index).copied())
+            .unwrap_or(global_limit as f64)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct BandwidthController {
+    limit: Arc<RwLock<Option<u64>>>,
+    state: Arc<parking_lot::Mutex<BandwidthState>>,
+}
+
+impl BandwidthController {
+    pub fn new() -> Self {
+        Self {
+            limit: Arc::new(RwLock::new(None)),
+            state: Arc::new(parking_lot::Mutex::new(BandwidthState::default())),
+        }
+    }
+
+    /// 全局上限（None = 不限速），由下载管理器按手动设置或时段规则写入
+    pub fn limit_handle(&self) -> Arc<RwLock<Option<u64>>> {
+        Arc::clone(&self.limit)
+    }
+
+    /// 更新每任务默认上限与按主机上限，立即对进行中的下载生效
+    pub fn configure(&self, limits: BandwidthLimits) {
+        let mut state = self.state.lock();
+        state
+            .hosts
+            .retain(|host, _| limits.hosts.iter().any(|(pattern, _)| pattern == host));
+        state.limits = limits;
+    }
+
+    /// 登记任务优先级与单独上限（None 时使用每任务默认上限）
+    pub fn register_task(&self, task_id: &str, priority: u8, limit: Option<u64>) {
+        let mut state = self.state.lock();
+        match state.tasks.get_mut(task_id) {
+            Some(entry) => {
+                entry.weight = priority_weight(priority);
+                entry.limit = limit;
+            }
+            None => {
+                state
+                    .tasks
+                    .insert(task_id.to_string(), TaskEntry::new(priority, limit));
+            }
+        }
+    }
+
+    pub fn set_task_limit(&self, task_id: &str, limit: Option<u64>) {
+        if let Some(entry) = self.state.lock().tasks.get_mut(task_id) {
+            entry.limit = limit;
+        }
+    }
+
+    pub fn unregister_task(&self, task_id: &str) {
+        self.state.lock().tasks.remove(task_id);
+    }
+
+    /// 不区分任务的全局限速
+    pub async fn throttle(&self, bytes: u64) {
+        let Some(limit) = self.global_limit().await else {
+            return;
+        };
+        let wait = self
+            .state
+            .lock()
+            .global
+            .reserve(bytes, limit as f64, Instant::now());
+        if !wait.is_zero() {
+            sleep(wait).await;
+        }
+    }
+
+    /// 任务收到 `bytes` 后调用：依次受每任务上限、加权后的全局份额和主机上限约束
+    pub async fn throttle_task(&self, task_id: &str, url: &str, bytes: u64) {
+        let global_limit = self.global_limit().await;
+        let wait = {
+            let mut guard = self.state.lock();
+            let state = &mut *guard;
+            let task_limit = state.tasks.get(task_id).and_then(|entry| entry.limit);
+            if global_limit.is_none()
+                && task_limit.is_none()
+                && state.limits == BandwidthLimits::default()
+            {
+                return;
+            }
+            let now = Instant::now();
+            state.tasks.retain(|_, entry| {
+                entry
+                    .last_active
+                    .is_none_or(|last| now.duration_since(last) < STALE_AFTER)
+            });
+            let share = {
+                let entry = state
+                    .tasks
+                    .entry(task_id.to_string())
+                    .or_insert_with(|| TaskEntry::new(DEFAULT_PRIORITY, None));
+                entry.last_active = Some(now);
+                global_limit.map(|limit| state.share_for(task_id, limit, now))
+            };
+
+            let mut wait = Duration::ZERO;
+            if let Some(entry) = state.tasks.get_mut(task_id) {
+                if let Some(cap) = entry.limit.or(state.limits.per_task) {
+                    wait = wait.max(entry.cap.reserve(bytes, cap as f64, now));
+                }
+                if let Some(share) = share {
+                    wait = wait.max(entry.share.reserve(bytes, share, now));
+                }
+            }
+            if let Some((host, cap)) = state.limits.host_limit_for(url) {
+                let bucket = state.hosts.entry(host.to_string()).or_default();
+                wait = wait.max(bucket.reserve(bytes, cap as f64, now));
+            }
+            wait
+        };
+        if !wait.is_zero() {
+            sleep(wait).await;
+        }
+    }
+
+    /// 外部进程（yt-dlp）无法逐块节流：返回任务当前应使用的速率并把任务记为活跃，
+    /// 调用方需在 `ACTIVE_WINDOW` 内反复调用以保持份额
+    pub async fn task_rate(&self, task_id: &str, url: &str) -> Option<u64> {
+        let global_limit = self.global_limit().await;
+        let mut state = self.state.lock();
+        let now = Instant::now();
+        let task_cap = {
+            let entry = state
+                .tasks
+                .entry(task_id.to_string())
+                .or_insert_with(|| TaskEntry::new(DEFAULT_PRIORITY, None));
+            entry.last_active = Some(now);
+            entry.limit
+        }
+        .or(state.limits.per_task);
+        let share = global_limit.map(|limit| state.share_for(task_id, limit, now));
+        let host_cap = state.limits.host_limit_for(url).map(|(_, cap)| cap);
+
+        [
+            task_cap.map(|cap| cap as f64),
+            share,
+            host_cap.map(|cap| cap as f64),
+        ]
+        .into_iter()
+        .flatten()
+        .reduce(f64::min)
+        .map(|rate| (rate as u64).max(1))
+    }
+
+    async fn global_limit(&self) -> Option<u64> {
+        self.limit.read().await.filter(|limit| *limit > 0)
+    }
+}
+
+impl Default for BandwidthController {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::expect_used)]
+mod tests {
+    use super::*;
+    use crate::core::models::HostRateLimit;
+
+    #[test]
+    fn high_priority_task_gets_larger_share_of_global_limit() {
+        // 1 个优先级 8 的任务与 4 个默认优先级任务竞争 12 MB/s
+        let mut demands = vec![(priority_weight(8), None)];
+        demands.extend(std::iter::repeat_n((priority_weight(5), None), 4));
+        let shares = weighted_shares(12.0, &demands);
+        assert!((shares[0] - 8.0).abs() < 1e-9);
+        assert!((shares[1] - 1.0).abs() < 1e-9);
+        assert!((shares.iter().sum::<f64>() - 12.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn capped_task_leaves_its_surplus_to_others() {
+        let shares = weighted_shares(10.0, &[(8.0, Some(2.0)), (1.0, None), (1.0, None)]);
+        assert_eq!(shares, vec![2.0, 4.0, 4.0]);
+    }
+
+    #[test]
+    fn host_limits_prefer_the_most_specific_rule() {
+        let config = DownloadConfig {
+            host_rate_limits: vec![
+                HostRateLimit {
+                    host: "example.com".to_string(),
+                    bytes_per_second: 1_000,
+                },
+                HostRateLimit {
+                    host: "https://cdn.example.com/".to_string(),
+                    bytes_per_second: 5_000,
+                },
+            ],
+            ..DownloadConfig::default()
+        };
+        let limits = BandwidthLimits::from_download_config(&config).unwrap();
+        assert_eq!(
+            limits.host_limit_for("https://a.cdn.example.com/v.mp4"),
+            Some(("cdn.example.com", 5_000))
+        );
+        assert_eq!(
+            limits.host_limit_for("https://www.example.com/v.mp4"),
+            Some(("example.com", 1_000))
+        );
+        assert_eq!(limits.host_limit_for("https://other.org/v.mp4"), None);
+
+        let invalid = DownloadConfig {
+            per_task_rate_limit: Some(0),
+            ..DownloadConfig::default()
+        };
+        assert!(BandwidthLimits::from_download_config(&invalid).is_err());
+    }
+
+    #[tokio::test]
+    async fn per_task_cap_applies_without_global_limit() {
+        let controller = BandwidthController::new();
+        controller.register_task("slow", DEFAULT_PRIORITY, Some(100_000));
+
+        let started = Instant::now();
+        // 100 KB/s 上限下 50KB 需要约 0.5 秒
+        controller
+            .throttle_task("slow", "https://a.com/v", 50_000)
+            .await;
+        let elapsed = started.elapsed();
+        assert!(elapsed >= Duration::from_millis(250), "{:?}", elapsed);
+
+        let started = Instant::now();
+        controller
+            .throttle_task("fast", "https://a.com/v", 50_000)
+            .await;
+        assert!(started.elapsed() < Duration::from_millis(100));
+    }
+
+    #[tokio::test]
+    async fn external_task_rate_follows_weighted_share_and_caps() {
+        let controller = BandwidthController::new();
+        controller.configure(BandwidthLimits {
+            per_task: None,
+            hosts: vec![("slow.example.com".to_string(), 300)],
+        });
+        assert_eq!(controller.task_rate("a", "https://a.com/v").await, None);
+
+        *controller.limit_handle().write().await = Some(1_000);
+        controller.register_task("a", 6, None);
+        controller.register_task("b", DEFAULT_PRIORITY, None);
+        // b 刚活跃过，a 按 2:1 拿到 2/3
+        controller.task_rate("b", "https://a.com/v").await;
+        assert_eq!(
+            controller.task_rate("a", "https://a.com/v").await,
+            Some(666)
+        );
+
+        controller.set_task_limit("a", Some(100));
+        assert_eq!(
+            controller.task_rate("a", "https://a.com/v").await,
+            Some(100)
+        );
+        assert_eq!(
+            controller
+                .task_rate("b", "https://slow.example.com/v")
+                .await,
+            Some(300)
+        );
+    }
+}
@@ -0,0 +1,235 @@
+//! 按时段切换的全局限速
+//!
+//! `DownloadConfig.bandwidth_schedule` 中的规则按本地时间匹配，第一条命中的规则生效，
+//! 没有规则命中时不限速。手动限速（`set_rate_limit`）优先于时段规则，可设置过期时间，
+//! 过期后自动回到时段规则。
+
+#![deny(clippy::unwrap_used, clippy::expect_used)]
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, NaiveTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::core::models::{BandwidthRule, DownloadConfig};
+
+/// 已校验的时段规则
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BandwidthSchedule {
+    rules: Vec<ScheduledLimit>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct ScheduledLimit {
+    start: NaiveTime,
+    end: NaiveTime,
+    bytes_per_second: Option<u64>,
+    label: String,
+}
+
+impl ScheduledLimit {
+    fn contains(&self, time: NaiveTime) -> bool {
+        if self.start == self.end {
+            true
+        } else if self.start < self.end {
+            self.start <= time && time < self.end
+        } else {
+            time >= self.start || time < self.end
+        }
+    }
+}
+
+impl BandwidthSchedule {
+    pub fn from_download_config(config: &DownloadConfig) -> Result<Self> {
+        Self::new(&config.bandwidth_schedule)
+    }
+
+    pub fn new(rules: &[BandwidthRule]) -> Result<Self> {
+        let rules = rules
+            .iter()
+            .map(|rule| {
+                let start = parse_time_of_day(&rule.start)?;
+                let end = parse_time_of_day(&rule.end)?;
+                if rule.bytes_per_second == Some(0) {
+                    anyhow::bail!(
+                        "Bandwidth rule {}-{} must use a positive limit or none",
+                        rule.start,
+                        rule.end
+                    );
+                }
+                let label = rule
+                    .label
+                    .as_deref()
+                    .map(str::trim)
+                    .filter(|label| !label.is_empty())
+                    .map(str::to_string)
+                    .unwrap_or_else(|| {
+                        format!("{}-{}", start.format("%H:%M"), end.format("%H:%M"))
+                    });
+                Ok(ScheduledLimit {
+                    start,
+                    end,
+                    bytes_per_second: rule.bytes_per_second,
+                    label,
+                })
+            })
+            .collect::<Result<_>>()?;
+        Ok(Self { rules })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// 指定本地时间命中的规则：(限速, 规则名)
+    pub fn limit_at(&self, time: NaiveTime) -> Option<(Option<u64>, &str)> {
+        self.rules
+            .iter()
+            .find(|rule| rule.contains(time))
+            .map(|rule| (rule.bytes_per_second, rule.label.as_str()))
+    }
+}
+
+fn parse_time_of_day(value: &str) -> Result<NaiveTime> {
+    let value = value.trim();
+    if value == "24:00" {
+        return Ok(NaiveTime::MIN);
+    }
+    NaiveTime::parse_from_str(value, "%H:%M").with_context(|| {
+        format!(
+            "Invalid bandwidth schedule time '{}', expected HH:MM",
+            value
+        )
+    })
+}
+
+/// 手动限速，`expires_at` 为 None 时一直有效
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RateLimitOverride {
+    pub bytes_per_second: Option<u64>,
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+impl RateLimitOverride {
+    pub fn is_expired(&self, now: DateTime<Utc>) -> bool {
+        self.expires_at.is_some_and(|expires_at| expires_at <= now)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RateLimitSource {
+    #[default]
+    Unlimited,
+    Schedule,
+    Manual,
+}
+
+/// 当前生效的全局限速（随 `rate_limit.changed` 事件推送给前端）
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RateLimitStatus {
+    pub bytes_per_second: Option<u64>,
+    pub source: RateLimitSource,
+    /// 命中的时段规则名
+    pub rule: Option<String>,
+    /// 手动限速的过期时间
+    pub override_expires_at: Option<DateTime<Utc>>,
+}
+
+impl RateLimitStatus {
+    /// 手动限速优先，其次是时段规则，都没有时不限速
+    pub fn resolve(
+        schedule: &BandwidthSchedule,
+        manual: Option<&RateLimitOverride>,
+        local_time: NaiveTime,
+    ) -> Self {
+        if let Some(manual) = manual {
+            return Self {
+                bytes_per_second: manual.bytes_per_second,
+                source: RateLimitSource::Manual,
+                rule: None,
+                override_expires_at: manual.expires_at,
+            };
+        }
+        match schedule.limit_at(local_time) {
+            Some((bytes_per_second, label)) => Self {
+                bytes_per_second,
+                source: RateLimitSource::Schedule,
+                rule: Some(label.to_string()),
+                override_expires_at: None,
+            },
+            None => Self::default(),
+        }
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::expect_used)]
+mod tests {
+    use super::*;
+
+    fn rule(start: &str, end: &str, limit: Option<u64>) -> BandwidthRule {
+        BandwidthRule {
+            start: start.to_string(),
+            end: end.to_string(),
+            bytes_per_second: limit,
+            label: None,
+        }
+    }
+
+    fn at(value: &str) -> NaiveTime {
+        NaiveTime::parse_from_str(value, "%H:%M").unwrap()
+    }
+
+    #[test]
+    fn picks_first_matching_window_including_overnight() {
+        let schedule = BandwidthSchedule::new(&[
+            rule("00:00", "08:00", None),
+            rule("09:00", "18:00", Some(2 * 1024 * 1024)),
+            rule("18:00", "24:00", Some(10 * 1024 * 1024)),
+        ])
+        .unwrap();
+
+        assert_eq!(schedule.limit_at(at("03:15")), Some((None, "00:00-08:00")));
+        assert_eq!(
+            schedule.limit_at(at("09:00")).map(|(limit, _)| limit),
+            Some(Some(2 * 1024 * 1024))
+        );
+        assert_eq!(
+            schedule.limit_at(at("23:59")),
+            Some((Some(10 * 1024 * 1024), "18:00-00:00"))
+        );
+        assert_eq!(schedule.limit_at(at("08:30")), None);
+
+        let overnight = BandwidthSchedule::new(&[rule("22:00", "06:00", Some(1))]).unwrap();
+        assert!(overnight.limit_at(at("23:00")).is_some());
+        assert!(overnight.limit_at(at("05:59")).is_some());
+        assert!(overnight.limit_at(at("06:00")).is_none());
+    }
+
+    #[test]
+    fn rejects_malformed_rules() {
+        assert!(BandwidthSchedule::new(&[rule("9am", "18:00", None)]).is_err());
+        assert!(BandwidthSchedule::new(&[rule("09:00", "25:00", None)]).is_err());
+        assert!(BandwidthSchedule::new(&[rule("09:00", "18:00", Some(0))]).is_err());
+    }
+
+    #[test]
+    fn manual_override_wins_until_it_expires() {
+        let schedule = BandwidthSchedule::new(&[rule("00:00", "00:00", Some(4096))]).unwrap();
+        let now = Utc::now();
+        let manual = RateLimitOverride {
+            bytes_per_second: Some(1024),
+            expires_at: Some(now + chrono::Duration::minutes(5)),
+        };
+
+        let status = RateLimitStatus::resolve(&schedule, Some(&manual), at("12:00"));
+        assert_eq!(status.source, RateLimitSource::Manual);
+        assert_eq!(status.bytes_per_second, Some(1024));
+        assert!(!manual.is_expired(now));
+        assert!(manual.is_expired(now + chrono::Duration::minutes(5)));
+
+        let status = RateLimitStatus::resolve(&schedule, None, at("12:00"));
+        assert_eq!(status.source, RateLimitSource::Schedule);
+        assert_eq!(status.bytes_per_second, Some(4096));
+    }
+}
@@ -50,12 +50,18 @@ mod tests {
             youtube: Some(YoutubeConfig {
                 default_quality: Some("720p".to_string()),
                 default_format: Some("mp4".to_string()),
+                default_video_codec: None,
+                default_audio_quality: None,
                 extract_audio: false,
                 audio_format: Some("mp3".to_string()),
+                audio_bitrate_kbps: None,
                 download_subtitles: true,
                 subtitle_languages: vec!["zh-CN".to_string(), "en".to_string()],
+                auto_subtitles: false,
+                subtitle_format: None,
                 download_thumbnail: true,
                 download_description: true,
+                write_info_json: false,
                 playlist_reverse: false,
                 playlist_max_items: Some(50),
             }),
@@ -79,6 +85,7 @@ mod tests {
                 enable_statistics: true,
                 statistics_retention_days: 30,
             },
+            platforms: Vec::new(),
         }
     }
 
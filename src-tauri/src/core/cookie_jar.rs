@@ -0,0 +1,574 @@
+//! 共享的 cookie jar
+//!
+//! 课程站点多用会话 cookie 登录：用户按域名导入浏览器导出的 Netscape `cookies.txt`，
+//! HTTP 请求自动附带匹配的 `Cookie`，响应（包括重定向途中）的 `Set-Cookie` 写回 jar。
+//! jar 与凭据库共用主密钥加密保存在配置目录；yt-dlp 通过临时导出的 cookies 文件使用同一份数据。
+
+#![deny(clippy::unwrap_used, clippy::expect_used)]
+
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, TimeZone, Utc};
+use parking_lot::RwLock;
+use reqwest::header::{HeaderMap, HeaderValue, SET_COOKIE};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use url::Url;
+
+use crate::core::credentials::{load_or_create_key, seal, unseal, KEY_FILE};
+use crate::core::request_headers::{host_matches, normalize_host_pattern};
+
+const JAR_FILE: &str = "cookies.jar";
+const NETSCAPE_HEADER: &str = "# Netscape HTTP Cookie File";
+const HTTP_ONLY_PREFIX: &str = "#HttpOnly_";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct StoredCookie {
+    /// 小写，不带前导点
+    domain: String,
+    include_subdomains: bool,
+    path: String,
+    secure: bool,
+    http_only: bool,
+    /// Unix 秒；None 为会话 cookie
+    expires: Option<i64>,
+    name: String,
+    value: String,
+}
+
+impl StoredCookie {
+    fn is_expired(&self, now: i64) -> bool {
+        self.expires.is_some_and(|expires| expires <= now)
+    }
+
+    fn same_slot(&self, other: &Self) -> bool {
+        self.domain == other.domain && self.path == other.path && self.name == other.name
+    }
+
+    fn matches(&self, url: &Url, host: &str, now: i64) -> bool {
+        let domain_ok = if self.include_subdomains {
+            host_matches(host, &self.domain)
+        } else {
+            host == self.domain
+        };
+        domain_ok
+            && path_matches(url.path(), &self.path)
+            && (!self.secure || url.scheme() == "https")
+            && !self.is_expired(now)
+    }
+
+    fn parse_netscape_line(line: &str) -> Option<Self> {
+        let (line, http_only) = match line.strip_prefix(HTTP_ONLY_PREFIX) {
+            Some(rest) => (rest, true),
+            None => (line, false),
+        };
+        let fields: Vec<&str> = line.split('\t').collect();
+        if fields.len() < 7 {
+            return None;
+        }
+        let domain = fields[0].trim();
+        let flag = fields[1].trim().eq_ignore_ascii_case("TRUE");
+        let name = fields[5].trim();
+        if domain.is_empty() || name.is_empty() {
+            return None;
+        }
+        let expires = fields[4]
+            .trim()
+            .parse::<i64>()
+            .ok()
+            .filter(|value| *value > 0);
+        Some(Self {
+            domain: domain.trim_start_matches('.').to_ascii_lowercase(),
+            include_subdomains: flag || domain.starts_with('.'),
+            path: Some(fields[2].trim())
+                .filter(|path| path.starts_with('/'))
+                .unwrap_or("/")
+                .to_string(),
+            secure: fields[3].trim().eq_ignore_ascii_case("TRUE"),
+            http_only,
+            expires,
+            name: name.to_string(),
+            value: fields[6..]
+                .join("\t")
+                .trim_end_matches(['\r', '\n'])
+                .to_string(),
+        })
+    }
+
+    fn to_netscape_line(&self) -> String {
+        let domain = if self.include_subdomains {
+            format!(".{}", self.domain)
+        } else {
+            self.domain.clone()
+        };
+        let flag = |value: bool| if value { "TRUE" } else { "FALSE" };
+        format!(
+            "{}{}\t{}\t{}\t{}\t{}\t{}\t{}",
+            if self.http_only { HTTP_ONLY_PREFIX } else { "" },
+            domain,
+            flag(self.include_subdomains),
+            self.path,
+            flag(self.secure),
+            self.expires.unwrap_or(0),
+            self.name,
+            self.value
+        )
+    }
+}
+
+/// 每个域名下的 cookie 概况（不返回 cookie 值）
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CookieDomainSummary {
+    pub domain: String,
+    pub cookies: usize,
+    /// 最早过期的 cookie 的过期时间（会话 cookie 不计）
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Default)]
+struct JarState {
+    /// None 时只保存在内存中
+    dir: Option<PathBuf>,
+    key: Option<[u8; 32]>,
+    cookies: Vec<StoredCookie>,
+}
+
+impl JarState {
+    fn upsert(&mut self, cookie: StoredCookie) -> bool {
+        match self
+            .cookies
+            .iter_mut()
+            .find(|existing| existing.same_slot(&cookie))
+        {
+            Some(existing) if *existing == cookie => false,
+            Some(existing) => {
+                *existing = cookie;
+                true
+            }
+            None => {
+                self.cookies.push(cookie);
+                true
+            }
+        }
+    }
+
+    fn prune_expired(&mut self, now: i64) {
+        self.cookies.retain(|cookie| !cookie.is_expired(now));
+    }
+
+    fn netscape(&self) -> String {
+        let mut content = format!("{}\n", NETSCAPE_HEADER);
+        for cookie in &self.cookies {
+            content.push_str(&cookie.to_netscape_line());
+            content.push('\n');
+        }
+        content
+    }
+
+    fn persist(&self) -> Result<()> {
+        let (Some(dir), Some(key)) = (&self.dir, &self.key) else {
+            return Ok(());
+        };
+        let path = dir.join(JAR_FILE);
+        let temp_path = dir.join(format!("{}.tmp", JAR_FILE));
+        std::fs::write(&temp_path, seal(key, &self.netscape())?)
+            .with_context(|| format!("Failed to write {}", temp_path.display()))?;
+        std::fs::rename(&temp_path, &path)
+            .with_context(|| format!("Failed to replace {}", path.display()))?;
+        Ok(())
+    }
+}
+
+/// 共享的 cookie jar 句柄，克隆后指向同一份数据
+#[derive(Clone, Default)]
+pub struct CookieJar {
+    state: Arc<RwLock<JarState>>,
+}
+
+impl CookieJar {
+    /// 只在内存中保存（测试或配置目录不可用时）
+    pub fn in_memory() -> Self {
+        Self::default()
+    }
+
+    pub fn open(dir: &Path) -> Result<Self> {
+        std::fs::create_dir_all(dir)
+            .with_context(|| format!("Failed to create {}", dir.display()))?;
+        let key = load_or_create_key(&dir.join(KEY_FILE))?;
+        let path = dir.join(JAR_FILE);
+        let mut state = JarState {
+            dir: Some(dir.to_path_buf()),
+            key: Some(key),
+            cookies: Vec::new(),
+        };
+        if path.exists() {
+            let sealed = std::fs::read_to_string(&path)
+                .with_context(|| format!("Failed to read {}", path.display()))?;
+            let content = unseal(&key, sealed.trim())
+                .with_context(|| format!("Failed to decrypt {}", path.display()))?;
+            for cookie in parse_netscape(&content) {
+                state.upsert(cookie);
+            }
+            state.prune_expired(Utc::now().timestamp());
+        }
+        Ok(Self {
+            state: Arc::new(RwLock::new(state)),
+        })
+    }
+
+    /// 导入 Netscape `cookies.txt`；指定 `domain` 时只导入该域名（含子域名）的 cookie，
+    /// 并替换 jar 中该域名原有的 cookie。返回导入条数。
+    pub fn import_netscape(&self, content: &str, domain: Option<&str>) -> Result<usize> {
+        let domain = domain
+            .map(normalize_host_pattern)
+            .filter(|domain| !domain.is_empty());
+        let now = Utc::now().timestamp();
+        let cookies: Vec<StoredCookie> = parse_netscape(content)
+            .into_iter()
+            .filter(|cookie| !cookie.is_expired(now))
+            .filter(|cookie| {
+                domain
+                    .as_deref()
+                    .is_none_or(|domain| host_matches(&cookie.domain, domain))
+            })
+            .collect();
+        if cookies.is_empty() {
+            match &domain {
+                Some(domain) => bail!("No unexpired cookies for {} in the file", domain),
+                None => bail!("No unexpired cookies found; is this a Netscape cookies.txt file?"),
+            }
+        }
+
+        let mut state = self.state.write();
+        if let Some(domain) = &domain {
+            state
+                .cookies
+                .retain(|cookie| !host_matches(&cookie.domain, domain));
+        }
+        let imported = cookies.len();
+        for cookie in cookies {
+            state.upsert(cookie);
+        }
+        state.persist()?;
+        Ok(imported)
+    }
+
+    pub fn domains(&self) -> Vec<CookieDomainSummary> {
+        let now = Utc::now().timestamp();
+        let state = self.state.read();
+        let mut domains: BTreeMap<&str, (usize, Option<i64>)> = BTreeMap::new();
+        for cookie in state
+            .cookies
+            .iter()
+            .filter(|cookie| !cookie.is_expired(now))
+        {
+            let entry = domains.entry(cookie.domain.as_str()).or_default();
+            entry.0 += 1;
+            if let Some(expires) = cookie.expires {
+                entry.1 = Some(entry.1.map_or(expires, |current| current.min(expires)));
+            }
+        }
+        domains
+            .into_iter()
+            .map(|(domain, (cookies, expires))| CookieDomainSummary {
+                domain: domain.to_string(),
+                cookies,
+                expires_at: expires.and_then(|expires| Utc.timestamp_opt(expires, 0).single()),
+            })
+            .collect()
+    }
+
+    /// 删除域名（含子域名）下的 cookie，返回删除条数
+    pub fn remove_domain(&self, domain: &str) -> Result<usize> {
+        let domain = normalize_host_pattern(domain);
+        let mut state = self.state.write();
+        let before = state.cookies.len();
+        state
+            .cookies
+            .retain(|cookie| !host_matches(&cookie.domain, &domain));
+        let removed = before - state.cookies.len();
+        if removed > 0 {
+            state.persist()?;
+        }
+        Ok(removed)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.state.read().cookies.is_empty()
+    }
+
+    /// 目标 URL 需要附带的 `Cookie` 请求头（路径越长越靠前）
+    pub fn cookie_header_for(&self, url: &str) -> Option<HeaderValue> {
+        let url = Url::parse(url).ok()?;
+        let host = url.host_str()?.to_ascii_lowercase();
+        let now = Utc::now().timestamp();
+        let state = self.state.read();
+        let mut matched: Vec<&StoredCookie> = state
+            .cookies
+            .iter()
+            .filter(|cookie| cookie.matches(&url, &host, now))
+            .collect();
+        if matched.is_empty() {
+            return None;
+        }
+        matched.sort_by_key(|cookie| std::cmp::Reverse(cookie.path.len()));
+        let value = matched
+            .iter()
+            .map(|cookie| format!("{}={}", cookie.name, cookie.value))
+            .collect::<Vec<_>>()
+            .join("; ");
+        let mut header = HeaderValue::from_str(&value).ok()?;
+        header.set_sensitive(true);
+        Some(header)
+    }
+
+    /// 记录响应中的 `Set-Cookie`（每一跳重定向都要调用）
+    pub fn store_response_cookies(&self, url: &Url, headers: &HeaderMap) {
+        let Some(host) = url.host_str().map(str::to_ascii_lowercase) else {
+            return;
+        };
+        let now = Utc::now().timestamp();
+        let mut changed = false;
+        let mut state = self.state.write();
+        for value in headers.get_all(SET_COOKIE) {
+            let Some(cookie) = value
+                .to_str()
+                .ok()
+                .and_then(|value| parse_set_cookie(value, url, &host, now))
+            else {
+                continue;
+            };
+            if cookie.is_expired(now) {
+                let before = state.cookies.len();
+                state
+                    .cookies
+                    .retain(|existing| !existing.same_slot(&cookie));
+                changed |= state.cookies.len() != before;
+            } else {
+                changed |= state.upsert(cookie);
+            }
+        }
+        if changed {
+            if let Err(err) = state.persist() {
+                tracing::warn!("🍪 Failed to persist cookie jar: {}", err);
+            }
+        }
+    }
+
+    /// 导出到仅当前用户可读的临时 Netscape 文件（供 yt-dlp `--cookies`），jar 为空时返回 None
+    pub fn export_temp_file(&self) -> Result<Option<TempCookieFile>> {
+        let content = {
+            let state = self.state.read();
+            if state.cookies.is_empty() {
+                return Ok(None);
+            }
+            state.netscape()
+        };
+        let dir = std::env::temp_dir().join("video_downloader_cookies");
+        std::fs::create_dir_all(&dir)?;
+        let path = dir.join(format!("{}.txt", uuid::Uuid::new_v4()));
+
+        let mut options = std::fs::OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+        let mut file = options
+            .open(&path)
+            .with_context(|| format!("Failed to create {}", path.display()))?;
+        std::io::Write::write_all(&mut file, content.as_bytes())?;
+        Ok(Some(TempCookieFile { path }))
+    }
+}
+
+impl std::fmt::Debug for CookieJar {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let state = self.state.read();
+        f.debug_struct("CookieJar")
+            .field("dir", &state.dir)
+            .field("cookies", &state.cookies.len())
+            .finish()
+    }
+}
+
+/// 导出的临时 cookies 文件，drop 时删除
+#[derive(Debug)]
+pub struct TempCookieFile {
+    path: PathBuf,
+}
+
+impl TempCookieFile {
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for TempCookieFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+fn parse_netscape(content: &str) -> Vec<StoredCookie> {
+    content
+        .lines()
+        .map(str::trim_start)
+        .filter(|line| {
+            !line.is_empty() && (!line.starts_with('#') || line.starts_with(HTTP_ONLY_PREFIX))
+        })
+        .filter_map(StoredCookie::parse_netscape_line)
+        .collect()
+}
+
+fn parse_set_cookie(value: &str, url: &Url, host: &str, now: i64) -> Option<StoredCookie> {
+    let parsed = cookie::Cookie::parse(value).ok()?;
+    let (domain, include_subdomains) = match parsed.domain() {
+        Some(domain) => {
+            let domain = domain.trim_start_matches('.').to_ascii_lowercase();
+            // 只接受本主机或其上级域名，且不能是顶级域
+            if !domain.contains('.') || !host_matches(host, &domain) {
+                return None;
+            }
+            (domain, true)
+        }
+        None => (host.to_string(), false),
+    };
+    let path = parsed
+        .path()
+        .filter(|path| path.starts_with('/'))
+        .map(str::to_string)
+        .unwrap_or_else(|| default_cookie_path(url.path()));
+    let expires = match parsed.max_age() {
+        Some(max_age) => Some(now.saturating_add(max_age.whole_seconds())),
+        None => parsed
+            .expires_datetime()
+            .map(|expires| expires.unix_timestamp()),
+    };
+    Some(StoredCookie {
+        domain,
+        include_subdomains,
+        path,
+        secure: parsed.secure().unwrap_or(false),
+        http_only: parsed.http_only().unwrap_or(false),
+        expires,
+        name: parsed.name().to_string(),
+        value: parsed.value().to_string(),
+    })
+}
+
+/// RFC 6265 §5.1.4 默认路径：请求路径去掉最后一段
+fn default_cookie_path(request_path: &str) -> String {
+    match request_path.rfind('/') {
+        Some(0) | None => "/".to_string(),
+        Some(index) => request_path[..index].to_string(),
+    }
+}
+
+fn path_matches(request_path: &str, cookie_path: &str) -> bool {
+    request_path == cookie_path
+        || (request_path.starts_with(cookie_path)
+            && (cookie_path.ends_with('/') || request_path[cookie_path.len()..].starts_with('/')))
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::expect_used)]
+mod tests {
+    use super::*;
+
+    const COOKIES_TXT: &str = "# Netscape HTTP Cookie File\n\
+        .course.com\tTRUE\t/\tTRUE\t4102444800\tsession\tabc123\n\
+        #HttpOnly_portal.course.com\tFALSE\t/learn\tFALSE\t0\tcsrftoken\txyz\n\
+        other.org\tFALSE\t/\tFALSE\t4102444800\ttrack\t1\n\
+        expired.com\tFALSE\t/\tFALSE\t1000\told\t1\n";
+
+    fn header(jar: &CookieJar, url: &str) -> Option<String> {
+        jar.cookie_header_for(url)
+            .map(|value| value.to_str().unwrap().to_string())
+    }
+
+    #[test]
+    fn imports_per_domain_and_matches_requests() {
+        let jar = CookieJar::in_memory();
+        assert_eq!(
+            jar.import_netscape(COOKIES_TXT, Some("course.com"))
+                .unwrap(),
+            2
+        );
+        assert!(jar
+            .import_netscape(COOKIES_TXT, Some("expired.com"))
+            .is_err());
+
+        assert_eq!(
+            header(&jar, "https://portal.course.com/learn/video/1").as_deref(),
+            Some("csrftoken=xyz; session=abc123")
+        );
+        // secure cookie 不会发给 http，host-only cookie 不会发给其他子域名
+        assert_eq!(header(&jar, "http://cdn.course.com/seg.ts"), None);
+        assert_eq!(
+            header(&jar, "https://cdn.course.com/seg.ts").as_deref(),
+            Some("session=abc123")
+        );
+        assert_eq!(header(&jar, "https://other.org/"), None);
+
+        let domains = jar.domains();
+        assert_eq!(domains.len(), 2);
+        assert_eq!(jar.remove_domain("course.com").unwrap(), 2);
+        assert!(jar.is_empty());
+    }
+
+    #[test]
+    fn set_cookie_updates_and_deletes_entries() {
+        let jar = CookieJar::in_memory();
+        let url = Url::parse("https://login.course.com/auth/callback").unwrap();
+        let mut headers = HeaderMap::new();
+        headers.append(
+            SET_COOKIE,
+            HeaderValue::from_static("sid=1; Domain=course.com; Path=/; Secure; HttpOnly"),
+        );
+        headers.append(SET_COOKIE, HeaderValue::from_static("step=2"));
+        headers.append(SET_COOKIE, HeaderValue::from_static("evil=1; Domain=com"));
+        jar.store_response_cookies(&url, &headers);
+
+        assert_eq!(
+            header(&jar, "https://media.course.com/v.mp4").as_deref(),
+            Some("sid=1")
+        );
+        assert_eq!(
+            header(&jar, "https://login.course.com/auth/next").as_deref(),
+            Some("step=2; sid=1")
+        );
+
+        let mut logout = HeaderMap::new();
+        logout.append(
+            SET_COOKIE,
+            HeaderValue::from_static("sid=; Domain=course.com; Path=/; Max-Age=0"),
+        );
+        jar.store_response_cookies(&url, &logout);
+        assert_eq!(header(&jar, "https://media.course.com/v.mp4"), None);
+    }
+
+    #[test]
+    fn jar_is_encrypted_at_rest_and_exports_for_ytdlp() {
+        let dir = tempfile::tempdir().unwrap();
+        let jar = CookieJar::open(dir.path()).unwrap();
+        jar.import_netscape(COOKIES_TXT, None).unwrap();
+
+        let on_disk = std::fs::read_to_string(dir.path().join(JAR_FILE)).unwrap();
+        assert!(!on_disk.contains("abc123"));
+
+        let reopened = CookieJar::open(dir.path()).unwrap();
+        assert_eq!(reopened.domains(), jar.domains());
+
+        let exported = reopened.export_temp_file().unwrap().unwrap();
+        let path = exported.path().to_path_buf();
+        let content = std::fs::read_to_string(&path).unwrap();
+        assert!(content.starts_with(NETSCAPE_HEADER));
+        assert!(content.contains("#HttpOnly_portal.course.com\tFALSE\t/learn"));
+        assert_eq!(parse_netscape(&content).len(), 3);
+        drop(exported);
+        assert!(!path.exists());
+    }
+}
@@ -0,0 +1,866 @@
+//! 按主机 / URL 前缀匹配的下载凭据
+//!
+//! 凭据保存在配置目录：`credentials.key` 是首次使用时随机生成的 32 字节主密钥（Unix 上权限 0600），
+//! `credentials.json` 中的密码/令牌用 AES-256-CBC 加密并附 HMAC-SHA256 校验，其余字段明文保存。
+//! HTTP 请求对 Basic/Bearer 预先附带 Authorization；Digest 在收到 401 质询后计算，并缓存 nonce 供后续请求复用。
+
+#![deny(clippy::unwrap_used, clippy::expect_used)]
+
+use aes::Aes256;
+use anyhow::{anyhow, bail, Context, Result};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
+use cbc::{Decryptor, Encryptor};
+use chrono::{DateTime, Utc};
+use cipher::{block_padding::Pkcs7, BlockDecryptMut, BlockEncryptMut, KeyIvInit};
+use hmac::{Hmac, Mac};
+use md5::Md5;
+use parking_lot::RwLock;
+use reqwest::header::HeaderValue;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use url::Url;
+
+use crate::core::request_headers::{host_matches, normalize_host_pattern};
+
+const CREDENTIALS_FILE: &str = "credentials.json";
+/// 凭据库与 cookie jar 共用的主密钥
+pub(crate) const KEY_FILE: &str = "credentials.key";
+const STORE_VERSION: u32 = 1;
+
+type HmacSha256 = Hmac<Sha256>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuthScheme {
+    Basic,
+    Digest,
+    Bearer,
+    /// 只用于 yt-dlp 的 `--video-password`
+    VideoPassword,
+}
+
+impl AuthScheme {
+    fn needs_username(self) -> bool {
+        matches!(self, Self::Basic | Self::Digest)
+    }
+}
+
+/// 前端提交的凭据；`id` 为空时新建，编辑时 `secret` 留空表示沿用原密码
+#[derive(Debug, Clone, Deserialize)]
+pub struct CredentialInput {
+    #[serde(default)]
+    pub id: Option<String>,
+    /// 主机（`example.com` 同时匹配子域名）或 URL 前缀（`https://example.com/private/`）
+    pub pattern: String,
+    pub scheme: AuthScheme,
+    #[serde(default)]
+    pub username: Option<String>,
+    #[serde(default)]
+    pub secret: String,
+    #[serde(default)]
+    pub label: Option<String>,
+}
+
+/// 返回给前端的凭据信息（不含密码/令牌）
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CredentialSummary {
+    pub id: String,
+    pub pattern: String,
+    pub scheme: AuthScheme,
+    pub username: Option<String>,
+    pub label: Option<String>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// 交给外部工具（yt-dlp）的认证参数
+#[derive(Clone, PartialEq, Eq)]
+pub enum ExternalAuth {
+    Login { username: String, password: String },
+    VideoPassword(String),
+    Bearer(String),
+}
+
+impl std::fmt::Debug for ExternalAuth {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Login { username, .. } => f
+                .debug_struct("Login")
+                .field("username", username)
+                .field("password", &"***")
+                .finish(),
+            Self::VideoPassword(_) => f.write_str("VideoPassword(***)"),
+            Self::Bearer(_) => f.write_str("Bearer(***)"),
+        }
+    }
+}
+
+#[derive(Clone)]
+struct Credential {
+    summary: CredentialSummary,
+    secret: String,
+}
+
+impl Credential {
+    /// 匹配程度：URL 前缀优先于主机，同类中越长/越具体越优先
+    fn specificity(&self, url: &Url, host: Option<&str>) -> Option<(u8, usize)> {
+        let pattern = &self.summary.pattern;
+        if pattern.contains("://") {
+            url.as_str()
+                .starts_with(pattern.as_str())
+                .then_some((1, pattern.len()))
+        } else {
+            host.filter(|host| host_matches(host, pattern))
+                .map(|_| (0, pattern.split('.').count()))
+        }
+    }
+
+    fn basic_authorization(&self) -> Option<HeaderValue> {
+        let username = self.summary.username.as_deref()?;
+        let token = BASE64.encode(format!("{}:{}", username, self.secret));
+        sensitive_header(format!("Basic {}", token))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DigestAlgorithm {
+    Md5,
+    Md5Sess,
+    Sha256,
+    Sha256Sess,
+}
+
+impl DigestAlgorithm {
+    fn parse(value: Option<&str>) -> Option<Self> {
+        match value.map(str::to_ascii_uppercase).as_deref() {
+            None | Some("MD5") => Some(Self::Md5),
+            Some("MD5-SESS") => Some(Self::Md5Sess),
+            Some("SHA-256") => Some(Self::Sha256),
+            Some("SHA-256-SESS") => Some(Self::Sha256Sess),
+            _ => None,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::Md5 => "MD5",
+            Self::Md5Sess => "MD5-sess",
+            Self::Sha256 => "SHA-256",
+            Self::Sha256Sess => "SHA-256-sess",
+        }
+    }
+
+    fn hash(self, input: &str) -> String {
+        match self {
+            Self::Md5 | Self::Md5Sess => hex::encode(Md5::digest(input.as_bytes())),
+            Self::Sha256 | Self::Sha256Sess => hex::encode(Sha256::digest(input.as_bytes())),
+        }
+    }
+
+    fn is_session(self) -> bool {
+        matches!(self, Self::Md5Sess | Self::Sha256Sess)
+    }
+}
+
+/// 服务器下发的 Digest 质询，后续请求复用 nonce 并递增 nc
+#[derive(Debug, Clone)]
+struct DigestSession {
+    realm: String,
+    nonce: String,
+    opaque: Option<String>,
+    algorithm: DigestAlgorithm,
+    qop_auth: bool,
+    nonce_count: u32,
+}
+
+impl DigestSession {
+    fn from_challenge(params: &[(String, String)]) -> Option<Self> {
+        let param = |name: &str| {
+            params
+                .iter()
+                .find(|(key, _)| key == name)
+                .map(|(_, value)| value.as_str())
+        };
+        let qop = param("qop");
+        Some(Self {
+            realm: param("realm").unwrap_or_default().to_string(),
+            nonce: param("nonce")?.to_string(),
+            opaque: param("opaque").map(str::to_string),
+            algorithm: DigestAlgorithm::parse(param("algorithm"))?,
+            qop_auth: qop.is_some_and(|qop| qop.split(',').any(|value| value.trim() == "auth")),
+            nonce_count: 0,
+        })
+    }
+
+    fn authorization(
+        &mut self,
+        credential: &Credential,
+        method: &str,
+        url: &Url,
+    ) -> Option<HeaderValue> {
+        let username = credential.summary.username.as_deref()?;
+        let uri = match url.query() {
+            Some(query) => format!("{}?{}", url.path(), query),
+            None => url.path().to_string(),
+        };
+        self.nonce_count = self.nonce_count.wrapping_add(1);
+        let nc = format!("{:08x}", self.nonce_count);
+        let cnonce = hex::encode(rand::random::<[u8; 8]>());
+
+        let algorithm = self.algorithm;
+        let mut ha1 = algorithm.hash(&format!(
+            "{}:{}:{}",
+            username, self.realm, credential.secret
+        ));
+        if algorithm.is_session() {
+            ha1 = algorithm.hash(&format!("{}:{}:{}", ha1, self.nonce, cnonce));
+        }
+        let ha2 = algorithm.hash(&format!("{}:{}", method, uri));
+        let response = if self.qop_auth {
+            algorithm.hash(&format!(
+                "{}:{}:{}:{}:auth:{}",
+                ha1, self.nonce, nc, cnonce, ha2
+            ))
+        } else {
+            algorithm.hash(&format!("{}:{}:{}", ha1, self.nonce, ha2))
+        };
+
+        let mut header = format!(
+            "Digest username=\"{}\", realm=\"{}\", nonce=\"{}\", uri=\"{}\", algorithm={}, response=\"{}\"",
+            quote_escape(username),
+            quote_escape(&self.realm),
+            quote_escape(&self.nonce),
+            quote_escape(&uri),
+            algorithm.name(),
+            response
+        );
+        if self.qop_auth {
+            header.push_str(&format!(", qop=auth, nc={}, cnonce=\"{}\"", nc, cnonce));
+        }
+        if let Some(opaque) = &self.opaque {
+            header.push_str(&format!(", opaque=\"{}\"", quote_escape(opaque)));
+        }
+        sensitive_header(header)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Challenge {
+    scheme: String,
+    params: Vec<(String, String)>,
+}
+
+#[derive(Default)]
+struct StoreState {
+    /// None 时只保存在内存中
+    dir: Option<PathBuf>,
+    key: Option<[u8; 32]>,
+    credentials: Vec<Credential>,
+    /// 按凭据 id 缓存的 Digest 会话
+    digest: HashMap<String, DigestSession>,
+}
+
+impl StoreState {
+    fn best_match(&self, url: &Url) -> Option<&Credential> {
+        let host = url.host_str().map(str::to_ascii_lowercase);
+        self.credentials
+            .iter()
+            .filter_map(|credential| {
+                credential
+                    .specificity(url, host.as_deref())
+                    .map(|rank| (rank, credential))
+            })
+            .max_by_key(|(rank, _)| *rank)
+            .map(|(_, credential)| credential)
+    }
+
+    fn persist(&self) -> Result<()> {
+        let (Some(dir), Some(key)) = (&self.dir, &self.key) else {
+            return Ok(());
+        };
+        let stored = StoredCredentials {
+            version: STORE_VERSION,
+            credentials: self
+                .credentials
+                .iter()
+                .map(|credential| {
+                    Ok(StoredCredential {
+                        summary: credential.summary.clone(),
+                        secret: seal(key, &credential.secret)?,
+                    })
+                })
+                .collect::<Result<_>>()?,
+        };
+        let path = dir.join(CREDENTIALS_FILE);
+        let temp_path = dir.join(format!("{}.tmp", CREDENTIALS_FILE));
+        std::fs::write(&temp_path, serde_json::to_vec_pretty(&stored)?)
+            .with_context(|| format!("Failed to write {}", temp_path.display()))?;
+        std::fs::rename(&temp_path, &path)
+            .with_context(|| format!("Failed to replace {}", path.display()))?;
+        Ok(())
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct StoredCredential {
+    #[serde(flatten)]
+    summary: CredentialSummary,
+    /// base64(iv ‖ 密文 ‖ HMAC)
+    secret: String,
+}
+
+#[derive(Serialize, Deserialize)]
+struct StoredCredentials {
+    version: u32,
+    credentials: Vec<StoredCredential>,
+}
+
+/// 共享的凭据库句柄，克隆后指向同一份数据
+#[derive(Clone, Default)]
+pub struct CredentialStore {
+    state: Arc<RwLock<StoreState>>,
+}
+
+impl CredentialStore {
+    /// 只在内存中保存（测试或配置目录不可用时）
+    pub fn in_memory() -> Self {
+        Self::default()
+    }
+
+    /// 打开 `dir` 下的凭据库，不存在时创建主密钥
+    pub fn open(dir: &Path) -> Result<Self> {
+        std::fs::create_dir_all(dir)
+            .with_context(|| format!("Failed to create {}", dir.display()))?;
+        let key = load_or_create_key(&dir.join(KEY_FILE))?;
+
+        let path = dir.join(CREDENTIALS_FILE);
+        let mut credentials = Vec::new();
+        if path.exists() {
+            let content = std::fs::read(&path)
+                .with_context(|| format!("Failed to read {}", path.display()))?;
+            let stored: StoredCredentials = serde_json::from_slice(&content)
+                .with_context(|| format!("Failed to parse {}", path.display()))?;
+            for entry in stored.credentials {
+                match unseal(&key, &entry.secret) {
+                    Ok(secret) => credentials.push(Credential {
+                        summary: entry.summary,
+                        secret,
+                    }),
+                    Err(err) => tracing::warn!(
+                        "🔑 Dropping credential {} for {}: {}",
+                        entry.summary.id,
+                        entry.summary.pattern,
+                        err
+                    ),
+                }
+            }
+        }
+
+        Ok(Self {
+            state: Arc::new(RwLock::new(StoreState {
+                dir: Some(dir.to_path_buf()),
+                key: Some(key),
+                credentials,
+                digest: HashMap::new(),
+            })),
+        })
+    }
+
+    pub fn list(&self) -> Vec<CredentialSummary> {
+        self.state
+            .read()
+            .credentials
+            .iter()
+            .map(|credential| credential.summary.clone())
+            .collect()
+    }
+
+    /// 新建或更新凭据并立即写盘
+    pub fn upsert(&self, input: CredentialInput) -> Result<CredentialSummary> {
+        let pattern = normalize_pattern(&input.pattern)?;
+        let username = input
+            .username
+            .as_deref()
+            .map(str::trim)
+            .filter(|username| !username.is_empty())
+            .map(str::to_string);
+        if input.scheme.needs_username() {
+            match &username {
+                None => bail!("{:?} credentials need a username", input.scheme),
+                Some(username) if username.contains(':') => {
+                    bail!("Username must not contain ':'")
+                }
+                _ => {}
+            }
+        }
+        let label = input
+            .label
+            .as_deref()
+            .map(str::trim)
+            .filter(|label| !label.is_empty())
+            .map(str::to_string);
+
+        let mut state = self.state.write();
+        let existing = input.id.as_deref().and_then(|id| {
+            state
+                .credentials
+                .iter()
+                .position(|credential| credential.summary.id == id)
+        });
+        let secret = match (input.secret.is_empty(), existing) {
+            (false, _) => input.secret,
+            (true, Some(index)) => state.credentials[index].secret.clone(),
+            (true, None) => bail!("Password or token must not be empty"),
+        };
+        let credential = Credential {
+            summary: CredentialSummary {
+                id: input
+                    .id
+                    .filter(|id| !id.trim().is_empty())
+                    .unwrap_or_else(|| uuid::Uuid::new_v4().to_string()),
+                pattern,
+                scheme: input.scheme,
+                username: username.filter(|_| input.scheme.needs_username()),
+                label,
+                updated_at: Utc::now(),
+            },
+            secret,
+        };
+        let summary = credential.summary.clone();
+        state.digest.remove(&summary.id);
+        match existing {
+            Some(index) => state.credentials[index] = credential,
+            None => state.credentials.push(credential),
+        }
+        state.persist()?;
+        Ok(summary)
+    }
+
+    pub fn remove(&self, id: &str) -> Result<bool> {
+        let mut state = self.state.write();
+        let before = state.credentials.len();
+        state
+            .credentials
+            .retain(|credential| credential.summary.id != id);
+        state.digest.remove(id);
+        if state.credentials.len() == before {
+            return Ok(false);
+        }
+        state.persist()?;
+        Ok(true)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.state.read().credentials.is_empty()
+    }
+
+    /// 无需等待质询即可附带的 Authorization（Basic、Bearer 以及已有会话的 Digest）
+    pub fn authorization_for(&self, method: &str, url: &str) -> Option<HeaderValue> {
+        let url = Url::parse(url).ok()?;
+        let mut state = self.state.write();
+        let credential = state.best_match(&url)?.clone();
+        match credential.summary.scheme {
+            AuthScheme::Basic => credential.basic_authorization(),
+            AuthScheme::Bearer => sensitive_header(format!("Bearer {}", credential.secret)),
+            AuthScheme::Digest => state.digest.get_mut(&credential.summary.id)?.authorization(
+                &credential,
+                method,
+                &url,
+            ),
+            AuthScheme::VideoPassword => None,
+        }
+    }
+
+    /// 根据 401 响应的 `WWW-Authenticate` 计算重试用的 Authorization
+    pub fn answer_challenge(
+        &self,
+        method: &str,
+        url: &Url,
+        www_authenticate: &[String],
+    ) -> Option<HeaderValue> {
+        let challenges: Vec<Challenge> = www_authenticate
+            .iter()
+            .flat_map(|value| parse_challenges(value))
+            .collect();
+        let mut state = self.state.write();
+        let credential = state.best_match(url)?.clone();
+        let find = |scheme: &str| {
+            challenges
+                .iter()
+                .find(|challenge| challenge.scheme == scheme)
+        };
+
+        match credential.summary.scheme {
+            AuthScheme::Basic | AuthScheme::Digest => {
+                if let Some(mut session) = find("digest")
+                    .and_then(|challenge| DigestSession::from_challenge(&challenge.params))
+                {
+                    let authorization = session.authorization(&credential, method, url);
+                    state.digest.insert(credential.summary.id.clone(), session);
+                    return authorization;
+                }
+                // Digest 凭据不降级为明文 Basic
+                if credential.summary.scheme == AuthScheme::Basic
+                    && (challenges.is_empty() || find("basic").is_some())
+                {
+                    return credential.basic_authorization();
+                }
+                None
+            }
+            AuthScheme::Bearer => sensitive_header(format!("Bearer {}", credential.secret)),
+            AuthScheme::VideoPassword => None,
+        }
+    }
+
+    /// yt-dlp 使用的认证参数
+    pub fn external_auth_for(&self, url: &str) -> Option<ExternalAuth> {
+        let url = Url::parse(url).ok()?;
+        let state = self.state.read();
+        let credential = state.best_match(&url)?;
+        Some(match credential.summary.scheme {
+            AuthScheme::Basic | AuthScheme::Digest => ExternalAuth::Login {
+                username: credential.summary.username.clone()?,
+                password: credential.secret.clone(),
+            },
+            AuthScheme::Bearer => ExternalAuth::Bearer(credential.secret.clone()),
+            AuthScheme::VideoPassword => ExternalAuth::VideoPassword(credential.secret.clone()),
+        })
+    }
+}
+
+impl std::fmt::Debug for CredentialStore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let state = self.state.read();
+        f.debug_struct("CredentialStore")
+            .field("dir", &state.dir)
+            .field("credentials", &state.credentials.len())
+            .finish()
+    }
+}
+
+fn normalize_pattern(pattern: &str) -> Result<String> {
+    let pattern = pattern.trim();
+    if pattern.contains("://") {
+        let url =
+            Url::parse(pattern).with_context(|| format!("Invalid URL prefix: {}", pattern))?;
+        if url.host_str().is_none() {
+            bail!("URL prefix must include a host: {}", pattern);
+        }
+        return Ok(url.to_string());
+    }
+    let host = normalize_host_pattern(pattern);
+    if host.is_empty() {
+        bail!("Credential host must not be empty");
+    }
+    Ok(host)
+}
+
+fn sensitive_header(value: String) -> Option<HeaderValue> {
+    let mut header = HeaderValue::from_str(&value).ok()?;
+    header.set_sensitive(true);
+    Some(header)
+}
+
+fn quote_escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// 解析 `WWW-Authenticate`，一个头里可能有多个质询
+fn parse_challenges(value: &str) -> Vec<Challenge> {
+    let mut challenges: Vec<Challenge> = Vec::new();
+    let mut rest = value;
+    loop {
+        rest = rest.trim_start_matches(|c: char| c == ',' || c.is_whitespace());
+        if rest.is_empty() {
+            break;
+        }
+        let token_end = rest
+            .find(|c: char| c == '=' || c == ',' || c.is_whitespace())
+            .unwrap_or(rest.len());
+        let token = &rest[..token_end];
+        let after = rest[token_end..].trim_start();
+
+        match after.strip_prefix('=') {
+            Some(value_part) if !challenges.is_empty() => {
+                let value_part = value_part.trim_start();
+                let (value, remaining) = match value_part.strip_prefix('"') {
+                    Some(quoted) => parse_quoted(quoted),
+                    None => {
+                        let end = value_part.find(',').unwrap_or(value_part.len());
+                        (value_part[..end].trim().to_string(), &value_part[end..])
+                    }
+                };
+                if let Some(challenge) = challenges.last_mut() {
+                    challenge.params.push((token.to_ascii_lowercase(), value));
+                }
+                rest = remaining;
+            }
+            _ => {
+                challenges.push(Challenge {
+                    scheme: token.to_ascii_lowercase(),
+                    params: Vec::new(),
+                });
+                rest = after;
+            }
+        }
+    }
+    challenges
+}
+
+fn parse_quoted(input: &str) -> (String, &str) {
+    let mut value = String::new();
+    let mut chars = input.char_indices();
+    while let Some((index, c)) = chars.next() {
+        match c {
+            '\\' => {
+                if let Some((_, escaped)) = chars.next() {
+                    value.push(escaped);
+                }
+            }
+            '"' => return (value, &input[index + 1..]),
+            _ => value.push(c),
+        }
+    }
+    (value, "")
+}
+
+pub(crate) fn load_or_create_key(path: &Path) -> Result<[u8; 32]> {
+    if path.exists() {
+        let bytes =
+            std::fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+        return bytes
+            .try_into()
+            .map_err(|_| anyhow!("Credential key {} is corrupted", path.display()));
+    }
+
+    let key = rand::random::<[u8; 32]>();
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options
+        .open(path)
+        .with_context(|| format!("Failed to create {}", path.display()))?;
+    std::io::Write::write_all(&mut file, &key)?;
+    file.sync_all()?;
+    Ok(key)
+}
+
+/// 主密钥派生出加密与校验两把子密钥
+fn derive_keys(master: &[u8; 32]) -> ([u8; 32], [u8; 32]) {
+    let derive = |purpose: &[u8]| -> [u8; 32] {
+        let mut hasher = Sha256::new();
+        hasher.update(purpose);
+        hasher.update(master);
+        hasher.finalize().into()
+    };
+    (
+        derive(b"credential-store/encrypt"),
+        derive(b"credential-store/mac"),
+    )
+}
+
+fn mac_for(mac_key: &[u8], iv: &[u8], ciphertext: &[u8]) -> Result<HmacSha256> {
+    let mut mac = <HmacSha256 as Mac>::new_from_slice(mac_key)
+        .map_err(|err| anyhow!("Invalid MAC key: {}", err))?;
+    mac.update(iv);
+    mac.update(ciphertext);
+    Ok(mac)
+}
+
+pub(crate) fn seal(master: &[u8; 32], plaintext: &str) -> Result<String> {
+    let (enc_key, mac_key) = derive_keys(master);
+    let iv = rand::random::<[u8; 16]>();
+    let ciphertext = Encryptor::<Aes256>::new_from_slices(&enc_key, &iv)
+        .map_err(|err| anyhow!(err.to_string()))?
+        .encrypt_padded_vec_mut::<Pkcs7>(plaintext.as_bytes());
+    let tag = mac_for(&mac_key, &iv, &ciphertext)?.finalize().into_bytes();
+
+    let mut sealed = Vec::with_capacity(iv.len() + ciphertext.len() + tag.len());
+    sealed.extend_from_slice(&iv);
+    sealed.extend_from_slice(&ciphertext);
+    sealed.extend_from_slice(&tag);
+    Ok(BASE64.encode(sealed))
+}
+
+pub(crate) fn unseal(master: &[u8; 32], sealed: &str) -> Result<String> {
+    let sealed = BASE64
+        .decode(sealed)
+        .context("Secret is not valid base64")?;
+    if sealed.len() < 16 + 16 + 32 {
+        bail!("Secret is truncated");
+    }
+    let (iv, rest) = sealed.split_at(16);
+    let (ciphertext, tag) = rest.split_at(rest.len() - 32);
+    let (enc_key, mac_key) = derive_keys(master);
+    mac_for(&mac_key, iv, ciphertext)?
+        .verify_slice(tag)
+        .map_err(|_| anyhow!("Secret failed integrity check (wrong key?)"))?;
+    let plaintext = Decryptor::<Aes256>::new_from_slices(&enc_key, iv)
+        .map_err(|err| anyhow!(err.to_string()))?
+        .decrypt_padded_vec_mut::<Pkcs7>(ciphertext)
+        .map_err(|_| anyhow!("Secret could not be decrypted"))?;
+    String::from_utf8(plaintext).context("Secret is not valid UTF-8")
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::expect_used)]
+mod tests {
+    use super::*;
+
+    fn input(pattern: &str, scheme: AuthScheme, secret: &str) -> CredentialInput {
+        CredentialInput {
+            id: None,
+            pattern: pattern.to_string(),
+            scheme,
+            username: Some("alice".to_string()),
+            secret: secret.to_string(),
+            label: None,
+        }
+    }
+
+    #[test]
+    fn secrets_are_encrypted_at_rest_and_reload() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = CredentialStore::open(dir.path()).unwrap();
+        let saved = store
+            .upsert(input("files.example.com", AuthScheme::Basic, "hunter2"))
+            .unwrap();
+
+        let on_disk = std::fs::read_to_string(dir.path().join(CREDENTIALS_FILE)).unwrap();
+        assert!(on_disk.contains("files.example.com"));
+        assert!(!on_disk.contains("hunter2"));
+
+        let reopened = CredentialStore::open(dir.path()).unwrap();
+        assert_eq!(reopened.list(), vec![saved.clone()]);
+        let header = reopened
+            .authorization_for("GET", "https://cdn.files.example.com/a.mp4")
+            .unwrap();
+        assert_eq!(
+            header.to_str().unwrap(),
+            format!("Basic {}", BASE64.encode("alice:hunter2"))
+        );
+
+        // 空 secret 编辑时沿用原密码
+        let renamed = reopened
+            .upsert(CredentialInput {
+                id: Some(saved.id.clone()),
+                label: Some("NAS".to_string()),
+                ..input("files.example.com", AuthScheme::Basic, "")
+            })
+            .unwrap();
+        assert_eq!(renamed.id, saved.id);
+        assert!(reopened
+            .authorization_for("GET", "https://files.example.com/")
+            .is_some());
+        assert!(reopened.remove(&saved.id).unwrap());
+        assert!(reopened.is_empty());
+    }
+
+    #[test]
+    fn url_prefix_wins_over_host_and_validates_input() {
+        let store = CredentialStore::in_memory();
+        store
+            .upsert(input("example.com", AuthScheme::Bearer, "host-token"))
+            .unwrap();
+        store
+            .upsert(input(
+                "https://example.com/private/",
+                AuthScheme::Bearer,
+                "prefix-token",
+            ))
+            .unwrap();
+
+        let auth = |url: &str| {
+            store
+                .authorization_for("GET", url)
+                .map(|value| value.to_str().unwrap().to_string())
+        };
+        assert_eq!(
+            auth("https://example.com/private/v.mp4").as_deref(),
+            Some("Bearer prefix-token")
+        );
+        assert_eq!(
+            auth("https://example.com/public/v.mp4").as_deref(),
+            Some("Bearer host-token")
+        );
+        assert_eq!(auth("https://other.org/v.mp4"), None);
+
+        assert!(store.upsert(input("  ", AuthScheme::Basic, "x")).is_err());
+        assert!(store
+            .upsert(CredentialInput {
+                username: None,
+                ..input("a.com", AuthScheme::Digest, "x")
+            })
+            .is_err());
+    }
+
+    #[test]
+    fn digest_challenge_produces_rfc_response() {
+        // RFC 2617 §3.5 示例
+        let store = CredentialStore::in_memory();
+        store
+            .upsert(CredentialInput {
+                username: Some("Mufasa".to_string()),
+                ..input("www.nowhere.org", AuthScheme::Digest, "Circle Of Life")
+            })
+            .unwrap();
+        let url = Url::parse("http://www.nowhere.org/dir/index.html").unwrap();
+        assert!(store.authorization_for("GET", url.as_str()).is_none());
+
+        let challenge = r#"Digest realm="testrealm@host.com", qop="auth,auth-int", nonce="dcd98b7102dd2f0e8b11d0f600bfb0c093", opaque="5ccc069c403ebaf9f0171e9517f40e41""#;
+        let header = store
+            .answer_challenge("GET", &url, &[challenge.to_string()])
+            .unwrap();
+        let header = header.to_str().unwrap();
+        let params = &parse_challenges(header)[0];
+        let param = |name: &str| {
+            params
+                .params
+                .iter()
+                .find(|(key, _)| key == name)
+                .map(|(_, value)| value.clone())
+                .unwrap()
+        };
+        assert_eq!(param("uri"), "/dir/index.html");
+        assert_eq!(param("nc"), "00000001");
+        let ha1 = hex::encode(Md5::digest(b"Mufasa:testrealm@host.com:Circle Of Life"));
+        let ha2 = hex::encode(Md5::digest(b"GET:/dir/index.html"));
+        let expected = hex::encode(Md5::digest(
+            format!(
+                "{}:dcd98b7102dd2f0e8b11d0f600bfb0c093:00000001:{}:auth:{}",
+                ha1,
+                param("cnonce"),
+                ha2
+            )
+            .as_bytes(),
+        ));
+        assert_eq!(param("response"), expected);
+        assert_eq!(param("opaque"), "5ccc069c403ebaf9f0171e9517f40e41");
+
+        // 之后的请求复用 nonce
+        let next = store.authorization_for("GET", url.as_str()).unwrap();
+        assert!(next.to_str().unwrap().contains("nc=00000002"));
+        // Digest 凭据不会响应 Basic 质询
+        assert!(store
+            .answer_challenge("GET", &url, &["Basic realm=\"x\"".to_string()])
+            .is_none());
+    }
+
+    #[test]
+    fn tampered_secret_is_rejected() {
+        let key = [7u8; 32];
+        let sealed = seal(&key, "token").unwrap();
+        assert_eq!(unseal(&key, &sealed).unwrap(), "token");
+        assert!(unseal(&[8u8; 32], &sealed).is_err());
+
+        let mut bytes = BASE64.decode(&sealed).unwrap();
+        bytes[20] ^= 1;
+        assert!(unseal(&key, &BASE64.encode(bytes)).is_err());
+    }
+}
@@ -0,0 +1,542 @@
+//! MPEG-DASH 下载器
+//!
+//! 解析 MPD（`parsers::mpd_parser`）后按 `VariantPolicy` 选视频 Representation，按音轨
+//! 语言偏好选音频 AdaptationSet，再把每个选中的 Representation 转成 `M3U8Playlist`
+//! （一个初始化段加若干可带字节范围的分片）交给 `M3U8Downloader::download_stream`。
+//! 分片并发、暂停/取消、进度、分片清单和 ffmpeg 封装都与 HLS 共用。
+
+#![deny(clippy::unwrap_used, clippy::expect_used)]
+
+use crate::core::hls_variant::{
+    self, HlsRendition, HlsVariant, RenditionKind, RenditionPreferences, VariantPolicy,
+};
+use crate::core::http_client::HttpClientHandle;
+use crate::core::m3u8_downloader::{
+    LiveRecordingLimits, M3U8Downloader, M3U8InitSection, M3U8Playlist, M3U8Segment, MediaTrack,
+};
+use crate::parsers::mpd_parser::{
+    self, DashAdaptationSet, DashContentKind, DashPeriod, DashRepresentation, DashSegment,
+    DashSegments,
+};
+use anyhow::{anyhow, bail, Result};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
+
+/// 选中的 AdaptationSet 及其中的 Representation
+#[derive(Debug, Clone, Copy)]
+struct SelectedStream<'a> {
+    set: &'a DashAdaptationSet,
+    representation: &'a DashRepresentation,
+}
+
+/// DASH 下载器（分片下载委托给共享的 `M3U8Downloader`）
+pub struct DashDownloader {
+    client: HttpClientHandle,
+    m3u8_downloader: Arc<M3U8Downloader>,
+}
+
+impl DashDownloader {
+    pub fn new(client: HttpClientHandle, m3u8_downloader: Arc<M3U8Downloader>) -> Self {
+        Self {
+            client,
+            m3u8_downloader,
+        }
+    }
+
+    /// 下载 DASH 流，返回实际写入的文件路径（按 mimeType 写 .mp4/.webm/.m4a，
+    /// 封装了独立音轨时为 .mp4）
+    #[allow(clippy::too_many_arguments)]
+    pub async fn download_dash(
+        &self,
+        task_id: &str,
+        mpd_url: &str,
+        output_path: &str,
+        variant_policy: &VariantPolicy,
+        max_missing_segments: usize,
+        rendition_preferences: &RenditionPreferences,
+        pause_flag: Arc<AtomicBool>,
+    ) -> Result<String> {
+        tracing::info!("开始下载DASH流: {}", mpd_url);
+        let content = self.fetch_text(mpd_url).await?;
+        let manifest = mpd_parser::parse_mpd(mpd_url, &content)?;
+        if manifest.is_dynamic {
+            bail!("暂不支持直播 DASH 流 (type=dynamic)");
+        }
+        let period = manifest
+            .periods
+            .first()
+            .ok_or_else(|| anyhow!("MPD 中没有 Period"))?;
+        if manifest.periods.len() > 1 {
+            tracing::warn!("MPD 共 {} 个 Period，只下载第一个", manifest.periods.len());
+        }
+
+        let (main, audio) = select_streams(period, variant_policy, rendition_preferences)?;
+        tracing::info!(
+            "DASH 选中 {} ({} bps {}), 独立音轨 {} 条",
+            main.representation.id,
+            main.representation.bandwidth,
+            main.representation
+                .height
+                .map(|height| format!("{}p", height))
+                .unwrap_or_default(),
+            audio.len()
+        );
+
+        let playlist = self.load_playlist(mpd_url, main.representation).await?;
+        self.m3u8_downloader.report_selected_variant(
+            task_id,
+            HlsVariant {
+                uri: playlist.url.clone(),
+                bandwidth: main.representation.bandwidth,
+                average_bandwidth: None,
+                width: main.representation.width,
+                height: main.representation.height,
+                codecs: main.representation.codecs.clone(),
+                frame_rate: main.representation.frame_rate,
+                audio_group: None,
+                subtitles_group: None,
+            },
+        );
+
+        let tracks = futures::future::try_join_all(audio.iter().map(|stream| async move {
+            Ok::<_, anyhow::Error>(MediaTrack {
+                rendition: HlsRendition {
+                    kind: RenditionKind::Audio,
+                    group_id: stream.set.id.clone().unwrap_or_else(|| "audio".to_string()),
+                    name: stream
+                        .set
+                        .language
+                        .clone()
+                        .unwrap_or_else(|| stream.representation.id.clone()),
+                    language: stream.set.language.clone(),
+                    uri: Some(mpd_url.to_string()),
+                    default: stream.set.is_main,
+                },
+                playlist: self.load_playlist(mpd_url, stream.representation).await?,
+            })
+        }))
+        .await?;
+
+        let output_path =
+            output_path_for_mime(output_path, main.representation.mime_type.as_deref());
+        self.m3u8_downloader
+            .download_stream(
+                task_id,
+                playlist,
+                tracks,
+                &output_path,
+                &LiveRecordingLimits::default(),
+                max_missing_segments,
+                rendition_preferences,
+                HashMap::new(),
+                pause_flag,
+            )
+            .await
+    }
+
+    /// 把 Representation 的分片展开为播放列表；SegmentBase 先取回 sidx 索引
+    async fn load_playlist(
+        &self,
+        mpd_url: &str,
+        representation: &DashRepresentation,
+    ) -> Result<M3U8Playlist> {
+        let (init, segments) = match &representation.segments {
+            DashSegments::Listed { init, segments } => (init.clone(), segments.clone()),
+            DashSegments::Indexed {
+                url,
+                init_range,
+                index_range,
+            } => {
+                let index = self.fetch_range(url, *index_range).await?;
+                let segments = mpd_parser::parse_sidx(url, &index, index_range.0)?;
+                let init_range = init_range
+                    .or_else(|| index_range.0.checked_sub(1).map(|init_end| (0, init_end)));
+                let init = init_range.map(|range| DashSegment {
+                    url: url.clone(),
+                    byte_range: Some(range),
+                    duration: 0.0,
+                });
+                (init, segments)
+            }
+        };
+        if segments.is_empty() {
+            bail!("Representation {} 没有分片", representation.id);
+        }
+
+        // 清单地址带上 Representation，换档位后不会复用旧的分片清单
+        Ok(build_playlist(
+            format!("{}#{}", mpd_url, representation.id),
+            mpd_url,
+            init,
+            segments,
+        ))
+    }
+
+    async fn fetch_text(&self, url: &str) -> Result<String> {
+        tracing::debug!("获取MPD: {}", url);
+        let response = self.client.send(self.client.get(url)).await?;
+        if !response.status().is_success() {
+            bail!("获取MPD失败: {}", response.status());
+        }
+        Ok(response.text().await?)
+    }
+
+    async fn fetch_range(&self, url: &str, (start, end): (u64, u64)) -> Result<Vec<u8>> {
+        let request = self
+            .client
+            .get(url)
+            .header("Range", format!("bytes={}-{}", start, end));
+        let response = self.client.send(request).await?;
+        if response.status().as_u16() != 206 {
+            bail!(
+                "读取 SegmentBase 索引失败，服务器未返回 206: {} - {}",
+                url,
+                response.status()
+            );
+        }
+        Ok(response.bytes().await?.to_vec())
+    }
+}
+
+/// 选出主流（有视频时为视频，纯音频清单时为第一条音轨）和需要单独下载并封装的音轨
+fn select_streams<'a>(
+    period: &'a DashPeriod,
+    policy: &VariantPolicy,
+    preferences: &RenditionPreferences,
+) -> Result<(SelectedStream<'a>, Vec<SelectedStream<'a>>)> {
+    let usable = |kind: DashContentKind| -> Result<Vec<&'a DashAdaptationSet>> {
+        let sets: Vec<&DashAdaptationSet> = period
+            .adaptation_sets
+            .iter()
+            .filter(|set| set.kind == kind && !set.representations.is_empty())
+            .collect();
+        if !sets.is_empty() && sets.iter().all(|set| set.protected) {
+            bail!("drm_protected: DASH 流声明了 ContentProtection，无法下载");
+        }
+        Ok(sets.into_iter().filter(|set| !set.protected).collect())
+    };
+    let videos = usable(DashContentKind::Video)?;
+    let audios = usable(DashContentKind::Audio)?;
+
+    // 音轨：按语言偏好取每种语言的第一个 AdaptationSet，都没匹配上时取 main（否则第一个）
+    let mut audio_sets: Vec<&DashAdaptationSet> = Vec::new();
+    for wanted in &preferences.audio_languages {
+        let wanted = wanted.trim().to_ascii_lowercase();
+        if wanted.is_empty() {
+            continue;
+        }
+        let found = audios
+            .iter()
+            .find(|set| hls_variant::language_matches(set.language.as_deref(), &wanted));
+        if let Some(set) = found {
+            if !audio_sets.iter().any(|known| std::ptr::eq(*known, *set)) {
+                audio_sets.push(set);
+            }
+        }
+    }
+    if audio_sets.is_empty() {
+        audio_sets.extend(
+            audios
+                .iter()
+                .find(|set| set.is_main)
+                .or_else(|| audios.first())
+                .copied(),
+        );
+    }
+    let audio_policy = match policy {
+        VariantPolicy::Lowest => VariantPolicy::Lowest,
+        _ => VariantPolicy::Highest,
+    };
+    let mut audio: Vec<SelectedStream> = audio_sets
+        .into_iter()
+        .filter_map(|set| {
+            hls_variant::select_variant(&set.representations, &audio_policy).map(|representation| {
+                SelectedStream {
+                    set,
+                    representation,
+                }
+            })
+        })
+        .collect();
+
+    let candidates: Vec<(&DashAdaptationSet, &DashRepresentation)> = videos
+        .iter()
+        .flat_map(|set| {
+            set.representations
+                .iter()
+                .map(move |representation| (*set, representation))
+        })
+        .collect();
+    let representations: Vec<&DashRepresentation> = candidates
+        .iter()
+        .map(|(_, representation)| *representation)
+        .collect();
+    let main = match hls_variant::select_variant(&representations, policy) {
+        Some(selected) => candidates
+            .iter()
+            .find(|(_, representation)| std::ptr::eq(*representation, *selected))
+            .map(|(set, representation)| SelectedStream {
+                set,
+                representation,
+            })
+            .ok_or_else(|| anyhow!("DASH 视频档位选择失败"))?,
+        None if !audio.is_empty() => audio.remove(0),
+        None => bail!("MPD 中没有可下载的音视频 AdaptationSet"),
+    };
+    Ok((main, audio))
+}
+
+fn build_playlist(
+    url: String,
+    base_url: &str,
+    init: Option<DashSegment>,
+    segments: Vec<DashSegment>,
+) -> M3U8Playlist {
+    let init_sections: Vec<M3U8InitSection> = init
+        .into_iter()
+        .map(|init| M3U8InitSection {
+            url: init.url,
+            byte_range: init.byte_range,
+            encryption: None,
+        })
+        .collect();
+    let init_section = (!init_sections.is_empty()).then_some(0);
+    let segments: Vec<M3U8Segment> = segments
+        .into_iter()
+        .enumerate()
+        .map(|(index, segment)| M3U8Segment {
+            index,
+            sequence: index as u64,
+            url: segment.url,
+            duration: segment.duration,
+            byte_range: segment.byte_range,
+            discontinuity: false,
+            program_date_time: None,
+            downloaded: false,
+            local_path: None,
+            encryption: None,
+            init_section,
+        })
+        .collect();
+
+    M3U8Playlist {
+        url,
+        base_url: base_url.to_string(),
+        duration: segments.iter().map(|segment| segment.duration).sum(),
+        target_duration: segments
+            .iter()
+            .map(|segment| segment.duration)
+            .fold(0.0, f64::max),
+        segments,
+        is_live: false,
+        media_sequence: 0,
+        discontinuity_sequence: 0,
+        version: 0,
+        encryption: None,
+        init_sections,
+    }
+}
+
+/// 按主流的 mimeType 修正扩展名；仅替换 .mpd 或缺失的扩展名
+fn output_path_for_mime(output_path: &str, mime_type: Option<&str>) -> String {
+    let path = Path::new(output_path);
+    let extension = path
+        .extension()
+        .map(|ext| ext.to_string_lossy().to_ascii_lowercase());
+    if !matches!(extension.as_deref(), None | Some("mpd")) {
+        return output_path.to_string();
+    }
+    let target = match mime_type.unwrap_or_default() {
+        "video/webm" | "audio/webm" => "webm",
+        "audio/mp4" => "m4a",
+        _ => "mp4",
+    };
+    path.with_extension(target).to_string_lossy().to_string()
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::expect_used)]
+mod tests {
+    use super::*;
+    use crate::core::http_client::HttpClientOptions;
+    use crate::core::m3u8_downloader::M3U8DownloaderConfig;
+    use crate::core::request_headers::HeaderRules;
+    use std::time::Duration;
+    use tempfile::tempdir;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    const MPD: &str = r#"<?xml version="1.0"?>
+<MPD xmlns="urn:mpeg:dash:schema:mpd:2011" type="static" mediaPresentationDuration="PT4S">
+  <Period>
+    <AdaptationSet contentType="video" mimeType="video/mp4">
+      <SegmentTemplate timescale="1" duration="2" initialization="video/$RepresentationID$/init.mp4" media="video/$RepresentationID$/$Number$.m4s"/>
+      <Representation id="hd" bandwidth="3000000" width="1920" height="1080"/>
+      <Representation id="sd" bandwidth="1000000" width="1280" height="720" codecs="avc1.4d401f"/>
+    </AdaptationSet>
+    <AdaptationSet id="2" contentType="audio" mimeType="audio/mp4" lang="en">
+      <Representation id="en" bandwidth="128000">
+        <BaseURL>audio/en.mp4</BaseURL>
+        <SegmentList timescale="1" duration="2">
+          <Initialization range="0-3"/>
+          <SegmentURL mediaRange="4-7"/>
+          <SegmentURL mediaRange="8-11"/>
+        </SegmentList>
+      </Representation>
+    </AdaptationSet>
+    <AdaptationSet id="3" contentType="audio" mimeType="audio/mp4" lang="fr">
+      <ContentProtection schemeIdUri="urn:mpeg:dash:mp4protection:2011" value="cenc"/>
+      <Representation id="fr" bandwidth="128000"><BaseURL>audio/fr.mp4</BaseURL></Representation>
+    </AdaptationSet>
+  </Period>
+</MPD>"#;
+
+    #[test]
+    fn selects_streams_by_policy_and_language() {
+        let manifest =
+            mpd_parser::parse_mpd("https://cdn.example.com/a/manifest.mpd", MPD).unwrap();
+        let period = &manifest.periods[0];
+
+        let (main, audio) = select_streams(
+            period,
+            &VariantPolicy::ClosestHeight { height: 720 },
+            &RenditionPreferences {
+                audio_languages: vec!["fr".to_string(), "EN".to_string()],
+                ..RenditionPreferences::default()
+            },
+        )
+        .unwrap();
+        assert_eq!(main.representation.id, "sd");
+        // 受保护的法语音轨被跳过
+        assert_eq!(audio.len(), 1);
+        assert_eq!(audio[0].representation.id, "en");
+
+        let (main, _) = select_streams(
+            period,
+            &VariantPolicy::default(),
+            &RenditionPreferences::default(),
+        )
+        .unwrap();
+        assert_eq!(main.representation.id, "hd");
+
+        let mut protected = period.clone();
+        protected
+            .adaptation_sets
+            .retain(|set| set.kind == DashContentKind::Video);
+        protected.adaptation_sets[0].protected = true;
+        let error = select_streams(
+            &protected,
+            &VariantPolicy::default(),
+            &RenditionPreferences::default(),
+        )
+        .unwrap_err();
+        assert!(error.to_string().starts_with("drm_protected"));
+
+        assert_eq!(
+            output_path_for_mime("/out/lesson.mpd", Some("video/webm")),
+            "/out/lesson.webm"
+        );
+        assert_eq!(
+            output_path_for_mime("/out/lesson", Some("audio/mp4")),
+            "/out/lesson.m4a"
+        );
+        assert_eq!(
+            output_path_for_mime("/out/lesson.mkv", Some("video/mp4")),
+            "/out/lesson.mkv"
+        );
+    }
+
+    #[tokio::test]
+    async fn downloads_template_video_and_ranged_audio_track() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            loop {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let mut buffer = [0u8; 2048];
+                let bytes_read = socket.read(&mut buffer).await.unwrap();
+                let request = String::from_utf8_lossy(&buffer[..bytes_read]).to_string();
+                let path = request
+                    .lines()
+                    .next()
+                    .and_then(|line| line.split_whitespace().nth(1))
+                    .unwrap_or("/")
+                    .to_string();
+                let range = request.lines().find_map(|line| {
+                    let (name, value) = line.split_once(':')?;
+                    let (start, end) = name
+                        .eq_ignore_ascii_case("range")
+                        .then_some(value.trim().strip_prefix("bytes=")?)?
+                        .split_once('-')?;
+                    Some((start.parse::<usize>().ok()?, end.parse::<usize>().ok()?))
+                });
+
+                let body: Vec<u8> = match path.as_str() {
+                    "/course/manifest.mpd" => MPD.as_bytes().to_vec(),
+                    "/course/video/sd/init.mp4" => b"INIT".to_vec(),
+                    "/course/video/sd/1.m4s" => b"seg1".to_vec(),
+                    "/course/video/sd/2.m4s" => b"seg2".to_vec(),
+                    "/course/audio/en.mp4" => b"ainiAAAABBBB".to_vec(),
+                    _ => Vec::new(),
+                };
+                let (status, body) = match range {
+                    _ if body.is_empty() => ("404 Not Found", body),
+                    Some((start, end)) => ("206 Partial Content", body[start..=end].to_vec()),
+                    None => ("200 OK", body),
+                };
+                let response = format!(
+                    "HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                    status,
+                    body.len()
+                );
+                socket.write_all(response.as_bytes()).await.unwrap();
+                socket.write_all(&body).await.unwrap();
+                socket.shutdown().await.unwrap();
+            }
+        });
+
+        let temp_dir = tempdir().unwrap();
+        let config = M3U8DownloaderConfig {
+            temp_dir: temp_dir.path().join("segments"),
+            ..M3U8DownloaderConfig::default()
+        };
+        let client = HttpClientHandle::new(HttpClientOptions {
+            timeout: Duration::from_secs(10),
+            user_agent: config.user_agent.clone(),
+            proxy: None,
+            headers: HeaderRules::default(),
+        })
+        .unwrap();
+        let m3u8_downloader =
+            Arc::new(M3U8Downloader::with_http_client(config, client.clone()).unwrap());
+        let downloader = DashDownloader::new(client, m3u8_downloader);
+        let output = temp_dir.path().join("lesson.mpd");
+
+        // 假数据无法被 ffmpeg 封装（或 ffmpeg 不存在），音轨落到独立文件
+        let written = downloader
+            .download_dash(
+                "dash-task",
+                &format!("http://{}/course/manifest.mpd", addr),
+                &output.to_string_lossy(),
+                &VariantPolicy::ClosestHeight { height: 720 },
+                0,
+                &RenditionPreferences::default(),
+                Arc::new(AtomicBool::new(false)),
+            )
+            .await
+            .unwrap();
+        server.abort();
+
+        let video = temp_dir.path().join("lesson.mp4");
+        assert_eq!(written, video.to_string_lossy());
+        assert_eq!(tokio::fs::read(&video).await.unwrap(), b"INITseg1seg2");
+        assert_eq!(
+            tokio::fs::read(temp_dir.path().join("lesson.en.m4a"))
+                .await
+                .unwrap(),
+            b"ainiAAAABBBB"
+        );
+    }
+}
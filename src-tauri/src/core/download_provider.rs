@@ -1,9 +1,12 @@
-use crate::core::models::SourcePlatform;
-use crate::core::ytdlp_support::{detect_platform, is_direct_media_url};
+use std::sync::Arc;
+
+use crate::core::platform_registry::{self, PlatformRegistry, PlatformRoute};
+use crate::core::ytdlp_support::is_direct_media_url;
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum InitialProviderDecision {
     M3u8,
+    Dash,
     YtDlp,
     NeedsHead,
 }
@@ -12,6 +15,7 @@ pub enum InitialProviderDecision {
 pub enum ResolvedProviderDecision {
     HttpSimple,
     HttpResumable,
+    Dash,
     YtDlp,
 }
 
@@ -24,29 +28,60 @@ pub struct ContentMetadata {
 #[derive(Debug, Clone)]
 pub struct DownloadProviderRouter {
     large_file_threshold_bytes: u64,
+    /// 固定使用的规则表；为空时每次取当前安装的规则表，配置更新后立即生效
+    registry: Option<Arc<PlatformRegistry>>,
 }
 
 impl DownloadProviderRouter {
     pub fn new(large_file_threshold_bytes: u64) -> Self {
         Self {
             large_file_threshold_bytes,
+            registry: None,
         }
     }
 
+    pub fn with_registry(mut self, registry: Arc<PlatformRegistry>) -> Self {
+        self.registry = Some(registry);
+        self
+    }
+
+    fn registry(&self) -> Arc<PlatformRegistry> {
+        self.registry
+            .clone()
+            .unwrap_or_else(platform_registry::current)
+    }
+
+    /// 平台规则的强制路由优先，其余按地址特征判断
     pub fn initial_decision(&self, url: &str) -> InitialProviderDecision {
-        if is_m3u8_url(url) {
+        match self.registry().route_for(url) {
+            Some(PlatformRoute::Ytdlp) => return InitialProviderDecision::YtDlp,
+            Some(PlatformRoute::M3u8) => return InitialProviderDecision::M3u8,
+            Some(PlatformRoute::Http) => return InitialProviderDecision::NeedsHead,
+            None => {}
+        }
+        if is_dash_url(url) {
+            InitialProviderDecision::Dash
+        } else if is_m3u8_url(url) {
             InitialProviderDecision::M3u8
-        } else if is_known_external_video_url(url) {
-            InitialProviderDecision::YtDlp
         } else {
             InitialProviderDecision::NeedsHead
         }
     }
 
     pub fn after_head(&self, url: &str, metadata: &ContentMetadata) -> ResolvedProviderDecision {
-        if should_use_ytdlp_after_head(url, metadata.content_type.as_deref()) {
+        let registry = self.registry();
+        if use_ytdlp_after_head(&registry, url, metadata.content_type.as_deref()) {
             return ResolvedProviderDecision::YtDlp;
         }
+        let forced_http = registry.route_for(url) == Some(PlatformRoute::Http);
+        if !forced_http
+            && metadata
+                .content_type
+                .as_deref()
+                .is_some_and(|ct| ct.to_lowercase().contains("application/dash+xml"))
+        {
+            return ResolvedProviderDecision::Dash;
+        }
 
         match metadata.content_length {
             Some(size) if size >= self.large_file_threshold_bytes => {
@@ -65,13 +100,29 @@ pub fn is_m3u8_url(url: &str) -> bool {
         || url.contains("master") && url.contains("m3u8")
 }
 
+/// 路径以 .mpd 结尾的 DASH 清单
+pub fn is_dash_url(url: &str) -> bool {
+    url::Url::parse(url)
+        .map(|parsed| parsed.path().to_lowercase().ends_with(".mpd"))
+        .unwrap_or(false)
+}
+
+/// 平台规则要求走 yt-dlp 的地址
 pub fn is_known_external_video_url(url: &str) -> bool {
-    !matches!(detect_platform(url), SourcePlatform::Generic)
+    platform_registry::current().route_for(url) == Some(PlatformRoute::Ytdlp)
 }
 
 pub fn should_use_ytdlp_after_head(url: &str, content_type: Option<&str>) -> bool {
-    if is_known_external_video_url(url) {
-        return true;
+    use_ytdlp_after_head(&platform_registry::current(), url, content_type)
+}
+
+fn use_ytdlp_after_head(
+    registry: &PlatformRegistry,
+    url: &str,
+    content_type: Option<&str>,
+) -> bool {
+    if let Some(route) = registry.route_for(url) {
+        return route == PlatformRoute::Ytdlp;
     }
     if is_direct_media_url(url) {
         return false;
@@ -85,7 +136,14 @@ pub fn should_use_ytdlp_after_head(url: &str, content_type: Option<&str>) -> boo
 }
 
 pub fn should_probe_with_ytdlp_for_info(url: &str) -> bool {
-    !is_m3u8_url(url) && (is_known_external_video_url(url) || !is_direct_media_url(url))
+    probe_with_ytdlp_for_info(&platform_registry::current(), url)
+}
+
+fn probe_with_ytdlp_for_info(registry: &PlatformRegistry, url: &str) -> bool {
+    if let Some(route) = registry.route_for(url) {
+        return route == PlatformRoute::Ytdlp;
+    }
+    !is_m3u8_url(url) && !is_dash_url(url) && !is_direct_media_url(url)
 }
 
 #[cfg(test)]
@@ -102,6 +160,33 @@ mod tests {
         );
     }
 
+    #[test]
+    fn routes_dash_manifests_before_and_after_head() {
+        let router = DownloadProviderRouter::new(50 * 1024 * 1024);
+
+        assert_eq!(
+            router.initial_decision("https://cdn.example.com/vod/manifest.MPD?token=abc"),
+            InitialProviderDecision::Dash
+        );
+        assert_eq!(
+            router.initial_decision("https://cdn.example.com/mpd/video.mp4"),
+            InitialProviderDecision::NeedsHead
+        );
+        assert_eq!(
+            router.after_head(
+                "https://cdn.example.com/manifest?id=42",
+                &ContentMetadata {
+                    content_length: Some(2048),
+                    content_type: Some("application/dash+xml".into()),
+                },
+            ),
+            ResolvedProviderDecision::Dash
+        );
+        assert!(!should_probe_with_ytdlp_for_info(
+            "https://cdn.example.com/vod/manifest.mpd"
+        ));
+    }
+
     #[test]
     fn routes_known_social_hosts_to_ytdlp_before_head() {
         let router = DownloadProviderRouter::new(50 * 1024 * 1024);
@@ -182,6 +267,56 @@ mod tests {
         );
     }
 
+    #[test]
+    fn configured_platform_routes_override_url_heuristics() {
+        let rule = |id: &str, host: &str, route| platform_registry::PlatformRule {
+            id: id.to_string(),
+            display_name: id.to_string(),
+            hosts: vec![host.to_string()],
+            route,
+            ytdlp_args: Vec::new(),
+            default_quality: None,
+            max_concurrent: None,
+        };
+        let registry = Arc::new(PlatformRegistry::new(&[
+            rule("vimeo", "vimeo.com", PlatformRoute::Ytdlp),
+            rule("courses", "learn.corp.example", PlatformRoute::M3u8),
+            rule("files", "files.corp.example", PlatformRoute::Http),
+        ]));
+        let router = DownloadProviderRouter::new(50 * 1024 * 1024).with_registry(registry.clone());
+
+        assert_eq!(
+            router.initial_decision("https://player.vimeo.com/video/42"),
+            InitialProviderDecision::YtDlp
+        );
+        assert_eq!(
+            router.initial_decision("https://learn.corp.example/lesson/7"),
+            InitialProviderDecision::M3u8
+        );
+        assert_eq!(
+            router.initial_decision("https://files.corp.example/export/manifest.mpd"),
+            InitialProviderDecision::NeedsHead
+        );
+        assert_eq!(
+            router.after_head(
+                "https://files.corp.example/page",
+                &ContentMetadata {
+                    content_length: Some(1024),
+                    content_type: Some("text/html".into()),
+                },
+            ),
+            ResolvedProviderDecision::HttpSimple
+        );
+        assert!(!probe_with_ytdlp_for_info(
+            &registry,
+            "https://learn.corp.example/lesson/7"
+        ));
+        assert_eq!(
+            router.initial_decision("https://www.youtube.com/watch?v=abc"),
+            InitialProviderDecision::YtDlp
+        );
+    }
+
     #[test]
     fn info_probe_keeps_m3u8_and_direct_media_on_native_path() {
         assert!(!should_probe_with_ytdlp_for_info(
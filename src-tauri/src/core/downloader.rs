@@ -7,72 +7,11 @@
 //! - 速度监控
 //! - 错误重试
 
-#[derive(Clone)]
-pub struct BandwidthController {
-    limit: Arc<RwLock<Option<u64>>>,
-    state: Arc<Mutex<BandwidthState>>,
-}
-
-#[derive(Debug)]
-struct BandwidthState {
-    window_start: Instant,
-    bytes_in_window: u64,
-}
-
-impl BandwidthState {
-    fn new() -> Self {
-        Self {
-            window_start: Instant::now(),
-            bytes_in_window: 0,
-        }
-    }
-}
-
-impl BandwidthController {
-    fn from_parts(limit: Arc<RwLock<Option<u64>>>, state: Arc<Mutex<BandwidthState>>) -> Self {
-        Self { limit, state }
-    }
-
-    pub fn new() -> Self {
-        Self::from_parts(
-            Arc::new(RwLock::new(None)),
-            Arc::new(Mutex::new(BandwidthState::new())),
-        )
-    }
-
-    pub fn limit_handle(&self) -> Arc<RwLock<Option<u64>>> {
-        Arc::clone(&self.limit)
-    }
-
-    pub async fn throttle(&self, bytes: u64) {
-        let limit_value = *self.limit.read().await;
-        if let Some(limit) = limit_value {
-            if limit == 0 {
-                return;
-            }
-            let mut state = self.state.lock().await;
-            let elapsed = state.window_start.elapsed();
-            if elapsed >= Duration::from_secs(1) {
-                state.window_start = Instant::now();
-                state.bytes_in_window = 0;
-            }
-            state.bytes_in_window += bytes;
-            if state.bytes_in_window > limit {
-                let excess = state.bytes_in_window - limit;
-                let sleep_secs = excess as f64 / limit as f64;
-                drop(state);
-                sleep(Duration::from_secs_f64(sleep_secs)).await;
-            }
-        }
-    }
-}
-
 use anyhow::Result;
 use futures_util::StreamExt;
-use reqwest::Client;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::sync::{
     atomic::{AtomicBool, AtomicUsize, Ordering},
     Arc,
@@ -80,18 +19,30 @@ use std::sync::{
 use std::time::{Duration, Instant};
 use tokio::fs::File;
 use tokio::io::AsyncWriteExt;
-use tokio::sync::{mpsc, Mutex, RwLock, Semaphore};
+use tokio::sync::{mpsc, RwLock, Semaphore};
 use tokio::time::sleep;
 use uuid::Uuid;
 
+use crate::core::audio_extract;
+pub use crate::core::bandwidth::BandwidthController;
+use crate::core::cookie_jar::CookieJar;
+use crate::core::credentials::CredentialStore;
+use crate::core::dash_downloader::DashDownloader;
 use crate::core::download_provider::{
     ContentMetadata, DownloadProviderRouter, InitialProviderDecision, ResolvedProviderDecision,
 };
-use crate::core::m3u8_downloader::{M3U8Downloader, M3U8DownloaderConfig};
+use crate::core::hls_variant::{HlsVariant, RenditionPreferences, VariantPolicy};
+use crate::core::http_client::{HttpClientHandle, HttpClientOptions};
+use crate::core::m3u8_downloader::{LiveRecordingLimits, M3U8Downloader, M3U8DownloaderConfig};
+use crate::core::mirror_pool::{MirrorPool, MirrorStats};
 use crate::core::models::*;
+use crate::core::proxy::ProxySettings;
+use crate::core::remote_filename::{resolve_response_filename, unique_filename, FilenameOrigin};
+use crate::core::request_headers::HeaderRules;
 use crate::core::resume_downloader::{
     ResumeDownloader, ResumeDownloaderConfig, ResumeInfo, ResumeProgressCallback,
 };
+use crate::core::youtube_downloader::YoutubeDownloadFormat;
 use crate::core::ytdlp_downloader::YtDlpDownloader;
 use directories::ProjectDirs;
 use sha2::{Digest, Sha256};
@@ -113,6 +64,12 @@ pub struct DownloaderConfig {
     pub user_agent: String,
     /// 是否启用断点续传
     pub resume_enabled: bool,
+    /// 代理设置（None 时沿用系统代理环境变量）
+    #[serde(default)]
+    pub proxy: Option<ProxySettings>,
+    /// 全局及按主机匹配的请求头
+    #[serde(default)]
+    pub header_rules: HeaderRules,
 }
 
 impl Default for DownloaderConfig {
@@ -125,6 +82,8 @@ impl Default for DownloaderConfig {
             buffer_size: 64 * 1024, // 64KB 缓冲区
             user_agent: "VideoDownloaderPro/1.0.0".to_string(),
             resume_enabled: true,
+            proxy: None,
+            header_rules: HeaderRules::default(),
         }
     }
 }
@@ -148,6 +107,27 @@ pub struct DownloadStats {
     pub start_time: chrono::DateTime<chrono::Utc>,
     /// 最后更新时间
     pub last_update: chrono::DateTime<chrono::Utc>,
+    /// 多镜像下载时各镜像的贡献（单一地址时为空）
+    #[serde(default)]
+    pub sources: Vec<MirrorStats>,
+    /// 按响应头改名后的完整文件路径（未改名时为空）
+    #[serde(default)]
+    pub resolved_path: Option<String>,
+    /// master playlist 选中的档位（仅 HLS 任务在选定时上报一次）
+    #[serde(default)]
+    pub hls_variant: Option<HlsVariant>,
+    /// 直播录制进度（录制时 `progress` 恒为 0）
+    #[serde(default)]
+    pub recording: Option<LiveRecordingProgress>,
+    /// 点播流完成时被跳过的片段（仅在下载结束时上报一次，空列表表示没有缺口）
+    #[serde(default)]
+    pub segment_gaps: Option<Vec<SegmentGap>>,
+    /// yt-dlp 实际选中的格式（开始下载前上报一次）
+    #[serde(default)]
+    pub selected_format: Option<SelectedFormat>,
+    /// yt-dlp 写在视频旁的附属文件（仅在下载结束时上报一次）
+    #[serde(default)]
+    pub sidecar_files: Option<Vec<SidecarFile>>,
 }
 
 impl Default for DownloadStats {
@@ -162,6 +142,13 @@ impl Default for DownloadStats {
             status_hint: None,
             start_time: now,
             last_update: now,
+            sources: Vec::new(),
+            resolved_path: None,
+            hls_variant: None,
+            recording: None,
+            segment_gaps: None,
+            selected_format: None,
+            sidecar_files: None,
         }
     }
 }
@@ -171,8 +158,41 @@ impl Default for DownloadStats {
 pub struct DownloadTask {
     pub id: String,
     pub url: String,
+    /// 同一文件的备用镜像地址（按优先级排序，不含 `url`）
+    #[serde(default)]
+    pub mirrors: Vec<String>,
     pub output_path: String,
     pub filename: String,
+    /// 文件名是否可按 Content-Disposition / Content-Type 改写
+    #[serde(default)]
+    pub filename_origin: FilenameOrigin,
+    /// HLS master playlist 的档位选择策略
+    #[serde(default)]
+    pub variant_policy: VariantPolicy,
+    /// yt-dlp 的格式选择
+    #[serde(default)]
+    pub ytdlp_format: YoutubeDownloadFormat,
+    /// yt-dlp 合并音视频时的输出容器（None 为 mp4）
+    #[serde(default)]
+    pub ytdlp_merge_format: Option<String>,
+    /// yt-dlp 需要写出的字幕、缩略图、简介等附属文件
+    #[serde(default)]
+    pub ytdlp_sidecars: YtDlpSidecarOptions,
+    /// 仅保留音频：yt-dlp 用 `-x` 直接产出，HTTP/HLS/DASH 在提交后用 ffmpeg 提取
+    #[serde(default)]
+    pub audio_extraction: Option<AudioExtraction>,
+    /// 任务未指定 `audio_extraction` 时 yt-dlp 使用的全局默认
+    #[serde(default)]
+    pub ytdlp_audio_extraction: Option<AudioExtraction>,
+    /// 直播 HLS 的录制上限
+    #[serde(default)]
+    pub live_limits: LiveRecordingLimits,
+    /// HLS/DASH 片段重试耗尽后最多容忍跳过的个数（0 表示任何缺失都失败）
+    #[serde(default)]
+    pub max_missing_segments: usize,
+    /// 需要一并下载的 HLS 独立音轨/字幕
+    #[serde(default)]
+    pub rendition_preferences: RenditionPreferences,
     pub status: TaskStatus,
     pub stats: DownloadStats,
     pub error_message: Option<String>,
@@ -187,8 +207,19 @@ impl DownloadTask {
         Self {
             id: Uuid::new_v4().to_string(),
             url,
+            mirrors: Vec::new(),
             output_path,
             filename,
+            filename_origin: FilenameOrigin::Fixed,
+            variant_policy: VariantPolicy::default(),
+            ytdlp_format: YoutubeDownloadFormat::default(),
+            ytdlp_merge_format: None,
+            ytdlp_sidecars: YtDlpSidecarOptions::default(),
+            audio_extraction: None,
+            ytdlp_audio_extraction: None,
+            live_limits: LiveRecordingLimits::default(),
+            max_missing_segments: 0,
+            rendition_preferences: RenditionPreferences::default(),
             status: TaskStatus::Pending,
             stats: DownloadStats {
                 speed: 0.0,
@@ -199,6 +230,13 @@ impl DownloadTask {
                 status_hint: None,
                 start_time: now,
                 last_update: now,
+                sources: Vec::new(),
+                resolved_path: None,
+                hls_variant: None,
+                recording: None,
+                segment_gaps: None,
+                selected_format: None,
+                sidecar_files: None,
             },
             error_message: None,
             retry_count: 0,
@@ -218,12 +256,6 @@ struct DownloadControl {
     effective_pause_flag: Arc<AtomicBool>,
 }
 
-impl Default for BandwidthController {
-    fn default() -> Self {
-        Self::new()
-    }
-}
-
 impl DownloadControl {
     fn new(global_pause: &Arc<AtomicBool>) -> Self {
         let cancel_flag = Arc::new(AtomicBool::new(false));
@@ -239,7 +271,7 @@ impl DownloadControl {
 
 pub struct HttpDownloader {
     config: DownloaderConfig,
-    client: Client,
+    client: HttpClientHandle,
     active_downloads: Arc<RwLock<HashMap<String, DownloadControl>>>,
     semaphore: Arc<Semaphore>,
     max_concurrent: Arc<AtomicUsize>,
@@ -247,19 +279,24 @@ pub struct HttpDownloader {
     is_paused: Arc<AtomicBool>,
     resume_downloader: Arc<ResumeDownloader>,
     m3u8_downloader: Arc<M3U8Downloader>,
+    dash_downloader: Arc<DashDownloader>,
     ytdlp_downloader: Arc<YtDlpDownloader>,
     provider_router: Arc<DownloadProviderRouter>,
     bandwidth_controller: BandwidthController,
+    /// 仅音频提取使用的 ffmpeg；为空时按 external_tools 解析
+    ffmpeg_path: Option<PathBuf>,
 }
 
 impl HttpDownloader {
     /// 创建新的下载器实例
     pub fn new(config: DownloaderConfig) -> Result<Self> {
         let bandwidth_controller = BandwidthController::new();
-        let client = Client::builder()
-            .timeout(Duration::from_secs(config.timeout))
-            .user_agent(&config.user_agent)
-            .build()?;
+        let client = HttpClientHandle::new(HttpClientOptions {
+            timeout: Duration::from_secs(config.timeout),
+            user_agent: config.user_agent.clone(),
+            proxy: config.proxy.clone(),
+            headers: config.header_rules.clone(),
+        })?;
 
         let resume_dir = Self::resolve_resume_dir();
 
@@ -275,6 +312,7 @@ impl HttpDownloader {
             retry_delay: Duration::from_secs(2),
             resume_info_dir: resume_dir,
             server_cache_ttl: Duration::from_secs(24 * 60 * 60), // 24小时
+            min_split_size: 1024 * 1024,                         // 拆分后每段至少 1MB
         };
 
         // 创建ResumeDownloader实例
@@ -290,11 +328,19 @@ impl HttpDownloader {
             user_agent: config.user_agent.clone(),
             temp_dir: std::env::temp_dir().join("video_downloader_m3u8"),
             keep_temp_files: false,
+            ..M3U8DownloaderConfig::default()
         };
 
         // 创建M3U8Downloader实例
-        let m3u8_downloader = M3U8Downloader::new(m3u8_config)?;
-        let ytdlp_downloader = YtDlpDownloader::default_with_user_agent(config.user_agent.clone());
+        let m3u8_downloader = Arc::new(
+            M3U8Downloader::with_http_client(m3u8_config, client.clone())?
+                .with_bandwidth_controller(bandwidth_controller.clone()),
+        );
+        // DASH 分片复用 M3U8Downloader 的下载、取消和进度通道
+        let dash_downloader = DashDownloader::new(client.clone(), Arc::clone(&m3u8_downloader));
+        let ytdlp_downloader = YtDlpDownloader::default_with_user_agent(config.user_agent.clone())
+            .with_http_client(client.clone())
+            .with_bandwidth_controller(bandwidth_controller.clone());
         let provider_router = DownloadProviderRouter::new(50 * 1024 * 1024);
 
         Ok(Self {
@@ -306,10 +352,12 @@ impl HttpDownloader {
             progress_tx: None,
             is_paused: Arc::new(AtomicBool::new(false)),
             resume_downloader: Arc::new(resume_downloader),
-            m3u8_downloader: Arc::new(m3u8_downloader),
+            m3u8_downloader,
+            dash_downloader: Arc::new(dash_downloader),
             ytdlp_downloader: Arc::new(ytdlp_downloader),
             provider_router: Arc::new(provider_router),
             bandwidth_controller,
+            ffmpeg_path: None,
         })
     }
 
@@ -317,6 +365,43 @@ impl HttpDownloader {
         self.bandwidth_controller.clone()
     }
 
+    pub fn credential_store(&self) -> CredentialStore {
+        self.client.credentials()
+    }
+
+    /// 换成持久化的凭据库（HTTP、断点续传、M3U8 与 yt-dlp 共用同一个客户端句柄）
+    pub fn set_credential_store(&self, credentials: CredentialStore) {
+        self.client.set_credentials(credentials);
+    }
+
+    /// 与下载共用代理、请求头、凭据和 cookie 的 yt-dlp（用于探测视频信息）
+    pub fn ytdlp_downloader(&self) -> Arc<YtDlpDownloader> {
+        self.ytdlp_downloader.clone()
+    }
+
+    pub fn cookie_jar(&self) -> CookieJar {
+        self.client.cookies()
+    }
+
+    /// 换成持久化的 cookie jar（同样由所有下载器与 yt-dlp 共用）
+    pub fn set_cookie_jar(&self, cookies: CookieJar) {
+        self.client.set_cookies(cookies);
+    }
+
+    /// 重建共享的 HTTP 客户端（代理/超时/UA 变更后调用，无需重启）
+    pub fn apply_client_options(&self, options: HttpClientOptions) -> Result<bool> {
+        let proxy = options
+            .proxy
+            .as_ref()
+            .map(ProxySettings::redacted_url)
+            .unwrap_or_else(|| "none".to_string());
+        let changed = self.client.reconfigure(options)?;
+        if changed {
+            tracing::info!("🌐 HTTP client rebuilt (proxy: {})", proxy);
+        }
+        Ok(changed)
+    }
+
     pub async fn load_resume_info(&self, resume_key: &str) -> Option<ResumeInfo> {
         self.resume_downloader
             .load_resume_info(resume_key)
@@ -465,6 +550,23 @@ impl HttpDownloader {
             result.is_ok()
         );
 
+        // 视频已提交：提取失败或被取消只记警告，任务仍按完成处理，重试不会重新下载
+        if result.is_ok() {
+            task.error_message = match self
+                .extract_audio_after_commit(&mut task, &control.cancel_flag)
+                .await
+            {
+                Ok(()) => None,
+                Err(e) if e.to_string() == "download_cancelled" => {
+                    Some("Audio extraction cancelled, kept the video".to_string())
+                }
+                Err(e) => Some(format!("Audio extraction failed, kept the video: {}", e)),
+            };
+            if let Some(warning) = &task.error_message {
+                tracing::warn!("⚠️ [DOWNLOAD_ENTRY] {}: {}", task.filename, warning);
+            }
+        }
+
         // 清理活跃下载记录
         {
             let mut downloads = self.active_downloads.write().await;
@@ -509,6 +611,61 @@ impl HttpDownloader {
         Ok(task)
     }
 
+    /// 仅音频模式：下载提交后用 ffmpeg 提取音轨，提取进度以 `Committing` 上报。
+    /// 文件已是目标格式（yt-dlp `-x` 的产物或源文件本身）时跳过；失败时保留视频不动。
+    async fn extract_audio_after_commit(
+        &self,
+        task: &mut DownloadTask,
+        cancel_flag: &AtomicBool,
+    ) -> Result<()> {
+        let Some(extraction) = task.audio_extraction.clone() else {
+            return Ok(());
+        };
+        let video_path = Path::new(&task.output_path).join(&task.filename);
+        if audio_extract::already_extracted(&video_path, extraction.format) {
+            return Ok(());
+        }
+        let audio_path = audio_extract::audio_output_path(&video_path, extraction.format);
+
+        let progress_tx = self.progress_tx.clone();
+        let task_id = task.id.clone();
+        let mut stats = DownloadStats {
+            downloaded_bytes: task.stats.downloaded_bytes,
+            total_bytes: task.stats.total_bytes,
+            status_hint: Some(TaskStatus::Committing),
+            start_time: task.stats.start_time,
+            ..DownloadStats::default()
+        };
+        audio_extract::extract_audio(
+            self.ffmpeg_path.as_deref(),
+            &video_path,
+            &audio_path,
+            &extraction,
+            cancel_flag,
+            |progress| {
+                stats.progress = progress;
+                stats.last_update = chrono::Utc::now();
+                if let Some(tx) = progress_tx.as_ref() {
+                    let _ = tx.send((task_id.clone(), stats.clone()));
+                }
+            },
+        )
+        .await?;
+
+        if let Err(e) = tokio::fs::remove_file(&video_path).await {
+            tracing::warn!(
+                "Failed to remove source video {:?} after audio extraction: {}",
+                video_path,
+                e
+            );
+        }
+        if let Some(name) = audio_path.file_name().and_then(|name| name.to_str()) {
+            task.filename = name.to_string();
+        }
+        task.stats.status_hint = Some(TaskStatus::Committing);
+        Ok(())
+    }
+
     /// 智能下载策略选择器
     /// 根据文件类型和大小自动选择最适合的下载方法
     async fn smart_download(
@@ -552,6 +709,10 @@ impl HttpDownloader {
                 tracing::info!("🟢 [SMART_DOWNLOAD] M3U8 URL detected, using M3U8 downloader");
                 return self.download_with_m3u8(task, cancel_flag, pause_flag).await;
             }
+            InitialProviderDecision::Dash => {
+                tracing::info!("🟢 [SMART_DOWNLOAD] DASH manifest detected, using DASH downloader");
+                return self.download_with_dash(task, cancel_flag, pause_flag).await;
+            }
             InitialProviderDecision::YtDlp => {
                 tracing::info!("🟢 [SMART_DOWNLOAD] Social/video webpage detected, using yt-dlp");
                 return self
@@ -567,14 +728,24 @@ impl HttpDownloader {
             task.id,
             task.url
         );
-        let metadata = match self.get_content_metadata(&task.url).await {
-            Ok(metadata) => metadata,
+        let (metadata, headers) = match self.get_content_metadata(&task.url).await {
+            Ok(probe) => probe,
             Err(e) => {
                 tracing::error!(
                     "🔴 [SMART_DOWNLOAD] ❌ Failed to get content metadata for task {}: {}",
                     task.id,
                     e
                 );
+                if !task.mirrors.is_empty() {
+                    tracing::info!(
+                        "🟢 [SMART_DOWNLOAD] Primary URL probe failed, trying {} mirror(s) for task {}",
+                        task.mirrors.len(),
+                        task.id
+                    );
+                    return self
+                        .download_with_resume_downloader(task, cancel_flag, pause_flag)
+                        .await;
+                }
                 tracing::info!(
                     "🟢 [SMART_DOWNLOAD] Falling back to download_with_resume for task {}",
                     task.id
@@ -585,7 +756,14 @@ impl HttpDownloader {
             }
         };
 
-        match self.provider_router.after_head(&task.url, &metadata) {
+        let decision = self.provider_router.after_head(&task.url, &metadata);
+        if !matches!(
+            decision,
+            ResolvedProviderDecision::YtDlp | ResolvedProviderDecision::Dash
+        ) {
+            self.apply_response_filename(task, &headers).await;
+        }
+        match decision {
             ResolvedProviderDecision::YtDlp => {
                 tracing::info!(
                     "🟢 [SMART_DOWNLOAD] HTML webpage detected after HEAD, using yt-dlp"
@@ -594,6 +772,10 @@ impl HttpDownloader {
                     .download_with_ytdlp(task, cancel_flag, pause_flag)
                     .await;
             }
+            ResolvedProviderDecision::Dash => {
+                tracing::info!("🟢 [SMART_DOWNLOAD] DASH manifest detected after HEAD");
+                return self.download_with_dash(task, cancel_flag, pause_flag).await;
+            }
             ResolvedProviderDecision::HttpResumable if metadata.content_length.is_none() => {
                 tracing::warn!("🟡 [SMART_DOWNLOAD] No content length returned for task {}, using resume downloader", task.id);
                 return self
@@ -636,6 +818,12 @@ impl HttpDownloader {
                     Instant::now(),
                 )
                 .await;
+                if !task.mirrors.is_empty() {
+                    tracing::info!("多镜像任务：使用ResumeDownloader以便镜像故障切换");
+                    return self
+                        .download_with_resume_downloader(task, cancel_flag, pause_flag)
+                        .await;
+                }
                 tracing::info!("小文件检测：使用传统HTTP下载");
                 self.download_with_resume(task, cancel_flag, pause_flag)
                     .await
@@ -671,8 +859,11 @@ impl HttpDownloader {
             })
         };
 
-        let task_url = task.url.clone();
+        let sources = Arc::new(MirrorPool::new(
+            std::iter::once(task.url.clone()).chain(task.mirrors.iter().cloned()),
+        ));
         let resume_key = self.build_resume_key(task);
+        let task_id = task.id.clone();
 
         // 读取已有断点信息，确保续传时进度从已下载位置开始
         if let Ok(Some(resume_info)) = self.resume_downloader.load_resume_info(&resume_key).await {
@@ -693,9 +884,10 @@ impl HttpDownloader {
             }
         }
 
-        let mut resume_future = Box::pin(self.resume_downloader.download_with_resume(
+        let mut resume_future = Box::pin(self.resume_downloader.download_from_mirrors(
             &resume_key,
-            &task_url,
+            &task_id,
+            Arc::clone(&sources),
             Path::new(&output_path_str),
             task.stats.total_bytes,
             Some(progress_callback),
@@ -728,6 +920,9 @@ impl HttpDownloader {
                             if total_hint.is_some() && downloaded >= total {
                                 task.status = TaskStatus::Committing;
                             }
+                            if sources.len() > 1 {
+                                task.stats.sources = sources.stats();
+                            }
                             self.update_progress(task, downloaded, total, start_time).await;
                         }
                         None => {
@@ -750,6 +945,9 @@ impl HttpDownloader {
                     };
                     downloaded = resume_info.downloaded_total.max(downloaded);
                     task.status = TaskStatus::Committing;
+                    if sources.len() > 1 {
+                        task.stats.sources = sources.stats();
+                    }
                     self.update_progress(task, downloaded, final_total, start_time).await;
                     task.stats.total_bytes = Some(final_total);
                     break;
@@ -761,16 +959,55 @@ impl HttpDownloader {
         Ok(())
     }
 
-    async fn get_content_metadata(&self, url: &str) -> Result<ContentMetadata> {
+    /// 按响应头改用更合适的文件名；只改自动生成、且还没有写入任何数据的文件名，
+    /// 保证续传时文件名不变
+    async fn apply_response_filename(
+        &self,
+        task: &mut DownloadTask,
+        headers: &reqwest::header::HeaderMap,
+    ) -> bool {
+        if task.filename_origin == FilenameOrigin::Fixed || task.stats.downloaded_bytes > 0 {
+            return false;
+        }
+        let Some(filename) =
+            resolve_response_filename(&task.filename, task.filename_origin, headers)
+        else {
+            return false;
+        };
+        let resume_key = self.build_resume_key(task);
+        if matches!(
+            self.resume_downloader.load_resume_info(&resume_key).await,
+            Ok(Some(_))
+        ) {
+            return false;
+        }
+
+        let output_dir = Path::new(&task.output_path);
+        let filename = unique_filename(output_dir, &filename);
+        tracing::info!(
+            "📝 Task {} filename resolved from response headers: {} -> {}",
+            task.id,
+            task.filename,
+            filename
+        );
+        task.stats.resolved_path = Some(output_dir.join(&filename).to_string_lossy().to_string());
+        task.filename = filename;
+        task.filename_origin = FilenameOrigin::Fixed;
+        true
+    }
+
+    async fn get_content_metadata(
+        &self,
+        url: &str,
+    ) -> Result<(ContentMetadata, reqwest::header::HeaderMap)> {
         tracing::info!("🔍 [GET_CONTENT_LENGTH] Sending HEAD request to: {}", url);
 
         // 使用较短的超时时间，防止阻塞
-        let client = Client::builder()
-            .timeout(Duration::from_secs(10))
-            .user_agent(&self.config.user_agent)
-            .build()?;
-
-        let response = match client.head(url).send().await {
+        let response = match self
+            .client
+            .send(self.client.head(url).timeout(Duration::from_secs(10)))
+            .await
+        {
             Ok(resp) => {
                 tracing::info!(
                     "🔍 [GET_CONTENT_LENGTH] HEAD response status: {}",
@@ -804,10 +1041,13 @@ impl HttpDownloader {
             "🔍 [GET_CONTENT_LENGTH] Content-Length: {:?}",
             content_length
         );
-        Ok(ContentMetadata {
-            content_length,
-            content_type,
-        })
+        Ok((
+            ContentMetadata {
+                content_length,
+                content_type,
+            },
+            response.headers().clone(),
+        ))
     }
 
     /// 格式化字节大小为可读格式
@@ -851,14 +1091,73 @@ impl HttpDownloader {
         tracing::info!("使用M3U8Downloader开始流媒体下载: {}", task.filename);
 
         // 调用M3U8Downloader的下载方法
-        self.m3u8_downloader
-            .download_m3u8(&task.id, &task.url, &output_path_str, pause_flag)
+        let written_path = self
+            .m3u8_downloader
+            .download_m3u8(
+                &task.id,
+                &task.url,
+                &output_path_str,
+                &task.variant_policy,
+                &task.live_limits,
+                task.max_missing_segments,
+                &task.rendition_preferences,
+                pause_flag,
+            )
             .await?;
+        // fMP4 流或封装独立音轨后会改写扩展名，完成事件按实际文件路径上报
+        if written_path != output_path_str {
+            if let Some(filename) = Path::new(&written_path).file_name() {
+                task.filename = filename.to_string_lossy().to_string();
+                task.stats.resolved_path = Some(written_path);
+            }
+        }
 
         tracing::info!("M3U8流媒体下载完成: {}", task.filename);
         Ok(())
     }
 
+    /// 使用DashDownloader下载 MPEG-DASH 流
+    async fn download_with_dash(
+        &self,
+        task: &mut DownloadTask,
+        cancel_flag: Arc<AtomicBool>,
+        pause_flag: Arc<AtomicBool>,
+    ) -> Result<()> {
+        let full_path = Path::new(&task.output_path).join(&task.filename);
+        let output_path_str = full_path.to_string_lossy().to_string();
+        if cancel_flag.load(Ordering::Relaxed) || pause_flag.load(Ordering::Relaxed) {
+            return Err(if cancel_flag.load(Ordering::Relaxed) {
+                anyhow::anyhow!("download_cancelled")
+            } else {
+                anyhow::anyhow!("download_paused")
+            });
+        }
+
+        tracing::info!("使用DashDownloader开始流媒体下载: {}", task.filename);
+        let written_path = self
+            .dash_downloader
+            .download_dash(
+                &task.id,
+                &task.url,
+                &output_path_str,
+                &task.variant_policy,
+                task.max_missing_segments,
+                &task.rendition_preferences,
+                pause_flag,
+            )
+            .await?;
+        // .mpd 文件名按 mimeType 改写扩展名，完成事件按实际文件路径上报
+        if written_path != output_path_str {
+            if let Some(filename) = Path::new(&written_path).file_name() {
+                task.filename = filename.to_string_lossy().to_string();
+                task.stats.resolved_path = Some(written_path);
+            }
+        }
+
+        tracing::info!("DASH流媒体下载完成: {}", task.filename);
+        Ok(())
+    }
+
     async fn download_with_ytdlp(
         &self,
         task: &mut DownloadTask,
@@ -927,7 +1226,7 @@ impl HttpDownloader {
 
         // 发送请求
         tracing::info!("🟣 [DOWNLOAD_WITH_RESUME] Sending HTTP GET request...");
-        let response = match request.send().await {
+        let response = match self.client.send(request).await {
             Ok(resp) => {
                 tracing::info!(
                     "🟣 [DOWNLOAD_WITH_RESUME] ✅ HTTP response received: status={}",
@@ -960,6 +1259,15 @@ impl HttpDownloader {
             existing_size = 0;
         }
 
+        // HEAD 没有给出文件名时，GET 响应头还有一次机会（尚未写入数据时）
+        let full_path =
+            if !range_requested && self.apply_response_filename(task, response.headers()).await {
+                self.update_progress(task, 0, 0, Instant::now()).await;
+                Path::new(&task.output_path).join(&task.filename)
+            } else {
+                full_path
+            };
+
         // 获取内容长度
         let content_length = response.content_length();
         tracing::info!(
@@ -1038,7 +1346,9 @@ impl HttpDownloader {
                 tracing::info!("[DOWNLOAD_TRACE] Received first chunk for task {}", task.id);
             }
             file.write_all(&chunk).await?;
-            self.bandwidth_controller.throttle(chunk.len() as u64).await;
+            self.bandwidth_controller
+                .throttle_task(&task.id, &task.url, chunk.len() as u64)
+                .await;
             downloaded += chunk.len() as u64;
 
             // 更新进度（限制更新频率为200ms，提供更平滑的进度显示）
@@ -1258,6 +1568,11 @@ impl HttpDownloader {
         Ok(())
     }
 
+    /// 结束直播录制：不再刷新播放列表，合并已录到的片段后正常完成
+    pub fn stop_live_recording(&self, task_id: &str) -> bool {
+        self.m3u8_downloader.stop_recording(task_id)
+    }
+
     /// 获取活跃下载数量
     pub async fn active_download_count(&self) -> usize {
         self.active_downloads.read().await.len()
@@ -1332,9 +1647,11 @@ impl Clone for HttpDownloader {
             is_paused: Arc::clone(&self.is_paused),
             resume_downloader: Arc::clone(&self.resume_downloader),
             m3u8_downloader: Arc::clone(&self.m3u8_downloader),
+            dash_downloader: Arc::clone(&self.dash_downloader),
             ytdlp_downloader: Arc::clone(&self.ytdlp_downloader),
             provider_router: Arc::clone(&self.provider_router),
             bandwidth_controller: self.bandwidth_controller.clone(),
+            ffmpeg_path: self.ffmpeg_path.clone(),
         }
     }
 }
@@ -1468,6 +1785,8 @@ mod tests {
             buffer_size: 32 * 1024,
             user_agent: "TestAgent/1.0".to_string(),
             resume_enabled: true,
+            proxy: None,
+            header_rules: HeaderRules::default(),
         };
 
         assert_eq!(config.max_concurrent, 5);
@@ -1744,6 +2063,13 @@ mod test_support {
             return Ok(());
         }
 
+        if let Some(size) = path.strip_prefix("/attachment/") {
+            let size: usize = size.parse().unwrap_or(0);
+            let header = "Content-Disposition: attachment; filename=\"lesson.bin\"; filename*=UTF-8''%E7%AC%AC1%E8%AF%BE.mp4\r\n";
+            write_response_with_headers(socket, 200, "OK", &vec![b'c'; size], header).await?;
+            return Ok(());
+        }
+
         if let Some(size) = path.strip_prefix("/bytes-no-range/") {
             let size: usize = size.parse().unwrap_or(0);
             let data = vec![b'b'; size];
@@ -1792,6 +2118,70 @@ mod integration_tests {
     use tempfile::tempdir;
     use tokio::fs;
 
+    #[tokio::test]
+    async fn test_audio_extraction_failure_keeps_completed_video() {
+        let mut downloader = HttpDownloader::new(DownloaderConfig::default()).unwrap();
+        let server = super::test_support::TestServer::start().await;
+        let temp_dir = tempdir().unwrap();
+        downloader.ffmpeg_path = Some(temp_dir.path().join("missing-ffmpeg"));
+
+        let mut task = DownloadTask::new(
+            format!("{}/bytes/32", server.url()),
+            temp_dir.path().to_string_lossy().to_string(),
+            "clip.mp4".to_string(),
+        );
+        task.audio_extraction = Some(AudioExtraction {
+            format: AudioFormat::Mp3,
+            bitrate_kbps: None,
+        });
+        let result = downloader.download(task).await.unwrap();
+
+        assert_eq!(result.status, TaskStatus::Completed);
+        assert_eq!(result.filename, "clip.mp4");
+        assert!(result
+            .error_message
+            .as_deref()
+            .unwrap_or_default()
+            .contains("ffmpeg_missing"));
+        let video = temp_dir.path().join("clip.mp4");
+        assert_eq!(fs::metadata(&video).await.unwrap().len(), 32);
+        assert!(!temp_dir.path().join("clip.mp3").exists());
+    }
+
+    #[tokio::test]
+    async fn test_generated_filename_follows_content_disposition() {
+        let downloader = HttpDownloader::new(DownloaderConfig::default()).unwrap();
+        let server = super::test_support::TestServer::start().await;
+        let temp_dir = tempdir().unwrap();
+
+        let mut task = DownloadTask::new(
+            format!("{}/attachment/64", server.url()),
+            temp_dir.path().to_string_lossy().to_string(),
+            "64".to_string(),
+        );
+        task.filename_origin = FilenameOrigin::Url;
+        let result = downloader.download(task).await.unwrap();
+
+        assert!(matches!(result.status, TaskStatus::Completed));
+        assert_eq!(result.filename, "第1课.mp4");
+        let expected = temp_dir.path().join("第1课.mp4");
+        assert_eq!(
+            result.stats.resolved_path.as_deref(),
+            Some(expected.to_string_lossy().as_ref())
+        );
+        assert_eq!(fs::metadata(&expected).await.unwrap().len(), 64);
+
+        // 用户指定的文件名不受响应头影响
+        let fixed = DownloadTask::new(
+            format!("{}/attachment/8", server.url()),
+            temp_dir.path().to_string_lossy().to_string(),
+            "mine.bin".to_string(),
+        );
+        let result = downloader.download(fixed).await.unwrap();
+        assert_eq!(result.filename, "mine.bin");
+        assert!(result.stats.resolved_path.is_none());
+    }
+
     #[tokio::test]
     async fn test_small_file_download() {
         let config = DownloaderConfig::default();
@@ -86,6 +86,9 @@ pub enum DownloadError {
         message: String,
         code: Option<u16>,
         is_retryable: bool,
+        /// 服务端 `Retry-After` 给出的等待时间
+        #[serde(default)]
+        retry_after: Option<Duration>,
     },
 
     #[error("Resource exhaustion: {message}")]
@@ -164,6 +167,14 @@ impl DownloadError {
         }
     }
 
+    /// Server-requested delay before the next attempt (`Retry-After`)
+    pub fn retry_after(&self) -> Option<Duration> {
+        match self {
+            Self::Protocol { retry_after, .. } => *retry_after,
+            _ => None,
+        }
+    }
+
     /// Get the backoff multiplier for this error type
     pub fn backoff_multiplier(&self) -> f64 {
         match self {
@@ -573,7 +584,7 @@ impl RetryExecutor {
                     // Success - update statistics and return
                     self.update_success_stats(last_error.as_ref().map(|e| e.category()))
                         .await;
-                    info!(
+                    debug!(
                         "Execution succeeded on attempt {} for {}",
                         attempt, attempt_id
                     );
@@ -634,6 +645,11 @@ impl RetryExecutor {
 
     /// Calculate delay for the next retry attempt
     async fn calculate_delay(&self, error: &DownloadError, attempt: u32) -> Duration {
+        // 服务端明确要求的等待时间优先，不再叠加 jitter
+        if let Some(retry_after) = error.retry_after() {
+            return retry_after.min(self.policy.max_delay);
+        }
+
         let base_delay =
             if let Some(category_policy) = self.policy.category_policies.get(&error.category()) {
                 category_policy.base_delay
@@ -714,6 +730,17 @@ impl RetryExecutor {
     }
 }
 
+/// Parse an HTTP `Retry-After` value (delta-seconds or HTTP-date)
+pub fn parse_retry_after(value: &str) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let at = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+    let remaining = at.signed_duration_since(chrono::Utc::now());
+    Some(remaining.to_std().unwrap_or(Duration::ZERO))
+}
+
 /// Convenience functions for creating common error types
 pub mod errors {
     use super::*;
@@ -754,6 +781,21 @@ pub mod errors {
             message: message.into(),
             code,
             is_retryable,
+            retry_after: None,
+        }
+    }
+
+    /// HTTP 状态码错误：408/425/429 与 5xx 可重试，并携带 `Retry-After`
+    pub fn http_status_error(
+        message: impl Into<String>,
+        code: u16,
+        retry_after: Option<Duration>,
+    ) -> DownloadError {
+        DownloadError::Protocol {
+            message: message.into(),
+            code: Some(code),
+            is_retryable: matches!(code, 408 | 425 | 429 | 500..=599),
+            retry_after,
         }
     }
 
@@ -967,4 +1009,28 @@ mod tests {
         // Second delay should be longer due to exponential backoff
         assert!(delay2 > delay1);
     }
+
+    #[tokio::test]
+    async fn test_retry_after_overrides_backoff() {
+        assert_eq!(parse_retry_after(" 7 "), Some(Duration::from_secs(7)));
+        assert_eq!(
+            parse_retry_after("Wed, 21 Oct 2015 07:28:00 GMT"),
+            Some(Duration::ZERO)
+        );
+        assert_eq!(parse_retry_after("soon"), None);
+
+        let throttled = errors::http_status_error("429", 429, Some(Duration::from_secs(3)));
+        assert!(throttled.is_retryable());
+        assert!(!errors::http_status_error("404", 404, None).is_retryable());
+        assert!(errors::http_status_error("503", 503, None).is_retryable());
+
+        let executor = RetryExecutor::new(RetryPolicy {
+            max_delay: Duration::from_secs(2),
+            ..Default::default()
+        });
+        assert_eq!(
+            executor.calculate_delay(&throttled, 1).await,
+            Duration::from_secs(2)
+        );
+    }
 }
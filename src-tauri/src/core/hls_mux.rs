@@ -0,0 +1,239 @@
+//! HLS 独立音轨/字幕的封装
+//!
+//! 选中的 `#EXT-X-MEDIA` 音轨和字幕下载合并后，用受管的 ffmpeg
+//! (`external_tools::resolve_tool_path("ffmpeg")`) 以 stream copy 封装进视频；
+//! ffmpeg 不可用或封装失败时，调用方改为在视频旁写独立文件。
+
+#![deny(clippy::unwrap_used, clippy::expect_used)]
+
+use crate::core::external_tools::resolve_tool_path;
+use crate::core::hls_variant::RenditionKind;
+use crate::utils::process::hidden_command;
+use anyhow::{bail, Result};
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+
+/// 一条待封装的音轨或字幕
+#[derive(Debug, Clone)]
+pub(crate) struct MuxInput {
+    pub path: PathBuf,
+    pub kind: RenditionKind,
+    pub language: Option<String>,
+}
+
+/// 调用 ffmpeg 把视频和额外音轨/字幕封装为 `output`（MP4，字幕转 mov_text）
+pub(crate) async fn mux_renditions(video: &Path, inputs: &[MuxInput], output: &Path) -> Result<()> {
+    let (ffmpeg, source) = resolve_tool_path("ffmpeg");
+    tracing::info!(
+        "使用 ffmpeg ({:?}) 封装 {} 条音轨/字幕: {}",
+        source,
+        inputs.len(),
+        output.display()
+    );
+
+    let result = hidden_command(&ffmpeg)
+        .args(build_mux_args(video, inputs, output))
+        .kill_on_drop(true)
+        .output()
+        .await;
+    let result = match result {
+        Ok(result) => result,
+        Err(e) => bail!("ffmpeg_missing: 无法启动 {}: {}", ffmpeg.display(), e),
+    };
+    if !result.status.success() {
+        let stderr = String::from_utf8_lossy(&result.stderr);
+        let tail: Vec<&str> = stderr.lines().rev().take(5).collect();
+        bail!(
+            "ffmpeg 封装失败 ({}): {}",
+            result.status,
+            tail.into_iter().rev().collect::<Vec<_>>().join(" | ")
+        );
+    }
+    Ok(())
+}
+
+pub(crate) fn build_mux_args(video: &Path, inputs: &[MuxInput], output: &Path) -> Vec<OsString> {
+    let mut args: Vec<OsString> = ["-y", "-hide_banner", "-loglevel", "error", "-i"]
+        .into_iter()
+        .map(OsString::from)
+        .collect();
+    args.push(video.into());
+    for input in inputs {
+        args.push("-i".into());
+        args.push(input.path.clone().into());
+    }
+
+    // 有独立音轨时视频流里的音频通常是空的或不需要的，只取画面
+    let has_audio = inputs
+        .iter()
+        .any(|input| input.kind == RenditionKind::Audio);
+    args.extend(["-map".into(), "0:v".into()]);
+    if !has_audio {
+        args.extend(["-map".into(), "0:a?".into()]);
+    }
+
+    let (mut audio_index, mut subtitle_index) = (0, 0);
+    let mut metadata = Vec::new();
+    for (offset, input) in inputs.iter().enumerate() {
+        let (selector, index) = match input.kind {
+            RenditionKind::Audio => ("a", &mut audio_index),
+            RenditionKind::Subtitles => ("s", &mut subtitle_index),
+        };
+        args.extend(["-map".into(), format!("{}:{}", offset + 1, selector).into()]);
+        if let Some(language) = &input.language {
+            metadata.push(format!("-metadata:s:{}:{}", selector, index).into());
+            metadata.push(format!("language={}", language).into());
+        }
+        *index += 1;
+    }
+
+    args.extend(["-c".into(), "copy".into(), "-c:s".into(), "mov_text".into()]);
+    args.extend(metadata);
+    args.push(output.into());
+    args
+}
+
+/// 拼接分段的 WebVTT：只保留第一段的文件头，其余段去掉 `WEBVTT` 头块
+pub(crate) fn merge_webvtt(parts: &[String]) -> String {
+    let mut merged = String::new();
+    for (index, part) in parts.iter().enumerate() {
+        let part = part.trim_start_matches('\u{feff}').replace("\r\n", "\n");
+        let body = if index == 0 {
+            part.trim_end().to_string()
+        } else if part.trim_start().starts_with("WEBVTT") {
+            part.split_once("\n\n")
+                .map(|(_, cues)| cues.trim().to_string())
+                .unwrap_or_default()
+        } else {
+            part.trim().to_string()
+        };
+        if body.is_empty() {
+            continue;
+        }
+        if !merged.is_empty() {
+            merged.push_str("\n\n");
+        }
+        merged.push_str(&body);
+    }
+    if !merged.starts_with("WEBVTT") {
+        merged.insert_str(0, "WEBVTT\n\n");
+    }
+    merged.push('\n');
+    merged
+}
+
+/// WebVTT 转 SRT：丢弃文件头、NOTE/STYLE/REGION 块和 cue 设置，重新编号
+pub(crate) fn webvtt_to_srt(vtt: &str) -> String {
+    let normalized = vtt.trim_start_matches('\u{feff}').replace("\r\n", "\n");
+    let mut srt = String::new();
+    let mut number = 0;
+
+    for block in normalized.split("\n\n") {
+        let lines: Vec<&str> = block.lines().filter(|line| !line.is_empty()).collect();
+        let Some(timing_at) = lines.iter().position(|line| line.contains("-->")) else {
+            continue;
+        };
+        let Some((start, rest)) = lines[timing_at].split_once("-->") else {
+            continue;
+        };
+        let end = rest.split_whitespace().next().unwrap_or_default();
+        number += 1;
+        srt.push_str(&format!(
+            "{}\n{} --> {}\n",
+            number,
+            srt_timestamp(start.trim()),
+            srt_timestamp(end)
+        ));
+        for text in &lines[timing_at + 1..] {
+            srt.push_str(text);
+            srt.push('\n');
+        }
+        srt.push('\n');
+    }
+    srt
+}
+
+/// `mm:ss.ttt` / `hh:mm:ss.ttt` -> `hh:mm:ss,ttt`
+fn srt_timestamp(vtt: &str) -> String {
+    let timestamp = vtt.replace('.', ",");
+    if timestamp.matches(':').count() == 1 {
+        format!("00:{}", timestamp)
+    } else {
+        timestamp
+    }
+}
+
+/// 视频旁的独立文件：`<stem>.<label>.<ext>`
+pub(crate) fn sidecar_path(video: &Path, label: &str, extension: &str) -> PathBuf {
+    let stem = video
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_else(|| "video".to_string());
+    let label: String = label
+        .chars()
+        .map(|ch| {
+            if ch.is_alphanumeric() || ch == '-' || ch == '_' {
+                ch
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    video.with_file_name(format!("{}.{}.{}", stem, label, extension))
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::expect_used)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builds_stream_copy_mux_arguments_with_languages() {
+        let inputs = vec![
+            MuxInput {
+                path: PathBuf::from("/tmp/audio_zh.aac"),
+                kind: RenditionKind::Audio,
+                language: Some("zh".to_string()),
+            },
+            MuxInput {
+                path: PathBuf::from("/tmp/subs_en.vtt"),
+                kind: RenditionKind::Subtitles,
+                language: Some("en".to_string()),
+            },
+        ];
+        let args: Vec<String> = build_mux_args(
+            Path::new("/tmp/video.ts"),
+            &inputs,
+            Path::new("/out/lesson.mp4"),
+        )
+        .into_iter()
+        .map(|arg| arg.to_string_lossy().to_string())
+        .collect();
+
+        assert_eq!(
+            args.join(" "),
+            "-y -hide_banner -loglevel error -i /tmp/video.ts -i /tmp/audio_zh.aac -i /tmp/subs_en.vtt \
+             -map 0:v -map 1:a -map 2:s -c copy -c:s mov_text \
+             -metadata:s:a:0 language=zh -metadata:s:s:0 language=en /out/lesson.mp4"
+        );
+    }
+
+    #[test]
+    fn merges_segmented_webvtt_and_converts_to_srt() {
+        let merged = merge_webvtt(&[
+            "WEBVTT\nX-TIMESTAMP-MAP=MPEGTS:900000,LOCAL:00:00:00.000\n\n00:01.000 --> 00:02.500 align:start\n你好\n".to_string(),
+            "WEBVTT\n\nNOTE 第二段\n\n01:02:03.000 --> 01:02:04.000\nline one\nline two\n".to_string(),
+        ]);
+        assert_eq!(merged.matches("WEBVTT").count(), 1);
+        assert!(merged.contains("你好") && merged.contains("line two"));
+
+        assert_eq!(
+            webvtt_to_srt(&merged),
+            "1\n00:00:01,000 --> 00:00:02,500\n你好\n\n2\n01:02:03,000 --> 01:02:04,000\nline one\nline two\n\n"
+        );
+        assert_eq!(
+            sidecar_path(Path::new("/out/lesson.mp4"), "zh-Hans", "srt"),
+            PathBuf::from("/out/lesson.zh-Hans.srt")
+        );
+    }
+}
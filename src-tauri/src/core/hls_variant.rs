@@ -0,0 +1,362 @@
+//! HLS 码率档位与音轨/字幕选择
+//!
+//! 课程站点给出的 .m3u8 大多是 master playlist：每个 `#EXT-X-STREAM-INF`
+//! 后面跟一个子播放列表地址，而不是 TS 片段。解析由 `parsers::m3u8_parser`
+//! 完成，这里只定义档位模型并按策略挑选；选中后的子播放列表仍交给
+//! `M3U8Downloader` 下载。独立音轨/字幕（`#EXT-X-MEDIA`）按档位引用的分组和
+//! 语言偏好挑选。
+
+#![deny(clippy::unwrap_used, clippy::expect_used)]
+
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+
+/// 码率档位选择策略（任务未指定时使用配置中的默认值）
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum VariantPolicy {
+    /// 带宽最高的档位
+    #[default]
+    Highest,
+    /// 带宽最低的档位
+    Lowest,
+    /// 分辨率高度最接近 `height`（如 720），距离相同取带宽更高者；都没有 RESOLUTION 时取最高档
+    ClosestHeight { height: u32 },
+    /// 不超过 `bandwidth`（bits/s）的最高档；全部超限时取最低档
+    MaxBandwidth { bandwidth: u64 },
+}
+
+impl VariantPolicy {
+    pub fn validate(&self) -> Result<()> {
+        match self {
+            VariantPolicy::ClosestHeight { height: 0 } => {
+                bail!("HLS variant target height must be positive")
+            }
+            VariantPolicy::MaxBandwidth { bandwidth: 0 } => {
+                bail!("HLS variant bandwidth cap must be positive")
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// master playlist 中的一个档位
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HlsVariant {
+    /// 子播放列表的绝对地址
+    pub uri: String,
+    /// BANDWIDTH（峰值，bits/s）
+    pub bandwidth: u64,
+    #[serde(default)]
+    pub average_bandwidth: Option<u64>,
+    #[serde(default)]
+    pub width: Option<u32>,
+    #[serde(default)]
+    pub height: Option<u32>,
+    #[serde(default)]
+    pub codecs: Option<String>,
+    #[serde(default)]
+    pub frame_rate: Option<f64>,
+    /// AUDIO 属性引用的 EXT-X-MEDIA 分组
+    #[serde(default)]
+    pub audio_group: Option<String>,
+    /// SUBTITLES 属性引用的 EXT-X-MEDIA 分组
+    #[serde(default)]
+    pub subtitles_group: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RenditionKind {
+    Audio,
+    Subtitles,
+}
+
+/// master playlist 中的一条 `#EXT-X-MEDIA`（只保留音轨和字幕）
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HlsRendition {
+    pub kind: RenditionKind,
+    pub group_id: String,
+    pub name: String,
+    #[serde(default)]
+    pub language: Option<String>,
+    /// 媒体播放列表的绝对地址；没有 URI 的音轨已混在视频流里
+    #[serde(default)]
+    pub uri: Option<String>,
+    #[serde(default)]
+    pub default: bool,
+}
+
+/// 字幕单独保存时的格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SubtitleFormat {
+    #[default]
+    Vtt,
+    Srt,
+}
+
+/// 音轨/字幕挑选偏好；语言按前缀匹配（"en" 可匹配 "en-US"），不区分大小写
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RenditionPreferences {
+    /// 需要的音轨语言；为空或都没匹配上时取 DEFAULT=YES（否则第一个）音轨
+    #[serde(default)]
+    pub audio_languages: Vec<String>,
+    /// 需要的字幕语言；为空时不下载字幕
+    #[serde(default)]
+    pub subtitle_languages: Vec<String>,
+    /// 字幕写成视频旁的独立文件，而不是封装进视频
+    #[serde(default)]
+    pub subtitle_sidecar: bool,
+    #[serde(default)]
+    pub subtitle_format: SubtitleFormat,
+}
+
+/// 挑选与档位配套的音轨和字幕；只返回带 URI、需要单独下载的条目
+pub fn select_renditions<'a>(
+    renditions: &'a [HlsRendition],
+    variant: &HlsVariant,
+    preferences: &RenditionPreferences,
+) -> Vec<&'a HlsRendition> {
+    let in_group = |kind: RenditionKind, group: &Option<String>| {
+        renditions
+            .iter()
+            .filter(move |rendition| {
+                rendition.kind == kind && Some(&rendition.group_id) == group.as_ref()
+            })
+            .collect::<Vec<_>>()
+    };
+
+    let audio = in_group(RenditionKind::Audio, &variant.audio_group);
+    let mut selected = matching_languages(&audio, &preferences.audio_languages);
+    if selected.is_empty() {
+        selected.extend(
+            audio
+                .iter()
+                .find(|rendition| rendition.default)
+                .or_else(|| audio.first())
+                .copied(),
+        );
+    }
+
+    let subtitles = in_group(RenditionKind::Subtitles, &variant.subtitles_group);
+    selected.extend(matching_languages(
+        &subtitles,
+        &preferences.subtitle_languages,
+    ));
+    selected.retain(|rendition| rendition.uri.is_some());
+    selected
+}
+
+/// 按偏好顺序取每种语言的第一条匹配
+fn matching_languages<'a>(
+    candidates: &[&'a HlsRendition],
+    languages: &[String],
+) -> Vec<&'a HlsRendition> {
+    let mut selected: Vec<&HlsRendition> = Vec::new();
+    for wanted in languages {
+        let wanted = wanted.trim().to_ascii_lowercase();
+        if wanted.is_empty() {
+            continue;
+        }
+        let found = candidates
+            .iter()
+            .find(|rendition| language_matches(rendition.language.as_deref(), &wanted));
+        if let Some(rendition) = found {
+            if !selected
+                .iter()
+                .any(|known| std::ptr::eq(*known, *rendition))
+            {
+                selected.push(rendition);
+            }
+        }
+    }
+    selected
+}
+
+/// `wanted` 为小写语言标签；"en" 可匹配 "en"、"en-US"
+pub(crate) fn language_matches(language: Option<&str>, wanted: &str) -> bool {
+    language.is_some_and(|language| {
+        let language = language.to_ascii_lowercase();
+        language == wanted || language.starts_with(&format!("{}-", wanted))
+    })
+}
+
+/// 可按 `VariantPolicy` 挑选的码率档位（HLS 档位、DASH Representation）
+pub trait StreamQuality {
+    /// 峰值带宽（bits/s）
+    fn bandwidth(&self) -> u64;
+    /// 分辨率高度
+    fn height(&self) -> Option<u32>;
+}
+
+impl StreamQuality for HlsVariant {
+    fn bandwidth(&self) -> u64 {
+        self.bandwidth
+    }
+
+    fn height(&self) -> Option<u32> {
+        self.height
+    }
+}
+
+impl<T: StreamQuality> StreamQuality for &T {
+    fn bandwidth(&self) -> u64 {
+        (*self).bandwidth()
+    }
+
+    fn height(&self) -> Option<u32> {
+        (*self).height()
+    }
+}
+
+/// 按策略挑选档位
+pub fn select_variant<'a, T: StreamQuality>(
+    variants: &'a [T],
+    policy: &VariantPolicy,
+) -> Option<&'a T> {
+    let highest = || variants.iter().max_by_key(|variant| variant.bandwidth());
+    let lowest = || variants.iter().min_by_key(|variant| variant.bandwidth());
+
+    match policy {
+        VariantPolicy::Highest => highest(),
+        VariantPolicy::Lowest => lowest(),
+        VariantPolicy::ClosestHeight { height } => variants
+            .iter()
+            .filter_map(|variant| {
+                variant
+                    .height()
+                    .map(|h| (variant, h.abs_diff(*height), Reverse(variant.bandwidth())))
+            })
+            .min_by_key(|(_, distance, bandwidth)| (*distance, *bandwidth))
+            .map(|(variant, _, _)| variant)
+            .or_else(highest),
+        VariantPolicy::MaxBandwidth { bandwidth } => variants
+            .iter()
+            .filter(|variant| variant.bandwidth() <= *bandwidth)
+            .max_by_key(|variant| variant.bandwidth())
+            .or_else(lowest),
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::expect_used)]
+mod tests {
+    use super::*;
+    use crate::parsers::m3u8_parser::{is_master_playlist, parse_master_playlist};
+
+    const MASTER: &str = r#"#EXTM3U
+#EXT-X-VERSION:4
+#EXT-X-STREAM-INF:BANDWIDTH=800000,RESOLUTION=640x360,CODECS="avc1.4d401e,mp4a.40.2",FRAME-RATE=25.000
+360p/index.m3u8
+#EXT-X-I-FRAME-STREAM-INF:BANDWIDTH=90000,URI="iframe.m3u8"
+#EXT-X-STREAM-INF:BANDWIDTH=2800000,AVERAGE-BANDWIDTH=2400000,RESOLUTION=1280x720,CODECS="avc1.4d401f,mp4a.40.2"
+720p/index.m3u8
+#EXT-X-STREAM-INF:BANDWIDTH=5000000,RESOLUTION=1920x1080
+https://cdn.example.com/1080p/index.m3u8
+"#;
+
+    #[test]
+    fn parses_stream_inf_attributes_and_resolves_uris() {
+        assert!(is_master_playlist(MASTER));
+        assert!(!is_master_playlist("#EXTM3U\n#EXTINF:1.0,\nseg.ts\n"));
+
+        let variants = parse_master_playlist("https://example.com/course/master.m3u8", MASTER)
+            .unwrap()
+            .variants;
+        assert_eq!(variants.len(), 3);
+        assert_eq!(
+            variants[0],
+            HlsVariant {
+                uri: "https://example.com/course/360p/index.m3u8".to_string(),
+                bandwidth: 800_000,
+                average_bandwidth: None,
+                width: Some(640),
+                height: Some(360),
+                codecs: Some("avc1.4d401e,mp4a.40.2".to_string()),
+                frame_rate: Some(25.0),
+                audio_group: None,
+                subtitles_group: None,
+            }
+        );
+        assert_eq!(variants[1].average_bandwidth, Some(2_400_000));
+        assert_eq!(variants[2].uri, "https://cdn.example.com/1080p/index.m3u8");
+        assert!(parse_master_playlist(
+            "https://example.com/master.m3u8",
+            "#EXTM3U\n#EXT-X-STREAM-INF:RESOLUTION=640x360\nlow.m3u8\n"
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn selects_variant_by_policy() {
+        let variants = parse_master_playlist("https://example.com/course/master.m3u8", MASTER)
+            .unwrap()
+            .variants;
+        let pick = |policy: VariantPolicy| select_variant(&variants, &policy).unwrap().bandwidth;
+
+        assert_eq!(pick(VariantPolicy::Highest), 5_000_000);
+        assert_eq!(pick(VariantPolicy::Lowest), 800_000);
+        assert_eq!(
+            pick(VariantPolicy::ClosestHeight { height: 720 }),
+            2_800_000
+        );
+        assert_eq!(
+            pick(VariantPolicy::ClosestHeight { height: 900 }),
+            5_000_000
+        );
+        assert_eq!(
+            pick(VariantPolicy::MaxBandwidth {
+                bandwidth: 3_000_000
+            }),
+            2_800_000
+        );
+        assert_eq!(
+            pick(VariantPolicy::MaxBandwidth { bandwidth: 100_000 }),
+            800_000
+        );
+    }
+
+    #[test]
+    fn selects_audio_and_subtitle_renditions_for_variant() {
+        let master = r#"#EXTM3U
+#EXT-X-MEDIA:TYPE=AUDIO,GROUP-ID="aud",NAME="English",LANGUAGE="en-US",DEFAULT=YES,URI="audio/en.m3u8"
+#EXT-X-MEDIA:TYPE=AUDIO,GROUP-ID="aud",NAME="中文",LANGUAGE="zh",URI="audio/zh.m3u8"
+#EXT-X-MEDIA:TYPE=AUDIO,GROUP-ID="other",NAME="Muxed",LANGUAGE="en"
+#EXT-X-MEDIA:TYPE=SUBTITLES,GROUP-ID="subs",NAME="English",LANGUAGE="en",URI="subs/en.m3u8"
+#EXT-X-MEDIA:TYPE=SUBTITLES,GROUP-ID="subs",NAME="Français",LANGUAGE="fr",URI="subs/fr.m3u8"
+#EXT-X-MEDIA:TYPE=CLOSED-CAPTIONS,GROUP-ID="cc",NAME="CC1",INSTREAM-ID="CC1"
+#EXT-X-STREAM-INF:BANDWIDTH=1200000,AUDIO="aud",SUBTITLES="subs",CLOSED-CAPTIONS="cc"
+video/720p.m3u8
+"#;
+        let url = "https://example.com/course/master.m3u8";
+        let parsed = parse_master_playlist(url, master).unwrap();
+        let renditions = parsed.renditions;
+        assert_eq!(renditions.len(), 5);
+        assert_eq!(
+            renditions[0].uri.as_deref(),
+            Some("https://example.com/course/audio/en.m3u8")
+        );
+        let variant = &parsed.variants[0];
+        assert_eq!(variant.audio_group.as_deref(), Some("aud"));
+        assert_eq!(variant.subtitles_group.as_deref(), Some("subs"));
+
+        let names = |preferences: RenditionPreferences| {
+            select_renditions(&renditions, variant, &preferences)
+                .into_iter()
+                .map(|rendition| rendition.name.clone())
+                .collect::<Vec<_>>()
+        };
+        // 默认只取 DEFAULT=YES 的音轨，不下载字幕
+        assert_eq!(names(RenditionPreferences::default()), vec!["English"]);
+        assert_eq!(
+            names(RenditionPreferences {
+                audio_languages: vec!["zh".into(), "en".into()],
+                subtitle_languages: vec!["FR".into(), "de".into()],
+                ..Default::default()
+            }),
+            vec!["中文", "English", "Français"]
+        );
+    }
+}
@@ -0,0 +1,378 @@
+//! HLS 有序流式写入
+//!
+//! 片段并发下载、乱序完成。写入器按播放列表顺序把片段直接追加到输出文件的 `.part`，
+//! fMP4 初始化段在变化处插入，不再为每个片段落一个临时文件再合并。还没轮到的片段
+//! 先暂存在内存，超过上限后溢出到临时目录。带分片清单时，已提交的连续前缀随写入
+//! 定期落盘，恢复时截断到该前缀续写。
+
+#![deny(clippy::unwrap_used, clippy::expect_used)]
+
+use crate::core::models::SegmentGap;
+use crate::core::part_file::{PartFileChunkWriter, PartFileWriter};
+use crate::core::segment_manifest::SegmentManifest;
+use anyhow::{anyhow, bail, Result};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// 每提交这么多个片段同步一次文件并落盘分片清单（结束时总会再保存一次）
+const CHECKPOINT_INTERVAL: usize = 16;
+
+/// 等待前面片段的乱序片段
+enum Pending {
+    Memory {
+        init_section: Option<usize>,
+        data: Vec<u8>,
+    },
+    Spilled {
+        init_section: Option<usize>,
+        path: PathBuf,
+    },
+    /// 重试耗尽、按容忍策略跳过的片段
+    Missing(SegmentGap),
+}
+
+/// 把片段按索引顺序写入 `.part`，全部写完后改名为最终文件
+pub struct OrderedSegmentWriter {
+    part: PartFileWriter,
+    file: PartFileChunkWriter,
+    init_sections: Vec<Vec<u8>>,
+    current_init: Option<usize>,
+    written_init: Option<Vec<u8>>,
+    /// 下一个要提交的片段索引
+    next: usize,
+    committed_bytes: u64,
+    gaps: Vec<SegmentGap>,
+    pending: BTreeMap<usize, Pending>,
+    buffered_bytes: usize,
+    max_buffered_bytes: usize,
+    spill_dir: PathBuf,
+    /// 分片清单、清单目录及已提交前缀的哈希（仅可恢复的点播流）
+    manifest: Option<(SegmentManifest, PathBuf, Sha256)>,
+    since_checkpoint: usize,
+}
+
+impl OrderedSegmentWriter {
+    /// 打开 `final_path` 对应的 `.part`；传入分片清单且前缀校验通过时截断到前缀续写，
+    /// 否则从头写
+    pub async fn open(
+        final_path: &Path,
+        init_sections: Vec<Vec<u8>>,
+        spill_dir: PathBuf,
+        max_buffered_bytes: usize,
+        manifest: Option<(SegmentManifest, PathBuf)>,
+    ) -> Result<Self> {
+        let part = PartFileWriter::new(final_path);
+        let part_path = part.part_path().to_path_buf();
+
+        let manifest = match manifest {
+            Some((mut manifest, root)) => {
+                let hasher = manifest.verify_prefix(&part_path).await;
+                manifest.part_path = Some(part_path.clone());
+                Some((manifest, root, hasher))
+            }
+            None => None,
+        };
+        let (next, committed_bytes, current_init, gaps) = match &manifest {
+            Some((manifest, _, Some(_))) => (
+                manifest.committed_segments,
+                manifest.committed_bytes,
+                manifest.current_init,
+                manifest.gaps.clone(),
+            ),
+            _ => (0, 0, None, Vec::new()),
+        };
+
+        if committed_bytes == 0 {
+            part.remove_part_if_exists().await?;
+        }
+        part.prepare(None).await?;
+        tokio::fs::OpenOptions::new()
+            .write(true)
+            .open(&part_path)
+            .await?
+            .set_len(committed_bytes)
+            .await?;
+        let file = part.open_chunk_writer(committed_bytes).await?;
+        if next > 0 {
+            tracing::info!(
+                "从已提交的前缀续写: {} 个片段, {} bytes",
+                next,
+                committed_bytes
+            );
+        }
+
+        let _ = tokio::fs::remove_dir_all(&spill_dir).await;
+        tokio::fs::create_dir_all(&spill_dir).await?;
+
+        let written_init = current_init.and_then(|index| init_sections.get(index).cloned());
+        Ok(Self {
+            part,
+            file,
+            init_sections,
+            current_init,
+            written_init,
+            next,
+            committed_bytes,
+            gaps,
+            pending: BTreeMap::new(),
+            buffered_bytes: 0,
+            max_buffered_bytes,
+            spill_dir,
+            manifest: manifest.map(|(manifest, root, hasher)| {
+                (manifest, root, hasher.unwrap_or_else(Sha256::new))
+            }),
+            since_checkpoint: 0,
+        })
+    }
+
+    /// 下一个要提交的片段索引（之前的片段都已写入或跳过）
+    pub fn next_index(&self) -> usize {
+        self.next
+    }
+
+    /// 已写入 `.part` 的字节数（含初始化段）
+    pub fn committed_bytes(&self) -> u64 {
+        self.committed_bytes
+    }
+
+    /// 追加一个初始化段（直播录制中途出现新的 EXT-X-MAP），返回其索引
+    pub fn add_init_section(&mut self, data: Vec<u8>) -> usize {
+        self.init_sections.push(data);
+        self.init_sections.len() - 1
+    }
+
+    /// 交付一个已下载（已解密）的片段；轮到它时连同其后已到达的片段一起写入
+    pub async fn push(
+        &mut self,
+        index: usize,
+        init_section: Option<usize>,
+        data: Vec<u8>,
+    ) -> Result<()> {
+        if index < self.next {
+            return Ok(());
+        }
+        let pending =
+            if index != self.next && self.buffered_bytes + data.len() > self.max_buffered_bytes {
+                let path = self.spill_dir.join(format!("spill_{:06}.bin", index));
+                tokio::fs::write(&path, &data).await?;
+                Pending::Spilled { init_section, path }
+            } else {
+                self.buffered_bytes += data.len();
+                Pending::Memory { init_section, data }
+            };
+        self.pending.insert(index, pending);
+        self.drain().await
+    }
+
+    /// 跳过一个重试耗尽的片段
+    pub async fn skip(&mut self, gap: SegmentGap) -> Result<()> {
+        if gap.index < self.next {
+            return Ok(());
+        }
+        self.pending.insert(gap.index, Pending::Missing(gap));
+        self.drain().await
+    }
+
+    /// 同步文件并落盘分片清单
+    pub async fn checkpoint(&mut self) -> Result<()> {
+        self.file.flush_and_sync().await?;
+        self.since_checkpoint = 0;
+        if let Some((manifest, root, hasher)) = &mut self.manifest {
+            manifest.commit(
+                self.next,
+                self.committed_bytes,
+                hex::encode(hasher.clone().finalize()),
+                self.current_init,
+                &self.gaps,
+            );
+            manifest.save(root).await?;
+        }
+        Ok(())
+    }
+
+    /// 所有片段都已交付：写入完成并把 `.part` 改名为最终文件，返回被跳过的片段
+    pub async fn finish(&mut self) -> Result<Vec<SegmentGap>> {
+        if let Some(index) = self.pending.keys().next() {
+            bail!("片段 #{} 之前还有片段未写入", index);
+        }
+        if self.next > 0 && self.gaps.len() == self.next {
+            bail!("所有片段都下载失败: {}", self.gaps[0].error);
+        }
+        self.file.flush_and_sync().await?;
+        self.part.commit().await?;
+        let _ = tokio::fs::remove_dir_all(&self.spill_dir).await;
+        Ok(std::mem::take(&mut self.gaps))
+    }
+
+    /// 录制中断：丢弃还没轮到的片段，只把已连续写入的部分改名为最终文件
+    pub async fn finish_committed(&mut self) -> Result<Vec<SegmentGap>> {
+        for pending in std::mem::take(&mut self.pending).into_values() {
+            if let Pending::Spilled { path, .. } = pending {
+                tokio::fs::remove_file(&path).await.ok();
+            }
+        }
+        self.buffered_bytes = 0;
+        self.finish().await
+    }
+
+    async fn drain(&mut self) -> Result<()> {
+        while let Some(pending) = self.pending.remove(&self.next) {
+            match pending {
+                Pending::Memory { init_section, data } => {
+                    self.buffered_bytes -= data.len();
+                    self.write_segment(init_section, &data).await?;
+                }
+                Pending::Spilled { init_section, path } => {
+                    let data = tokio::fs::read(&path).await?;
+                    tokio::fs::remove_file(&path).await.ok();
+                    self.write_segment(init_section, &data).await?;
+                }
+                Pending::Missing(gap) => self.gaps.push(gap),
+            }
+            self.next += 1;
+            self.since_checkpoint += 1;
+            if self.since_checkpoint >= CHECKPOINT_INTERVAL {
+                self.checkpoint().await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// 初始化段变化时先写入新的初始化段（内容相同的重复声明只写一次）
+    async fn write_segment(&mut self, init_section: Option<usize>, data: &[u8]) -> Result<()> {
+        if let Some(init_index) = init_section.filter(|i| Some(*i) != self.current_init) {
+            let init = self
+                .init_sections
+                .get(init_index)
+                .cloned()
+                .ok_or_else(|| anyhow!("缺少初始化段 #{}", init_index))?;
+            if self.written_init.as_deref() != Some(init.as_slice()) {
+                if self.written_init.is_some() {
+                    tracing::warn!("fMP4 初始化段在第 {} 个片段处发生变化", self.next);
+                }
+                self.write_bytes(&init).await?;
+                self.written_init = Some(init);
+            }
+            self.current_init = Some(init_index);
+        }
+        self.write_bytes(data).await
+    }
+
+    async fn write_bytes(&mut self, data: &[u8]) -> Result<()> {
+        self.file.write_all(data).await?;
+        if let Some((_, _, hasher)) = &mut self.manifest {
+            hasher.update(data);
+        }
+        self.committed_bytes += data.len() as u64;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::expect_used)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn gap(index: usize) -> SegmentGap {
+        SegmentGap {
+            index,
+            start_secs: index as f64,
+            end_secs: index as f64 + 1.0,
+            error: "404".to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn writes_out_of_order_segments_in_order_and_spills_over_the_limit() {
+        let temp_dir = tempdir().unwrap();
+        let output = temp_dir.path().join("lesson.mp4");
+        let spill_dir = temp_dir.path().join("spill");
+        let mut writer =
+            OrderedSegmentWriter::open(&output, vec![b"INIT".to_vec()], spill_dir.clone(), 4, None)
+                .await
+                .unwrap();
+
+        writer.push(3, Some(0), b"[3]".to_vec()).await.unwrap();
+        // 超过 4 字节的暂存上限，溢出到磁盘
+        writer.push(2, Some(0), b"[2]".to_vec()).await.unwrap();
+        assert!(spill_dir.join("spill_000002.bin").exists());
+        writer.skip(gap(1)).await.unwrap();
+        assert_eq!(writer.next_index(), 0);
+
+        writer.push(0, Some(0), b"[0]".to_vec()).await.unwrap();
+        assert_eq!(writer.next_index(), 4);
+        let gaps = writer.finish().await.unwrap();
+
+        assert_eq!(tokio::fs::read(&output).await.unwrap(), b"INIT[0][2][3]");
+        assert_eq!(gaps, vec![gap(1)]);
+        assert!(!spill_dir.exists());
+    }
+
+    #[tokio::test]
+    async fn resumes_from_the_committed_prefix() {
+        let temp_dir = tempdir().unwrap();
+        let root = temp_dir.path().join("manifests");
+        let output = temp_dir.path().join("lesson.ts");
+        let spill_dir = temp_dir.path().join("spill");
+        let manifest = SegmentManifest::new("task", "https://example.com/a.m3u8");
+        let mut writer = OrderedSegmentWriter::open(
+            &output,
+            Vec::new(),
+            spill_dir.clone(),
+            1024,
+            Some((manifest, root.clone())),
+        )
+        .await
+        .unwrap();
+        writer.push(0, None, b"[0]".to_vec()).await.unwrap();
+        writer.push(2, None, b"[2]".to_vec()).await.unwrap();
+        writer.checkpoint().await.unwrap();
+        drop(writer);
+
+        // 中断时 .part 末尾可能残留未提交的数据
+        let part = temp_dir.path().join("lesson.ts.part");
+        tokio::fs::write(&part, b"[0]junk").await.unwrap();
+
+        let manifest =
+            SegmentManifest::load_or_new(&root, "task", "https://example.com/a.m3u8").await;
+        assert_eq!(manifest.committed_segments, 1);
+        let mut writer = OrderedSegmentWriter::open(
+            &output,
+            Vec::new(),
+            spill_dir,
+            1024,
+            Some((manifest, root)),
+        )
+        .await
+        .unwrap();
+        assert_eq!(writer.next_index(), 1);
+        writer.push(1, None, b"[1]".to_vec()).await.unwrap();
+        writer.push(2, None, b"[2]".to_vec()).await.unwrap();
+        writer.finish().await.unwrap();
+
+        assert_eq!(tokio::fs::read(&output).await.unwrap(), b"[0][1][2]");
+        assert!(!part.exists());
+    }
+
+    #[tokio::test]
+    async fn finish_committed_keeps_only_the_contiguous_prefix() {
+        let temp_dir = tempdir().unwrap();
+        let output = temp_dir.path().join("live.ts");
+        let spill_dir = temp_dir.path().join("spill");
+        let mut writer =
+            OrderedSegmentWriter::open(&output, Vec::new(), spill_dir.clone(), 0, None)
+                .await
+                .unwrap();
+        writer.push(0, None, b"[0]".to_vec()).await.unwrap();
+        writer.skip(gap(1)).await.unwrap();
+        writer.push(3, None, b"[3]".to_vec()).await.unwrap();
+        assert!(writer.finish().await.is_err());
+
+        let gaps = writer.finish_committed().await.unwrap();
+        assert_eq!(tokio::fs::read(&output).await.unwrap(), b"[0]");
+        assert_eq!(gaps, vec![gap(1)]);
+        assert!(!spill_dir.exists());
+    }
+}
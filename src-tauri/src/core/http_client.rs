@@ -0,0 +1,575 @@
+//! 共享的 reqwest 客户端句柄
+//!
+//! HTTP、断点续传、M3U8 下载器持有同一个 [`HttpClientHandle`]，
+//! 配置变更时原地重建客户端，新发起的请求立即使用新的代理/超时/请求头设置，
+//! 进行中的请求继续使用旧客户端直到结束。凭据库与 cookie jar 同样挂在句柄上，请求按 URL 自动附带
+//! 认证与 cookie；重定向由 [`HttpClientHandle::send`] 逐跳处理，以便记录每一跳的 `Set-Cookie`。
+
+#![deny(clippy::unwrap_used, clippy::expect_used)]
+
+use anyhow::Result;
+use parking_lot::RwLock;
+use reqwest::header::{
+    HeaderMap, HeaderName, HeaderValue, AUTHORIZATION, CONTENT_TYPE, COOKIE, IF_RANGE, LOCATION,
+    RANGE, WWW_AUTHENTICATE,
+};
+use reqwest::{Client, Method, Request, RequestBuilder, Response, StatusCode};
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::core::cookie_jar::CookieJar;
+use crate::core::credentials::{CredentialStore, ExternalAuth};
+use crate::core::models::DownloadConfig;
+use crate::core::proxy::ProxySettings;
+use crate::core::request_headers::HeaderRules;
+
+/// 与浏览器一致的最大重定向次数
+const MAX_REDIRECTS: usize = 10;
+
+/// 跨主机重定向时保留的传输层请求头，其余请求头按新主机重新生成
+const CROSS_HOST_REDIRECT_HEADERS: [HeaderName; 3] = [RANGE, IF_RANGE, CONTENT_TYPE];
+
+/// 构建 reqwest 客户端所需的网络参数
+#[derive(Debug, Clone, PartialEq)]
+pub struct HttpClientOptions {
+    pub timeout: Duration,
+    pub user_agent: String,
+    pub proxy: Option<ProxySettings>,
+    pub headers: HeaderRules,
+}
+
+impl HttpClientOptions {
+    pub fn from_download_config(config: &DownloadConfig) -> Result<Self> {
+        Ok(Self {
+            timeout: Duration::from_secs(config.timeout_seconds),
+            user_agent: config.user_agent.clone(),
+            proxy: ProxySettings::from_download_config(config)?,
+            headers: HeaderRules::from_download_config(config)?,
+        })
+    }
+
+    pub fn build_client(&self) -> Result<Client> {
+        let mut builder = Client::builder()
+            .timeout(self.timeout)
+            .user_agent(&self.user_agent)
+            // 重定向在 send() 中手动跟随
+            .redirect(reqwest::redirect::Policy::none());
+        if let Some(proxy) = &self.proxy {
+            builder = builder.proxy(proxy.to_reqwest_proxy()?);
+        }
+        Ok(builder.build()?)
+    }
+}
+
+struct HttpClientState {
+    client: Client,
+    options: Option<HttpClientOptions>,
+    credentials: CredentialStore,
+    cookies: CookieJar,
+}
+
+impl HttpClientState {
+    /// 目标 URL 的规则请求头、Authorization 与 jar 中的 Cookie
+    fn request_headers(&self, method: &str, url: &str) -> HeaderMap {
+        let mut headers = match &self.options {
+            Some(options) if !options.headers.is_empty() => options.headers.header_map_for_url(url),
+            _ => HeaderMap::new(),
+        };
+        if let Some(authorization) = self.credentials.authorization_for(method, url) {
+            headers.insert(AUTHORIZATION, authorization);
+        }
+        if let Some(jar_cookies) = self.cookies.cookie_header_for(url) {
+            // 规则里手动配置的 Cookie 在前，jar 中的在后
+            let merged = match headers.get(COOKIE).and_then(|value| value.to_str().ok()) {
+                Some(configured) => jar_cookies
+                    .to_str()
+                    .ok()
+                    .and_then(|jar| HeaderValue::from_str(&format!("{}; {}", configured, jar)).ok())
+                    .map(|mut value| {
+                        value.set_sensitive(true);
+                        value
+                    }),
+                None => Some(jar_cookies),
+            };
+            if let Some(merged) = merged {
+                headers.insert(COOKIE, merged);
+            }
+        }
+        headers
+    }
+
+    fn prepare(&self, request: RequestBuilder, method: &str, url: &str) -> RequestBuilder {
+        let headers = self.request_headers(method, url);
+        if headers.is_empty() {
+            request
+        } else {
+            request.headers(headers)
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct HttpClientHandle {
+    state: Arc<RwLock<HttpClientState>>,
+}
+
+impl HttpClientHandle {
+    pub fn new(options: HttpClientOptions) -> Result<Self> {
+        let client = options.build_client()?;
+        Ok(Self {
+            state: Arc::new(RwLock::new(HttpClientState {
+                client,
+                options: Some(options),
+                credentials: CredentialStore::in_memory(),
+                cookies: CookieJar::in_memory(),
+            })),
+        })
+    }
+
+    /// 当前客户端（reqwest::Client 内部是 Arc，克隆开销很小）
+    pub fn client(&self) -> Client {
+        self.state.read().client.clone()
+    }
+
+    pub fn proxy(&self) -> Option<ProxySettings> {
+        self.state
+            .read()
+            .options
+            .as_ref()
+            .and_then(|options| options.proxy.clone())
+    }
+
+    /// 附带全局及主机匹配请求头（以及匹配凭据）的 GET 请求
+    pub fn get(&self, url: &str) -> RequestBuilder {
+        let state = self.state.read();
+        state.prepare(state.client.get(url), "GET", url)
+    }
+
+    /// 附带全局及主机匹配请求头（以及匹配凭据）的 HEAD 请求
+    pub fn head(&self, url: &str) -> RequestBuilder {
+        let state = self.state.read();
+        state.prepare(state.client.head(url), "HEAD", url)
+    }
+
+    /// 发送请求：逐跳跟随重定向并把每一跳的 `Set-Cookie` 写入 jar；
+    /// 某一跳返回 401 且凭据库能应答质询时带上 Authorization 重试一次
+    pub async fn send(&self, request: RequestBuilder) -> reqwest::Result<Response> {
+        let (client, request) = request.build_split();
+        let mut request = request?;
+        for _ in 0..MAX_REDIRECTS {
+            let next = request.try_clone();
+            let response = self.execute_with_auth(&client, request).await?;
+            let Some(mut next) = next.filter(|_| response.status().is_redirection()) else {
+                return Ok(response);
+            };
+            let Some(location) = redirect_location(&response) else {
+                return Ok(response);
+            };
+            tracing::debug!("↪️ Redirect {} -> {}", response.url(), location);
+
+            if matches!(
+                response.status(),
+                StatusCode::MOVED_PERMANENTLY | StatusCode::FOUND | StatusCode::SEE_OTHER
+            ) && !matches!(*next.method(), Method::GET | Method::HEAD)
+            {
+                *next.method_mut() = Method::GET;
+                *next.body_mut() = None;
+            }
+            let same_host = next.url().host_str() == location.host_str()
+                && next.url().port_or_known_default() == location.port_or_known_default();
+            *next.url_mut() = location;
+            // 凭据、cookie 与主机规则按新 URL 重新匹配，不把上一跳的带到其他主机
+            if same_host {
+                next.headers_mut().remove(AUTHORIZATION);
+                next.headers_mut().remove(COOKIE);
+            } else {
+                let previous = std::mem::take(next.headers_mut());
+                for name in CROSS_HOST_REDIRECT_HEADERS {
+                    if name == CONTENT_TYPE && next.body().is_none() {
+                        continue;
+                    }
+                    if let Some(value) = previous.get(&name) {
+                        next.headers_mut().insert(name, value.clone());
+                    }
+                }
+            }
+            let headers = self
+                .state
+                .read()
+                .request_headers(next.method().as_str(), next.url().as_str());
+            next.headers_mut().extend(headers);
+            request = next;
+        }
+        self.execute_with_auth(&client, request).await
+    }
+
+    async fn execute_with_auth(
+        &self,
+        client: &Client,
+        request: Request,
+    ) -> reqwest::Result<Response> {
+        let retry = request.try_clone();
+        let response = self.execute(client, request).await?;
+        if response.status() != StatusCode::UNAUTHORIZED {
+            return Ok(response);
+        }
+        let Some(mut retry) = retry else {
+            return Ok(response);
+        };
+
+        let challenges: Vec<String> = response
+            .headers()
+            .get_all(WWW_AUTHENTICATE)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .map(str::to_string)
+            .collect();
+        let authorization =
+            self.credentials()
+                .answer_challenge(retry.method().as_str(), retry.url(), &challenges);
+        let Some(authorization) = authorization else {
+            return Ok(response);
+        };
+        // 同样的凭据已经被拒绝，不再重试
+        if retry.headers().get(AUTHORIZATION) == Some(&authorization) {
+            return Ok(response);
+        }
+        tracing::debug!("🔑 Answering auth challenge for {}", retry.url());
+        retry.headers_mut().insert(AUTHORIZATION, authorization);
+        // 401 响应可能刚下发了 cookie
+        let cookies = self
+            .state
+            .read()
+            .request_headers(retry.method().as_str(), retry.url().as_str())
+            .remove(COOKIE);
+        if let Some(cookies) = cookies {
+            retry.headers_mut().insert(COOKIE, cookies);
+        }
+        self.execute(client, retry).await
+    }
+
+    async fn execute(&self, client: &Client, request: Request) -> reqwest::Result<Response> {
+        let response = client.execute(request).await?;
+        self.cookies()
+            .store_response_cookies(response.url(), response.headers());
+        Ok(response)
+    }
+
+    pub fn credentials(&self) -> CredentialStore {
+        self.state.read().credentials.clone()
+    }
+
+    /// 换成持久化的凭据库，所有共享此句柄的下载器立即生效
+    pub fn set_credentials(&self, credentials: CredentialStore) {
+        self.state.write().credentials = credentials;
+    }
+
+    pub fn cookies(&self) -> CookieJar {
+        self.state.read().cookies.clone()
+    }
+
+    /// 换成持久化的 cookie jar
+    pub fn set_cookies(&self, cookies: CookieJar) {
+        self.state.write().cookies = cookies;
+    }
+
+    /// 目标 URL 匹配的凭据（供 yt-dlp `--username/--password` 等参数使用）
+    pub fn external_auth_for(&self, url: &str) -> Option<ExternalAuth> {
+        self.state.read().credentials.external_auth_for(url)
+    }
+
+    /// 目标 URL 需要附带的请求头（供 yt-dlp `--add-header` 使用）
+    pub fn headers_for_url(&self, url: &str) -> Vec<(String, String)> {
+        self.state
+            .read()
+            .options
+            .as_ref()
+            .map(|options| options.headers.headers_for_url(url))
+            .unwrap_or_default()
+    }
+
+    /// 目标 URL 实际使用的代理 URL（命中直连列表时为 None）
+    pub fn proxy_url_for(&self, target_url: &str) -> Option<String> {
+        self.proxy()
+            .filter(|proxy| proxy.applies_to_url(target_url))
+            .map(|proxy| proxy.to_url())
+    }
+
+    /// 使用新的参数重建客户端；参数未变化时返回 false
+    pub fn reconfigure(&self, options: HttpClientOptions) -> Result<bool> {
+        if self.state.read().options.as_ref() == Some(&options) {
+            return Ok(false);
+        }
+        let client = options.build_client()?;
+        let mut state = self.state.write();
+        state.client = client;
+        state.options = Some(options);
+        Ok(true)
+    }
+}
+
+impl std::fmt::Debug for HttpClientHandle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("HttpClientHandle")
+            .field("proxy", &self.proxy().map(|proxy| proxy.redacted_url()))
+            .finish()
+    }
+}
+
+impl From<Client> for HttpClientHandle {
+    fn from(client: Client) -> Self {
+        Self {
+            state: Arc::new(RwLock::new(HttpClientState {
+                client,
+                options: None,
+                credentials: CredentialStore::in_memory(),
+                cookies: CookieJar::in_memory(),
+            })),
+        }
+    }
+}
+
+fn redirect_location(response: &Response) -> Option<url::Url> {
+    let location = response.headers().get(LOCATION)?.to_str().ok()?;
+    response.url().join(location).ok()
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::expect_used)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reconfigure_swaps_proxy_for_shared_clones() {
+        let handle = HttpClientHandle::new(HttpClientOptions {
+            timeout: Duration::from_secs(5),
+            user_agent: "test".to_string(),
+            proxy: None,
+            headers: HeaderRules::default(),
+        })
+        .unwrap();
+        let shared = handle.clone();
+        assert!(shared.proxy().is_none());
+
+        let config = DownloadConfig {
+            proxy: Some("socks5h://user:pw@proxy.corp:1080".to_string()),
+            no_proxy: vec!["intranet.corp".to_string()],
+            ..DownloadConfig::default()
+        };
+        let options = HttpClientOptions::from_download_config(&config).unwrap();
+        assert!(handle.reconfigure(options.clone()).unwrap());
+        assert!(!handle.reconfigure(options).unwrap());
+
+        assert_eq!(
+            shared.proxy_url_for("https://www.youtube.com/watch?v=abc"),
+            Some("socks5h://user:pw@proxy.corp:1080".to_string())
+        );
+        assert_eq!(shared.proxy_url_for("http://cdn.intranet.corp/v.mp4"), None);
+    }
+
+    #[test]
+    fn requests_carry_host_matched_headers() {
+        let config = DownloadConfig {
+            headers: std::collections::HashMap::from([("X-Client".to_string(), "vdp".to_string())]),
+            host_headers: vec![crate::core::models::HostHeaderRule {
+                host: "course.com".to_string(),
+                referer: Some("https://course.com/".to_string()),
+                ..Default::default()
+            }],
+            ..DownloadConfig::default()
+        };
+        let handle =
+            HttpClientHandle::new(HttpClientOptions::from_download_config(&config).unwrap())
+                .unwrap();
+
+        let request = handle
+            .get("https://media.course.com/seg1.ts")
+            .header("Range", "bytes=0-99")
+            .build()
+            .unwrap();
+        assert_eq!(request.headers()["referer"], "https://course.com/");
+        assert_eq!(request.headers()["x-client"], "vdp");
+        assert_eq!(request.headers()["range"], "bytes=0-99");
+
+        let probe = handle.head("https://elsewhere.org/a.mp4").build().unwrap();
+        assert!(probe.headers().get("referer").is_none());
+        assert_eq!(probe.headers()["x-client"], "vdp");
+    }
+
+    #[tokio::test]
+    async fn answers_digest_challenge_and_retries_once() {
+        use crate::core::credentials::{AuthScheme, CredentialInput};
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            let mut authorizations = Vec::new();
+            for _ in 0..2 {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let mut buffer = vec![0u8; 4096];
+                let read = socket.read(&mut buffer).await.unwrap();
+                let request = String::from_utf8_lossy(&buffer[..read]).to_string();
+                let authorization = request
+                    .lines()
+                    .find(|line| line.to_ascii_lowercase().starts_with("authorization:"))
+                    .map(str::to_string);
+                let response = if authorization.is_some() {
+                    "HTTP/1.1 200 OK\r\nContent-Length: 2\r\nConnection: close\r\n\r\nok"
+                } else {
+                    "HTTP/1.1 401 Unauthorized\r\nWWW-Authenticate: Digest realm=\"vault\", nonce=\"abc\", qop=\"auth\"\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+                };
+                socket.write_all(response.as_bytes()).await.unwrap();
+                authorizations.push(authorization);
+            }
+            authorizations
+        });
+
+        let handle = HttpClientHandle::from(Client::new());
+        let credentials = CredentialStore::in_memory();
+        credentials
+            .upsert(CredentialInput {
+                id: None,
+                pattern: "127.0.0.1".to_string(),
+                scheme: AuthScheme::Digest,
+                username: Some("alice".to_string()),
+                secret: "s3cret".to_string(),
+                label: None,
+            })
+            .unwrap();
+        handle.set_credentials(credentials);
+
+        let url = format!("http://{}/private/video.mp4", addr);
+        let response = handle.send(handle.get(&url)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let authorizations = server.await.unwrap();
+        assert!(authorizations[0].is_none());
+        let retried = authorizations[1].as_deref().unwrap();
+        assert!(retried.contains("Digest username=\"alice\""));
+        assert!(retried.contains("uri=\"/private/video.mp4\""));
+    }
+
+    #[tokio::test]
+    async fn redirect_hops_update_cookie_jar() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            let mut cookies = Vec::new();
+            for _ in 0..2 {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let mut buffer = vec![0u8; 4096];
+                let read = socket.read(&mut buffer).await.unwrap();
+                let request = String::from_utf8_lossy(&buffer[..read]).to_string();
+                let response = if request.starts_with("GET /login") {
+                    "HTTP/1.1 302 Found\r\nLocation: /video.mp4\r\nSet-Cookie: sid=s1; Path=/\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+                } else {
+                    "HTTP/1.1 200 OK\r\nContent-Length: 2\r\nConnection: close\r\n\r\nok"
+                };
+                socket.write_all(response.as_bytes()).await.unwrap();
+                cookies.push(
+                    request
+                        .lines()
+                        .find(|line| line.to_ascii_lowercase().starts_with("cookie:"))
+                        .map(str::to_string),
+                );
+            }
+            cookies
+        });
+
+        let handle = HttpClientHandle::new(HttpClientOptions {
+            timeout: Duration::from_secs(5),
+            user_agent: "test".to_string(),
+            proxy: None,
+            headers: HeaderRules::default(),
+        })
+        .unwrap();
+        let jar = CookieJar::in_memory();
+        jar.import_netscape("127.0.0.1\tFALSE\t/\tFALSE\t0\tpref\tdark\n", None)
+            .unwrap();
+        handle.set_cookies(jar.clone());
+
+        let url = format!("http://{}/login", addr);
+        let response = handle.send(handle.get(&url)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(response.url().path().ends_with("/video.mp4"));
+
+        let cookies = server.await.unwrap();
+        assert_eq!(cookies[0].as_deref(), Some("cookie: pref=dark"));
+        let redirected = cookies[1].as_deref().unwrap();
+        assert!(redirected.contains("sid=s1") && redirected.contains("pref=dark"));
+        assert_eq!(jar.domains()[0].cookies, 2);
+    }
+
+    #[tokio::test]
+    async fn cross_host_redirect_drops_origin_host_headers() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            let mut requests = Vec::new();
+            for _ in 0..2 {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let mut buffer = vec![0u8; 4096];
+                let read = socket.read(&mut buffer).await.unwrap();
+                let request = String::from_utf8_lossy(&buffer[..read]).to_ascii_lowercase();
+                let response = if request.starts_with("get /origin") {
+                    format!(
+                        "HTTP/1.1 302 Found\r\nLocation: http://localhost:{}/target.mp4\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                        addr.port()
+                    )
+                } else {
+                    "HTTP/1.1 206 Partial Content\r\nContent-Length: 2\r\nConnection: close\r\n\r\nok"
+                        .to_string()
+                };
+                socket.write_all(response.as_bytes()).await.unwrap();
+                requests.push(request);
+            }
+            requests
+        });
+
+        let config = DownloadConfig {
+            host_headers: vec![crate::core::models::HostHeaderRule {
+                host: "127.0.0.1".to_string(),
+                referer: Some("https://origin.example/".to_string()),
+                headers: std::collections::HashMap::from([(
+                    "X-Api-Key".to_string(),
+                    "secret".to_string(),
+                )]),
+                ..Default::default()
+            }],
+            ..DownloadConfig::default()
+        };
+        let handle =
+            HttpClientHandle::new(HttpClientOptions::from_download_config(&config).unwrap())
+                .unwrap();
+
+        let url = format!("http://{}/origin.mp4", addr);
+        let response = handle
+            .send(handle.get(&url).header(RANGE, "bytes=0-1"))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+
+        let requests = server.await.unwrap();
+        assert!(requests[0].contains("x-api-key: secret"));
+        assert!(requests[0].contains("referer: https://origin.example/"));
+        assert!(!requests[1].contains("x-api-key"));
+        assert!(!requests[1].contains("referer"));
+        assert!(requests[1].contains("range: bytes=0-1"));
+    }
+
+    #[test]
+    fn rejects_invalid_proxy_without_replacing_client() {
+        let handle = HttpClientHandle::from(Client::new());
+        let config = DownloadConfig {
+            proxy: Some("gopher://proxy:70".to_string()),
+            ..DownloadConfig::default()
+        };
+        assert!(HttpClientOptions::from_download_config(&config).is_err());
+        assert!(handle.proxy().is_none());
+    }
+}
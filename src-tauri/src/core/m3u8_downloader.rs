@@ -3,33 +3,53 @@
 //! 基于Go项目的M3U8Downloader实现，支持：
 //! - .m3u8 播放列表解析
 //! - .ts 片段并发下载
-//! - 自动合并为完整视频文件
+//! - 按播放列表顺序直接写入输出文件的 .part，不再落临时片段再合并
+//! - fMP4/CMAF 分片（EXT-X-MAP 初始化段）输出为 .mp4
+//! - 直播流（无 EXT-X-ENDLIST）按 EXT-X-MEDIA-SEQUENCE 增量录制
+//! - 分片清单：暂停/失败/崩溃后从已校验的连续前缀续写
+//! - 片段按 RetryExecutor 退避重试（尊重 Retry-After），可容忍有限个缺失片段
+//! - EXT-X-MEDIA 独立音轨/字幕与视频并行下载，ffmpeg 封装或写成独立文件
 //! - 支持AES加密的HLS流
 //! - 实时进度跟踪
 
+use crate::core::bandwidth::BandwidthController;
 use crate::core::downloader::DownloadStats;
-use crate::core::models::TaskStatus;
+use crate::core::error_handling::{self, errors, DownloadError, RetryExecutor, RetryPolicy};
+use crate::core::hls_mux::{self, MuxInput};
+use crate::core::hls_variant::{
+    self, HlsRendition, HlsVariant, RenditionKind, RenditionPreferences, SubtitleFormat,
+    VariantPolicy,
+};
+use crate::core::hls_writer::OrderedSegmentWriter;
+use crate::core::http_client::{HttpClientHandle, HttpClientOptions};
+use crate::core::models::{LiveRecordingProgress, SegmentGap, TaskStatus};
+use crate::core::part_file::PartFileWriter;
+use crate::core::request_headers::HeaderRules;
+use crate::core::segment_manifest::SegmentManifest;
+use crate::parsers::m3u8_parser::{self, HlsPlaylist};
+pub use crate::parsers::m3u8_parser::{M3U8Encryption, M3U8InitSection, M3U8Playlist, M3U8Segment};
 use aes::Aes128;
 use anyhow::{anyhow, bail, Result};
 use cbc::Decryptor;
 use cipher::{block_padding::Pkcs7, BlockDecryptMut, KeyIvInit};
 use hex;
 use parking_lot::RwLock as ParkingRwLock;
-use reqwest::Client;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::path::{Path, PathBuf};
 use std::sync::{
-    atomic::{AtomicBool, AtomicU64, Ordering},
+    atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering},
     Arc,
 };
 use std::time::{Duration, Instant};
-use tokio::fs::File;
-use tokio::io::{AsyncReadExt, AsyncWriteExt};
 use tokio::sync::{mpsc, RwLock, Semaphore};
 use url::Url;
 
 type ProgressSender = Arc<ParkingRwLock<Option<mpsc::UnboundedSender<(String, DownloadStats)>>>>;
+type SharedWriter = Arc<tokio::sync::Mutex<OrderedSegmentWriter>>;
+
+/// 直播播放列表连续这么多次刷新都没有新片段时视为已结束
+const LIVE_STALL_RELOADS: u32 = 6;
 
 /// M3U8下载器配置
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -38,7 +58,7 @@ pub struct M3U8DownloaderConfig {
     pub max_concurrent_segments: usize,
     /// 请求超时时间（秒）
     pub timeout: u64,
-    /// 重试次数
+    /// 单个片段失败后的重试次数
     pub retry_attempts: usize,
     /// 缓冲区大小（字节）
     pub buffer_size: usize,
@@ -48,6 +68,13 @@ pub struct M3U8DownloaderConfig {
     pub temp_dir: PathBuf,
     /// 是否保留临时片段文件
     pub keep_temp_files: bool,
+    /// 乱序到达、尚未写入的片段在内存中最多暂存的字节数，超出部分溢出到临时目录
+    #[serde(default = "default_max_buffered_bytes")]
+    pub max_buffered_bytes: usize,
+}
+
+fn default_max_buffered_bytes() -> usize {
+    64 * 1024 * 1024
 }
 
 impl Default for M3U8DownloaderConfig {
@@ -60,82 +87,115 @@ impl Default for M3U8DownloaderConfig {
             user_agent: "VideoDownloaderPro/1.0.0".to_string(),
             temp_dir: std::env::temp_dir().join("video_downloader_m3u8"),
             keep_temp_files: false,
+            max_buffered_bytes: default_max_buffered_bytes(),
         }
     }
 }
 
-/// M3U8播放列表信息
-#[derive(Debug, Clone)]
-pub struct M3U8Playlist {
-    /// 播放列表URL
-    pub url: String,
-    /// 基础URL（用于解析相对路径）
-    pub base_url: String,
-    /// 片段列表
-    pub segments: Vec<M3U8Segment>,
-    /// 总时长（秒）
-    pub duration: f64,
-    /// 是否为Live流
-    pub is_live: bool,
-    /// 目标时长
-    pub target_duration: f64,
-    /// 版本
-    pub version: u32,
-    /// 加密信息
-    pub encryption: Option<M3U8Encryption>,
+/// 直播录制上限（None 表示不限制，仍会在 ENDLIST 或手动停止时结束）
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LiveRecordingLimits {
+    /// 已录制媒体时长上限（秒）
+    #[serde(default)]
+    pub max_duration_secs: Option<u64>,
+    /// 已录制字节数上限
+    #[serde(default)]
+    pub max_bytes: Option<u64>,
+}
+
+/// 片段的去处：按顺序写入输出文件，或收集在内存里（字幕合并时要逐段处理 WebVTT 头）
+#[derive(Clone)]
+enum SegmentSink {
+    Ordered(SharedWriter),
+    Collect(Arc<tokio::sync::Mutex<BTreeMap<usize, Vec<u8>>>>),
+}
+
+impl SegmentSink {
+    /// 已提交的片段索引之前的片段无需再下载
+    async fn next_index(&self) -> usize {
+        match self {
+            Self::Ordered(writer) => writer.lock().await.next_index(),
+            Self::Collect(_) => 0,
+        }
+    }
+
+    async fn push(&self, index: usize, init_section: Option<usize>, data: Vec<u8>) -> Result<()> {
+        match self {
+            Self::Ordered(writer) => writer.lock().await.push(index, init_section, data).await,
+            Self::Collect(parts) => {
+                parts.lock().await.insert(index, data);
+                Ok(())
+            }
+        }
+    }
+
+    async fn skip(&self, gap: SegmentGap) -> Result<()> {
+        match self {
+            Self::Ordered(writer) => writer.lock().await.skip(gap).await,
+            Self::Collect(_) => bail!("片段 #{} 缺失: {}", gap.index, gap.error),
+        }
+    }
+
+    async fn checkpoint(&self) -> Result<()> {
+        match self {
+            Self::Ordered(writer) => writer.lock().await.checkpoint().await,
+            Self::Collect(_) => Ok(()),
+        }
+    }
 }
 
-/// M3U8片段信息
-#[derive(Debug, Clone)]
-pub struct M3U8Segment {
-    /// 片段索引
-    pub index: usize,
-    /// 片段URL
-    pub url: String,
-    /// 时长（秒）
-    pub duration: f64,
-    /// 字节范围（可选）
-    pub byte_range: Option<(u64, u64)>,
-    /// 是否已下载
-    pub downloaded: bool,
-    /// 本地文件路径
-    pub local_path: Option<PathBuf>,
-    /// 片段加密信息
-    pub encryption: Option<M3U8Encryption>,
+/// 与视频一起下载的独立音轨/字幕（媒体播放列表已解析、密钥已获取）
+pub(crate) struct MediaTrack {
+    pub rendition: HlsRendition,
+    pub playlist: M3U8Playlist,
 }
 
-/// M3U8加密信息
-#[derive(Debug, Clone)]
-pub struct M3U8Encryption {
-    /// 加密方法
-    pub method: String,
-    /// 密钥URL
-    pub key_url: Option<String>,
-    /// 初始化向量
-    pub iv: Option<String>,
-    /// 密钥数据
-    pub key_data: Option<Vec<u8>>,
+/// 已合并为单个文件的独立音轨/字幕（字幕为 WebVTT）
+struct DownloadedRendition {
+    rendition: HlsRendition,
+    path: PathBuf,
 }
 
 /// M3U8下载器
 pub struct M3U8Downloader {
     config: M3U8DownloaderConfig,
-    client: Client,
+    client: HttpClientHandle,
     active_downloads: Arc<RwLock<HashMap<String, Arc<AtomicBool>>>>,
+    /// 正在录制的直播任务的“停止录制”标记
+    recording_stops: Arc<ParkingRwLock<HashMap<String, Arc<AtomicBool>>>>,
     progress_tx: ProgressSender,
     semaphore: Arc<Semaphore>,
     is_paused: Arc<AtomicBool>,
+    bandwidth_controller: BandwidthController,
+    /// 所有片段共用的重试执行器（指数退避 + jitter，Retry-After 优先）
+    retry_executor: Arc<RetryExecutor>,
 }
 
 impl M3U8Downloader {
     /// 创建新的M3U8下载器实例
     pub fn new(config: M3U8DownloaderConfig) -> Result<Self> {
-        let client = Client::builder()
-            .timeout(Duration::from_secs(config.timeout))
-            .user_agent(&config.user_agent)
-            .build()?;
+        let client = HttpClientHandle::new(HttpClientOptions {
+            timeout: Duration::from_secs(config.timeout),
+            user_agent: config.user_agent.clone(),
+            proxy: None,
+            headers: HeaderRules::default(),
+        })?;
+        Self::with_http_client(config, client)
+    }
 
+    /// 使用共享的 HTTP 客户端创建（代理等网络设置随之生效）
+    pub fn with_http_client(
+        config: M3U8DownloaderConfig,
+        client: HttpClientHandle,
+    ) -> Result<Self> {
         let semaphore = Arc::new(Semaphore::new(config.max_concurrent_segments));
+        let retry_executor = Arc::new(RetryExecutor::new(RetryPolicy {
+            max_attempts: (config.retry_attempts as u32 + 1)
+                .min(error_handling::MAX_RETRY_ATTEMPTS),
+            base_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(30),
+            ..Default::default()
+        }));
 
         // 确保临时目录存在
         std::fs::create_dir_all(&config.temp_dir)?;
@@ -144,73 +204,249 @@ impl M3U8Downloader {
             config,
             client,
             active_downloads: Arc::new(RwLock::new(HashMap::new())),
+            recording_stops: Arc::new(ParkingRwLock::new(HashMap::new())),
             progress_tx: Arc::new(ParkingRwLock::new(None)),
             semaphore,
             is_paused: Arc::new(AtomicBool::new(false)),
+            bandwidth_controller: BandwidthController::new(),
+            retry_executor,
         })
     }
 
+    /// 与 HTTP 下载共用限速器，分片数据计入全局/任务/主机限速
+    pub fn with_bandwidth_controller(mut self, bandwidth_controller: BandwidthController) -> Self {
+        self.bandwidth_controller = bandwidth_controller;
+        self
+    }
+
     /// 设置进度回调
     pub fn set_progress_callback(&self, tx: mpsc::UnboundedSender<(String, DownloadStats)>) {
         *self.progress_tx.write() = Some(tx);
     }
 
-    /// 下载M3U8流
+    /// 下载M3U8流，返回实际写入的文件路径（fMP4 流或封装了独立音轨/字幕时为 .mp4）；
+    /// 直播流会持续录制，直到 ENDLIST、手动停止或达到 `live_limits`；
+    /// 点播流最多容忍 `max_missing_segments` 个重试耗尽的片段，跳过它们并上报缺口
+    #[allow(clippy::too_many_arguments)]
     pub async fn download_m3u8(
         &self,
         task_id: &str,
         m3u8_url: &str,
         output_path: &str,
+        variant_policy: &VariantPolicy,
+        live_limits: &LiveRecordingLimits,
+        max_missing_segments: usize,
+        rendition_preferences: &RenditionPreferences,
         pause_flag: Arc<AtomicBool>,
-    ) -> Result<()> {
+    ) -> Result<String> {
         tracing::info!("开始下载M3U8流: {}", m3u8_url);
-        // 解析M3U8播放列表
-        let playlist = self.parse_m3u8_playlist(m3u8_url).await?;
+        // 解析M3U8播放列表（master playlist 先按策略选定档位和配套的音轨/字幕）
+        let (playlist, variant, renditions) = self
+            .parse_m3u8_playlist(m3u8_url, variant_policy, rendition_preferences)
+            .await?;
+        if let Some(variant) = variant {
+            self.report_selected_variant(task_id, variant);
+        }
         tracing::info!(
             "解析到 {} 个片段，总时长: {:.2}秒",
             playlist.segments.len(),
             playlist.duration
         );
 
-        if playlist.segments.is_empty() {
+        if playlist.segments.is_empty() && !playlist.is_live {
             bail!("M3U8播放列表为空");
         }
 
+        let mut playlist = playlist;
+        let mut key_cache = HashMap::new();
+        self.hydrate_segment_encryption_keys(&mut playlist, &mut key_cache)
+            .await?;
+
+        let tracks = if playlist.is_live {
+            if !renditions.is_empty() {
+                tracing::warn!(
+                    "直播录制暂不下载独立音轨/字幕，忽略 {} 条",
+                    renditions.len()
+                );
+            }
+            Vec::new()
+        } else {
+            futures::future::try_join_all(
+                renditions
+                    .iter()
+                    .map(|rendition| self.load_rendition(rendition)),
+            )
+            .await?
+        };
+
+        self.download_stream(
+            task_id,
+            playlist,
+            tracks,
+            output_path,
+            live_limits,
+            max_missing_segments,
+            rendition_preferences,
+            key_cache,
+            pause_flag,
+        )
+        .await
+    }
+
+    /// 下载已解析的媒体流及其独立音轨/字幕，返回实际写入的文件路径。
+    /// HLS 和 DASH 共用：分片并发、暂停/取消、进度上报、分片清单和 ffmpeg 封装都在这里
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) async fn download_stream(
+        &self,
+        task_id: &str,
+        playlist: M3U8Playlist,
+        tracks: Vec<MediaTrack>,
+        output_path: &str,
+        live_limits: &LiveRecordingLimits,
+        max_missing_segments: usize,
+        rendition_preferences: &RenditionPreferences,
+        mut key_cache: HashMap<String, Vec<u8>>,
+        pause_flag: Arc<AtomicBool>,
+    ) -> Result<String> {
         // 创建任务临时目录
         let task_temp_dir = self.config.temp_dir.join(task_id);
         tokio::fs::create_dir_all(&task_temp_dir).await?;
 
-        let mut playlist = playlist;
-        self.hydrate_segment_encryption_keys(&mut playlist).await?;
-
         let cancel_flag = Arc::new(AtomicBool::new(false));
         {
             let mut downloads = self.active_downloads.write().await;
             downloads.insert(task_id.to_string(), cancel_flag.clone());
         }
+        let is_live = playlist.is_live;
+        let stop_flag = Arc::new(AtomicBool::new(false));
+        if is_live {
+            self.recording_stops
+                .write()
+                .insert(task_id.to_string(), Arc::clone(&stop_flag));
+        }
 
-        // 下载所有片段
-        let result = self
-            .download_segments(
-                task_id,
-                &playlist,
-                &task_temp_dir,
-                cancel_flag.clone(),
-                Arc::clone(&pause_flag),
-            )
-            .await;
+        // 视频按播放列表顺序直接写入输出文件的 .part，写完改名
+        let output_path = Self::output_path_for_container(output_path, playlist.is_fmp4());
+        let spill_dir = task_temp_dir.join("spill");
+
+        let result: Result<Vec<DownloadedRendition>> = async {
+            if playlist.is_live {
+                let writer = OrderedSegmentWriter::open(
+                    Path::new(&output_path),
+                    Vec::new(),
+                    spill_dir,
+                    self.config.max_buffered_bytes,
+                    None,
+                )
+                .await?;
+                let writer = Arc::new(tokio::sync::Mutex::new(writer));
+                let recorded = self
+                    .record_live(
+                        task_id,
+                        playlist,
+                        &writer,
+                        live_limits,
+                        &mut key_cache,
+                        cancel_flag.clone(),
+                        Arc::clone(&pause_flag),
+                        stop_flag,
+                    )
+                    .await;
+                let mut writer = writer.lock().await;
+                // 出错、暂停或取消都按手动停止收尾，保留已录制的内容
+                let gaps = match recorded {
+                    Ok(()) => writer.finish().await?,
+                    Err(e) if writer.next_index() > 0 => {
+                        tracing::warn!(
+                            "直播录制中断（{:#}），保留已录制的 {} 个片段",
+                            e,
+                            writer.next_index()
+                        );
+                        writer.finish_committed().await?
+                    }
+                    Err(e) => return Err(e),
+                };
+                self.report_segment_gaps(task_id, gaps);
+                return Ok(Vec::new());
+            }
+
+            let manifest =
+                SegmentManifest::load_or_new(&self.config.temp_dir, task_id, &playlist.url).await;
+            if manifest.committed_segments > 0 {
+                tracing::info!(
+                    "发现分片清单，已提交 {} 个片段待校验续写",
+                    manifest.committed_segments
+                );
+            }
+
+            // 视频：先下载初始化段，再下载所有片段；独立音轨/字幕同时进行
+            let video = async {
+                let init_sections = self
+                    .download_init_sections(
+                        task_id,
+                        &playlist,
+                        cancel_flag.clone(),
+                        Arc::clone(&pause_flag),
+                    )
+                    .await?;
+                let writer = OrderedSegmentWriter::open(
+                    Path::new(&output_path),
+                    init_sections,
+                    spill_dir,
+                    self.config.max_buffered_bytes,
+                    Some((manifest, self.config.temp_dir.clone())),
+                )
+                .await?;
+                let writer = Arc::new(tokio::sync::Mutex::new(writer));
+                self.download_segments(
+                    task_id,
+                    &playlist,
+                    &SegmentSink::Ordered(Arc::clone(&writer)),
+                    cancel_flag.clone(),
+                    Arc::clone(&pause_flag),
+                    true,
+                    max_missing_segments,
+                )
+                .await?;
+                let gaps = writer.lock().await.finish().await?;
+                Ok::<_, anyhow::Error>(gaps)
+            };
+            let tracks =
+                futures::future::join_all(tracks.iter().enumerate().map(|(index, track)| {
+                    self.download_track(
+                        task_id,
+                        index,
+                        track,
+                        &task_temp_dir,
+                        cancel_flag.clone(),
+                        Arc::clone(&pause_flag),
+                    )
+                }));
+            // 用 join 而不是 try_join：任何一边失败都要等另一边收尾（分片清单需要落盘）
+            let (video, tracks) = tokio::join!(video, tracks);
+            let gaps = video?;
+            let renditions = tracks.into_iter().collect::<Result<Vec<_>>>()?;
+            // 上报被容忍的缺失片段（空列表用于清除上一次的缺口）
+            self.report_segment_gaps(task_id, gaps);
+            Ok(renditions)
+        }
+        .await;
 
         // 清理活跃下载记录
         {
             let mut downloads = self.active_downloads.write().await;
             downloads.remove(task_id);
         }
+        self.recording_stops.write().remove(task_id);
 
         match result {
-            Ok(segment_files) => {
-                tracing::info!("分片下载完成，开始合并");
-
-                self.merge_segments(&segment_files, output_path).await?;
+            Ok(renditions) => {
+                let output_path =
+                    Self::attach_renditions(output_path, &renditions, rendition_preferences)
+                        .await?;
+                SegmentManifest::remove(&self.config.temp_dir, task_id)
+                    .await
+                    .ok();
 
                 if !self.config.keep_temp_files {
                     self.cleanup_temp_files(&task_temp_dir).await?;
@@ -219,221 +455,321 @@ impl M3U8Downloader {
                 }
 
                 tracing::info!("M3U8下载完成: {}", output_path);
-                Ok(())
+                Ok(output_path)
             }
             Err(e) => {
-                if !self.config.keep_temp_files {
-                    self.cleanup_temp_files(&task_temp_dir).await.ok();
+                // 点播流保留已提交的前缀和清单，恢复/重试时续写；取消或未录到内容的直播则丢弃
+                let cancelled = e.to_string().contains("download_cancelled");
+                let part = PartFileWriter::new(&output_path);
+                if !is_live && !cancelled {
+                    tracing::info!(
+                        "下载中断，已写入的连续前缀保留在 {} 供恢复时续写",
+                        part.part_path().display()
+                    );
+                } else if !self.config.keep_temp_files {
+                    self.discard_task_files(task_id).await;
+                    part.remove_part_if_exists().await.ok();
                 } else {
-                    tracing::warn!("下载失败，临时分片保留在: {}", task_temp_dir.display());
+                    tracing::warn!("下载失败，临时文件保留在: {}", task_temp_dir.display());
                 }
                 Err(e)
             }
         }
     }
 
-    /// 解析M3U8播放列表
-    async fn parse_m3u8_playlist(&self, m3u8_url: &str) -> Result<M3U8Playlist> {
-        tracing::debug!("获取M3U8播放列表: {}", m3u8_url);
+    /// 解析M3U8播放列表；master playlist 返回选中的档位及需要单独下载的音轨/字幕
+    async fn parse_m3u8_playlist(
+        &self,
+        m3u8_url: &str,
+        variant_policy: &VariantPolicy,
+        rendition_preferences: &RenditionPreferences,
+    ) -> Result<(M3U8Playlist, Option<HlsVariant>, Vec<HlsRendition>)> {
+        let content = self.fetch_playlist_text(m3u8_url).await?;
+        let master = match m3u8_parser::parse_hls_playlist(m3u8_url, &content)? {
+            HlsPlaylist::Media(playlist) => return Ok((*playlist, None, Vec::new())),
+            HlsPlaylist::Master(master) => master,
+        };
 
-        let response = self.client.get(m3u8_url).send().await?;
+        let variants = master.variants;
+        let variant = hls_variant::select_variant(&variants, variant_policy)
+            .cloned()
+            .ok_or_else(|| anyhow!("master playlist 中没有可用的档位"))?;
+        tracing::info!(
+            "master playlist 共 {} 个档位，按 {:?} 选中 {} bps {}",
+            variants.len(),
+            variant_policy,
+            variant.bandwidth,
+            variant
+                .height
+                .map(|height| format!("{}p", height))
+                .unwrap_or_default()
+        );
 
-        if !response.status().is_success() {
-            bail!("获取M3U8播放列表失败: {}", response.status());
+        let media_content = self.fetch_playlist_text(&variant.uri).await?;
+        let playlist = self
+            .parse_m3u8_content(&variant.uri, &media_content)
+            .await?;
+
+        let renditions: Vec<HlsRendition> =
+            hls_variant::select_renditions(&master.renditions, &variant, rendition_preferences)
+                .into_iter()
+                .cloned()
+                .collect();
+        for rendition in &renditions {
+            tracing::info!(
+                "选中独立{}: {} ({})",
+                match rendition.kind {
+                    RenditionKind::Audio => "音轨",
+                    RenditionKind::Subtitles => "字幕",
+                },
+                rendition.name,
+                rendition.language.as_deref().unwrap_or("-")
+            );
         }
+        Ok((playlist, Some(variant), renditions))
+    }
 
-        let content = response.text().await?;
-        self.parse_m3u8_content(m3u8_url, &content).await
+    /// 获取并解析独立音轨/字幕的媒体播放列表
+    async fn load_rendition(&self, rendition: &HlsRendition) -> Result<MediaTrack> {
+        let uri = rendition
+            .uri
+            .as_deref()
+            .ok_or_else(|| anyhow!("{} 没有可下载的 URI", rendition.name))?;
+        let content = self.fetch_playlist_text(uri).await?;
+        let mut playlist = self.parse_m3u8_content(uri, &content).await?;
+        if playlist.segments.is_empty() {
+            bail!("{} 的播放列表为空", rendition.name);
+        }
+        self.hydrate_segment_encryption_keys(&mut playlist, &mut HashMap::new())
+            .await?;
+        Ok(MediaTrack {
+            rendition: rendition.clone(),
+            playlist,
+        })
     }
 
-    /// 解析M3U8内容
-    async fn parse_m3u8_content(&self, m3u8_url: &str, content: &str) -> Result<M3U8Playlist> {
-        let base_url = self.get_base_url(m3u8_url)?;
-        let mut playlist = M3U8Playlist {
-            url: m3u8_url.to_string(),
-            base_url,
-            segments: Vec::new(),
-            duration: 0.0,
-            is_live: false,
-            target_duration: 0.0,
-            version: 1,
-            encryption: None,
+    /// 下载一条独立音轨/字幕并写成单个文件
+    async fn download_track(
+        &self,
+        task_id: &str,
+        index: usize,
+        track: &MediaTrack,
+        temp_dir: &Path,
+        cancel_flag: Arc<AtomicBool>,
+        pause_flag: Arc<AtomicBool>,
+    ) -> Result<DownloadedRendition> {
+        let (rendition, playlist) = (&track.rendition, &track.playlist);
+        let path = match rendition.kind {
+            RenditionKind::Subtitles => {
+                let parts = Arc::new(tokio::sync::Mutex::new(BTreeMap::new()));
+                self.download_segments(
+                    task_id,
+                    playlist,
+                    &SegmentSink::Collect(Arc::clone(&parts)),
+                    cancel_flag,
+                    pause_flag,
+                    false,
+                    0,
+                )
+                .await?;
+                let parts: Vec<String> = parts
+                    .lock()
+                    .await
+                    .values()
+                    .map(|data| String::from_utf8_lossy(data).into_owned())
+                    .collect();
+                let path = temp_dir.join(format!("rendition_{:02}.vtt", index));
+                tokio::fs::write(&path, hls_mux::merge_webvtt(&parts)).await?;
+                path
+            }
+            RenditionKind::Audio => {
+                let path = temp_dir.join(format!(
+                    "rendition_{:02}.{}",
+                    index,
+                    Self::audio_extension(playlist)
+                ));
+                let init_sections = self
+                    .download_init_sections(
+                        task_id,
+                        playlist,
+                        cancel_flag.clone(),
+                        pause_flag.clone(),
+                    )
+                    .await?;
+                let writer = OrderedSegmentWriter::open(
+                    &path,
+                    init_sections,
+                    temp_dir.join(format!("rendition_{:02}_spill", index)),
+                    self.config.max_buffered_bytes,
+                    None,
+                )
+                .await?;
+                let writer = Arc::new(tokio::sync::Mutex::new(writer));
+                self.download_segments(
+                    task_id,
+                    playlist,
+                    &SegmentSink::Ordered(Arc::clone(&writer)),
+                    cancel_flag,
+                    pause_flag,
+                    false,
+                    0,
+                )
+                .await?;
+                writer.lock().await.finish().await?;
+                path
+            }
         };
+        Ok(DownloadedRendition {
+            rendition: rendition.clone(),
+            path,
+        })
+    }
 
-        let lines: Vec<&str> = content.lines().collect();
+    /// 独立音轨的容器：fMP4 为 m4a，packed audio 沿用片段扩展名，其余按 TS 处理
+    fn audio_extension(playlist: &M3U8Playlist) -> &'static str {
+        if playlist.is_fmp4() {
+            return "m4a";
+        }
+        let extension = playlist
+            .segments
+            .first()
+            .and_then(|segment| Url::parse(&segment.url).ok())
+            .and_then(|url| {
+                Path::new(url.path())
+                    .extension()
+                    .map(|ext| ext.to_string_lossy().to_ascii_lowercase())
+            });
+        match extension.as_deref() {
+            Some("aac") => "aac",
+            Some("mp3") => "mp3",
+            Some("ac3") => "ac3",
+            Some("ec3") | Some("eac3") => "eac3",
+            _ => "ts",
+        }
+    }
 
-        // 验证是否为有效的M3U8文件
-        if !lines.first().unwrap_or(&"").starts_with("#EXTM3U") {
-            bail!("无效的M3U8文件格式");
+    /// 把独立音轨/字幕封装进视频；ffmpeg 不可用或失败时写成视频旁的独立文件。
+    /// 返回最终的视频路径
+    async fn attach_renditions(
+        output_path: String,
+        tracks: &[DownloadedRendition],
+        preferences: &RenditionPreferences,
+    ) -> Result<String> {
+        if tracks.is_empty() {
+            return Ok(output_path);
         }
+        let video = PathBuf::from(&output_path);
+        let (mut sidecars, to_mux): (Vec<&DownloadedRendition>, Vec<&DownloadedRendition>) =
+            tracks.iter().partition(|track| {
+                track.rendition.kind == RenditionKind::Subtitles && preferences.subtitle_sidecar
+            });
 
-        let mut i = 0;
-        let mut segment_index = 0;
-        let mut current_segment_duration = 0.0;
-        let mut pending_byte_range: Option<(u64, u64)> = None;
-        let mut last_byte_range_end: Option<u64> = None;
-        let mut current_encryption: Option<M3U8Encryption> = None;
-
-        while i < lines.len() {
-            let line = lines[i].trim();
-
-            if line.is_empty() || line.starts_with('#') {
-                if line.starts_with("#EXT-X-VERSION:") {
-                    playlist.version = line
-                        .replace("#EXT-X-VERSION:", "")
-                        .parse::<u32>()
-                        .unwrap_or(1);
-                } else if line.starts_with("#EXT-X-TARGETDURATION:") {
-                    playlist.target_duration = line
-                        .replace("#EXT-X-TARGETDURATION:", "")
-                        .parse::<f64>()
-                        .unwrap_or(0.0);
-                } else if line.starts_with("#EXTINF:") {
-                    // 解析片段时长
-                    let line_without_prefix = line.replace("#EXTINF:", "");
-                    let duration_str = line_without_prefix.split(',').next().unwrap_or("0");
-                    current_segment_duration = duration_str.parse::<f64>().unwrap_or(0.0);
-                } else if line.starts_with("#EXT-X-KEY:") {
-                    // 解析加密信息
-                    current_encryption = self.parse_encryption_line(line)?;
-                    if let Some(ref mut encryption) = current_encryption {
-                        self.resolve_encryption_key_url(&playlist.base_url, encryption)?;
-                    }
-                    playlist.encryption = current_encryption.clone();
-                } else if line.starts_with("#EXT-X-BYTERANGE:") {
-                    let value = line.replace("#EXT-X-BYTERANGE:", "");
-                    let mut parts = value.split('@');
-                    let length = parts
-                        .next()
-                        .unwrap_or("0")
-                        .trim()
-                        .parse::<u64>()
-                        .unwrap_or(0);
-                    if length == 0 {
-                        tracing::warn!("检测到长度为 0 的 EXT-X-BYTERANGE: {}", line);
-                        pending_byte_range = None;
-                    } else {
-                        let start = parts
-                            .next()
-                            .map(|s| s.trim().parse::<u64>().unwrap_or(0))
-                            .or_else(|| last_byte_range_end.map(|end| end + 1))
-                            .unwrap_or(0);
-                        let end = start.saturating_add(length.saturating_sub(1));
-                        pending_byte_range = Some((start, end));
+        let mut final_path = video.clone();
+        if !to_mux.is_empty() {
+            let inputs: Vec<MuxInput> = to_mux
+                .iter()
+                .map(|track| MuxInput {
+                    path: track.path.clone(),
+                    kind: track.rendition.kind,
+                    language: track.rendition.language.clone(),
+                })
+                .collect();
+            let staging = hls_mux::sidecar_path(&video, "muxing", "mp4");
+            match hls_mux::mux_renditions(&video, &inputs, &staging).await {
+                Ok(()) => {
+                    let muxed = video.with_extension("mp4");
+                    tokio::fs::rename(&staging, &muxed).await?;
+                    if muxed != video {
+                        tokio::fs::remove_file(&video).await.ok();
                     }
-                } else if line.contains("#EXT-X-ENDLIST") {
-                    // 非Live流
-                    playlist.is_live = false;
+                    final_path = muxed;
+                }
+                Err(e) => {
+                    tracing::warn!("独立音轨/字幕封装失败，改为写成独立文件: {}", e);
+                    tokio::fs::remove_file(&staging).await.ok();
+                    sidecars.extend(to_mux);
                 }
-                i += 1;
-                continue;
             }
+        }
 
-            // 处理片段URL
-            let segment_url = if line.starts_with("http") {
-                line.to_string()
-            } else {
-                self.resolve_relative_url(&playlist.base_url, line)?
-            };
-
-            let byte_range = pending_byte_range.take();
-            if let Some((_, end)) = byte_range {
-                last_byte_range_end = Some(end);
+        for track in sidecars {
+            let label = track
+                .rendition
+                .language
+                .clone()
+                .unwrap_or_else(|| track.rendition.name.clone());
+            match track.rendition.kind {
+                RenditionKind::Subtitles => {
+                    let vtt = tokio::fs::read_to_string(&track.path).await?;
+                    let (content, extension) = match preferences.subtitle_format {
+                        SubtitleFormat::Vtt => (vtt, "vtt"),
+                        SubtitleFormat::Srt => (hls_mux::webvtt_to_srt(&vtt), "srt"),
+                    };
+                    let path = hls_mux::sidecar_path(&final_path, &label, extension);
+                    tokio::fs::write(&path, content).await?;
+                    tracing::info!("字幕已保存: {}", path.display());
+                }
+                RenditionKind::Audio => {
+                    let extension = track
+                        .path
+                        .extension()
+                        .map(|ext| ext.to_string_lossy().to_string())
+                        .unwrap_or_else(|| "ts".to_string());
+                    let path = hls_mux::sidecar_path(&final_path, &label, &extension);
+                    tokio::fs::copy(&track.path, &path).await?;
+                    tracing::info!("音轨已保存: {}", path.display());
+                }
             }
-
-            let segment = M3U8Segment {
-                index: segment_index,
-                url: segment_url,
-                duration: current_segment_duration,
-                byte_range,
-                downloaded: false,
-                local_path: None,
-                encryption: current_encryption.clone(),
-            };
-
-            playlist.segments.push(segment);
-            playlist.duration += current_segment_duration;
-            segment_index += 1;
-            current_segment_duration = 0.0;
-
-            i += 1;
         }
-
-        tracing::debug!(
-            "解析完成: {} 个片段, 总时长: {:.2}秒",
-            playlist.segments.len(),
-            playlist.duration
-        );
-
-        Ok(playlist)
+        Ok(final_path.to_string_lossy().to_string())
     }
 
-    /// 解析加密行信息
-    fn parse_encryption_line(&self, line: &str) -> Result<Option<M3U8Encryption>> {
-        let key_line = line.replace("#EXT-X-KEY:", "");
-        let parts: Vec<&str> = key_line.split(',').collect();
-
-        let mut method = String::new();
-        let mut key_url = None;
-        let mut iv = None;
-
-        for part in parts {
-            let kv: Vec<&str> = part.split('=').collect();
-            if kv.len() != 2 {
-                continue;
-            }
-
-            let key = kv[0].trim();
-            let value = kv[1].trim().trim_matches('"');
+    async fn fetch_playlist_text(&self, m3u8_url: &str) -> Result<String> {
+        tracing::debug!("获取M3U8播放列表: {}", m3u8_url);
 
-            match key {
-                "METHOD" => method = value.to_string(),
-                "URI" => key_url = Some(value.to_string()),
-                "IV" => iv = Some(value.to_string()),
-                _ => {}
-            }
-        }
+        let response = self.client.send(self.client.get(m3u8_url)).await?;
 
-        if method == "NONE" {
-            return Ok(None);
+        if !response.status().is_success() {
+            bail!("获取M3U8播放列表失败: {}", response.status());
         }
 
-        Ok(Some(M3U8Encryption {
-            method,
-            key_url,
-            iv,
-            key_data: None,
-        }))
+        Ok(response.text().await?)
     }
 
-    fn resolve_encryption_key_url(
-        &self,
-        base_url: &str,
-        encryption: &mut M3U8Encryption,
-    ) -> Result<()> {
-        let Some(key_url) = encryption.key_url.as_deref() else {
-            return Ok(());
-        };
-
-        if Url::parse(key_url).is_ok() {
-            return Ok(());
+    /// 通过进度通道把选中的档位告知任务管理器
+    pub(crate) fn report_selected_variant(&self, task_id: &str, variant: HlsVariant) {
+        if let Some(tx) = self.progress_tx.read().as_ref() {
+            let stats = DownloadStats {
+                hls_variant: Some(variant),
+                ..DownloadStats::default()
+            };
+            let _ = tx.send((task_id.to_string(), stats));
         }
-
-        encryption.key_url = Some(Url::parse(base_url)?.join(key_url)?.to_string());
-        Ok(())
     }
 
-    /// 获取基础URL
-    fn get_base_url(&self, m3u8_url: &str) -> Result<String> {
-        let url = Url::parse(m3u8_url)?;
-        Ok(url.to_string())
+    /// 通过进度通道把本次下载跳过的片段告知任务管理器
+    fn report_segment_gaps(&self, task_id: &str, gaps: Vec<SegmentGap>) {
+        for gap in &gaps {
+            tracing::warn!(
+                "片段 #{} ({:.2}s - {:.2}s) 重试后仍失败，已跳过: {}",
+                gap.index,
+                gap.start_secs,
+                gap.end_secs,
+                gap.error
+            );
+        }
+        if let Some(tx) = self.progress_tx.read().as_ref() {
+            let stats = DownloadStats {
+                segment_gaps: Some(gaps),
+                ..DownloadStats::default()
+            };
+            let _ = tx.send((task_id.to_string(), stats));
+        }
     }
 
-    /// 解析相对URL
-    fn resolve_relative_url(&self, base_url: &str, relative_url: &str) -> Result<String> {
-        let base = Url::parse(base_url)?;
-        let resolved = base.join(relative_url)?;
-        Ok(resolved.to_string())
+    /// 解析M3U8内容
+    async fn parse_m3u8_content(&self, m3u8_url: &str, content: &str) -> Result<M3U8Playlist> {
+        m3u8_parser::parse_media_playlist(m3u8_url, content)
     }
 
     /// 获取加密密钥
@@ -441,7 +777,7 @@ impl M3U8Downloader {
         if let Some(ref key_url) = encryption.key_url {
             tracing::debug!("获取加密密钥: {}", key_url);
 
-            let response = self.client.get(key_url).send().await?;
+            let response = self.client.send(self.client.get(key_url)).await?;
             if !response.status().is_success() {
                 bail!("获取加密密钥失败: {}", response.status());
             }
@@ -453,17 +789,38 @@ impl M3U8Downloader {
         }
     }
 
-    async fn hydrate_segment_encryption_keys(&self, playlist: &mut M3U8Playlist) -> Result<()> {
-        let mut key_cache: HashMap<String, Vec<u8>> = HashMap::new();
-
-        for segment in &mut playlist.segments {
-            let Some(encryption) = segment.encryption.as_mut() else {
+    async fn hydrate_segment_encryption_keys(
+        &self,
+        playlist: &mut M3U8Playlist,
+        key_cache: &mut HashMap<String, Vec<u8>>,
+    ) -> Result<()> {
+        let cached_before = key_cache.len();
+
+        let encryptions = playlist
+            .segments
+            .iter_mut()
+            .map(|segment| &mut segment.encryption)
+            .chain(
+                playlist
+                    .init_sections
+                    .iter_mut()
+                    .map(|section| &mut section.encryption),
+            );
+        for encryption in encryptions {
+            let Some(encryption) = encryption.as_mut() else {
                 continue;
             };
 
             if encryption.method.to_uppercase() == "NONE" || encryption.key_data.is_some() {
                 continue;
             }
+            if !encryption.is_identity_key() {
+                bail!(
+                    "drm_protected: {} 加密使用 KEYFORMAT={}，无法获取密钥",
+                    encryption.method,
+                    encryption.key_format.as_deref().unwrap_or_default()
+                );
+            }
 
             let Some(key_url) = encryption.key_url.clone() else {
                 continue;
@@ -483,30 +840,357 @@ impl M3U8Downloader {
             encryption.key_data = Some(key);
         }
 
-        if !key_cache.is_empty() {
-            tracing::info!("已获取 {} 个 HLS AES-128 密钥", key_cache.len());
+        if key_cache.len() > cached_before {
+            tracing::info!(
+                "已获取 {} 个 HLS AES-128 密钥",
+                key_cache.len() - cached_before
+            );
+        }
+
+        Ok(())
+    }
+
+    /// 下载 EXT-X-MAP 初始化段（加密时按声明时的 EXT-X-KEY 解密），内容留在内存里
+    async fn download_init_sections(
+        &self,
+        task_id: &str,
+        playlist: &M3U8Playlist,
+        cancel_flag: Arc<AtomicBool>,
+        pause_flag: Arc<AtomicBool>,
+    ) -> Result<Vec<Vec<u8>>> {
+        let mut init_sections = Vec::with_capacity(playlist.init_sections.len());
+        for (index, section) in playlist.init_sections.iter().enumerate() {
+            // 规范要求加密的初始化段带 IV；缺省时按其后第一个片段的序号推导
+            let iv_sequence = playlist
+                .segments
+                .iter()
+                .find(|segment| segment.init_section == Some(index))
+                .map(|segment| segment.sequence)
+                .unwrap_or(0);
+            let data = self
+                .download_init_section(
+                    task_id,
+                    index,
+                    section,
+                    iv_sequence,
+                    cancel_flag.clone(),
+                    pause_flag.clone(),
+                )
+                .await?;
+            init_sections.push(data);
+        }
+        Ok(init_sections)
+    }
+
+    async fn download_init_section(
+        &self,
+        task_id: &str,
+        index: usize,
+        section: &M3U8InitSection,
+        iv_sequence: u64,
+        cancel_flag: Arc<AtomicBool>,
+        pause_flag: Arc<AtomicBool>,
+    ) -> Result<Vec<u8>> {
+        Self::download_segment_static(
+            &self.client,
+            &self.retry_executor,
+            &self.bandwidth_controller,
+            task_id,
+            &section.url,
+            section.byte_range,
+            iv_sequence,
+            section.encryption.clone(),
+            cancel_flag,
+            pause_flag,
+        )
+        .await
+        .map_err(|e| anyhow!("初始化段 #{} ({}) 下载失败: {:#}", index, section.url, e))
+    }
+
+    /// 直播录制：每隔一个目标时长刷新媒体播放列表，按媒体序号只追加新片段
+    #[allow(clippy::too_many_arguments)]
+    async fn record_live(
+        &self,
+        task_id: &str,
+        mut snapshot: M3U8Playlist,
+        writer: &SharedWriter,
+        limits: &LiveRecordingLimits,
+        key_cache: &mut HashMap<String, Vec<u8>>,
+        cancel_flag: Arc<AtomicBool>,
+        pause_flag: Arc<AtomicBool>,
+        stop_flag: Arc<AtomicBool>,
+    ) -> Result<()> {
+        tracing::info!("检测到直播流，开始录制: {}", snapshot.url);
+        let started = Instant::now();
+        let mut recorded = M3U8Playlist {
+            segments: Vec::new(),
+            init_sections: Vec::new(),
+            duration: 0.0,
+            ..snapshot.clone()
+        };
+        let mut recorded_bytes = 0u64;
+        let mut next_sequence: Option<u64> = None;
+        let mut stalled_reloads = 0u32;
+        let mut empty_batches = 0u32;
+
+        loop {
+            self.hydrate_segment_encryption_keys(&mut snapshot, key_cache)
+                .await?;
+            let new_segments: Vec<M3U8Segment> = snapshot
+                .segments
+                .iter()
+                .filter(|segment| next_sequence.is_none_or(|next| segment.sequence >= next))
+                .cloned()
+                .collect();
+
+            if let Some(first) = new_segments.first() {
+                stalled_reloads = 0;
+                if let Some(expected) = next_sequence.filter(|expected| first.sequence > *expected)
+                {
+                    tracing::warn!(
+                        "直播录制跳过了 {} 个已滑出播放列表的片段",
+                        first.sequence - expected
+                    );
+                }
+                next_sequence = new_segments.last().map(|segment| segment.sequence + 1);
+
+                let mut batch = Vec::with_capacity(new_segments.len());
+                for mut segment in new_segments {
+                    segment.index = recorded.segments.len() + batch.len();
+                    if let Some(snapshot_init) = segment.init_section {
+                        let section = &snapshot.init_sections[snapshot_init];
+                        let existing = recorded.init_sections.iter().position(|known| {
+                            known.url == section.url && known.byte_range == section.byte_range
+                        });
+                        segment.init_section = Some(match existing {
+                            Some(index) => index,
+                            None => {
+                                let data = self
+                                    .download_init_section(
+                                        task_id,
+                                        recorded.init_sections.len(),
+                                        section,
+                                        segment.sequence,
+                                        cancel_flag.clone(),
+                                        pause_flag.clone(),
+                                    )
+                                    .await?;
+                                recorded.init_sections.push(section.clone());
+                                writer.lock().await.add_init_section(data)
+                            }
+                        });
+                    }
+                    batch.push(segment);
+                }
+
+                let batch = M3U8Playlist {
+                    segments: batch,
+                    init_sections: recorded.init_sections.clone(),
+                    ..recorded.clone()
+                };
+                // 直播片段滑出窗口后无法重下，重试耗尽的片段一律记为缺口继续录制
+                self.download_segments(
+                    task_id,
+                    &batch,
+                    &SegmentSink::Ordered(Arc::clone(writer)),
+                    cancel_flag.clone(),
+                    pause_flag.clone(),
+                    false,
+                    usize::MAX,
+                )
+                .await?;
+                let committed_bytes = writer.lock().await.committed_bytes();
+                if committed_bytes == recorded_bytes {
+                    empty_batches += 1;
+                } else {
+                    empty_batches = 0;
+                }
+                recorded_bytes = committed_bytes;
+                recorded.duration += batch
+                    .segments
+                    .iter()
+                    .map(|segment| segment.duration)
+                    .sum::<f64>();
+                recorded.segments.extend(batch.segments);
+                self.report_recording_progress(task_id, started, recorded_bytes, &recorded);
+            } else {
+                stalled_reloads += 1;
+            }
+
+            let stop_reason = if !snapshot.is_live {
+                Some("播放列表已结束 (EXT-X-ENDLIST)")
+            } else if stop_flag.load(Ordering::Relaxed) {
+                Some("手动停止")
+            } else if limits
+                .max_duration_secs
+                .is_some_and(|max| recorded.duration >= max as f64)
+            {
+                Some("达到录制时长上限")
+            } else if limits.max_bytes.is_some_and(|max| recorded_bytes >= max) {
+                Some("达到录制大小上限")
+            } else if stalled_reloads >= LIVE_STALL_RELOADS {
+                Some("播放列表长时间未更新")
+            } else if empty_batches >= LIVE_STALL_RELOADS {
+                Some("连续多次刷新的新片段全部下载失败")
+            } else {
+                None
+            };
+            if let Some(reason) = stop_reason {
+                tracing::info!(
+                    "直播录制结束（{}）: {} 个片段, {:.1} 秒",
+                    reason,
+                    recorded.segments.len(),
+                    recorded.duration
+                );
+                break;
+            }
+
+            // 有新片段时等一个目标时长，否则按规范等半个目标时长再刷新
+            let target = Duration::from_secs_f64(snapshot.target_duration.max(1.0));
+            let wait = if stalled_reloads > 0 {
+                target / 2
+            } else {
+                target
+            };
+            if !Self::wait_for_reload(wait, &cancel_flag, &pause_flag, &stop_flag).await? {
+                continue;
+            }
+
+            match self.fetch_playlist_text(&recorded.url).await {
+                Ok(content) => match self.parse_m3u8_content(&recorded.url, &content).await {
+                    Ok(reloaded) => snapshot = reloaded,
+                    Err(e) => tracing::warn!("直播播放列表解析失败，稍后重试: {}", e),
+                },
+                Err(e) => tracing::warn!("直播播放列表刷新失败，稍后重试: {}", e),
+            }
         }
 
+        if recorded.segments.is_empty() {
+            bail!("直播录制未获取到任何片段");
+        }
         Ok(())
     }
 
-    /// 下载所有片段
+    /// 等待下一次刷新；取消/暂停时返回错误，手动停止时返回 false（不再刷新）
+    async fn wait_for_reload(
+        wait: Duration,
+        cancel_flag: &AtomicBool,
+        pause_flag: &AtomicBool,
+        stop_flag: &AtomicBool,
+    ) -> Result<bool> {
+        let deadline = Instant::now() + wait;
+        loop {
+            if cancel_flag.load(Ordering::Relaxed) {
+                bail!("download_cancelled");
+            }
+            if pause_flag.load(Ordering::Relaxed) {
+                bail!("download_paused");
+            }
+            if stop_flag.load(Ordering::Relaxed) {
+                return Ok(false);
+            }
+            let now = Instant::now();
+            if now >= deadline {
+                return Ok(true);
+            }
+            tokio::time::sleep((deadline - now).min(Duration::from_millis(200))).await;
+        }
+    }
+
+    /// 录制没有总大小，进度上报已录制时长而不是百分比
+    fn report_recording_progress(
+        &self,
+        task_id: &str,
+        started: Instant,
+        recorded_bytes: u64,
+        recorded: &M3U8Playlist,
+    ) {
+        let Some(tx) = self.progress_tx.read().clone() else {
+            return;
+        };
+        let elapsed = started.elapsed();
+        let stats = DownloadStats {
+            speed: if elapsed.as_secs_f64() > 0.0 {
+                recorded_bytes as f64 / elapsed.as_secs_f64()
+            } else {
+                0.0
+            },
+            downloaded_bytes: recorded_bytes,
+            recording: Some(LiveRecordingProgress {
+                elapsed_secs: elapsed.as_secs(),
+                recorded_secs: recorded.duration,
+                segments: recorded.segments.len(),
+            }),
+            ..DownloadStats::default()
+        };
+        let _ = tx.send((task_id.to_string(), stats));
+    }
+
+    /// 请求结束直播录制；任务不在录制中时返回 false
+    pub fn stop_recording(&self, task_id: &str) -> bool {
+        match self.recording_stops.read().get(task_id) {
+            Some(flag) => {
+                flag.store(true, Ordering::Relaxed);
+                tracing::info!("请求结束直播录制: {}", task_id);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// 按容器格式修正输出扩展名：fMP4 写 .mp4，TS 写 .ts；
+    /// 仅替换 .m3u8/.m3u 或缺失的扩展名（fMP4 额外替换 .ts），用户给定的其他扩展名保持不变
+    fn output_path_for_container(output_path: &str, is_fmp4: bool) -> String {
+        let path = Path::new(output_path);
+        let extension = path
+            .extension()
+            .map(|ext| ext.to_string_lossy().to_ascii_lowercase());
+        let replace = match extension.as_deref() {
+            None | Some("m3u8") | Some("m3u") => true,
+            Some("ts") => is_fmp4,
+            Some(_) => false,
+        };
+        if !replace {
+            return output_path.to_string();
+        }
+        let target = if is_fmp4 { "mp4" } else { "ts" };
+        path.with_extension(target).to_string_lossy().to_string()
+    }
+
+    /// 下载所有片段并交给 `sink`；写入器已提交的前缀直接跳过（恢复时续写）。
+    /// 重试耗尽的片段不超过 `max_missing` 个时跳过它们并记为缺口，否则整体失败
+    #[allow(clippy::too_many_arguments)]
     async fn download_segments(
         &self,
         task_id: &str,
         playlist: &M3U8Playlist,
-        temp_dir: &Path,
+        sink: &SegmentSink,
         cancel_flag: Arc<AtomicBool>,
         pause_flag: Arc<AtomicBool>,
-    ) -> Result<Vec<PathBuf>> {
-        tracing::info!("开始下载 {} 个片段", playlist.segments.len());
+        report_progress: bool,
+        max_missing: usize,
+    ) -> Result<()> {
+        let committed = sink.next_index().await;
+        let resumed = playlist
+            .segments
+            .iter()
+            .filter(|segment| segment.index < committed)
+            .count();
+        tracing::info!(
+            "开始下载 {} 个片段（已提交 {} 个）",
+            playlist.segments.len(),
+            resumed
+        );
 
-        let mut segment_files = Vec::with_capacity(playlist.segments.len());
         let mut handles = Vec::new();
-
         let total_segments = playlist.segments.len();
-        let downloaded_count = Arc::new(AtomicU64::new(0));
-        let downloaded_bytes = Arc::new(AtomicU64::new(0));
+        let downloaded_count = Arc::new(AtomicU64::new(resumed as u64));
+        let resumed_bytes = match sink {
+            SegmentSink::Ordered(writer) if resumed > 0 => writer.lock().await.committed_bytes(),
+            _ => 0,
+        };
+        let downloaded_bytes = Arc::new(AtomicU64::new(resumed_bytes));
+        let missing = Arc::new(AtomicUsize::new(0));
         let total_bytes_hint = if playlist.segments.iter().all(|seg| seg.byte_range.is_some()) {
             let mut sum = 0u64;
             for segment in &playlist.segments {
@@ -519,36 +1203,56 @@ impl M3U8Downloader {
             None
         };
         let start_time = Instant::now();
-
-        for (index, segment) in playlist.segments.iter().enumerate() {
-            if cancel_flag.load(Ordering::Relaxed)
+        let mut first_error = None;
+        let mut segment_start = 0.0;
+
+        for segment in &playlist.segments {
+            let mut gap = SegmentGap {
+                index: segment.index,
+                start_secs: segment_start,
+                end_secs: segment_start + segment.duration,
+                error: String::new(),
+            };
+            segment_start = gap.end_secs;
+            if segment.index < committed {
+                continue;
+            }
+            if cancel_flag.load(Ordering::Relaxed)
                 || pause_flag.load(Ordering::Relaxed)
                 || self.is_paused.load(Ordering::Relaxed)
             {
-                return Err(if cancel_flag.load(Ordering::Relaxed) {
+                // 已派发的片段仍需等待结束，以便把已提交的前缀记入分片清单
+                first_error = Some(if cancel_flag.load(Ordering::Relaxed) {
                     anyhow::anyhow!("download_cancelled")
                 } else {
                     anyhow::anyhow!("download_paused")
                 });
+                break;
             }
-            let segment_file = temp_dir.join(format!("segment_{:06}.ts", index));
-            segment_files.push(segment_file.clone());
-
             let semaphore = Arc::clone(&self.semaphore);
             let client = self.client.clone();
             let segment_url = segment.url.clone();
-            let config = self.config.clone();
+            let retry_executor = Arc::clone(&self.retry_executor);
             let cancel_flag = Arc::clone(&cancel_flag);
             let downloaded_count = Arc::clone(&downloaded_count);
             let downloaded_bytes = Arc::clone(&downloaded_bytes);
             let task_id = task_id.to_string();
             let progress_tx: Option<mpsc::UnboundedSender<(String, DownloadStats)>> =
-                { self.progress_tx.read().clone() };
+                if report_progress {
+                    self.progress_tx.read().clone()
+                } else {
+                    None
+                };
             let byte_range = segment.byte_range;
             let encryption = segment.encryption.clone();
             let segment_index = segment.index;
+            let init_section = segment.init_section;
+            let sequence = segment.sequence;
             let pause_flag = Arc::clone(&pause_flag);
             let global_pause = Arc::clone(&self.is_paused);
+            let bandwidth_controller = self.bandwidth_controller.clone();
+            let sink = sink.clone();
+            let missing = Arc::clone(&missing);
 
             let handle = tokio::spawn(async move {
                 let _permit = semaphore
@@ -573,28 +1277,39 @@ impl M3U8Downloader {
                     total_segments,
                     segment_url
                 );
-
-                let bytes_written = Self::download_segment_static(
+                let data = match Self::download_segment_static(
                     &client,
-                    &config,
+                    &retry_executor,
+                    &bandwidth_controller,
+                    &task_id,
                     &segment_url,
-                    &segment_file,
                     byte_range,
-                    segment_index,
+                    sequence,
                     encryption,
                     cancel_flag.clone(),
                     pause_flag.clone(),
                 )
                 .await
-                .map_err(|e| {
-                    anyhow::anyhow!(
-                        "片段 #{}/{} ({}) 下载失败: {}",
-                        segment_index,
-                        total_segments,
-                        segment_url,
-                        e
-                    )
-                })?;
+                {
+                    Ok(data) => data,
+                    Err(e) if e.to_string().starts_with("download_") => return Err(e),
+                    Err(e) => {
+                        let error = anyhow!(
+                            "片段 #{}/{} ({}) 下载失败: {:#}",
+                            segment_index,
+                            total_segments,
+                            segment_url,
+                            e
+                        );
+                        if missing.fetch_add(1, Ordering::SeqCst) >= max_missing {
+                            return Err(error);
+                        }
+                        gap.error = format!("{:#}", error);
+                        return sink.skip(gap).await;
+                    }
+                };
+                let bytes_written = data.len() as u64;
+                sink.push(segment_index, init_section, data).await?;
 
                 let current_downloaded = downloaded_count.fetch_add(1, Ordering::Relaxed) + 1;
                 let total_written =
@@ -643,6 +1358,13 @@ impl M3U8Downloader {
                         },
                         start_time: chrono::Utc::now(),
                         last_update: chrono::Utc::now(),
+                        sources: Vec::new(),
+                        resolved_path: None,
+                        hls_variant: None,
+                        recording: None,
+                        segment_gaps: None,
+                        selected_format: None,
+                        sidecar_files: None,
                     };
                     let _ = tx.send((task_id.clone(), stats));
                 }
@@ -661,94 +1383,116 @@ impl M3U8Downloader {
         }
 
         for handle in handles {
-            handle.await??;
+            if let Err(e) = handle.await? {
+                first_error.get_or_insert(e);
+            }
+        }
+        // 无论成功与否都把已提交的前缀落盘，暂停或崩溃后从这里继续
+        if let Err(e) = sink.checkpoint().await {
+            tracing::warn!("保存分片清单失败: {}", e);
+        }
+        if let Some(e) = first_error {
+            return Err(e);
         }
 
-        tracing::info!("所有片段下载完成");
-        Ok(segment_files)
+        let missing = missing.load(Ordering::SeqCst);
+        if missing == 0 {
+            tracing::info!("所有片段下载完成");
+        } else {
+            tracing::warn!(
+                "片段下载完成，{} 个片段缺失（容忍上限 {}）",
+                missing,
+                max_missing
+            );
+        }
+        Ok(())
     }
 
-    /// 静态方法下载单个片段
+    /// 下载单个片段：失败时交给 RetryExecutor 按错误类型退避重试
     #[allow(clippy::too_many_arguments)]
     async fn download_segment_static(
-        client: &Client,
-        config: &M3U8DownloaderConfig,
+        client: &HttpClientHandle,
+        retry_executor: &RetryExecutor,
+        bandwidth_controller: &BandwidthController,
+        task_id: &str,
         segment_url: &str,
-        output_file: &Path,
         byte_range: Option<(u64, u64)>,
-        segment_index: usize,
+        sequence: u64,
         encryption: Option<M3U8Encryption>,
         cancel_flag: Arc<AtomicBool>,
         pause_flag: Arc<AtomicBool>,
-    ) -> Result<u64> {
-        let mut retry_count = 0;
+    ) -> Result<Vec<u8>> {
+        let result = retry_executor
+            .execute(|_ctx| {
+                let client = client.clone();
+                let bandwidth_controller = bandwidth_controller.clone();
+                let task_id = task_id.to_string();
+                let segment_url = segment_url.to_string();
+                let encryption = encryption.clone();
+                let cancel_flag = Arc::clone(&cancel_flag);
+                let pause_flag = Arc::clone(&pause_flag);
+                Box::pin(async move {
+                    Self::download_segment_attempt(
+                        &client,
+                        &bandwidth_controller,
+                        &task_id,
+                        &segment_url,
+                        byte_range,
+                        sequence,
+                        encryption,
+                        cancel_flag,
+                        pause_flag,
+                    )
+                    .await
+                })
+            })
+            .await;
 
-        while retry_count <= config.retry_attempts {
-            if cancel_flag.load(Ordering::Relaxed) || pause_flag.load(Ordering::Relaxed) {
-                return Err(if cancel_flag.load(Ordering::Relaxed) {
-                    anyhow::anyhow!("download_cancelled")
-                } else {
-                    anyhow::anyhow!("download_paused")
-                });
-            }
-            match Self::download_segment_attempt(
-                client,
-                segment_url,
-                output_file,
-                byte_range,
-                segment_index,
-                encryption.clone(),
-                cancel_flag.clone(),
-                pause_flag.clone(),
-            )
-            .await
-            {
-                Ok(bytes) => return Ok(bytes),
-                Err(e) => {
-                    retry_count += 1;
-                    if retry_count <= config.retry_attempts {
-                        tracing::warn!(
-                            "片段下载失败，第 {} 次重试: {} - {}",
-                            retry_count,
-                            segment_url,
-                            e
-                        );
-                        tokio::time::sleep(Duration::from_millis(1000)).await;
-                    } else {
-                        return Err(e);
-                    }
-                }
+        // 取消/暂停以原始标记返回，上层据此区分中断和失败
+        result.map_err(|e| {
+            if cancel_flag.load(Ordering::Relaxed) {
+                anyhow!("download_cancelled")
+            } else if pause_flag.load(Ordering::Relaxed) {
+                anyhow!("download_paused")
+            } else {
+                e
             }
-        }
-
-        unreachable!()
+        })
     }
 
-    /// 单次片段下载尝试
+    /// 单次片段下载尝试；错误按是否值得重试分类
     #[allow(clippy::too_many_arguments)]
     async fn download_segment_attempt(
-        client: &Client,
+        client: &HttpClientHandle,
+        bandwidth_controller: &BandwidthController,
+        task_id: &str,
         segment_url: &str,
-        output_file: &Path,
         byte_range: Option<(u64, u64)>,
-        segment_index: usize,
+        sequence: u64,
         encryption: Option<M3U8Encryption>,
         cancel_flag: Arc<AtomicBool>,
         pause_flag: Arc<AtomicBool>,
-    ) -> Result<u64> {
-        if cancel_flag.load(Ordering::Relaxed) || pause_flag.load(Ordering::Relaxed) {
-            if cancel_flag.load(Ordering::Relaxed) {
-                bail!("download_cancelled");
+    ) -> std::result::Result<Vec<u8>, DownloadError> {
+        let interrupted = || {
+            let reason = if cancel_flag.load(Ordering::Relaxed) {
+                "download_cancelled"
             } else {
-                bail!("download_paused");
-            }
+                "download_paused"
+            };
+            errors::system_error(reason, None, false)
+        };
+        if cancel_flag.load(Ordering::Relaxed) || pause_flag.load(Ordering::Relaxed) {
+            return Err(interrupted());
         }
         let mut request = client.get(segment_url);
         if let Some((start, end)) = byte_range {
             request = request.header("Range", format!("bytes={}-{}", start, end));
         }
 
-        let response = request.send().await?;
+        let mut response = client
+            .send(request)
+            .await
+            .map_err(|e| errors::network_error(e.to_string(), !e.is_builder()))?;
         let response_status = response.status();
 
         if !response_status.is_success() {
@@ -763,43 +1507,61 @@ impl M3U8Downloader {
             } else {
                 tracing::error!("片段请求失败: {} - {}", segment_url, response_status);
             }
-            bail!("下载片段失败: {} - {}", segment_url, response_status);
+            let retry_after = response
+                .headers()
+                .get(reqwest::header::RETRY_AFTER)
+                .and_then(|value| value.to_str().ok())
+                .and_then(error_handling::parse_retry_after);
+            return Err(errors::http_status_error(
+                format!("下载片段失败: {} - {}", segment_url, response_status),
+                response_status.as_u16(),
+                retry_after,
+            ));
         }
 
         if byte_range.is_some() && response_status.as_u16() != 206 {
-            bail!(
-                "服务器未返回 M3U8 byte-range 分片响应(206)，无法安全写入片段: {}",
-                response_status
-            );
+            return Err(errors::protocol_error(
+                format!(
+                    "服务器未返回 M3U8 byte-range 分片响应(206)，无法安全写入片段: {}",
+                    response_status
+                ),
+                Some(response_status.as_u16()),
+                false,
+            ));
         }
 
-        let mut data = response.bytes().await?.to_vec();
-        if cancel_flag.load(Ordering::Relaxed) || pause_flag.load(Ordering::Relaxed) {
-            if cancel_flag.load(Ordering::Relaxed) {
-                bail!("download_cancelled");
-            } else {
-                bail!("download_paused");
+        let mut data = Vec::with_capacity(response.content_length().unwrap_or(0) as usize);
+        while let Some(chunk) = response
+            .chunk()
+            .await
+            .map_err(|e| errors::network_error(e.to_string(), true))?
+        {
+            data.extend_from_slice(&chunk);
+            bandwidth_controller
+                .throttle_task(task_id, segment_url, chunk.len() as u64)
+                .await;
+            if cancel_flag.load(Ordering::Relaxed) || pause_flag.load(Ordering::Relaxed) {
+                break;
             }
         }
+        if cancel_flag.load(Ordering::Relaxed) || pause_flag.load(Ordering::Relaxed) {
+            return Err(interrupted());
+        }
         if let Some(enc) = encryption {
             if enc.method.to_uppercase() == "AES-128" {
-                Self::decrypt_segment_data(&mut data, &enc, segment_index)?;
+                Self::decrypt_segment_data(&mut data, &enc, sequence)
+                    .map_err(|e| errors::data_integrity_error(e.to_string(), None, None))?;
             }
         }
 
-        let mut file = File::create(output_file).await?;
-        file.write_all(&data).await?;
-        file.flush().await?;
-        file.sync_all().await?;
-
-        Ok(data.len() as u64)
+        Ok(data)
     }
 
     /// 解密单个 TS 片段
     fn decrypt_segment_data(
         data: &mut Vec<u8>,
         encryption: &M3U8Encryption,
-        segment_index: usize,
+        sequence: u64,
     ) -> Result<()> {
         let key = encryption
             .key_data
@@ -809,7 +1571,7 @@ impl M3U8Downloader {
             bail!("AES-128 密钥长度必须为 16 字节，当前为 {}", key.len());
         }
 
-        let iv = Self::derive_iv_bytes(encryption, segment_index)?;
+        let iv = Self::derive_iv_bytes(encryption, sequence)?;
         let decryptor =
             Decryptor::<Aes128>::new_from_slices(key, &iv).map_err(|e| anyhow!(e.to_string()))?;
         let decrypted = decryptor
@@ -820,8 +1582,8 @@ impl M3U8Downloader {
         Ok(())
     }
 
-    /// 计算 AES-128 IV
-    fn derive_iv_bytes(encryption: &M3U8Encryption, segment_index: usize) -> Result<[u8; 16]> {
+    /// 计算 AES-128 IV（未指定时按规范使用媒体序号）
+    fn derive_iv_bytes(encryption: &M3U8Encryption, sequence: u64) -> Result<[u8; 16]> {
         if let Some(ref iv) = encryption.iv {
             if let Some(parsed) = Self::parse_iv(iv) {
                 return Ok(parsed);
@@ -830,7 +1592,7 @@ impl M3U8Downloader {
         }
 
         let mut iv = [0u8; 16];
-        iv[8..].copy_from_slice(&(segment_index as u64).to_be_bytes());
+        iv[8..].copy_from_slice(&sequence.to_be_bytes());
         Ok(iv)
     }
 
@@ -848,48 +1610,6 @@ impl M3U8Downloader {
         Some(arr)
     }
 
-    /// 合并片段为最终文件
-    async fn merge_segments(&self, segment_files: &[PathBuf], output_path: &str) -> Result<()> {
-        tracing::info!("合并 {} 个片段到: {}", segment_files.len(), output_path);
-
-        let output_file_path = Path::new(output_path);
-        if let Some(parent) = output_file_path.parent() {
-            tokio::fs::create_dir_all(parent).await?;
-        }
-
-        let mut output_file = File::create(output_file_path).await?;
-
-        for (index, segment_file) in segment_files.iter().enumerate() {
-            if !segment_file.exists() {
-                bail!("片段文件不存在: {:?}", segment_file);
-            }
-
-            tracing::debug!(
-                "合并片段 {}/{}: {:?}",
-                index + 1,
-                segment_files.len(),
-                segment_file
-            );
-
-            let mut segment_file_handle = File::open(segment_file).await?;
-            let mut buffer = vec![0u8; self.config.buffer_size];
-
-            loop {
-                let bytes_read = segment_file_handle.read(&mut buffer).await?;
-                if bytes_read == 0 {
-                    break;
-                }
-                output_file.write_all(&buffer[..bytes_read]).await?;
-            }
-        }
-
-        output_file.flush().await?;
-        output_file.sync_all().await?;
-
-        tracing::info!("片段合并完成");
-        Ok(())
-    }
-
     /// 清理临时文件
     async fn cleanup_temp_files(&self, temp_dir: &Path) -> Result<()> {
         if temp_dir.exists() {
@@ -913,13 +1633,31 @@ impl M3U8Downloader {
         Ok(())
     }
 
-    /// 取消下载
+    /// 删除任务的临时分片目录和分片清单
+    async fn discard_task_files(&self, task_id: &str) {
+        if let Some(part_path) = SegmentManifest::load(&self.config.temp_dir, task_id)
+            .await
+            .and_then(|manifest| manifest.part_path)
+        {
+            tokio::fs::remove_file(part_path).await.ok();
+        }
+        self.cleanup_temp_files(&self.config.temp_dir.join(task_id))
+            .await
+            .ok();
+        SegmentManifest::remove(&self.config.temp_dir, task_id)
+            .await
+            .ok();
+    }
+
+    /// 取消下载；已暂停（不在下载中）的任务直接丢弃保留的分片
     pub async fn cancel_download(&self, task_id: &str) -> Result<()> {
         if let Some(cancel_flag) = {
             let downloads = self.active_downloads.read().await;
             downloads.get(task_id).cloned()
         } {
             cancel_flag.store(true, Ordering::Relaxed);
+        } else if !self.config.keep_temp_files {
+            self.discard_task_files(task_id).await;
         }
         tracing::info!("M3U8下载已取消: {}", task_id);
         Ok(())
@@ -931,6 +1669,7 @@ mod tests {
     use super::*;
     use cbc::Encryptor;
     use cipher::BlockEncryptMut;
+    use reqwest::Client;
     use tempfile::tempdir;
     use tokio::io::{AsyncReadExt, AsyncWriteExt};
     use tokio::net::TcpListener;
@@ -979,12 +1718,10 @@ http://example.com/segment002.ts
 
     #[test]
     fn test_relative_url_resolution() {
-        let downloader = M3U8Downloader::new(M3U8DownloaderConfig::default()).unwrap();
-
         let base_url = "https://example.com/videos/";
         let relative_url = "segment000.ts";
 
-        let resolved = downloader.resolve_relative_url(base_url, relative_url);
+        let resolved = m3u8_parser::resolve_playlist_url(base_url, relative_url);
         assert!(resolved.is_ok());
         assert_eq!(
             resolved.unwrap(),
@@ -1111,6 +1848,10 @@ segment000.ts
                 "multi-key-task",
                 &format!("http://{}/master.m3u8", addr),
                 &output.to_string_lossy(),
+                &VariantPolicy::default(),
+                &LiveRecordingLimits::default(),
+                0,
+                &RenditionPreferences::default(),
                 Arc::new(AtomicBool::new(false)),
             )
             .await
@@ -1124,6 +1865,756 @@ segment000.ts
         server.await.unwrap();
     }
 
+    #[tokio::test]
+    async fn test_master_playlist_downloads_selected_variant() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            // master + 选中档位的播放列表 + 一个片段
+            for _ in 0..3 {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let mut buffer = [0u8; 1024];
+                let bytes_read = socket.read(&mut buffer).await.unwrap();
+                let request = String::from_utf8_lossy(&buffer[..bytes_read]);
+                let path = request
+                    .lines()
+                    .next()
+                    .and_then(|line| line.split_whitespace().nth(1))
+                    .unwrap_or("/")
+                    .to_string();
+
+                let body: Vec<u8> = match path.as_str() {
+                    "/course/master.m3u8" => b"#EXTM3U\n#EXT-X-STREAM-INF:BANDWIDTH=4000000,RESOLUTION=1920x1080\nhd/index.m3u8\n#EXT-X-STREAM-INF:BANDWIDTH=1200000,RESOLUTION=1280x720,CODECS=\"avc1.4d401f,mp4a.40.2\"\nsd/index.m3u8\n".to_vec(),
+                    "/course/sd/index.m3u8" => {
+                        b"#EXTM3U\n#EXTINF:1.0,\nseg0.ts\n#EXT-X-ENDLIST\n".to_vec()
+                    }
+                    "/course/sd/seg0.ts" => b"720p segment".to_vec(),
+                    _ => Vec::new(),
+                };
+                let status = if body.is_empty() {
+                    "404 Not Found"
+                } else {
+                    "200 OK"
+                };
+                let response = format!(
+                    "HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                    status,
+                    body.len()
+                );
+                socket.write_all(response.as_bytes()).await.unwrap();
+                socket.write_all(&body).await.unwrap();
+                socket.shutdown().await.unwrap();
+            }
+        });
+
+        let temp_dir = tempdir().unwrap();
+        let config = M3U8DownloaderConfig {
+            temp_dir: temp_dir.path().join("segments"),
+            ..M3U8DownloaderConfig::default()
+        };
+        let downloader = M3U8Downloader::new(config).unwrap();
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        downloader.set_progress_callback(tx);
+        let output = temp_dir.path().join("lesson.ts");
+
+        downloader
+            .download_m3u8(
+                "variant-task",
+                &format!("http://{}/course/master.m3u8", addr),
+                &output.to_string_lossy(),
+                &VariantPolicy::ClosestHeight { height: 720 },
+                &LiveRecordingLimits::default(),
+                0,
+                &RenditionPreferences::default(),
+                Arc::new(AtomicBool::new(false)),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(tokio::fs::read(&output).await.unwrap(), b"720p segment");
+        let (task_id, stats) = rx.recv().await.unwrap();
+        assert_eq!(task_id, "variant-task");
+        let variant = stats.hls_variant.expect("selected variant is reported");
+        assert_eq!(variant.uri, format!("http://{}/course/sd/index.m3u8", addr));
+        assert_eq!(variant.bandwidth, 1_200_000);
+        assert_eq!(variant.codecs.as_deref(), Some("avc1.4d401f,mp4a.40.2"));
+        server.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_master_renditions_fall_back_to_sidecar_files() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            loop {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let mut buffer = [0u8; 1024];
+                let bytes_read = socket.read(&mut buffer).await.unwrap();
+                let request = String::from_utf8_lossy(&buffer[..bytes_read]);
+                let path = request
+                    .lines()
+                    .next()
+                    .and_then(|line| line.split_whitespace().nth(1))
+                    .unwrap_or("/")
+                    .to_string();
+
+                let body: Vec<u8> = match path.as_str() {
+                    "/course/master.m3u8" => b"#EXTM3U\n#EXT-X-MEDIA:TYPE=AUDIO,GROUP-ID=\"aud\",NAME=\"English\",LANGUAGE=\"en\",DEFAULT=YES,URI=\"audio/en.m3u8\"\n#EXT-X-MEDIA:TYPE=SUBTITLES,GROUP-ID=\"subs\",NAME=\"English\",LANGUAGE=\"en\",URI=\"subs/en.m3u8\"\n#EXT-X-STREAM-INF:BANDWIDTH=1200000,AUDIO=\"aud\",SUBTITLES=\"subs\"\nvideo/index.m3u8\n".to_vec(),
+                    "/course/video/index.m3u8" => b"#EXTM3U\n#EXTINF:2.0,\nv0.ts\n#EXT-X-ENDLIST\n".to_vec(),
+                    "/course/video/v0.ts" => b"video only".to_vec(),
+                    "/course/audio/en.m3u8" => b"#EXTM3U\n#EXTINF:2.0,\na0.aac\n#EXT-X-ENDLIST\n".to_vec(),
+                    "/course/audio/a0.aac" => b"adts audio".to_vec(),
+                    "/course/subs/en.m3u8" => b"#EXTM3U\n#EXTINF:1.0,\ns0.vtt\n#EXTINF:1.0,\ns1.vtt\n#EXT-X-ENDLIST\n".to_vec(),
+                    "/course/subs/s0.vtt" => b"WEBVTT\n\n00:00.500 --> 00:01.000\nhello\n".to_vec(),
+                    "/course/subs/s1.vtt" => b"WEBVTT\n\n00:01.500 --> 00:02.000\nworld\n".to_vec(),
+                    _ => Vec::new(),
+                };
+                let status = if body.is_empty() {
+                    "404 Not Found"
+                } else {
+                    "200 OK"
+                };
+                let response = format!(
+                    "HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                    status,
+                    body.len()
+                );
+                socket.write_all(response.as_bytes()).await.unwrap();
+                socket.write_all(&body).await.unwrap();
+                socket.shutdown().await.unwrap();
+            }
+        });
+
+        let temp_dir = tempdir().unwrap();
+        let config = M3U8DownloaderConfig {
+            temp_dir: temp_dir.path().join("segments"),
+            ..M3U8DownloaderConfig::default()
+        };
+        let downloader = M3U8Downloader::new(config).unwrap();
+        let output = temp_dir.path().join("lesson.ts");
+
+        // 假数据无法被 ffmpeg 封装（或 ffmpeg 不存在），音轨和字幕都落到独立文件
+        let written = downloader
+            .download_m3u8(
+                "rendition-task",
+                &format!("http://{}/course/master.m3u8", addr),
+                &output.to_string_lossy(),
+                &VariantPolicy::default(),
+                &LiveRecordingLimits::default(),
+                0,
+                &RenditionPreferences {
+                    subtitle_languages: vec!["en".to_string()],
+                    subtitle_format: SubtitleFormat::Srt,
+                    ..RenditionPreferences::default()
+                },
+                Arc::new(AtomicBool::new(false)),
+            )
+            .await
+            .unwrap();
+        server.abort();
+
+        assert_eq!(written, output.to_string_lossy());
+        assert_eq!(tokio::fs::read(&output).await.unwrap(), b"video only");
+        assert_eq!(
+            tokio::fs::read(temp_dir.path().join("lesson.en.aac"))
+                .await
+                .unwrap(),
+            b"adts audio"
+        );
+        assert_eq!(
+            tokio::fs::read_to_string(temp_dir.path().join("lesson.en.srt"))
+                .await
+                .unwrap(),
+            "1\n00:00:00,500 --> 00:00:01,000\nhello\n\n2\n00:00:01,500 --> 00:00:02,000\nworld\n\n"
+        );
+        assert!(!temp_dir.path().join("lesson.muxing.mp4").exists());
+    }
+
+    #[tokio::test]
+    async fn test_ext_x_map_parsing_tracks_init_changes() {
+        let m3u8_content = r#"#EXTM3U
+#EXT-X-VERSION:7
+#EXT-X-MAP:URI="init.mp4",BYTERANGE="720@0"
+#EXTINF:4.0,
+main.mp4
+#EXTINF:4.0,
+part2.m4s
+#EXT-X-DISCONTINUITY
+#EXT-X-MAP:URI="https://cdn.example.com/ad/init.mp4"
+#EXTINF:2.0,
+https://cdn.example.com/ad/seg1.m4s
+#EXT-X-ENDLIST"#;
+
+        let downloader = M3U8Downloader::new(M3U8DownloaderConfig::default()).unwrap();
+        let playlist = downloader
+            .parse_m3u8_content("https://example.com/cmaf/video.m3u8", m3u8_content)
+            .await
+            .unwrap();
+
+        assert!(playlist.is_fmp4());
+        assert_eq!(playlist.init_sections.len(), 2);
+        assert_eq!(
+            playlist.init_sections[0].url,
+            "https://example.com/cmaf/init.mp4"
+        );
+        assert_eq!(playlist.init_sections[0].byte_range, Some((0, 719)));
+        assert!(playlist.init_sections[1].byte_range.is_none());
+        let init_indices: Vec<_> = playlist
+            .segments
+            .iter()
+            .map(|segment| segment.init_section)
+            .collect();
+        assert_eq!(init_indices, vec![Some(0), Some(0), Some(1)]);
+        assert_eq!(
+            M3U8Downloader::output_path_for_container("/videos/第1课.m3u8", true),
+            "/videos/第1课.mp4"
+        );
+        assert_eq!(
+            M3U8Downloader::output_path_for_container("/videos/lesson.ts", false),
+            "/videos/lesson.ts"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_fmp4_download_writes_decrypted_init_before_fragments() {
+        fn encrypt(data: &[u8], key: &[u8; 16], iv: &[u8; 16]) -> Vec<u8> {
+            Encryptor::<Aes128>::new_from_slices(key, iv)
+                .unwrap()
+                .encrypt_padded_vec_mut::<Pkcs7>(data)
+        }
+
+        let key = *b"0123456789abcdef";
+        let mut iv = [0u8; 16];
+        iv[15] = 1;
+        let init_a = b"ftyp-moov-a".to_vec();
+        let encrypted_init_a = encrypt(&init_a, &key, &iv);
+        let encrypted_a1 = encrypt(b"moof-mdat-a1", &key, &iv);
+        let init_b = b"ftyp-moov-b".to_vec();
+        let served_init_b = init_b.clone();
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            // 播放列表、密钥、两个初始化段（第二个重复声明）与三个片段
+            for _ in 0..8 {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let mut buffer = [0u8; 1024];
+                let bytes_read = socket.read(&mut buffer).await.unwrap();
+                let request = String::from_utf8_lossy(&buffer[..bytes_read]);
+                let path = request
+                    .lines()
+                    .next()
+                    .and_then(|line| line.split_whitespace().nth(1))
+                    .unwrap_or("/")
+                    .to_string();
+
+                let body: Vec<u8> = match path.as_str() {
+                    "/cmaf/video.m3u8" => concat!(
+                        "#EXTM3U\n#EXT-X-VERSION:7\n",
+                        "#EXT-X-KEY:METHOD=AES-128,URI=\"key.bin\",IV=0x00000000000000000000000000000001\n",
+                        "#EXT-X-MAP:URI=\"init-a.mp4\"\n",
+                        "#EXTINF:1.0,\na1.m4s\n",
+                        "#EXT-X-DISCONTINUITY\n#EXT-X-KEY:METHOD=NONE\n",
+                        "#EXT-X-MAP:URI=\"init-b.mp4\"\n",
+                        "#EXTINF:1.0,\nb1.m4s\n",
+                        "#EXT-X-MAP:URI=\"init-b-again.mp4\"\n",
+                        "#EXTINF:1.0,\nb2.m4s\n#EXT-X-ENDLIST\n",
+                    )
+                    .as_bytes()
+                    .to_vec(),
+                    "/cmaf/key.bin" => key.to_vec(),
+                    "/cmaf/init-a.mp4" => encrypted_init_a.clone(),
+                    "/cmaf/init-b.mp4" | "/cmaf/init-b-again.mp4" => served_init_b.clone(),
+                    "/cmaf/a1.m4s" => encrypted_a1.clone(),
+                    "/cmaf/b1.m4s" => b"moof-mdat-b1".to_vec(),
+                    "/cmaf/b2.m4s" => b"moof-mdat-b2".to_vec(),
+                    _ => Vec::new(),
+                };
+                let status = if body.is_empty() {
+                    "404 Not Found"
+                } else {
+                    "200 OK"
+                };
+                let response = format!(
+                    "HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                    status,
+                    body.len()
+                );
+                socket.write_all(response.as_bytes()).await.unwrap();
+                socket.write_all(&body).await.unwrap();
+                socket.shutdown().await.unwrap();
+            }
+        });
+
+        let temp_dir = tempdir().unwrap();
+        let config = M3U8DownloaderConfig {
+            temp_dir: temp_dir.path().join("segments"),
+            max_concurrent_segments: 1,
+            ..M3U8DownloaderConfig::default()
+        };
+        let downloader = M3U8Downloader::new(config).unwrap();
+        let requested = temp_dir.path().join("lesson.m3u8");
+
+        let written = downloader
+            .download_m3u8(
+                "fmp4-task",
+                &format!("http://{}/cmaf/video.m3u8", addr),
+                &requested.to_string_lossy(),
+                &VariantPolicy::default(),
+                &LiveRecordingLimits::default(),
+                0,
+                &RenditionPreferences::default(),
+                Arc::new(AtomicBool::new(false)),
+            )
+            .await
+            .unwrap();
+
+        let expected_path = temp_dir.path().join("lesson.mp4");
+        assert_eq!(written, expected_path.to_string_lossy());
+        assert_eq!(
+            tokio::fs::read(&expected_path).await.unwrap(),
+            [
+                init_a.as_slice(),
+                b"moof-mdat-a1",
+                init_b.as_slice(),
+                b"moof-mdat-b1",
+                b"moof-mdat-b2",
+            ]
+            .concat()
+        );
+        server.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_media_sequence_and_live_detection() {
+        let downloader = M3U8Downloader::new(M3U8DownloaderConfig::default()).unwrap();
+        let live = downloader
+            .parse_m3u8_content(
+                "https://example.com/live/index.m3u8",
+                "#EXTM3U\n#EXT-X-TARGETDURATION:2\n#EXT-X-MEDIA-SEQUENCE:41\n#EXTINF:2.0,\na.ts\n#EXTINF:2.0,\nb.ts\n",
+            )
+            .await
+            .unwrap();
+        assert!(live.is_live);
+        assert_eq!(live.media_sequence, 41);
+        let sequences: Vec<u64> = live.segments.iter().map(|s| s.sequence).collect();
+        assert_eq!(sequences, vec![41, 42]);
+
+        // 默认 IV 取媒体序号而不是列表内位置
+        let encryption = M3U8Encryption {
+            method: "AES-128".to_string(),
+            key_url: None,
+            iv: None,
+            key_data: None,
+            key_format: None,
+            key_format_versions: None,
+        };
+        let iv = M3U8Downloader::derive_iv_bytes(&encryption, live.segments[1].sequence).unwrap();
+        assert_eq!(iv[15], 42);
+
+        let vod = downloader
+            .parse_m3u8_content(
+                "https://example.com/vod/index.m3u8",
+                "#EXTM3U\n#EXT-X-PLAYLIST-TYPE:VOD\n#EXTINF:2.0,\na.ts\n",
+            )
+            .await
+            .unwrap();
+        assert!(!vod.is_live);
+    }
+
+    #[tokio::test]
+    async fn test_live_recording_appends_new_segments_until_endlist() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            let mut reloads = 0;
+            loop {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let mut buffer = [0u8; 1024];
+                let bytes_read = socket.read(&mut buffer).await.unwrap();
+                let request = String::from_utf8_lossy(&buffer[..bytes_read]);
+                let path = request
+                    .lines()
+                    .next()
+                    .and_then(|line| line.split_whitespace().nth(1))
+                    .unwrap_or("/")
+                    .to_string();
+
+                let body: Vec<u8> = match path.as_str() {
+                    "/live/index.m3u8" => {
+                        reloads += 1;
+                        // 滑动窗口：旧片段滑出、新片段追加，第三次刷新时直播结束
+                        match reloads {
+                            1 => "#EXTM3U\n#EXT-X-TARGETDURATION:1\n#EXT-X-MEDIA-SEQUENCE:7\n#EXTINF:1.0,\ns7.ts\n#EXTINF:1.0,\ns8.ts\n",
+                            2 => "#EXTM3U\n#EXT-X-TARGETDURATION:1\n#EXT-X-MEDIA-SEQUENCE:8\n#EXTINF:1.0,\ns8.ts\n#EXTINF:1.0,\ns9.ts\n",
+                            _ => "#EXTM3U\n#EXT-X-TARGETDURATION:1\n#EXT-X-MEDIA-SEQUENCE:9\n#EXTINF:1.0,\ns9.ts\n#EXTINF:1.0,\ns10.ts\n#EXT-X-ENDLIST\n",
+                        }
+                        .as_bytes()
+                        .to_vec()
+                    }
+                    "/live/s7.ts" => b"[7]".to_vec(),
+                    "/live/s8.ts" => b"[8]".to_vec(),
+                    "/live/s9.ts" => b"[9]".to_vec(),
+                    "/live/s10.ts" => b"[10]".to_vec(),
+                    _ => Vec::new(),
+                };
+                let status = if body.is_empty() {
+                    "404 Not Found"
+                } else {
+                    "200 OK"
+                };
+                let response = format!(
+                    "HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                    status,
+                    body.len()
+                );
+                socket.write_all(response.as_bytes()).await.unwrap();
+                socket.write_all(&body).await.unwrap();
+                socket.shutdown().await.unwrap();
+            }
+        });
+
+        let temp_dir = tempdir().unwrap();
+        let config = M3U8DownloaderConfig {
+            temp_dir: temp_dir.path().join("segments"),
+            ..M3U8DownloaderConfig::default()
+        };
+        let downloader = M3U8Downloader::new(config).unwrap();
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        downloader.set_progress_callback(tx);
+        let output = temp_dir.path().join("live.ts");
+
+        let written = downloader
+            .download_m3u8(
+                "live-task",
+                &format!("http://{}/live/index.m3u8", addr),
+                &output.to_string_lossy(),
+                &VariantPolicy::default(),
+                &LiveRecordingLimits::default(),
+                0,
+                &RenditionPreferences::default(),
+                Arc::new(AtomicBool::new(false)),
+            )
+            .await
+            .unwrap();
+        server.abort();
+
+        assert_eq!(written, output.to_string_lossy());
+        assert_eq!(tokio::fs::read(&output).await.unwrap(), b"[7][8][9][10]");
+        assert!(!downloader.stop_recording("live-task"));
+
+        let mut recordings = Vec::new();
+        while let Ok((_, stats)) = rx.try_recv() {
+            assert_eq!(stats.progress, 0.0);
+            assert!(stats.total_bytes.is_none());
+            recordings.extend(stats.recording);
+        }
+        let segments: Vec<usize> = recordings.iter().map(|r| r.segments).collect();
+        assert_eq!(segments, vec![2, 3, 4]);
+        assert_eq!(recordings.last().unwrap().recorded_secs, 4.0);
+    }
+
+    #[tokio::test]
+    async fn test_live_recording_keeps_gaps_and_finalizes_on_pause() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let pause_flag = Arc::new(AtomicBool::new(false));
+        let server = {
+            let pause_flag = Arc::clone(&pause_flag);
+            tokio::spawn(async move {
+                let mut reloads = 0;
+                loop {
+                    let (mut socket, _) = listener.accept().await.unwrap();
+                    let mut buffer = [0u8; 1024];
+                    let bytes_read = socket.read(&mut buffer).await.unwrap();
+                    let request = String::from_utf8_lossy(&buffer[..bytes_read]);
+                    let path = request
+                        .lines()
+                        .next()
+                        .and_then(|line| line.split_whitespace().nth(1))
+                        .unwrap_or("/")
+                        .to_string();
+
+                    let body: Vec<u8> = match path.as_str() {
+                        "/live/index.m3u8" => {
+                            reloads += 1;
+                            if reloads == 3 {
+                                // 录到 s3 后用户暂停
+                                pause_flag.store(true, Ordering::SeqCst);
+                            }
+                            match reloads {
+                                1 => "#EXTM3U\n#EXT-X-TARGETDURATION:1\n#EXT-X-MEDIA-SEQUENCE:1\n#EXTINF:1.0,\ns1.ts\n#EXTINF:1.0,\ns2.ts\n",
+                                _ => "#EXTM3U\n#EXT-X-TARGETDURATION:1\n#EXT-X-MEDIA-SEQUENCE:2\n#EXTINF:1.0,\ns2.ts\n#EXTINF:1.0,\ns3.ts\n",
+                            }
+                            .as_bytes()
+                            .to_vec()
+                        }
+                        "/live/s1.ts" => b"[1]".to_vec(),
+                        // s2 已滑出 CDN 缓存
+                        "/live/s3.ts" => b"[3]".to_vec(),
+                        _ => Vec::new(),
+                    };
+                    let status = if body.is_empty() {
+                        "404 Not Found"
+                    } else {
+                        "200 OK"
+                    };
+                    let response = format!(
+                        "HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                        status,
+                        body.len()
+                    );
+                    socket.write_all(response.as_bytes()).await.unwrap();
+                    socket.write_all(&body).await.unwrap();
+                    socket.shutdown().await.unwrap();
+                }
+            })
+        };
+
+        let temp_dir = tempdir().unwrap();
+        let config = M3U8DownloaderConfig {
+            temp_dir: temp_dir.path().join("segments"),
+            retry_attempts: 1,
+            ..M3U8DownloaderConfig::default()
+        };
+        let downloader = M3U8Downloader::new(config).unwrap();
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        downloader.set_progress_callback(tx);
+        let output = temp_dir.path().join("live.ts");
+
+        // 缺失的片段不中断录制，暂停时已录制的内容按手动停止收尾
+        let written = downloader
+            .download_m3u8(
+                "live-gap-task",
+                &format!("http://{}/live/index.m3u8", addr),
+                &output.to_string_lossy(),
+                &VariantPolicy::default(),
+                &LiveRecordingLimits::default(),
+                0,
+                &RenditionPreferences::default(),
+                Arc::clone(&pause_flag),
+            )
+            .await
+            .unwrap();
+        server.abort();
+
+        assert_eq!(written, output.to_string_lossy());
+        assert_eq!(tokio::fs::read(&output).await.unwrap(), b"[1][3]");
+        assert!(!temp_dir.path().join("live.ts.part").exists());
+
+        let mut gaps = Vec::new();
+        while let Ok((_, stats)) = rx.try_recv() {
+            gaps.extend(stats.segment_gaps.unwrap_or_default());
+        }
+        assert_eq!(gaps.len(), 1);
+        assert_eq!(gaps[0].index, 1);
+        assert!(gaps[0].error.contains("404"));
+    }
+
+    #[tokio::test]
+    async fn test_interrupted_download_reuses_manifest_segments() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let serve_s1 = Arc::new(AtomicBool::new(false));
+        let s0_requests = Arc::new(AtomicU64::new(0));
+        let server = {
+            let serve_s1 = Arc::clone(&serve_s1);
+            let s0_requests = Arc::clone(&s0_requests);
+            tokio::spawn(async move {
+                loop {
+                    let (mut socket, _) = listener.accept().await.unwrap();
+                    let mut buffer = [0u8; 1024];
+                    let bytes_read = socket.read(&mut buffer).await.unwrap();
+                    let request = String::from_utf8_lossy(&buffer[..bytes_read]);
+                    let path = request
+                        .lines()
+                        .next()
+                        .and_then(|line| line.split_whitespace().nth(1))
+                        .unwrap_or("/")
+                        .to_string();
+
+                    let body: Vec<u8> = match path.as_str() {
+                        "/vod/index.m3u8" => b"#EXTM3U\n#EXT-X-TARGETDURATION:1\n#EXTINF:1.0,\ns0.ts\n#EXTINF:1.0,\ns1.ts\n#EXT-X-ENDLIST\n".to_vec(),
+                        "/vod/s0.ts" => {
+                            s0_requests.fetch_add(1, Ordering::SeqCst);
+                            b"[0]".to_vec()
+                        }
+                        "/vod/s1.ts" if serve_s1.load(Ordering::SeqCst) => b"[1]".to_vec(),
+                        _ => Vec::new(),
+                    };
+                    let status = if body.is_empty() {
+                        "404 Not Found"
+                    } else {
+                        "200 OK"
+                    };
+                    let response = format!(
+                        "HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                        status,
+                        body.len()
+                    );
+                    socket.write_all(response.as_bytes()).await.unwrap();
+                    socket.write_all(&body).await.unwrap();
+                    socket.shutdown().await.unwrap();
+                }
+            })
+        };
+
+        let temp_dir = tempdir().unwrap();
+        let segments_root = temp_dir.path().join("segments");
+        let config = M3U8DownloaderConfig {
+            temp_dir: segments_root.clone(),
+            retry_attempts: 0,
+            max_concurrent_segments: 1,
+            ..M3U8DownloaderConfig::default()
+        };
+        let downloader = M3U8Downloader::new(config).unwrap();
+        let output = temp_dir.path().join("lesson.ts");
+        let url = format!("http://{}/vod/index.m3u8", addr);
+        let (policy, limits, renditions) = (
+            VariantPolicy::default(),
+            LiveRecordingLimits::default(),
+            RenditionPreferences::default(),
+        );
+        let download = || {
+            downloader.download_m3u8(
+                "resume-task",
+                &url,
+                output.to_str().unwrap(),
+                &policy,
+                &limits,
+                0,
+                &renditions,
+                Arc::new(AtomicBool::new(false)),
+            )
+        };
+
+        // 第一次：片段 1 失败，片段 0 已写入 .part，清单记录了这段前缀
+        assert!(download().await.is_err());
+        let manifest = SegmentManifest::load_or_new(&segments_root, "resume-task", &url).await;
+        assert_eq!(manifest.committed_segments, 1);
+        assert_eq!(
+            tokio::fs::read(temp_dir.path().join("lesson.ts.part"))
+                .await
+                .unwrap(),
+            b"[0]"
+        );
+
+        // 第二次：只请求缺失的片段并续写，结果完整，清单和临时目录被清理
+        serve_s1.store(true, Ordering::SeqCst);
+        download().await.unwrap();
+        server.abort();
+
+        assert_eq!(s0_requests.load(Ordering::SeqCst), 1);
+        assert_eq!(tokio::fs::read(&output).await.unwrap(), b"[0][1]");
+        assert!(!SegmentManifest::path(&segments_root, "resume-task").exists());
+        assert!(!segments_root.join("resume-task").exists());
+    }
+
+    #[tokio::test]
+    async fn test_missing_segment_within_tolerance_leaves_gap() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let s0_requests = Arc::new(AtomicU64::new(0));
+        let s1_requests = Arc::new(AtomicU64::new(0));
+        let server = {
+            let s0_requests = Arc::clone(&s0_requests);
+            let s1_requests = Arc::clone(&s1_requests);
+            tokio::spawn(async move {
+                loop {
+                    let (mut socket, _) = listener.accept().await.unwrap();
+                    let mut buffer = [0u8; 1024];
+                    let bytes_read = socket.read(&mut buffer).await.unwrap();
+                    let request = String::from_utf8_lossy(&buffer[..bytes_read]);
+                    let path = request
+                        .lines()
+                        .next()
+                        .and_then(|line| line.split_whitespace().nth(1))
+                        .unwrap_or("/")
+                        .to_string();
+
+                    // s0 第一次返回 503 + Retry-After，s1 始终 404
+                    let (status, body): (&str, Vec<u8>) = match path.as_str() {
+                        "/vod/index.m3u8" => ("200 OK", b"#EXTM3U\n#EXT-X-TARGETDURATION:3\n#EXTINF:1.0,\ns0.ts\n#EXTINF:2.5,\ns1.ts\n#EXTINF:1.0,\ns2.ts\n#EXT-X-ENDLIST\n".to_vec()),
+                        "/vod/s0.ts" if s0_requests.fetch_add(1, Ordering::SeqCst) == 0 => {
+                            ("503 Service Unavailable\r\nRetry-After: 0", Vec::new())
+                        }
+                        "/vod/s0.ts" => ("200 OK", b"[0]".to_vec()),
+                        "/vod/s1.ts" => {
+                            s1_requests.fetch_add(1, Ordering::SeqCst);
+                            ("404 Not Found", Vec::new())
+                        }
+                        "/vod/s2.ts" => ("200 OK", b"[2]".to_vec()),
+                        _ => ("404 Not Found", Vec::new()),
+                    };
+                    let response = format!(
+                        "HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                        status,
+                        body.len()
+                    );
+                    socket.write_all(response.as_bytes()).await.unwrap();
+                    socket.write_all(&body).await.unwrap();
+                    socket.shutdown().await.unwrap();
+                }
+            })
+        };
+
+        let temp_dir = tempdir().unwrap();
+        let config = M3U8DownloaderConfig {
+            temp_dir: temp_dir.path().join("segments"),
+            retry_attempts: 1,
+            max_concurrent_segments: 1,
+            ..M3U8DownloaderConfig::default()
+        };
+        let downloader = M3U8Downloader::new(config).unwrap();
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        downloader.set_progress_callback(tx);
+        let url = format!("http://{}/vod/index.m3u8", addr);
+        let output = temp_dir.path().join("lecture.ts");
+        let (policy, limits, renditions) = (
+            VariantPolicy::default(),
+            LiveRecordingLimits::default(),
+            RenditionPreferences::default(),
+        );
+        let download = |task_id: &'static str, max_missing: usize| {
+            downloader.download_m3u8(
+                task_id,
+                &url,
+                output.to_str().unwrap(),
+                &policy,
+                &limits,
+                max_missing,
+                &renditions,
+                Arc::new(AtomicBool::new(false)),
+            )
+        };
+
+        // 默认不容忍缺失：整体失败
+        let error = download("strict-task", 0).await.unwrap_err();
+        assert!(format!("{:#}", error).contains("404"));
+
+        download("gap-task", 1).await.unwrap();
+        server.abort();
+
+        assert_eq!(tokio::fs::read(&output).await.unwrap(), b"[0][2]");
+        // 503 按 Retry-After 重试一次；404 不可重试，每次下载只请求一次
+        assert_eq!(s0_requests.load(Ordering::SeqCst), 3);
+        assert_eq!(s1_requests.load(Ordering::SeqCst), 2);
+
+        let mut reports = Vec::new();
+        while let Ok((task_id, stats)) = rx.try_recv() {
+            if let Some(gaps) = stats.segment_gaps {
+                reports.push((task_id, gaps));
+            }
+        }
+        assert_eq!(reports.len(), 1);
+        let (task_id, gaps) = &reports[0];
+        assert_eq!(task_id, "gap-task");
+        assert_eq!(gaps.len(), 1);
+        assert_eq!(gaps[0].index, 1);
+        assert_eq!((gaps[0].start_secs, gaps[0].end_secs), (1.0, 3.5));
+        assert!(gaps[0].error.contains("404"));
+    }
+
     #[tokio::test]
     async fn test_byte_range_segment_rejects_ignored_range() {
         let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
@@ -1142,12 +2633,11 @@ segment000.ts
             socket.shutdown().await.unwrap();
         });
 
-        let temp_dir = tempdir().unwrap();
-        let output_file = temp_dir.path().join("segment.ts");
         let result = M3U8Downloader::download_segment_attempt(
-            &Client::new(),
+            &HttpClientHandle::from(Client::new()),
+            &BandwidthController::new(),
+            "range-task",
             &format!("http://{}", addr),
-            &output_file,
             Some((0, 3)),
             0,
             None,
@@ -1161,13 +2651,63 @@ segment000.ts
         server.await.unwrap();
     }
 
-    #[test]
-    fn test_encryption_parsing() {
+    #[tokio::test]
+    async fn test_segment_body_goes_through_task_throttle() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let body = vec![7u8; 50_000];
+        let served = body.clone();
+        let server = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut buffer = [0u8; 1024];
+            let _ = socket.read(&mut buffer).await.unwrap();
+            let response = format!(
+                "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                served.len()
+            );
+            socket.write_all(response.as_bytes()).await.unwrap();
+            socket.write_all(&served).await.unwrap();
+            socket.shutdown().await.unwrap();
+        });
+
+        let controller = BandwidthController::new();
+        controller.register_task("hls-task", 5, Some(100_000));
+        let started = Instant::now();
+        let data = M3U8Downloader::download_segment_attempt(
+            &HttpClientHandle::from(Client::new()),
+            &controller,
+            "hls-task",
+            &format!("http://{}/seg.ts", addr),
+            None,
+            0,
+            None,
+            Arc::new(AtomicBool::new(false)),
+            Arc::new(AtomicBool::new(false)),
+        )
+        .await
+        .unwrap();
+
+        // 100 KB/s 上限下 50KB 需要约 0.5 秒
+        assert!(started.elapsed() >= Duration::from_millis(250));
+        assert_eq!(data, body);
+        server.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_encryption_parsing() {
         let downloader = M3U8Downloader::new(M3U8DownloaderConfig::default()).unwrap();
 
-        let encryption_line = r#"#EXT-X-KEY:METHOD=AES-128,URI="https://example.com/key.bin",IV=0X99b74007b6254e4bd1c6e03631cad15b"#;
+        let m3u8_content = r#"#EXTM3U
+#EXT-X-KEY:METHOD=AES-128,URI="https://example.com/key.bin",IV=0X99b74007b6254e4bd1c6e03631cad15b
+#EXTINF:9.009,
+segment000.ts
+#EXT-X-ENDLIST"#;
 
-        let encryption = downloader.parse_encryption_line(encryption_line).unwrap();
+        let playlist = downloader
+            .parse_m3u8_content("https://example.com/playlist.m3u8", m3u8_content)
+            .await
+            .unwrap();
+        let encryption = playlist.segments[0].encryption.clone();
         assert!(encryption.is_some());
 
         let encryption = encryption.unwrap();
@@ -9,6 +9,7 @@ mod events;
 mod identity;
 mod integrity;
 mod queue;
+mod rate_limit;
 #[cfg(test)]
 mod runtime_state_tests;
 mod state;
@@ -25,18 +26,28 @@ use tokio::sync::{mpsc, Mutex, RwLock};
 use tracing::{debug, error, info, warn};
 use uuid::Uuid;
 
+use crate::core::bandwidth::BandwidthLimits;
+use crate::core::bandwidth_schedule::{BandwidthSchedule, RateLimitStatus};
 use crate::core::config::AppConfig;
+use crate::core::cookie_jar::CookieJar;
+use crate::core::credentials::CredentialStore;
 use crate::core::downloader::{DownloadStats, DownloadTask, DownloaderConfig, HttpDownloader};
 use crate::core::error_handling::{
     errors, DownloadError, ErrorCategory, RetryContext, RetryExecutor, RetryPolicy, RetryStats,
 };
+use crate::core::hls_variant::{HlsVariant, VariantPolicy};
+use crate::core::http_client::HttpClientOptions;
 use crate::core::integrity_checker::{
     HashAlgorithm, IntegrityChecker, IntegrityConfig, IntegrityResult,
 };
+use crate::core::m3u8_downloader::LiveRecordingLimits;
 use crate::core::models::{
-    AppError, AppResult, DownloadConfig, DownloadStats as ModelsDownloadStats, DownloaderType,
-    ProgressUpdate, TaskStatus, VideoTask,
+    AppError, AppResult, AudioExtraction, DownloadConfig, DownloadStats as ModelsDownloadStats,
+    DownloaderType, ExternalVideoInfo, ProgressUpdate, SegmentGap, SelectedFormat, SidecarFile,
+    TaskStatus, VideoTask,
 };
+use crate::core::youtube_downloader::YoutubeDownloadFormat;
+use crate::core::ytdlp_downloader::YtDlpDownloader;
 
 use self::state::{
     decide_pause_transition, decide_queue_admission, decide_resume_transition,
@@ -44,7 +55,11 @@ use self::state::{
     mark_resumed_active, worker_action_for_activity, QueueAdmissionResult, TaskTransitionDecision,
     WorkerLifecycleAction,
 };
+use crate::core::platform_registry::{self, PlatformRegistry};
 use crate::core::progress_tracker::{EnhancedProgressStats, ProgressTrackingManager};
+use crate::core::proxy::ProxySettings;
+use crate::core::remote_filename::FilenameOrigin;
+use crate::core::request_headers::HeaderRules;
 
 /// Events that can be emitted by the download manager
 #[allow(clippy::large_enum_variant)]
@@ -70,9 +85,36 @@ pub enum DownloadEvent {
         task_id: String,
         progress: EnhancedProgressStats,
     },
+    /// Downloader picked the final file name from response headers before writing data
+    TaskFileResolved {
+        task_id: String,
+        file_path: String,
+    },
+    /// HLS downloader picked a variant from the master playlist
+    TaskVariantSelected {
+        task_id: String,
+        variant: HlsVariant,
+    },
+    /// HLS/DASH download finished with some segments skipped (empty = no gaps)
+    TaskSegmentGaps {
+        task_id: String,
+        gaps: Vec<SegmentGap>,
+    },
+    /// yt-dlp picked a format for the download
+    TaskFormatSelected {
+        task_id: String,
+        format: SelectedFormat,
+    },
+    /// yt-dlp finished writing subtitle/thumbnail/description/metadata files
+    TaskSidecarFiles {
+        task_id: String,
+        files: Vec<SidecarFile>,
+    },
     TaskCompleted {
         task_id: String,
         file_path: String,
+        /// Post-processing problem that left the download usable (e.g. audio extraction failed)
+        warning: Option<String>,
     },
     TaskFailed {
         task_id: String,
@@ -126,6 +168,10 @@ pub enum DownloadEvent {
     StatsUpdated {
         stats: ModelsDownloadStats,
     },
+    /// Effective global rate limit changed (schedule window or manual override)
+    RateLimitChanged {
+        status: RateLimitStatus,
+    },
 }
 
 #[derive(Debug, Clone)]
@@ -145,6 +191,36 @@ enum DownloadOutcome {
 struct EffectiveDownloadTarget {
     output_path: String,
     preferred_title: Option<String>,
+    /// `output_path` is the already resolved file path (which may lack an extension)
+    is_file_path: bool,
+    /// Whether the downloader may rename the file from response headers
+    filename_origin: FilenameOrigin,
+    /// Task's own HLS variant policy (None = config default)
+    variant_policy: Option<VariantPolicy>,
+    /// Task's own yt-dlp format choice (None = config default)
+    ytdlp_format: Option<YoutubeDownloadFormat>,
+    /// Task's audio-only mode (None = keep the video)
+    audio_extraction: Option<AudioExtraction>,
+}
+
+impl EffectiveDownloadTarget {
+    /// Output directory and filename handed to the downloader
+    fn split(&self, url: &str) -> (String, String) {
+        let path = Path::new(&self.output_path);
+        if self.is_file_path {
+            if let Some(filename) = path.file_name() {
+                let dir = path
+                    .parent()
+                    .map(|parent| parent.to_string_lossy().to_string())
+                    .unwrap_or_default();
+                return (
+                    DownloadManager::normalize_output_path(&dir),
+                    filename.to_string_lossy().to_string(),
+                );
+            }
+        }
+        DownloadManager::split_output_path(url, &self.output_path, self.preferred_title.as_deref())
+    }
 }
 
 const QUEUE_PRIORITY_DEFAULT: u8 = 5;
@@ -219,6 +295,9 @@ pub struct DownloadManager {
     /// Rate limiting: bytes per second (0 = unlimited)
     rate_limit: Arc<RwLock<Option<u64>>>,
 
+    /// Bandwidth schedule, manual override and the status currently applied
+    rate_limit_control: Mutex<rate_limit::RateLimitControl>,
+
     /// Flag to indicate if manager is running
     is_running: bool,
 
@@ -239,6 +318,9 @@ pub struct DownloadManager {
 
     /// Background task scheduler handle
     scheduler_handle: Option<tokio::task::JoinHandle<()>>,
+
+    /// Fixed platform registry; None follows the installed one so config updates apply at once
+    platform_registry: Option<Arc<PlatformRegistry>>,
 }
 
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
@@ -317,15 +399,26 @@ impl DownloadManager {
             return EffectiveDownloadTarget {
                 output_path: task.output_path.clone(),
                 preferred_title,
+                is_file_path: false,
+                filename_origin: FilenameOrigin::Fixed,
+                variant_policy: task.variant_policy.clone(),
+                ytdlp_format: task.ytdlp_format.clone(),
+                audio_extraction: task.audio_extraction.clone(),
             };
         }
 
+        let resolved_path = task
+            .resolved_path
+            .clone()
+            .filter(|path| !path.trim().is_empty());
         EffectiveDownloadTarget {
-            output_path: task
-                .resolved_path
-                .clone()
-                .unwrap_or_else(|| task.output_path.clone()),
+            is_file_path: resolved_path.is_some(),
+            output_path: resolved_path.unwrap_or_else(|| task.output_path.clone()),
+            filename_origin: Self::filename_origin(task, preferred_title.as_deref()),
             preferred_title,
+            variant_policy: task.variant_policy.clone(),
+            ytdlp_format: task.ytdlp_format.clone(),
+            audio_extraction: task.audio_extraction.clone(),
         }
     }
 
@@ -360,9 +453,32 @@ impl DownloadManager {
             display_speed_bps,
             eta,
             progress: download_stats.progress,
+            sources: download_stats.sources.clone(),
+            recording: download_stats.recording.clone(),
         }
     }
 
+    /// 清理镜像列表：仅保留 http(s) 地址，去掉空白、重复项和主地址本身
+    fn normalize_mirrors(url: &str, mirrors: Vec<String>) -> Vec<String> {
+        let mut normalized: Vec<String> = Vec::new();
+        for mirror in mirrors {
+            let mirror = mirror.trim();
+            let is_http = url::Url::parse(mirror)
+                .map(|parsed| matches!(parsed.scheme(), "http" | "https"))
+                .unwrap_or(false);
+            if !is_http {
+                if !mirror.is_empty() {
+                    warn!("Ignoring invalid mirror URL: {}", mirror);
+                }
+                continue;
+            }
+            if mirror != url && !normalized.iter().any(|existing| existing == mirror) {
+                normalized.push(mirror.to_string());
+            }
+        }
+        normalized
+    }
+
     /// Create a new download manager with the given configuration
     pub fn new(config: DownloadConfig) -> AppResult<Self> {
         let state_path = Self::default_state_path()?;
@@ -391,12 +507,23 @@ impl DownloadManager {
             buffer_size: 64 * 1024, // 64KB buffer
             user_agent: config.user_agent.clone(),
             resume_enabled: true, // Always enable resume by default
+            proxy: ProxySettings::from_download_config(&config)
+                .map_err(|e| AppError::Config(format!("Invalid proxy configuration: {}", e)))?,
+            header_rules: HeaderRules::from_download_config(&config)
+                .map_err(|e| AppError::Config(format!("Invalid header rules: {}", e)))?,
         };
+        let bandwidth_schedule = BandwidthSchedule::from_download_config(&config)
+            .map_err(|e| AppError::Config(format!("Invalid bandwidth schedule: {}", e)))?;
 
         // Create HTTP downloader
         let http_downloader = HttpDownloader::new(downloader_config)
             .map_err(|e| AppError::System(format!("Failed to create downloader: {}", e)))?;
-        let rate_limit_handle = http_downloader.bandwidth_controller().limit_handle();
+        let bandwidth_controller = http_downloader.bandwidth_controller();
+        bandwidth_controller.configure(
+            BandwidthLimits::from_download_config(&config)
+                .map_err(|e| AppError::Config(format!("Invalid bandwidth limits: {}", e)))?,
+        );
+        let rate_limit_handle = bandwidth_controller.limit_handle();
 
         // Create integrity checker configuration
         let _integrity_config = IntegrityConfig {
@@ -439,6 +566,7 @@ impl DownloadManager {
             state_path,
             persistence_enabled,
             rate_limit: rate_limit_handle,
+            rate_limit_control: Mutex::new(rate_limit::RateLimitControl::new(bandwidth_schedule)),
             is_running: false,
             progress_tracker: Arc::new(ProgressTrackingManager::new()),
             integrity_checker: Arc::new(integrity_checker),
@@ -446,6 +574,7 @@ impl DownloadManager {
             task_lifecycle_timings: HashMap::new(),
             lifecycle_metrics: DownloadLifecycleMetrics::default(),
             scheduler_handle: None,
+            platform_registry: None,
         };
 
         if let Err(err) = manager.load_persisted_state() {
@@ -703,6 +832,72 @@ impl DownloadManager {
         Ok(updated_tasks)
     }
 
+    /// Set (or clear) a task's HLS variant policy; takes effect the next time the task starts
+    pub async fn set_task_variant_policy(
+        &mut self,
+        task_id: &str,
+        policy: Option<VariantPolicy>,
+    ) -> AppResult<VideoTask> {
+        if let Some(policy) = &policy {
+            policy
+                .validate()
+                .map_err(|err| AppError::Config(err.to_string()))?;
+        }
+        let task = self
+            .tasks
+            .get_mut(task_id)
+            .ok_or_else(|| AppError::Download(format!("Task not found: {}", task_id)))?;
+        task.variant_policy = policy;
+        task.updated_at = chrono::Utc::now();
+        let task = task.clone();
+
+        if let Err(err) = self.persist_state().await {
+            warn!(
+                "Failed to persist state after variant policy change: {}",
+                err
+            );
+        }
+        Ok(task)
+    }
+
+    /// 单个任务的仅音频模式（None 时保留视频），下次开始下载时生效
+    pub async fn set_task_audio_extraction(
+        &mut self,
+        task_id: &str,
+        extraction: Option<AudioExtraction>,
+    ) -> AppResult<VideoTask> {
+        if let Some(bitrate) = extraction.as_ref().and_then(|value| value.bitrate_kbps) {
+            if !(32..=512).contains(&bitrate) {
+                return Err(AppError::Config(
+                    "Audio bitrate should be between 32 and 512 kbps".to_string(),
+                ));
+            }
+        }
+        let task = self
+            .tasks
+            .get_mut(task_id)
+            .ok_or_else(|| AppError::Download(format!("Task not found: {}", task_id)))?;
+        task.audio_extraction = extraction;
+        task.updated_at = chrono::Utc::now();
+        let task = task.clone();
+
+        if let Err(err) = self.persist_state().await {
+            warn!(
+                "Failed to persist state after audio extraction change: {}",
+                err
+            );
+        }
+        Ok(task)
+    }
+
+    /// 结束直播录制：已录到的片段照常合并，任务正常完成；任务不在录制中时返回 false
+    pub fn stop_live_recording(&self, task_id: &str) -> AppResult<bool> {
+        if !self.tasks.contains_key(task_id) {
+            return Err(AppError::Download(format!("Task not found: {}", task_id)));
+        }
+        Ok(self.http_downloader.stop_live_recording(task_id))
+    }
+
     pub async fn runtime_add_tasks(
         manager: &Arc<RwLock<Self>>,
         tasks: Vec<VideoTask>,
@@ -726,6 +921,32 @@ impl DownloadManager {
         manager.update_task_output_paths(&updates).await
     }
 
+    pub async fn runtime_set_task_variant_policy(
+        manager: &Arc<RwLock<Self>>,
+        task_id: &str,
+        policy: Option<VariantPolicy>,
+    ) -> AppResult<VideoTask> {
+        let mut manager = manager.write().await;
+        manager.set_task_variant_policy(task_id, policy).await
+    }
+
+    pub async fn runtime_set_task_audio_extraction(
+        manager: &Arc<RwLock<Self>>,
+        task_id: &str,
+        extraction: Option<AudioExtraction>,
+    ) -> AppResult<VideoTask> {
+        let mut manager = manager.write().await;
+        manager.set_task_audio_extraction(task_id, extraction).await
+    }
+
+    pub async fn runtime_stop_live_recording(
+        manager: &Arc<RwLock<Self>>,
+        task_id: &str,
+    ) -> AppResult<bool> {
+        let manager = manager.read().await;
+        manager.stop_live_recording(task_id)
+    }
+
     pub async fn runtime_remove_tasks(
         manager: &Arc<RwLock<Self>>,
         task_ids: Vec<String>,
@@ -914,6 +1135,15 @@ impl DownloadManager {
         let mut task = VideoTask {
             id: task_id.clone(),
             url: url.clone(),
+            mirrors: Vec::new(),
+            rate_limit: None,
+            variant_policy: None,
+            ytdlp_format: None,
+            hls_variant: None,
+            recording: None,
+            segment_gaps: Vec::new(),
+            sidecar_files: Vec::new(),
+            audio_extraction: None,
             title: inferred_title,
             output_path: output_dir,
             resolved_path,
@@ -1010,7 +1240,11 @@ impl DownloadManager {
         }
 
         // 即使 semaphore 仍有可用 permit，也要遵守当前并发配置，避免降配后短时间超发。
-        if self.active_downloads.len() >= self.config.concurrent_downloads {
+        // 平台规则的并发上限同样只排队、不报失败。
+        let platform_full = self.platform_at_capacity(&self.platform_registry(), &task.url);
+        if self.active_downloads.len() >= self.config.concurrent_downloads
+            || platform_full.is_some()
+        {
             self.enqueue_task(task_id, QUEUE_PRIORITY_MANUAL).await;
             if let Some(task) = self.tasks.get_mut(task_id) {
                 if task.status == TaskStatus::Failed {
@@ -1023,9 +1257,10 @@ impl DownloadManager {
                     }
                 }
             }
-            return Err(AppError::Download(
-                "Maximum concurrent downloads reached".to_string(),
-            ));
+            return Err(AppError::Download(match platform_full {
+                Some(platform) => format!("Maximum concurrent downloads reached for {}", platform),
+                None => "Maximum concurrent downloads reached".to_string(),
+            }));
         }
 
         // Check if we can start a new download
@@ -1051,6 +1286,7 @@ impl DownloadManager {
         };
 
         self.remove_task_from_queue(task_id).await;
+        self.register_task_bandwidth(task_id, QUEUE_PRIORITY_MANUAL);
         self.start_download_with_permit(task_id, task, permit).await
     }
 
@@ -1088,9 +1324,8 @@ impl DownloadManager {
         // Create download task
         let task_id_clone = task_id.to_string();
         let url = task.url.clone();
+        let mirrors = task.mirrors.clone();
         let target = Self::effective_download_target(&task);
-        let output_path = target.output_path;
-        let preferred_title = target.preferred_title;
         let initial_downloaded_size = task.downloaded_size;
         let initial_file_size = task.file_size;
         let event_sender = self
@@ -1115,8 +1350,8 @@ impl DownloadManager {
             match Self::execute_download(
                 &task_id_clone,
                 &url,
-                &output_path,
-                preferred_title,
+                mirrors,
+                target,
                 initial_downloaded_size,
                 initial_file_size,
                 downloader,
@@ -1532,19 +1767,19 @@ impl DownloadManager {
             retry_executor,
             download_config,
             url,
-            output_path,
-            preferred_title,
+            mirrors,
+            target,
             initial_downloaded_size,
             initial_file_size,
         ) =
             {
                 let mut guard = manager.write().await;
                 let (
-                    effective_output_path,
-                    effective_preferred_title,
+                    effective_target,
                     current_downloaded_size,
                     current_file_size,
                     current_url,
+                    current_mirrors,
                 ) = {
                     let task_mut = guard.tasks.get_mut(task_id).ok_or_else(|| {
                         AppError::Download(format!("Task not found: {}", task_id))
@@ -1562,13 +1797,12 @@ impl DownloadManager {
                         task_mut.eta = None;
                     }
 
-                    let target = Self::effective_download_target(task_mut);
                     (
-                        target.output_path,
-                        target.preferred_title,
+                        Self::effective_download_target(task_mut),
                         task_mut.downloaded_size,
                         task_mut.file_size,
                         task_mut.url.clone(),
+                        task_mut.mirrors.clone(),
                     )
                 };
 
@@ -1586,8 +1820,8 @@ impl DownloadManager {
                     Arc::clone(&guard.retry_executor),
                     guard.config.clone(),
                     current_url,
-                    effective_output_path,
-                    effective_preferred_title,
+                    current_mirrors,
+                    effective_target,
                     current_downloaded_size,
                     current_file_size,
                 )
@@ -1608,8 +1842,8 @@ impl DownloadManager {
             match Self::execute_download(
                 &task_id_clone,
                 &url,
-                &output_path,
-                preferred_title,
+                mirrors,
+                target,
                 initial_downloaded_size,
                 initial_file_size,
                 downloader,
@@ -1652,7 +1886,7 @@ impl DownloadManager {
     ) -> AppResult<()> {
         let task = Self::runtime_hydrate_task_file_state(manager, task_id).await?;
 
-        let (queue_admission, semaphore, task_for_start, active_paused_downloader) = {
+        let (queue_admission, platform_full, semaphore, task_for_start, active_paused_downloader) = {
             let mut guard = manager.write().await;
 
             guard.settle_pending_semaphore_reduction();
@@ -1686,16 +1920,21 @@ impl DownloadManager {
             if active_paused_downloader.is_some() {
                 (
                     QueueAdmissionResult::StartNow,
+                    None,
                     Arc::clone(&guard.download_semaphore),
                     task,
                     active_paused_downloader,
                 )
             } else {
+                // 平台规则的并发上限与全局并发一样只排队、不报失败
+                let platform_full =
+                    guard.platform_at_capacity(&guard.platform_registry(), &task_snapshot.url);
                 (
                     decide_queue_admission(
                         guard.active_downloads.len(),
                         guard.config.concurrent_downloads,
                     ),
+                    platform_full,
                     Arc::clone(&guard.download_semaphore),
                     task,
                     None,
@@ -1728,7 +1967,7 @@ impl DownloadManager {
             return Ok(());
         }
 
-        if queue_admission == QueueAdmissionResult::QueueForConcurrency {
+        if queue_admission == QueueAdmissionResult::QueueForConcurrency || platform_full.is_some() {
             let _ = Self::runtime_enqueue_task(manager, task_id, QUEUE_PRIORITY_MANUAL).await;
             {
                 let mut guard = manager.write().await;
@@ -1742,9 +1981,10 @@ impl DownloadManager {
                 "persist state after queueing (runtime start limit)",
             )
             .await;
-            return Err(AppError::Download(
-                "Maximum concurrent downloads reached".to_string(),
-            ));
+            return Err(AppError::Download(match platform_full {
+                Some(platform) => format!("Maximum concurrent downloads reached for {}", platform),
+                None => "Maximum concurrent downloads reached".to_string(),
+            }));
         }
 
         let permit = match semaphore.try_acquire_owned() {
@@ -1770,6 +2010,10 @@ impl DownloadManager {
         };
 
         let _ = Self::runtime_remove_task_from_queue(manager, task_id).await;
+        manager
+            .read()
+            .await
+            .register_task_bandwidth(task_id, QUEUE_PRIORITY_MANUAL);
 
         Self::runtime_start_with_permit(manager, task_id, task_for_start, permit).await
     }
@@ -2137,6 +2381,17 @@ impl DownloadManager {
 
     /// Update the download configuration
     pub async fn update_config(&mut self, config: DownloadConfig) -> AppResult<()> {
+        let bandwidth_schedule = BandwidthSchedule::from_download_config(&config)
+            .map_err(|e| AppError::Config(format!("Invalid bandwidth schedule: {}", e)))?;
+        let bandwidth_limits = BandwidthLimits::from_download_config(&config)
+            .map_err(|e| AppError::Config(format!("Invalid bandwidth limits: {}", e)))?;
+        // Rebuild the shared HTTP client first so invalid network settings leave the config untouched.
+        let client_options = HttpClientOptions::from_download_config(&config)
+            .map_err(|e| AppError::Config(format!("Invalid network configuration: {}", e)))?;
+        self.http_downloader
+            .apply_client_options(client_options)
+            .map_err(|e| AppError::Config(format!("Failed to rebuild HTTP client: {}", e)))?;
+
         // First settle any deferred changes so bookkeeping starts from latest real capacity.
         self.settle_pending_semaphore_reduction();
 
@@ -2145,6 +2400,11 @@ impl DownloadManager {
             .saturating_sub(self.pending_semaphore_reduction);
 
         self.config = config;
+        self.rate_limit_control.get_mut().schedule = bandwidth_schedule;
+        self.http_downloader
+            .bandwidth_controller()
+            .configure(bandwidth_limits);
+        self.refresh_rate_limit().await;
         let new_target = self.config.concurrent_downloads.max(1);
 
         if new_target > old_target {
@@ -2238,24 +2498,21 @@ impl DownloadManager {
         Ok(retry_count)
     }
 
-    /// Set download rate limit in bytes per second (None = unlimited)
+    /// Set download rate limit in bytes per second.
+    /// Overrides the bandwidth schedule until cleared; None returns to the schedule.
     pub async fn set_rate_limit(&self, bytes_per_second: Option<u64>) {
-        let mut rate_limit = self.rate_limit.write().await;
-        *rate_limit = bytes_per_second;
-
-        if let Some(limit) = bytes_per_second {
-            info!("🚦 Download rate limit set to {} bytes/sec", limit);
-        } else {
-            info!("🚦 Download rate limit removed");
-        }
+        self.set_rate_limit_override(bytes_per_second, None).await;
     }
 
     pub async fn runtime_set_rate_limit(
         manager: &Arc<RwLock<Self>>,
         bytes_per_second: Option<u64>,
+        expires_in: Option<Duration>,
     ) -> AppResult<Option<u64>> {
         let manager = manager.write().await;
-        manager.set_rate_limit(bytes_per_second).await;
+        manager
+            .set_rate_limit_override(bytes_per_second, expires_in)
+            .await;
         Ok(manager.get_rate_limit().await)
     }
 
@@ -2264,6 +2521,16 @@ impl DownloadManager {
         *self.rate_limit.read().await
     }
 
+    /// Share a persistent credential store with this manager's downloaders
+    pub fn set_credential_store(&self, credentials: CredentialStore) {
+        self.http_downloader.set_credential_store(credentials);
+    }
+
+    /// Share a persistent cookie jar with this manager's downloaders
+    pub fn set_cookie_jar(&self, cookies: CookieJar) {
+        self.http_downloader.set_cookie_jar(cookies);
+    }
+
     /// Get enhanced progress stats for a specific task
     pub async fn get_enhanced_progress(&self, task_id: &str) -> Option<EnhancedProgressStats> {
         self.progress_tracker.get_progress(task_id).await
@@ -2482,12 +2749,84 @@ impl DownloadManager {
                 }
                 self.update_stats().await;
             }
-            DownloadEvent::TaskCompleted { task_id, file_path } => {
-                self.finalize_task_state(task_id, TaskStatus::Completed, Some(file_path), None)
-                    .await?;
+            DownloadEvent::TaskFileResolved { task_id, file_path } => {
+                // 先落盘，续传和重启后沿用同一个文件名
+                if let Some(task) = self.tasks.get_mut(task_id) {
+                    task.resolved_path = Some(file_path.clone());
+                    task.updated_at = chrono::Utc::now();
+                    if let Err(err) = self.persist_state().await {
+                        warn!("Failed to persist resolved file path: {}", err);
+                    }
+                }
+            }
+            DownloadEvent::TaskVariantSelected { task_id, variant } => {
+                if let Some(task) = self.tasks.get_mut(task_id) {
+                    task.hls_variant = Some(variant.clone());
+                    task.updated_at = chrono::Utc::now();
+                    if let Err(err) = self.persist_state().await {
+                        warn!("Failed to persist selected HLS variant: {}", err);
+                    }
+                }
+            }
+            DownloadEvent::TaskSegmentGaps { task_id, gaps } => {
+                if let Some(task) = self.tasks.get_mut(task_id) {
+                    task.segment_gaps = gaps.clone();
+                    task.updated_at = chrono::Utc::now();
+                    if let Err(err) = self.persist_state().await {
+                        warn!("Failed to persist segment gap report: {}", err);
+                    }
+                }
+            }
+            DownloadEvent::TaskFormatSelected { task_id, format } => {
+                if let Some(task) = self.tasks.get_mut(task_id) {
+                    let info = task.external_info.get_or_insert_with(|| ExternalVideoInfo {
+                        source_platform: YtDlpDownloader::detect_platform(&task.url),
+                        extractor: None,
+                        webpage_url: None,
+                        title: None,
+                        thumbnail: None,
+                        duration_seconds: None,
+                        format_id: None,
+                        format_note: None,
+                        requires_auth: false,
+                        playlist_id: None,
+                        playlist_title: None,
+                        playlist_index: None,
+                    });
+                    info.format_id = Some(format.format_id.clone());
+                    info.format_note = format.format_note.clone();
+                    task.updated_at = chrono::Utc::now();
+                    if let Err(err) = self.persist_state().await {
+                        warn!("Failed to persist selected format: {}", err);
+                    }
+                }
+            }
+            DownloadEvent::TaskSidecarFiles { task_id, files } => {
+                if let Some(task) = self.tasks.get_mut(task_id) {
+                    task.sidecar_files = files.clone();
+                    task.updated_at = chrono::Utc::now();
+                    if let Err(err) = self.persist_state().await {
+                        warn!("Failed to persist sidecar files: {}", err);
+                    }
+                }
+            }
+            DownloadEvent::TaskCompleted {
+                task_id,
+                file_path,
+                warning,
+            } => {
+                self.release_task_bandwidth(task_id);
+                self.finalize_task_state(
+                    task_id,
+                    TaskStatus::Completed,
+                    Some(file_path),
+                    warning.clone(),
+                )
+                .await?;
                 should_replenish_queue = true;
             }
             DownloadEvent::TaskFailed { task_id, error } => {
+                self.release_task_bandwidth(task_id);
                 self.finalize_task_state(
                     task_id,
                     TaskStatus::Failed,
@@ -2498,6 +2837,7 @@ impl DownloadManager {
                 should_replenish_queue = true;
             }
             DownloadEvent::TaskCancelled { task_id } => {
+                self.release_task_bandwidth(task_id);
                 self.finalize_task_state(task_id, TaskStatus::Cancelled, None, None)
                     .await?;
                 should_replenish_queue = true;
@@ -2518,6 +2858,7 @@ impl DownloadManager {
                     !(task.status == TaskStatus::Downloading && task.paused_at.is_none())
                 });
                 if should_mark_paused {
+                    self.release_task_bandwidth(task_id);
                     self.update_task_status(task_id, TaskStatus::Paused).await?;
                 }
                 should_replenish_queue = true;
@@ -2612,6 +2953,9 @@ impl DownloadManager {
             }
 
             task.progress = next_progress;
+            if progress.recording.is_some() {
+                task.recording = progress.recording.clone();
+            }
             task.updated_at = chrono::Utc::now();
         }
 
@@ -2739,8 +3083,8 @@ impl DownloadManager {
     async fn execute_download(
         task_id: &str,
         url: &str,
-        output_path: &str,
-        preferred_title: Option<String>,
+        mirrors: Vec<String>,
+        target: EffectiveDownloadTarget,
         initial_downloaded_size: u64,
         initial_file_size: Option<u64>,
         downloader: Arc<HttpDownloader>,
@@ -2752,22 +3096,28 @@ impl DownloadManager {
     ) -> AppResult<DownloadOutcome> {
         info!(
             "🔽 Starting download with retry mechanism: {} -> {}",
-            url, output_path
+            url, target.output_path
         );
 
         // Clone data for retry closure
         let task_id = task_id.to_string();
         let url = url.to_string();
-        let output_path = output_path.to_string();
-        let preferred_title = preferred_title.filter(|title| !title.trim().is_empty());
+        let mirrors = Self::normalize_mirrors(&url, mirrors);
+        // 某次尝试按响应头改名后，后续重试沿用新文件名
+        let target = Arc::new(Mutex::new(EffectiveDownloadTarget {
+            preferred_title: target
+                .preferred_title
+                .filter(|title| !title.trim().is_empty()),
+            ..target
+        }));
 
         // Execute download with retry mechanism
         let result = retry_executor
             .execute(|retry_context| {
                 let task_id = task_id.clone();
                 let url = url.clone();
-                let output_path = output_path.clone();
-                let preferred_title = preferred_title.clone();
+                let mirrors = mirrors.clone();
+                let target = Arc::clone(&target);
                 let downloader = Arc::clone(&downloader);
                 let event_sender = event_sender.clone();
                 let progress_tracker = Arc::clone(&progress_tracker);
@@ -2784,8 +3134,8 @@ impl DownloadManager {
                     match Self::execute_download_attempt(
                         &task_id,
                         &url,
-                        &output_path,
-                        preferred_title,
+                        mirrors,
+                        target,
                         initial_downloaded_size,
                         initial_file_size,
                         downloader,
@@ -2842,8 +3192,8 @@ impl DownloadManager {
     async fn execute_download_attempt(
         task_id: &str,
         url: &str,
-        output_path: &str,
-        preferred_title: Option<String>,
+        mirrors: Vec<String>,
+        target: Arc<Mutex<EffectiveDownloadTarget>>,
         initial_downloaded_size: u64,
         initial_file_size: Option<u64>,
         downloader: Arc<HttpDownloader>,
@@ -2852,16 +3202,38 @@ impl DownloadManager {
         integrity_checker: Arc<IntegrityChecker>,
         config: DownloadConfig,
     ) -> AppResult<DownloadOutcome> {
-        debug!("🔄 Attempting download: {} -> {}", url, output_path);
+        let current_target = target.lock().await.clone();
+        debug!(
+            "🔄 Attempting download: {} -> {}",
+            url, current_target.output_path
+        );
 
         // Resolve output directory and filename (supports full file path inputs).
-        let (output_dir, filename) =
-            Self::split_output_path(url, output_path, preferred_title.as_deref());
+        let (output_dir, filename) = current_target.split(url);
 
         // Create download task and ensure IDs match the manager task ID so progress events line up.
         let mut download_task =
             DownloadTask::new(url.to_string(), output_dir.to_string(), filename);
         download_task.id = task_id.to_string();
+        download_task.filename_origin = current_target.filename_origin;
+        download_task.variant_policy = current_target
+            .variant_policy
+            .clone()
+            .unwrap_or_else(|| config.hls_variant_policy.clone());
+        download_task.ytdlp_format = current_target.ytdlp_format.clone().unwrap_or_else(|| {
+            platform_registry::current().ytdlp_format_for(url, &config.ytdlp_format)
+        });
+        download_task.ytdlp_merge_format = config.ytdlp_merge_format.clone();
+        download_task.ytdlp_sidecars = config.ytdlp_sidecars.clone();
+        download_task.audio_extraction = current_target.audio_extraction.clone();
+        download_task.ytdlp_audio_extraction = config.ytdlp_audio_extraction.clone();
+        download_task.live_limits = LiveRecordingLimits {
+            max_duration_secs: config.live_max_duration_secs,
+            max_bytes: config.live_max_bytes,
+        };
+        download_task.max_missing_segments = config.hls_max_missing_segments as usize;
+        download_task.rendition_preferences = config.hls_renditions.clone();
+        download_task.mirrors = mirrors;
         download_task.stats.downloaded_bytes = initial_downloaded_size;
         download_task.stats.total_bytes = initial_file_size;
         if let Some(total) = initial_file_size {
@@ -2883,8 +3255,54 @@ impl DownloadManager {
         // Spawn enhanced progress tracking task
         let progress_handle = tokio::spawn(async move {
             let mut committing_emitted = false;
+            let mut resolved_emitted = false;
             while let Some((task_id, download_stats)) = download_progress_rx.recv().await {
                 if task_id == task_id_clone {
+                    if let Some(variant) = download_stats.hls_variant.clone() {
+                        let _ = event_sender_clone.send(DownloadEvent::TaskVariantSelected {
+                            task_id: task_id_clone.clone(),
+                            variant,
+                        });
+                    }
+                    if let Some(gaps) = download_stats.segment_gaps.clone() {
+                        let _ = event_sender_clone.send(DownloadEvent::TaskSegmentGaps {
+                            task_id: task_id_clone.clone(),
+                            gaps,
+                        });
+                        // 缺口报告不携带进度，不能当作进度更新
+                        continue;
+                    }
+                    if let Some(format) = download_stats.selected_format.clone() {
+                        let _ = event_sender_clone.send(DownloadEvent::TaskFormatSelected {
+                            task_id: task_id_clone.clone(),
+                            format,
+                        });
+                        continue;
+                    }
+                    if let Some(files) = download_stats.sidecar_files.clone() {
+                        let _ = event_sender_clone.send(DownloadEvent::TaskSidecarFiles {
+                            task_id: task_id_clone.clone(),
+                            files,
+                        });
+                        continue;
+                    }
+                    if let Some(file_path) = download_stats
+                        .resolved_path
+                        .as_ref()
+                        .filter(|_| !resolved_emitted)
+                    {
+                        {
+                            let mut target = target.lock().await;
+                            target.output_path = file_path.clone();
+                            target.is_file_path = true;
+                            target.filename_origin = FilenameOrigin::Fixed;
+                        }
+                        let _ = event_sender_clone.send(DownloadEvent::TaskFileResolved {
+                            task_id: task_id_clone.clone(),
+                            file_path: file_path.clone(),
+                        });
+                        resolved_emitted = true;
+                    }
                     if matches!(download_stats.status_hint, Some(TaskStatus::Committing))
                         && !committing_emitted
                     {
@@ -2950,6 +3368,7 @@ impl DownloadManager {
                     let _ = event_sender.send(DownloadEvent::TaskCompleted {
                         task_id: task_id.to_string(),
                         file_path: file_path.clone(),
+                        warning: completed_task.error_message.clone(),
                     });
 
                     if let Err(err) = Self::persist_completion_marker(&file_path_buf, url).await {
@@ -3615,6 +4034,15 @@ mod tests {
         let base_task = VideoTask {
             id: "task-1".to_string(),
             url: "https://example.com/video.mp4".to_string(),
+            mirrors: Vec::new(),
+            rate_limit: None,
+            variant_policy: None,
+            ytdlp_format: None,
+            hls_variant: None,
+            recording: None,
+            segment_gaps: Vec::new(),
+            sidecar_files: Vec::new(),
+            audio_extraction: None,
             title: "Test Video".to_string(),
             output_path: "./downloads".to_string(),
             resolved_path: None,
@@ -3649,6 +4077,15 @@ mod tests {
         let duplicate_task = VideoTask {
             id: "task-2".to_string(),
             url: "https://example.com/another.mp4".to_string(),
+            mirrors: Vec::new(),
+            rate_limit: None,
+            variant_policy: None,
+            ytdlp_format: None,
+            hls_variant: None,
+            recording: None,
+            segment_gaps: Vec::new(),
+            sidecar_files: Vec::new(),
+            audio_extraction: None,
             title: "Duplicate Video".to_string(),
             output_path: "./other".to_string(),
             resolved_path: None,
@@ -3758,6 +4195,8 @@ mod tests {
                     display_speed_bps: 1024,
                     eta: None,
                     progress: 1.0,
+                    sources: Vec::new(),
+                    recording: None,
                 },
             })
             .await?;
@@ -3815,6 +4254,7 @@ mod tests {
             .apply_event_side_effects(&DownloadEvent::TaskCompleted {
                 task_id: task_id.clone(),
                 file_path: final_path.to_string_lossy().to_string(),
+                warning: None,
             })
             .await?;
 
@@ -3858,6 +4298,7 @@ mod tests {
             .apply_event_side_effects(&DownloadEvent::TaskCompleted {
                 task_id: task_id.clone(),
                 file_path: final_path.to_string_lossy().to_string(),
+                warning: None,
             })
             .await?;
         drop(manager);
@@ -3924,6 +4365,15 @@ mod tests {
         let task = VideoTask {
             id: "task-generic-filename".to_string(),
             url: "https://example.com/playlist.f9.mp4".to_string(),
+            mirrors: Vec::new(),
+            rate_limit: None,
+            variant_policy: None,
+            ytdlp_format: None,
+            hls_variant: None,
+            recording: None,
+            segment_gaps: Vec::new(),
+            sidecar_files: Vec::new(),
+            audio_extraction: None,
             title: "2、阳台月季种植".to_string(),
             output_path: "F:/temp/downloads".to_string(),
             resolved_path: None,
@@ -4241,6 +4691,27 @@ mod tests {
         assert!(!integrity_error.is_retryable());
     }
 
+    #[test]
+    fn test_normalize_mirrors_filters_invalid_and_duplicate_urls() {
+        let mirrors = DownloadManager::normalize_mirrors(
+            "https://a.cdn.com/v.mp4",
+            vec![
+                " https://b.cdn.com/v.mp4 ".to_string(),
+                "https://a.cdn.com/v.mp4".to_string(),
+                "ftp://c.cdn.com/v.mp4".to_string(),
+                "https://b.cdn.com/v.mp4".to_string(),
+                "".to_string(),
+                "http://c.cdn.com/v.mp4".to_string(),
+            ],
+        );
+        assert_eq!(
+            mirrors,
+            vec![
+                "https://b.cdn.com/v.mp4".to_string(),
+                "http://c.cdn.com/v.mp4".to_string()
+            ]
+        );
+    }
     #[tokio::test]
     async fn test_add_task_with_priority() -> AppResult<()> {
         let config = DownloadConfig::default();
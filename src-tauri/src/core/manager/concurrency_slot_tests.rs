@@ -66,6 +66,7 @@ async fn finished_handle_does_not_free_slot_before_terminal_event_updates_status
         .apply_event_side_effects(&DownloadEvent::TaskCompleted {
             task_id: active_id.clone(),
             file_path: completed_path.to_string_lossy().to_string(),
+            warning: None,
         })
         .await?;
 
@@ -86,3 +87,61 @@ async fn finished_handle_does_not_free_slot_before_terminal_event_updates_status
 
     Ok(())
 }
+
+#[tokio::test]
+async fn platform_cap_counts_only_active_tasks_of_the_same_platform() -> AppResult<()> {
+    let temp_dir = TempDir::new().unwrap();
+    let state_path = temp_dir.path().join("download_state.json");
+    let config = DownloadConfig {
+        output_directory: temp_dir.path().to_string_lossy().to_string(),
+        ..DownloadConfig::default()
+    };
+    let mut manager = DownloadManager::new_with_state_path(config, state_path)?;
+
+    let registry = PlatformRegistry::new(&[platform_registry::PlatformRule {
+        id: "courses".to_string(),
+        display_name: "内部课程".to_string(),
+        hosts: vec!["learn.corp.example".to_string()],
+        route: platform_registry::PlatformRoute::Http,
+        ytdlp_args: Vec::new(),
+        default_quality: None,
+        max_concurrent: Some(1),
+    }]);
+
+    let other_id = manager
+        .add_task(
+            "https://example.com/other.mp4".to_string(),
+            temp_dir.path().to_string_lossy().to_string(),
+        )
+        .await?;
+    manager
+        .active_downloads
+        .insert(other_id, tokio::spawn(std::future::pending()));
+    assert_eq!(
+        manager.platform_at_capacity(&registry, "https://learn.corp.example/a.mp4"),
+        None
+    );
+
+    let course_id = manager
+        .add_task(
+            "https://learn.corp.example/lesson-1.mp4".to_string(),
+            temp_dir.path().to_string_lossy().to_string(),
+        )
+        .await?;
+    manager
+        .active_downloads
+        .insert(course_id, tokio::spawn(std::future::pending()));
+    assert_eq!(
+        manager.platform_at_capacity(&registry, "https://learn.corp.example/lesson-2.mp4"),
+        Some("内部课程".to_string())
+    );
+    assert_eq!(
+        manager.platform_at_capacity(&registry, "https://www.youtube.com/watch?v=abc"),
+        None
+    );
+
+    for (_, handle) in manager.active_downloads.drain() {
+        handle.abort();
+    }
+    Ok(())
+}
@@ -3,6 +3,7 @@ use std::path::{Path, PathBuf};
 
 use super::DownloadManager;
 use crate::core::models::VideoTask;
+use crate::core::remote_filename::FilenameOrigin;
 use crate::utils::file_utils::sanitize_filename;
 
 impl DownloadManager {
@@ -26,6 +27,39 @@ impl DownloadManager {
         Some(Path::new(&output_dir).join(filename))
     }
 
+    /// 目标文件名仍是自动生成的（来自 URL 或标题）时，下载器可按响应头改名；
+    /// 用户指定的文件名和已经改过名的路径保持不变
+    pub(super) fn filename_origin(
+        task: &VideoTask,
+        preferred_title: Option<&str>,
+    ) -> FilenameOrigin {
+        let resolved = task
+            .resolved_path
+            .as_deref()
+            .map(str::trim)
+            .filter(|path| !path.is_empty());
+        let current = match resolved {
+            Some(resolved) => match Path::new(resolved).file_name() {
+                Some(filename) => filename.to_string_lossy().to_string(),
+                None => return FilenameOrigin::Fixed,
+            },
+            None => Self::split_output_path(&task.url, &task.output_path, preferred_title).1,
+        };
+
+        if current == Self::derive_output_filename(&task.url, None) {
+            return FilenameOrigin::Url;
+        }
+        let from_title = [preferred_title, Some(task.title.as_str())]
+            .into_iter()
+            .flatten()
+            .any(|title| current == Self::derive_output_filename(&task.url, Some(title)));
+        if from_title {
+            FilenameOrigin::Title
+        } else {
+            FilenameOrigin::Fixed
+        }
+    }
+
     pub(super) fn normalize_output_path(output_path: &str) -> String {
         output_path.trim_end_matches(['/', '\\']).to_string()
     }
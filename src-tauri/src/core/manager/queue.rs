@@ -67,6 +67,9 @@ impl DownloadManager {
         if self.queue_paused {
             return;
         }
+        let registry = self.platform_registry();
+        // 平台并发已满的任务本轮跳过，循环结束后原样放回队列
+        let mut deferred = Vec::new();
         loop {
             self.settle_pending_semaphore_reduction();
             self.reap_finished_active_downloads();
@@ -105,6 +108,11 @@ impl DownloadManager {
                 None => continue,
             };
 
+            if self.platform_at_capacity(&registry, &task.url).is_some() {
+                deferred.push(task_priority);
+                continue;
+            }
+
             let permit = match self.download_semaphore.clone().try_acquire_owned() {
                 Ok(permit) => permit,
                 Err(_) => {
@@ -113,6 +121,7 @@ impl DownloadManager {
                 }
             };
 
+            self.register_task_bandwidth(&task_id, task_priority.priority);
             if let Err(err) = self
                 .start_download_with_permit(&task_id, task, permit)
                 .await
@@ -120,6 +129,37 @@ impl DownloadManager {
                 warn!("Failed to start queued task {}: {}", task_id, err);
             }
         }
+        if !deferred.is_empty() {
+            self.task_queue.lock().await.extend(deferred);
+        }
+    }
+
+    /// 并发上限使用的平台规则表
+    pub(super) fn platform_registry(&self) -> Arc<PlatformRegistry> {
+        self.platform_registry
+            .clone()
+            .unwrap_or_else(platform_registry::current)
+    }
+
+    /// 任务所属平台的并发上限已被占满时返回平台名
+    pub(super) fn platform_at_capacity(
+        &self,
+        registry: &PlatformRegistry,
+        url: &str,
+    ) -> Option<String> {
+        let rule = registry.match_url(url)?;
+        let limit = rule.max_concurrent?;
+        let active = self
+            .active_downloads
+            .keys()
+            .filter_map(|task_id| self.tasks.get(task_id))
+            .filter(|task| {
+                registry
+                    .match_url(&task.url)
+                    .is_some_and(|other| other.id == rule.id)
+            })
+            .count();
+        (active >= limit).then(|| rule.display_name.clone())
     }
 
     pub(super) fn settle_pending_semaphore_reduction(&mut self) {
@@ -0,0 +1,303 @@
+#![deny(clippy::unwrap_used, clippy::expect_used)]
+
+use super::*;
+use crate::core::bandwidth_schedule::RateLimitOverride;
+use chrono::{DateTime, Local};
+
+#[derive(Debug, Default)]
+pub(super) struct RateLimitControl {
+    pub(super) schedule: BandwidthSchedule,
+    manual: Option<RateLimitOverride>,
+    applied: RateLimitStatus,
+}
+
+impl RateLimitControl {
+    pub(super) fn new(schedule: BandwidthSchedule) -> Self {
+        Self {
+            schedule,
+            ..Self::default()
+        }
+    }
+}
+
+impl DownloadManager {
+    /// Manual rate limit that wins over the bandwidth schedule; `expires_in` = None keeps it
+    /// until cleared or replaced. Removing the limit without an expiry returns to the schedule
+    /// instead of pinning a permanent "unlimited" override.
+    pub async fn set_rate_limit_override(
+        &self,
+        bytes_per_second: Option<u64>,
+        expires_in: Option<Duration>,
+    ) -> RateLimitStatus {
+        if bytes_per_second.is_none() && expires_in.is_none() {
+            info!("🚦 Download rate limit removed, returning to bandwidth schedule");
+            return self.clear_rate_limit_override().await;
+        }
+
+        let expires_at = expires_in.and_then(|duration| {
+            chrono::Duration::from_std(duration)
+                .ok()
+                .map(|duration| chrono::Utc::now() + duration)
+        });
+        self.rate_limit_control.lock().await.manual = Some(RateLimitOverride {
+            bytes_per_second,
+            expires_at,
+        });
+
+        match (bytes_per_second, expires_at) {
+            (Some(limit), Some(expires_at)) => info!(
+                "🚦 Download rate limit set to {} bytes/sec until {}",
+                limit, expires_at
+            ),
+            (Some(limit), None) => info!("🚦 Download rate limit set to {} bytes/sec", limit),
+            (None, _) => info!("🚦 Download rate limit removed"),
+        }
+        self.refresh_rate_limit().await
+    }
+
+    /// Drop the manual override and fall back to the bandwidth schedule
+    pub async fn clear_rate_limit_override(&self) -> RateLimitStatus {
+        self.rate_limit_control.lock().await.manual = None;
+        self.refresh_rate_limit().await
+    }
+
+    pub async fn rate_limit_status(&self) -> RateLimitStatus {
+        self.rate_limit_control.lock().await.applied.clone()
+    }
+
+    /// Re-evaluate schedule and override against the current local time
+    pub async fn refresh_rate_limit(&self) -> RateLimitStatus {
+        self.refresh_rate_limit_at(Local::now()).await
+    }
+
+    pub(super) async fn refresh_rate_limit_at(&self, now: DateTime<Local>) -> RateLimitStatus {
+        let mut control = self.rate_limit_control.lock().await;
+        if control
+            .manual
+            .as_ref()
+            .is_some_and(|manual| manual.is_expired(now.with_timezone(&chrono::Utc)))
+        {
+            info!("🚦 Manual rate limit expired, returning to bandwidth schedule");
+            control.manual = None;
+        }
+
+        let status =
+            RateLimitStatus::resolve(&control.schedule, control.manual.as_ref(), now.time());
+        if status != control.applied {
+            *self.rate_limit.write().await = status.bytes_per_second;
+            info!(
+                "🚦 Effective rate limit {:?} bytes/sec ({:?}, rule {:?})",
+                status.bytes_per_second, status.source, status.rule
+            );
+            control.applied = status.clone();
+            self.emit_event(DownloadEvent::RateLimitChanged {
+                status: status.clone(),
+            });
+        }
+        status
+    }
+
+    /// Register a starting task's priority weight and own cap with the bandwidth controller
+    pub(super) fn register_task_bandwidth(&self, task_id: &str, priority: u8) {
+        let priority = self.queue_priority_for_task_id(task_id, priority);
+        let limit = self.tasks.get(task_id).and_then(|task| task.rate_limit);
+        self.http_downloader
+            .bandwidth_controller()
+            .register_task(task_id, priority, limit);
+    }
+
+    pub(super) fn release_task_bandwidth(&self, task_id: &str) {
+        self.http_downloader
+            .bandwidth_controller()
+            .unregister_task(task_id);
+    }
+
+    /// Set (or clear) a task's own cap; applies immediately if the task is downloading
+    pub async fn set_task_rate_limit(
+        &mut self,
+        task_id: &str,
+        bytes_per_second: Option<u64>,
+    ) -> AppResult<VideoTask> {
+        if bytes_per_second == Some(0) {
+            return Err(AppError::Config(
+                "Task rate limit must be positive".to_string(),
+            ));
+        }
+        let task = self
+            .tasks
+            .get_mut(task_id)
+            .ok_or_else(|| AppError::Download(format!("Task not found: {}", task_id)))?;
+        task.rate_limit = bytes_per_second;
+        task.updated_at = chrono::Utc::now();
+        let task = task.clone();
+
+        self.http_downloader
+            .bandwidth_controller()
+            .set_task_limit(task_id, bytes_per_second);
+        if let Err(err) = self.persist_state().await {
+            warn!(
+                "Failed to persist state after task rate limit change: {}",
+                err
+            );
+        }
+        info!(
+            "🚦 Task {} rate limit set to {:?} bytes/sec",
+            task_id, bytes_per_second
+        );
+        Ok(task)
+    }
+
+    pub async fn runtime_set_task_rate_limit(
+        manager: &Arc<RwLock<Self>>,
+        task_id: &str,
+        bytes_per_second: Option<u64>,
+    ) -> AppResult<VideoTask> {
+        let mut manager = manager.write().await;
+        manager.set_task_rate_limit(task_id, bytes_per_second).await
+    }
+
+    pub async fn runtime_clear_rate_limit_override(
+        manager: &Arc<RwLock<Self>>,
+    ) -> AppResult<RateLimitStatus> {
+        let manager = manager.read().await;
+        Ok(manager.clear_rate_limit_override().await)
+    }
+
+    pub async fn runtime_refresh_rate_limit(
+        manager: &Arc<RwLock<Self>>,
+    ) -> AppResult<RateLimitStatus> {
+        let manager = manager.read().await;
+        Ok(manager.refresh_rate_limit().await)
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::expect_used)]
+mod tests {
+    use super::*;
+    use crate::core::bandwidth_schedule::RateLimitSource;
+    use crate::core::models::BandwidthRule;
+    use chrono::TimeZone;
+
+    fn local(hour: u32, minute: u32) -> DateTime<Local> {
+        Local
+            .with_ymd_and_hms(2026, 3, 2, hour, minute, 0)
+            .single()
+            .expect("unambiguous local time")
+    }
+
+    fn office_hours_config() -> DownloadConfig {
+        DownloadConfig {
+            bandwidth_schedule: vec![
+                BandwidthRule {
+                    start: "09:00".to_string(),
+                    end: "18:00".to_string(),
+                    bytes_per_second: Some(2 * 1024 * 1024),
+                    label: Some("office".to_string()),
+                },
+                BandwidthRule {
+                    start: "18:00".to_string(),
+                    end: "24:00".to_string(),
+                    bytes_per_second: Some(10 * 1024 * 1024),
+                    label: None,
+                },
+            ],
+            ..DownloadConfig::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn schedule_switches_limit_and_emits_event() -> AppResult<()> {
+        let mut manager = DownloadManager::new(office_hours_config())?;
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        manager.event_sender = Some(tx);
+
+        let status = manager.refresh_rate_limit_at(local(10, 0)).await;
+        assert_eq!(status.source, RateLimitSource::Schedule);
+        assert_eq!(status.rule.as_deref(), Some("office"));
+        assert_eq!(manager.get_rate_limit().await, Some(2 * 1024 * 1024));
+        assert!(matches!(
+            rx.try_recv(),
+            Ok(DownloadEvent::RateLimitChanged { status }) if status.bytes_per_second == Some(2 * 1024 * 1024)
+        ));
+
+        // 同一时段重复检查不会重复发事件
+        manager.refresh_rate_limit_at(local(11, 30)).await;
+        assert!(rx.try_recv().is_err());
+
+        manager.refresh_rate_limit_at(local(7, 0)).await;
+        assert_eq!(manager.get_rate_limit().await, None);
+        assert_eq!(
+            manager.rate_limit_status().await.source,
+            RateLimitSource::Unlimited
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn manual_override_expires_back_to_schedule() -> AppResult<()> {
+        let manager = DownloadManager::new(office_hours_config())?;
+
+        let status = manager
+            .set_rate_limit_override(Some(512 * 1024), Some(Duration::from_secs(60)))
+            .await;
+        assert_eq!(status.source, RateLimitSource::Manual);
+        assert!(status.override_expires_at.is_some());
+        assert_eq!(manager.get_rate_limit().await, Some(512 * 1024));
+
+        // 两分钟后手动限速已过期，回到当前时段的规则
+        let later = Local::now() + chrono::Duration::minutes(2);
+        let status = manager.refresh_rate_limit_at(later).await;
+        assert_ne!(status.source, RateLimitSource::Manual);
+        assert_eq!(manager.get_rate_limit().await, status.bytes_per_second);
+
+        manager.set_rate_limit(Some(1024 * 1024)).await;
+        manager.clear_rate_limit_override().await;
+        assert_ne!(
+            manager.rate_limit_status().await.source,
+            RateLimitSource::Manual
+        );
+
+        // 不带时长地取消限速等同于恢复时段规则，而不是永久不限速
+        manager.set_rate_limit(Some(1024 * 1024)).await;
+        manager.set_rate_limit(None).await;
+        assert_ne!(
+            manager.rate_limit_status().await.source,
+            RateLimitSource::Manual
+        );
+
+        // 带时长的不限速仍是会过期的手动覆盖
+        let status = manager
+            .set_rate_limit_override(None, Some(Duration::from_secs(60)))
+            .await;
+        assert_eq!(status.source, RateLimitSource::Manual);
+        assert!(status.override_expires_at.is_some());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn task_rate_limit_is_persisted_on_the_task() -> AppResult<()> {
+        let temp_dir = tempfile::tempdir()?;
+        let mut manager = DownloadManager::new_with_state_path(
+            DownloadConfig::default(),
+            temp_dir.path().join("state.json"),
+        )?;
+        let task_id = manager
+            .add_task(
+                "https://example.com/a.mp4".to_string(),
+                temp_dir.path().to_string_lossy().to_string(),
+            )
+            .await?;
+
+        let task = manager
+            .set_task_rate_limit(&task_id, Some(256 * 1024))
+            .await?;
+        assert_eq!(task.rate_limit, Some(256 * 1024));
+        assert!(manager
+            .set_task_rate_limit(&task_id, Some(0))
+            .await
+            .is_err());
+        assert!(manager.set_task_rate_limit("missing", None).await.is_err());
+        Ok(())
+    }
+}
@@ -76,6 +76,66 @@ async fn runtime_start_queues_failed_task_and_clears_error_when_full() -> AppRes
     Ok(())
 }
 
+#[tokio::test]
+async fn runtime_start_queues_task_when_platform_cap_is_reached() -> AppResult<()> {
+    let temp_dir = TempDir::new().unwrap();
+    let state_path = temp_dir.path().join("download_state.json");
+    let config = DownloadConfig {
+        concurrent_downloads: 3,
+        output_directory: temp_dir.path().to_string_lossy().to_string(),
+        ..DownloadConfig::default()
+    };
+    let mut manager = DownloadManager::new_with_state_path(config, state_path)?;
+    manager.platform_registry = Some(Arc::new(PlatformRegistry::new(&[
+        platform_registry::PlatformRule {
+            id: "courses".to_string(),
+            display_name: "内部课程".to_string(),
+            hosts: vec!["learn.corp.example".to_string()],
+            route: platform_registry::PlatformRoute::Http,
+            ytdlp_args: Vec::new(),
+            default_quality: None,
+            max_concurrent: Some(1),
+        },
+    ])));
+
+    let running_id = manager
+        .add_task(
+            "https://learn.corp.example/lesson-1.mp4".to_string(),
+            temp_dir.path().to_string_lossy().to_string(),
+        )
+        .await?;
+    let second_id = manager
+        .add_task(
+            "https://learn.corp.example/lesson-2.mp4".to_string(),
+            temp_dir.path().to_string_lossy().to_string(),
+        )
+        .await?;
+    if let Some(task) = manager.tasks.get_mut(&running_id) {
+        task.status = TaskStatus::Downloading;
+    }
+    manager
+        .active_downloads
+        .insert(running_id.clone(), tokio::spawn(std::future::pending()));
+
+    let manager = Arc::new(RwLock::new(manager));
+    let result = DownloadManager::runtime_start_download(&manager, &second_id).await;
+    assert!(matches!(result, Err(AppError::Download(message)) if message.contains("内部课程")));
+
+    let mut guard = manager.write().await;
+    let task = guard.tasks.get(&second_id).expect("task must exist");
+    assert_eq!(task.status, TaskStatus::Pending);
+    assert!(!guard.active_downloads.contains_key(&second_id));
+    {
+        let queue = guard.task_queue.lock().await;
+        assert!(queue.iter().any(|queued| queued.task_id == second_id));
+    }
+    if let Some(handle) = guard.active_downloads.remove(&running_id) {
+        handle.abort();
+    }
+
+    Ok(())
+}
+
 #[tokio::test]
 async fn runtime_pause_rejects_terminal_and_committing_statuses() -> AppResult<()> {
     for status in [
@@ -1,5 +1,6 @@
 use super::*;
 use crate::core::models::{DownloaderType, ExternalVideoInfo, SourcePlatform, VideoTask};
+use crate::core::remote_filename::FilenameOrigin;
 
 fn task_for_target(
     downloader_type: Option<DownloaderType>,
@@ -11,6 +12,15 @@ fn task_for_target(
     VideoTask {
         id: "task-ytdlp-target".to_string(),
         url: "https://www.youtube.com/watch?v=rYGpQwTKUcI".to_string(),
+        mirrors: Vec::new(),
+        rate_limit: None,
+        variant_policy: None,
+        ytdlp_format: None,
+        hls_variant: None,
+        recording: None,
+        segment_gaps: Vec::new(),
+        sidecar_files: Vec::new(),
+        audio_extraction: None,
         title: title.to_string(),
         output_path: output_path.to_string(),
         resolved_path: resolved_path.map(str::to_string),
@@ -43,6 +53,9 @@ fn youtube_info(title: &str) -> ExternalVideoInfo {
         format_id: None,
         format_note: None,
         requires_auth: false,
+        playlist_id: None,
+        playlist_title: None,
+        playlist_index: None,
     }
 }
 
@@ -141,3 +154,38 @@ fn ytdlp_target_uses_provider_title_when_table_title_is_missing_placeholder() {
         Some("Provider Video Title")
     );
 }
+
+#[test]
+fn http_target_lets_downloader_rename_generated_names_only() {
+    let mut task = task_for_target(
+        Some(DownloaderType::Http),
+        "/data/videos",
+        Some("/data/videos/download"),
+        "download",
+    );
+    task.url = "https://cdn.course.com/download?id=123".to_string();
+
+    // 没有扩展名的已解析路径仍然是文件，而不是目录
+    let target = DownloadManager::effective_download_target(&task);
+    assert_eq!(target.filename_origin, FilenameOrigin::Url);
+    assert_eq!(
+        target.split(&task.url),
+        ("/data/videos".to_string(), "download".to_string())
+    );
+
+    task.title = "第一课".to_string();
+    task.resolved_path = Some("/data/videos/第一课".to_string());
+    assert_eq!(
+        DownloadManager::effective_download_target(&task).filename_origin,
+        FilenameOrigin::Title
+    );
+
+    // 已按响应头改名或用户指定的文件名保持不变
+    task.resolved_path = Some("/data/videos/lesson-1.mp4".to_string());
+    let target = DownloadManager::effective_download_target(&task);
+    assert_eq!(target.filename_origin, FilenameOrigin::Fixed);
+    assert_eq!(
+        target.split(&task.url),
+        ("/data/videos".to_string(), "lesson-1.mp4".to_string())
+    );
+}
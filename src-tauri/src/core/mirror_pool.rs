@@ -0,0 +1,314 @@
+//! 多镜像下载源
+//!
+//! 同一文件发布在多个 CDN 上时，按实测速度加权把分片分配到各镜像；
+//! 连续失败或内容不一致的镜像会被剔除，只要还有可用镜像任务就不会失败。
+
+#![deny(clippy::unwrap_used, clippy::expect_used)]
+
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// 镜像连续失败多少次后被剔除
+const DEFAULT_MAX_CONSECUTIVE_FAILURES: u32 = 2;
+
+/// 单个镜像的下载统计（随进度事件一起上报）
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct MirrorStats {
+    pub url: String,
+    /// 由该镜像下载的字节数
+    pub downloaded_bytes: u64,
+    /// 实测单连接速度（字节/秒），尚未测得时为 0
+    pub speed_bps: u64,
+    pub chunks_completed: usize,
+    pub active_chunks: usize,
+    /// 已被剔除
+    pub disabled: bool,
+    pub last_error: Option<String>,
+}
+
+/// 一次分片请求占用的镜像
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MirrorLease {
+    pub index: usize,
+    pub url: String,
+}
+
+/// 镜像失败后的处理结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MirrorFailure {
+    /// 仍可继续使用该镜像
+    Retry,
+    /// 镜像已被剔除，分片可立即换源重试
+    Dropped,
+    /// 已是最后一个可用镜像，按普通失败处理
+    LastMirror,
+}
+
+#[derive(Debug)]
+struct MirrorState {
+    url: String,
+    active: usize,
+    downloaded_bytes: u64,
+    measured_bytes: u64,
+    measured_time: Duration,
+    chunks_completed: usize,
+    consecutive_failures: u32,
+    disabled: bool,
+    last_error: Option<String>,
+}
+
+impl MirrorState {
+    fn speed(&self) -> Option<f64> {
+        let secs = self.measured_time.as_secs_f64();
+        (self.measured_bytes > 0 && secs > 0.0).then(|| self.measured_bytes as f64 / secs)
+    }
+}
+
+#[derive(Debug)]
+pub struct MirrorPool {
+    mirrors: Mutex<Vec<MirrorState>>,
+    max_consecutive_failures: u32,
+}
+
+impl MirrorPool {
+    /// 按优先级顺序创建，忽略空白和重复地址
+    pub fn new(urls: impl IntoIterator<Item = String>) -> Self {
+        let mut mirrors: Vec<MirrorState> = Vec::new();
+        for url in urls {
+            let url = url.trim().to_string();
+            if url.is_empty() || mirrors.iter().any(|mirror| mirror.url == url) {
+                continue;
+            }
+            mirrors.push(MirrorState {
+                url,
+                active: 0,
+                downloaded_bytes: 0,
+                measured_bytes: 0,
+                measured_time: Duration::ZERO,
+                chunks_completed: 0,
+                consecutive_failures: 0,
+                disabled: false,
+                last_error: None,
+            });
+        }
+        Self {
+            mirrors: Mutex::new(mirrors),
+            max_consecutive_failures: DEFAULT_MAX_CONSECUTIVE_FAILURES,
+        }
+    }
+
+    pub fn single(url: &str) -> Self {
+        Self::new(std::iter::once(url.to_string()))
+    }
+
+    /// 配置的镜像总数（包含已剔除的）
+    pub fn len(&self) -> usize {
+        self.mirrors.lock().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.mirrors.lock().is_empty()
+    }
+
+    /// 可用镜像 (index, url)，按优先级排序
+    pub fn available(&self) -> Vec<(usize, String)> {
+        self.mirrors
+            .lock()
+            .iter()
+            .enumerate()
+            .filter(|(_, mirror)| !mirror.disabled)
+            .map(|(index, mirror)| (index, mirror.url.clone()))
+            .collect()
+    }
+
+    /// 优先级最高的可用镜像
+    pub fn primary_url(&self) -> Option<String> {
+        self.available().into_iter().next().map(|(_, url)| url)
+    }
+
+    /// 为下一个分片选择镜像：已分配连接数 / 实测速度 最小者优先，
+    /// 尚未测速的镜像按当前最快速度估计，保证每个镜像都能被测到。
+    pub fn acquire(&self) -> Option<MirrorLease> {
+        let mut mirrors = self.mirrors.lock();
+        let fastest = mirrors
+            .iter()
+            .filter(|mirror| !mirror.disabled)
+            .filter_map(MirrorState::speed)
+            .fold(1.0_f64, f64::max);
+
+        let (index, _) = mirrors
+            .iter()
+            .enumerate()
+            .filter(|(_, mirror)| !mirror.disabled)
+            .map(|(index, mirror)| {
+                let weight = mirror.speed().unwrap_or(fastest);
+                (index, (mirror.active + 1) as f64 / weight)
+            })
+            .fold(None, |best: Option<(usize, f64)>, candidate| match best {
+                Some(best) if best.1 <= candidate.1 => Some(best),
+                _ => Some(candidate),
+            })?;
+
+        let mirror = &mut mirrors[index];
+        mirror.active += 1;
+        Some(MirrorLease {
+            index,
+            url: mirror.url.clone(),
+        })
+    }
+
+    /// 记录已写入的字节（用于实时归属统计）
+    pub fn record_bytes(&self, index: usize, bytes: u64) {
+        if let Some(mirror) = self.mirrors.lock().get_mut(index) {
+            mirror.downloaded_bytes = mirror.downloaded_bytes.saturating_add(bytes);
+        }
+    }
+
+    /// 请求结束（成功、暂停或取消），用本次传输更新测速
+    pub fn finish(&self, index: usize, transferred: u64, elapsed: Duration, chunk_completed: bool) {
+        if let Some(mirror) = self.mirrors.lock().get_mut(index) {
+            mirror.active = mirror.active.saturating_sub(1);
+            if transferred > 0 {
+                mirror.measured_bytes = mirror.measured_bytes.saturating_add(transferred);
+                mirror.measured_time += elapsed;
+                mirror.consecutive_failures = 0;
+            }
+            if chunk_completed {
+                mirror.chunks_completed += 1;
+            }
+        }
+    }
+
+    /// 请求失败；连续失败达到上限且仍有其他镜像时剔除该镜像
+    pub fn report_failure(&self, index: usize, error: &str) -> MirrorFailure {
+        let mut mirrors = self.mirrors.lock();
+        let others_available = mirrors
+            .iter()
+            .enumerate()
+            .any(|(other, mirror)| other != index && !mirror.disabled);
+        let Some(mirror) = mirrors.get_mut(index) else {
+            return MirrorFailure::Retry;
+        };
+        mirror.active = mirror.active.saturating_sub(1);
+        mirror.consecutive_failures += 1;
+        mirror.last_error = Some(error.to_string());
+
+        if !others_available {
+            return MirrorFailure::LastMirror;
+        }
+        if mirror.consecutive_failures >= self.max_consecutive_failures {
+            mirror.disabled = true;
+            tracing::warn!("剔除下载镜像 {}: {}", mirror.url, error);
+            return MirrorFailure::Dropped;
+        }
+        MirrorFailure::Retry
+    }
+
+    /// 立即剔除镜像（探测不一致或内容已变化）；最后一个可用镜像不会被剔除
+    pub fn drop_mirror(&self, index: usize, reason: &str) -> bool {
+        let mut mirrors = self.mirrors.lock();
+        let others_available = mirrors
+            .iter()
+            .enumerate()
+            .any(|(other, mirror)| other != index && !mirror.disabled);
+        let Some(mirror) = mirrors.get_mut(index) else {
+            return false;
+        };
+        mirror.last_error = Some(reason.to_string());
+        if !others_available || mirror.disabled {
+            return false;
+        }
+        mirror.disabled = true;
+        tracing::warn!("剔除下载镜像 {}: {}", mirror.url, reason);
+        true
+    }
+
+    /// 释放租约但不计入成功或失败（例如被剔除后换源）
+    pub fn release(&self, index: usize) {
+        if let Some(mirror) = self.mirrors.lock().get_mut(index) {
+            mirror.active = mirror.active.saturating_sub(1);
+        }
+    }
+
+    pub fn stats(&self) -> Vec<MirrorStats> {
+        self.mirrors
+            .lock()
+            .iter()
+            .map(|mirror| MirrorStats {
+                url: mirror.url.clone(),
+                downloaded_bytes: mirror.downloaded_bytes,
+                speed_bps: mirror
+                    .speed()
+                    .map(|speed| speed.round() as u64)
+                    .unwrap_or(0),
+                chunks_completed: mirror.chunks_completed,
+                active_chunks: mirror.active,
+                disabled: mirror.disabled,
+                last_error: mirror.last_error.clone(),
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::expect_used)]
+mod tests {
+    use super::*;
+
+    fn pool(urls: &[&str]) -> MirrorPool {
+        MirrorPool::new(urls.iter().map(|url| url.to_string()))
+    }
+
+    #[test]
+    fn spreads_chunks_by_measured_speed() {
+        let pool = pool(&[
+            "https://a.cdn/v.mp4",
+            "https://b.cdn/v.mp4",
+            "https://a.cdn/v.mp4",
+        ]);
+        assert_eq!(pool.len(), 2);
+
+        // 未测速时轮流分配
+        let first = pool.acquire().unwrap();
+        let second = pool.acquire().unwrap();
+        assert_eq!((first.index, second.index), (0, 1));
+
+        // a 的速度是 b 的三倍
+        pool.finish(0, 3_000_000, Duration::from_secs(1), true);
+        pool.finish(1, 1_000_000, Duration::from_secs(1), true);
+
+        let assigned: Vec<usize> = (0..4).map(|_| pool.acquire().unwrap().index).collect();
+        assert_eq!(assigned.iter().filter(|index| **index == 0).count(), 3);
+        assert_eq!(assigned.iter().filter(|index| **index == 1).count(), 1);
+
+        let stats = pool.stats();
+        assert_eq!(stats[0].speed_bps, 3_000_000);
+        assert_eq!(stats[0].active_chunks, 3);
+    }
+
+    #[test]
+    fn drops_failing_mirror_but_keeps_the_last_one() {
+        let pool = pool(&["https://a.cdn/v.mp4", "https://b.cdn/v.mp4"]);
+
+        let lease = pool.acquire().unwrap();
+        assert_eq!(
+            pool.report_failure(lease.index, "timeout"),
+            MirrorFailure::Retry
+        );
+        let lease = pool.acquire().unwrap();
+        assert_eq!(
+            pool.report_failure(lease.index, "timeout"),
+            MirrorFailure::Dropped
+        );
+        assert_eq!(pool.acquire().unwrap().index, 1);
+
+        assert_eq!(pool.primary_url().as_deref(), Some("https://b.cdn/v.mp4"));
+        assert!(!pool.drop_mirror(1, "last one"));
+        assert_eq!(pool.report_failure(1, "503"), MirrorFailure::LastMirror);
+        assert_eq!(pool.report_failure(1, "503"), MirrorFailure::LastMirror);
+        assert_eq!(pool.available().len(), 1);
+        assert!(pool.stats()[0].disabled);
+        assert_eq!(pool.stats()[0].last_error.as_deref(), Some("timeout"));
+    }
+}
@@ -4,23 +4,39 @@
 //! for the video downloader application.
 
 pub mod app_bootstrap;
+pub mod audio_extract;
+pub mod bandwidth;
+pub mod bandwidth_schedule;
 pub mod config;
+pub mod cookie_jar;
+pub mod credentials;
+pub mod dash_downloader;
 pub mod download_provider;
 pub mod downloader;
 pub mod error_handling;
 mod external_tool_compat;
 pub mod external_tools;
 pub mod file_parser;
+pub mod hls_mux;
+pub mod hls_variant;
+pub mod hls_writer;
+pub mod http_client;
 pub mod integrity_checker;
 pub mod m3u8_downloader;
 pub mod manager;
+pub mod mirror_pool;
 pub mod models;
 
 pub mod part_file;
+pub mod platform_registry;
 pub mod progress_tracker;
+pub mod proxy;
 pub mod queue_scheduler;
+pub mod remote_filename;
+pub mod request_headers;
 pub mod resume_downloader;
 pub mod runtime;
+pub mod segment_manifest;
 pub mod youtube_downloader;
 pub mod ytdlp_downloader;
 #[cfg(test)]
@@ -5,6 +5,10 @@ use serde::{Deserialize, Deserializer, Serialize};
 use std::collections::HashMap;
 use std::path::Path;
 
+use crate::core::hls_variant::{HlsVariant, RenditionPreferences, VariantPolicy};
+use crate::core::mirror_pool::MirrorStats;
+use crate::core::youtube_downloader::YoutubeDownloadFormat;
+
 /// Task status enumeration
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -32,6 +36,8 @@ pub enum DownloaderType {
 
     M3u8,
 
+    Dash,
+
     YtDlp,
 }
 
@@ -44,6 +50,7 @@ impl<'de> Deserialize<'de> for DownloaderType {
         match value.as_str() {
             "Http" | "http" => Ok(Self::Http),
             "M3u8" | "m3u8" => Ok(Self::M3u8),
+            "Dash" | "dash" => Ok(Self::Dash),
             "YtDlp" | "ytdlp" | "Youtube" | "youtube" => Ok(Self::YtDlp),
             other => Err(serde::de::Error::custom(format!(
                 "unknown downloader type: {}",
@@ -53,14 +60,59 @@ impl<'de> Deserialize<'de> for DownloaderType {
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
-#[serde(rename_all = "lowercase")]
+/// Source platform, serialized as the platform id ("youtube", "bilibili", ...)
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum SourcePlatform {
     Youtube,
     Tiktok,
     Instagram,
     Facebook,
     Generic,
+    /// Platform defined in `AppConfig::platforms`
+    Other(String),
+}
+
+impl SourcePlatform {
+    pub fn from_id(id: &str) -> Self {
+        match id {
+            "youtube" => Self::Youtube,
+            "tiktok" => Self::Tiktok,
+            "instagram" => Self::Instagram,
+            "facebook" => Self::Facebook,
+            "" | "generic" => Self::Generic,
+            other => Self::Other(other.to_string()),
+        }
+    }
+
+    pub fn id(&self) -> &str {
+        match self {
+            Self::Youtube => "youtube",
+            Self::Tiktok => "tiktok",
+            Self::Instagram => "instagram",
+            Self::Facebook => "facebook",
+            Self::Generic => "generic",
+            Self::Other(id) => id,
+        }
+    }
+}
+
+impl Serialize for SourcePlatform {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.serialize_str(self.id())
+    }
+}
+
+impl<'de> Deserialize<'de> for SourcePlatform {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let value = String::deserialize(deserializer)?;
+        Ok(Self::from_id(&value.to_lowercase()))
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -75,6 +127,156 @@ pub struct ExternalVideoInfo {
     pub format_note: Option<String>,
     #[serde(default)]
     pub requires_auth: bool,
+    /// Playlist/channel this video was expanded from
+    #[serde(default)]
+    pub playlist_id: Option<String>,
+    #[serde(default)]
+    pub playlist_title: Option<String>,
+    /// 1-based position within the playlist
+    #[serde(default)]
+    pub playlist_index: Option<u32>,
+}
+
+/// Format yt-dlp actually picked for a download
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct SelectedFormat {
+    /// e.g. "137+140" when video and audio are merged
+    pub format_id: String,
+    pub format_note: Option<String>,
+}
+
+/// Kind of file yt-dlp writes next to the video
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SidecarKind {
+    Subtitle,
+    Thumbnail,
+    Description,
+    InfoJson,
+}
+
+/// Subtitle, thumbnail, description or metadata file saved next to the video
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct SidecarFile {
+    pub kind: SidecarKind,
+    pub path: String,
+    /// Subtitle language as named by yt-dlp (e.g. "en", "zh-Hans")
+    #[serde(default)]
+    pub language: Option<String>,
+}
+
+/// Which sidecar files yt-dlp writes next to the video
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct YtDlpSidecarOptions {
+    #[serde(default)]
+    pub subtitles: bool,
+    /// Subtitle languages (empty = yt-dlp default)
+    #[serde(default)]
+    pub subtitle_languages: Vec<String>,
+    /// Fall back to automatic captions when no uploaded subtitles exist
+    #[serde(default)]
+    pub auto_subtitles: bool,
+    /// Convert subtitles to "srt", "vtt", "ass" or "lrc" (None = as served)
+    #[serde(default)]
+    pub subtitle_format: Option<String>,
+    /// Thumbnail converted to jpg
+    #[serde(default)]
+    pub thumbnail: bool,
+    #[serde(default)]
+    pub description: bool,
+    #[serde(default)]
+    pub info_json: bool,
+}
+
+impl YtDlpSidecarOptions {
+    pub fn any(&self) -> bool {
+        self.subtitles || self.thumbnail || self.description || self.info_json
+    }
+}
+
+/// Target format of audio-only downloads
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AudioFormat {
+    #[default]
+    Mp3,
+    M4a,
+    Opus,
+    Flac,
+    Wav,
+}
+
+impl AudioFormat {
+    /// File extension of the extracted audio
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Mp3 => "mp3",
+            Self::M4a => "m4a",
+            Self::Opus => "opus",
+            Self::Flac => "flac",
+            Self::Wav => "wav",
+        }
+    }
+
+    /// Lossless formats ignore the bitrate
+    pub fn is_lossless(self) -> bool {
+        matches!(self, Self::Flac | Self::Wav)
+    }
+}
+
+impl std::str::FromStr for AudioFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "mp3" => Ok(Self::Mp3),
+            "m4a" | "aac" => Ok(Self::M4a),
+            "opus" => Ok(Self::Opus),
+            "flac" => Ok(Self::Flac),
+            "wav" => Ok(Self::Wav),
+            other => anyhow::bail!("Invalid audio format: {}", other),
+        }
+    }
+}
+
+/// Audio-only mode: keep just the audio track, converted to `format`
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AudioExtraction {
+    #[serde(default)]
+    pub format: AudioFormat,
+    /// Target bitrate in kbps (None = encoder default; ignored for lossless formats)
+    #[serde(default)]
+    pub bitrate_kbps: Option<u32>,
+}
+
+/// One video listed by `yt-dlp --flat-playlist`
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PlaylistEntry {
+    /// 1-based position within the playlist
+    pub index: u32,
+    pub url: String,
+    #[serde(default)]
+    pub id: Option<String>,
+    #[serde(default)]
+    pub title: Option<String>,
+    #[serde(default)]
+    pub duration_seconds: Option<f64>,
+    #[serde(default)]
+    pub thumbnail: Option<String>,
+}
+
+/// A playlist, channel or course expanded into its entries (in download order)
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PlaylistExpansion {
+    pub source_platform: SourcePlatform,
+    pub webpage_url: String,
+    #[serde(default)]
+    pub playlist_id: Option<String>,
+    #[serde(default)]
+    pub title: Option<String>,
+    #[serde(default)]
+    pub extractor: Option<String>,
+    pub entries: Vec<PlaylistEntry>,
 }
 
 /// Main video download task structure
@@ -86,6 +288,42 @@ pub struct VideoTask {
 
     pub url: String,
 
+    /// Additional mirror URLs for the same file, in priority order (excluding `url`)
+    #[serde(default)]
+    pub mirrors: Vec<String>,
+
+    /// Per-task cap in bytes/sec (None = `DownloadConfig::per_task_rate_limit`)
+    #[serde(default)]
+    pub rate_limit: Option<u64>,
+
+    /// HLS variant policy for master playlists (None = `DownloadConfig::hls_variant_policy`)
+    #[serde(default)]
+    pub variant_policy: Option<VariantPolicy>,
+
+    /// yt-dlp format choice (None = `DownloadConfig::ytdlp_format`)
+    #[serde(default)]
+    pub ytdlp_format: Option<YoutubeDownloadFormat>,
+
+    /// Audio-only mode (None = keep the video; yt-dlp tasks fall back to `DownloadConfig::ytdlp_audio_extraction`)
+    #[serde(default)]
+    pub audio_extraction: Option<AudioExtraction>,
+
+    /// Variant the last HLS download picked from the master playlist
+    #[serde(default)]
+    pub hls_variant: Option<HlsVariant>,
+
+    /// Live HLS recording progress (None for regular downloads)
+    #[serde(default)]
+    pub recording: Option<LiveRecordingProgress>,
+
+    /// HLS segments skipped by the gap tolerance policy in the last download
+    #[serde(default)]
+    pub segment_gaps: Vec<SegmentGap>,
+
+    /// Subtitles, thumbnail, description and metadata saved next to the video
+    #[serde(default)]
+    pub sidecar_files: Vec<SidecarFile>,
+
     pub title: String,
 
     pub output_path: String,
@@ -154,6 +392,40 @@ pub struct ProgressUpdate {
     pub eta: Option<u64>,
 
     pub progress: f64,
+
+    /// Per-mirror attribution for multi-mirror downloads
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub sources: Vec<MirrorStats>,
+
+    /// Live recordings report elapsed time instead of a percentage
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub recording: Option<LiveRecordingProgress>,
+}
+
+/// Progress of a live HLS recording (no total size, so no percentage)
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct LiveRecordingProgress {
+    /// Wall-clock seconds since recording started
+    pub elapsed_secs: u64,
+
+    /// Media seconds recorded so far (sum of EXTINF)
+    pub recorded_secs: f64,
+
+    pub segments: usize,
+}
+
+/// An HLS segment that still failed after retries and was left out of the file
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SegmentGap {
+    /// Segment index within the media playlist
+    pub index: usize,
+
+    /// Missing media time range (seconds from the start of the stream)
+    pub start_secs: f64,
+    pub end_secs: f64,
+
+    /// Last error seen for this segment
+    pub error: String,
 }
 
 /// Video information structure matching Go version
@@ -225,8 +497,64 @@ pub struct DownloadConfig {
 
     pub proxy: Option<String>,
 
+    /// Hosts that bypass the proxy (domain suffixes, IPs, CIDR ranges or `*`)
+    #[serde(default)]
+    pub no_proxy: Vec<String>,
+
     pub headers: HashMap<String, String>,
 
+    /// Per-host header overrides applied on top of `headers`
+    #[serde(default)]
+    pub host_headers: Vec<HostHeaderRule>,
+
+    /// Time-of-day global rate limits; first matching rule wins, no match = unlimited
+    #[serde(default)]
+    pub bandwidth_schedule: Vec<BandwidthRule>,
+
+    /// Default cap for each task, applied under the global limit
+    #[serde(default)]
+    pub per_task_rate_limit: Option<u64>,
+
+    /// Caps shared by all downloads from a host (and its subdomains)
+    #[serde(default)]
+    pub host_rate_limits: Vec<HostRateLimit>,
+
+    /// How to pick a variant when an HLS URL is a master playlist
+    #[serde(default)]
+    pub hls_variant_policy: VariantPolicy,
+
+    /// Stop live HLS recordings after this many recorded media seconds
+    #[serde(default)]
+    pub live_max_duration_secs: Option<u64>,
+
+    /// Stop live HLS recordings once this many bytes are recorded
+    #[serde(default)]
+    pub live_max_bytes: Option<u64>,
+
+    /// Which EXT-X-MEDIA audio/subtitle renditions to download alongside the video
+    #[serde(default)]
+    pub hls_renditions: RenditionPreferences,
+
+    /// Finish HLS downloads with up to this many segments missing after retries (0 = fail)
+    #[serde(default)]
+    pub hls_max_missing_segments: u32,
+
+    /// yt-dlp format choice, derived from `YoutubeConfig` by `effective_download_config`
+    #[serde(skip)]
+    pub ytdlp_format: YoutubeDownloadFormat,
+
+    /// Container yt-dlp merges separate video/audio streams into (None = mp4)
+    #[serde(skip)]
+    pub ytdlp_merge_format: Option<String>,
+
+    /// yt-dlp sidecar files, derived from `YoutubeConfig` by `effective_download_config`
+    #[serde(skip)]
+    pub ytdlp_sidecars: YtDlpSidecarOptions,
+
+    /// Audio-only default for yt-dlp tasks, derived from `YoutubeConfig` by `effective_download_config`
+    #[serde(skip)]
+    pub ytdlp_audio_extraction: Option<AudioExtraction>,
+
     pub output_directory: String,
 
     /// Whether to automatically verify file integrity after download
@@ -252,8 +580,36 @@ impl Default for DownloadConfig {
 
             proxy: None,
 
+            no_proxy: Vec::new(),
+
             headers: HashMap::new(),
 
+            host_headers: Vec::new(),
+
+            bandwidth_schedule: Vec::new(),
+
+            per_task_rate_limit: None,
+
+            host_rate_limits: Vec::new(),
+
+            hls_variant_policy: VariantPolicy::default(),
+
+            live_max_duration_secs: None,
+
+            live_max_bytes: None,
+
+            hls_renditions: RenditionPreferences::default(),
+
+            hls_max_missing_segments: 0,
+
+            ytdlp_format: YoutubeDownloadFormat::default(),
+
+            ytdlp_merge_format: None,
+
+            ytdlp_sidecars: YtDlpSidecarOptions::default(),
+
+            ytdlp_audio_extraction: None,
+
             output_directory: default_download_directory(),
 
             auto_verify_integrity: false, // Disabled by default for performance
@@ -265,6 +621,48 @@ impl Default for DownloadConfig {
     }
 }
 
+/// Header overrides for requests whose host matches `host` (the host itself or any subdomain)
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct HostHeaderRule {
+    pub host: String,
+
+    #[serde(default)]
+    pub user_agent: Option<String>,
+
+    #[serde(default)]
+    pub referer: Option<String>,
+
+    #[serde(default)]
+    pub origin: Option<String>,
+
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+}
+
+/// Rate limit shared by every download whose host matches `host` (the host itself or any subdomain)
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct HostRateLimit {
+    pub host: String,
+
+    pub bytes_per_second: u64,
+}
+
+/// Global rate limit applied between `start` and `end` (local time, "HH:MM").
+/// `end` earlier than `start` wraps past midnight; equal times cover the whole day.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct BandwidthRule {
+    pub start: String,
+
+    pub end: String,
+
+    /// None = unlimited during this window
+    #[serde(default)]
+    pub bytes_per_second: Option<u64>,
+
+    #[serde(default)]
+    pub label: Option<String>,
+}
+
 fn default_download_directory() -> String {
     if cfg!(target_os = "windows") {
         std::env::var("USERPROFILE")
@@ -0,0 +1,481 @@
+//! 平台规则表
+//!
+//! 内置的 YouTube/TikTok/Instagram/Facebook 规则（`ytdlp_support::platform_host_rules`）
+//! 加上配置里的 `AppConfig::platforms`。`detect_platform`、下载路由、yt-dlp 参数、
+//! 默认画质和按平台的并发上限都从这里取；配置更新时整表替换。
+
+#![deny(clippy::unwrap_used, clippy::expect_used)]
+
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, OnceLock, PoisonError, RwLock};
+
+use crate::core::youtube_downloader::{
+    AudioCodecPreference, AudioQuality, VideoCodecPreference, VideoQuality, YoutubeDownloadFormat,
+};
+use crate::core::ytdlp_support::{host_matches, platform_host_rules};
+
+/// 平台强制使用的下载路径
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PlatformRoute {
+    #[default]
+    Ytdlp,
+    Http,
+    M3u8,
+}
+
+/// 一个平台：按域名识别，决定下载路径和 yt-dlp 参数
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PlatformRule {
+    /// 写进 `ExternalVideoInfo::source_platform` 的稳定 ID，如 "bilibili"
+    pub id: String,
+    pub display_name: String,
+    /// 域名后缀，"bilibili.com" 同时匹配 "www.bilibili.com"
+    pub hosts: Vec<String>,
+    #[serde(default)]
+    pub route: PlatformRoute,
+    /// 追加在 yt-dlp 探测、展开和下载命令末尾的参数
+    #[serde(default)]
+    pub ytdlp_args: Vec<String>,
+    /// 任务没有自己的格式选择时使用的画质（"720p"、"best" 等）
+    #[serde(default)]
+    pub default_quality: Option<String>,
+    /// 同一平台同时下载的任务数上限（None 不限）
+    #[serde(default)]
+    pub max_concurrent: Option<usize>,
+}
+
+impl PlatformRule {
+    fn matches_host(&self, host: &str) -> bool {
+        self.hosts
+            .iter()
+            .any(|candidate| host_matches(host, candidate))
+    }
+}
+
+/// 不允许平台参数使用的 yt-dlp 选项：输出和进度由应用管理；能执行外部命令或加载
+/// 额外配置的选项一律禁止；代理、Cookie 和限速走应用自己的设置
+const RESERVED_YTDLP_ARGS: &[&str] = &[
+    // 输出与进度
+    "-o",
+    "--output",
+    "-P",
+    "--paths",
+    "-O",
+    "--print",
+    "--print-to-file",
+    "--progress-template",
+    "--newline",
+    "--no-progress",
+    "-q",
+    "--quiet",
+    // 执行外部命令 / 加载额外配置
+    "--exec",
+    "--exec-before-download",
+    "--netrc-cmd",
+    "--downloader",
+    "--external-downloader",
+    "--downloader-args",
+    "--external-downloader-args",
+    "--postprocessor-args",
+    "--ppa",
+    "--use-postprocessor",
+    "--ffmpeg-location",
+    "--config-location",
+    "--config-locations",
+    "--plugin-dirs",
+    "-a",
+    "--batch-file",
+    // 网络、Cookie 与限速
+    "--proxy",
+    "--geo-verification-proxy",
+    "--cookies",
+    "--cookies-from-browser",
+    "-r",
+    "--limit-rate",
+    "--throttled-rate",
+];
+
+/// 参数是否命中保留选项；兼容 `--opt=value` 和短选项紧跟取值（`-oname`）的写法
+fn is_reserved_ytdlp_arg(arg: &str) -> bool {
+    let arg = arg.trim();
+    let name = arg.split('=').next().unwrap_or_default();
+    RESERVED_YTDLP_ARGS.iter().any(|reserved| {
+        name == *reserved
+            || (reserved.len() == 2 && !arg.starts_with("--") && arg.starts_with(reserved))
+    })
+}
+
+/// 校验配置里的自定义平台规则
+pub fn validate_rules(rules: &[PlatformRule]) -> Result<()> {
+    for (index, rule) in rules.iter().enumerate() {
+        let id = rule.id.as_str();
+        if id.is_empty()
+            || !id
+                .chars()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_')
+        {
+            bail!(
+                "Platform id must be lowercase letters, digits, '-' or '_': {:?}",
+                rule.id
+            );
+        }
+        if id == "generic" {
+            bail!("Platform id 'generic' is reserved");
+        }
+        if rules[..index].iter().any(|other| other.id == rule.id) {
+            bail!("Duplicate platform id: {}", rule.id);
+        }
+        if rule.display_name.trim().is_empty() {
+            bail!("Platform {} needs a display name", rule.id);
+        }
+        if rule.hosts.is_empty() {
+            bail!("Platform {} needs at least one host", rule.id);
+        }
+        for host in &rule.hosts {
+            let host = normalize_host(host);
+            if host.is_empty()
+                || host
+                    .chars()
+                    .any(|c| c.is_whitespace() || matches!(c, '/' | ':' | '*' | '?'))
+            {
+                bail!("Invalid host for platform {}: {:?}", rule.id, host);
+            }
+        }
+        for arg in &rule.ytdlp_args {
+            if arg.trim().is_empty() {
+                bail!("Platform {} has an empty yt-dlp argument", rule.id);
+            }
+            if is_reserved_ytdlp_arg(arg) {
+                bail!(
+                    "Platform {} cannot override yt-dlp option {}",
+                    rule.id,
+                    arg.trim()
+                );
+            }
+        }
+        if let Some(quality) = &rule.default_quality {
+            quality.parse::<VideoQuality>()?;
+        }
+        if rule.max_concurrent == Some(0) {
+            bail!("Platform {} max_concurrent must be positive", rule.id);
+        }
+    }
+    Ok(())
+}
+
+fn normalize_host(host: &str) -> String {
+    host.trim()
+        .trim_start_matches("*.")
+        .trim_start_matches('.')
+        .to_ascii_lowercase()
+}
+
+/// 内置平台 + 自定义平台；自定义规则先匹配，同 ID 的内置规则被替换
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlatformRegistry {
+    rules: Vec<PlatformRule>,
+}
+
+impl PlatformRegistry {
+    pub fn new(custom: &[PlatformRule]) -> Self {
+        let mut rules: Vec<PlatformRule> = custom
+            .iter()
+            .cloned()
+            .map(|mut rule| {
+                rule.hosts = rule.hosts.iter().map(|host| normalize_host(host)).collect();
+                rule
+            })
+            .collect();
+        for builtin in builtin_rules() {
+            if !rules.iter().any(|rule| rule.id == builtin.id) {
+                rules.push(builtin);
+            }
+        }
+        Self { rules }
+    }
+
+    pub fn rules(&self) -> &[PlatformRule] {
+        &self.rules
+    }
+
+    pub fn match_url(&self, url: &str) -> Option<&PlatformRule> {
+        let host = url::Url::parse(url)
+            .ok()
+            .and_then(|parsed| parsed.host_str().map(|host| host.to_lowercase()))?;
+        self.rules.iter().find(|rule| rule.matches_host(&host))
+    }
+
+    pub fn route_for(&self, url: &str) -> Option<PlatformRoute> {
+        self.match_url(url).map(|rule| rule.route)
+    }
+
+    pub fn ytdlp_args_for(&self, url: &str) -> Vec<String> {
+        self.match_url(url)
+            .map(|rule| rule.ytdlp_args.clone())
+            .unwrap_or_default()
+    }
+
+    /// 平台配置了默认画质时套用到全局格式上；纯音频和指定格式 ID 保持不变
+    pub fn ytdlp_format_for(
+        &self,
+        url: &str,
+        fallback: &YoutubeDownloadFormat,
+    ) -> YoutubeDownloadFormat {
+        let Some(quality) = self
+            .match_url(url)
+            .and_then(|rule| rule.default_quality.as_deref())
+            .and_then(|quality| quality.parse::<VideoQuality>().ok())
+        else {
+            return fallback.clone();
+        };
+        match fallback.clone() {
+            YoutubeDownloadFormat::BestAvailable => YoutubeDownloadFormat::CompleteVideo {
+                video_quality: quality,
+                video_codec: VideoCodecPreference::Any,
+                audio_quality: AudioQuality::Best,
+                audio_codec: AudioCodecPreference::Any,
+            },
+            YoutubeDownloadFormat::CompleteVideo {
+                video_codec,
+                audio_quality,
+                audio_codec,
+                ..
+            } => YoutubeDownloadFormat::CompleteVideo {
+                video_quality: quality,
+                video_codec,
+                audio_quality,
+                audio_codec,
+            },
+            YoutubeDownloadFormat::VideoOnly { codec, .. } => {
+                YoutubeDownloadFormat::VideoOnly { quality, codec }
+            }
+            other => other,
+        }
+    }
+}
+
+impl Default for PlatformRegistry {
+    fn default() -> Self {
+        Self::new(&[])
+    }
+}
+
+fn builtin_rules() -> impl Iterator<Item = PlatformRule> {
+    platform_host_rules().iter().map(|rule| PlatformRule {
+        id: rule.platform.id().to_string(),
+        display_name: rule.display_name.to_string(),
+        hosts: rule.hosts.iter().map(|host| host.to_string()).collect(),
+        route: PlatformRoute::Ytdlp,
+        ytdlp_args: Vec::new(),
+        default_quality: None,
+        max_concurrent: None,
+    })
+}
+
+static INSTALLED: RwLock<Option<Arc<PlatformRegistry>>> = RwLock::new(None);
+
+/// 当前生效的规则表（未安装配置时只有内置平台）
+pub fn current() -> Arc<PlatformRegistry> {
+    static BUILTIN: OnceLock<Arc<PlatformRegistry>> = OnceLock::new();
+    INSTALLED
+        .read()
+        .unwrap_or_else(PoisonError::into_inner)
+        .clone()
+        .unwrap_or_else(|| BUILTIN.get_or_init(Default::default).clone())
+}
+
+/// 按配置重建规则表；启动和每次配置更新时调用
+pub fn install(custom: &[PlatformRule]) {
+    let registry = Arc::new(PlatformRegistry::new(custom));
+    *INSTALLED.write().unwrap_or_else(PoisonError::into_inner) = Some(registry);
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::expect_used)]
+mod tests {
+    use super::*;
+
+    fn bilibili() -> PlatformRule {
+        PlatformRule {
+            id: "bilibili".to_string(),
+            display_name: "哔哩哔哩".to_string(),
+            hosts: vec!["*.Bilibili.com".to_string(), "b23.tv".to_string()],
+            route: PlatformRoute::Ytdlp,
+            ytdlp_args: vec![
+                "--referer".to_string(),
+                "https://www.bilibili.com".to_string(),
+            ],
+            default_quality: Some("1080p".to_string()),
+            max_concurrent: Some(2),
+        }
+    }
+
+    #[test]
+    fn custom_rules_match_before_builtins_and_replace_same_id() {
+        let courses = PlatformRule {
+            id: "courses".to_string(),
+            display_name: "内部课程".to_string(),
+            hosts: vec!["learn.corp.example".to_string()],
+            route: PlatformRoute::M3u8,
+            ytdlp_args: Vec::new(),
+            default_quality: None,
+            max_concurrent: None,
+        };
+        let youtube = PlatformRule {
+            id: "youtube".to_string(),
+            display_name: "YouTube".to_string(),
+            hosts: vec!["youtube.com".to_string()],
+            route: PlatformRoute::Ytdlp,
+            ytdlp_args: vec![
+                "--extractor-args".to_string(),
+                "youtube:lang=en".to_string(),
+            ],
+            default_quality: None,
+            max_concurrent: Some(1),
+        };
+        let registry = PlatformRegistry::new(&[bilibili(), courses, youtube]);
+
+        let rule = registry
+            .match_url("https://www.bilibili.com/video/BV1xx")
+            .unwrap();
+        assert_eq!(rule.id, "bilibili");
+        assert_eq!(rule.hosts, ["bilibili.com", "b23.tv"]);
+        assert_eq!(
+            registry.route_for("https://learn.corp.example/hls/index"),
+            Some(PlatformRoute::M3u8)
+        );
+        assert_eq!(
+            registry.ytdlp_args_for("https://youtu.be/abc"),
+            Vec::<String>::new()
+        );
+        assert_eq!(
+            registry.ytdlp_args_for("https://www.youtube.com/watch?v=abc"),
+            ["--extractor-args", "youtube:lang=en"]
+        );
+        assert_eq!(
+            registry
+                .rules()
+                .iter()
+                .filter(|rule| rule.id == "youtube")
+                .count(),
+            1
+        );
+        assert_eq!(
+            registry
+                .match_url("https://www.tiktok.com/@user/video/1")
+                .map(|rule| rule.display_name.as_str()),
+            Some("TikTok")
+        );
+        assert!(registry
+            .match_url("https://bilibili.com.evil.example/")
+            .is_none());
+    }
+
+    #[test]
+    fn applies_platform_default_quality_to_fallback_format() {
+        let registry = PlatformRegistry::new(&[bilibili()]);
+        assert_eq!(
+            registry.ytdlp_format_for(
+                "https://www.bilibili.com/video/BV1xx",
+                &YoutubeDownloadFormat::BestAvailable
+            ),
+            YoutubeDownloadFormat::CompleteVideo {
+                video_quality: VideoQuality::Height(1080),
+                video_codec: VideoCodecPreference::Any,
+                audio_quality: AudioQuality::Best,
+                audio_codec: AudioCodecPreference::Any,
+            }
+        );
+        let audio = YoutubeDownloadFormat::AudioOnly {
+            quality: AudioQuality::High,
+            codec: AudioCodecPreference::Opus,
+        };
+        assert_eq!(
+            registry.ytdlp_format_for("https://b23.tv/abc", &audio),
+            audio
+        );
+        assert_eq!(
+            registry.ytdlp_format_for(
+                "https://example.com/watch",
+                &YoutubeDownloadFormat::BestAvailable
+            ),
+            YoutubeDownloadFormat::BestAvailable
+        );
+    }
+
+    #[test]
+    fn validates_custom_rules() {
+        assert!(validate_rules(&[bilibili()]).is_ok());
+        for broken in [
+            PlatformRule {
+                id: "Bili Bili".to_string(),
+                ..bilibili()
+            },
+            PlatformRule {
+                id: "generic".to_string(),
+                ..bilibili()
+            },
+            PlatformRule {
+                hosts: vec!["https://bilibili.com/".to_string()],
+                ..bilibili()
+            },
+            PlatformRule {
+                ytdlp_args: vec!["--output".to_string(), "x.mp4".to_string()],
+                ..bilibili()
+            },
+            PlatformRule {
+                ytdlp_args: vec!["--exec=rm -rf ~".to_string()],
+                ..bilibili()
+            },
+            PlatformRule {
+                ytdlp_args: vec!["--netrc-cmd".to_string(), "cat secrets".to_string()],
+                ..bilibili()
+            },
+            PlatformRule {
+                ytdlp_args: vec!["--proxy".to_string(), "socks5://evil:1080".to_string()],
+                ..bilibili()
+            },
+            PlatformRule {
+                ytdlp_args: vec!["--cookies=/tmp/cookies.txt".to_string()],
+                ..bilibili()
+            },
+            PlatformRule {
+                ytdlp_args: vec!["-r50K".to_string()],
+                ..bilibili()
+            },
+            PlatformRule {
+                ytdlp_args: vec!["-O".to_string(), "%(title)s".to_string()],
+                ..bilibili()
+            },
+            PlatformRule {
+                ytdlp_args: vec!["-O%(id)s".to_string()],
+                ..bilibili()
+            },
+            PlatformRule {
+                ytdlp_args: vec![
+                    "--print-to-file".to_string(),
+                    "%(title)s".to_string(),
+                    "titles.txt".to_string(),
+                ],
+                ..bilibili()
+            },
+            PlatformRule {
+                default_quality: Some("hd".to_string()),
+                ..bilibili()
+            },
+            PlatformRule {
+                max_concurrent: Some(0),
+                ..bilibili()
+            },
+        ] {
+            assert!(
+                validate_rules(std::slice::from_ref(&broken)).is_err(),
+                "{:?}",
+                broken
+            );
+        }
+        assert!(validate_rules(&[bilibili(), bilibili()]).is_err());
+    }
+}
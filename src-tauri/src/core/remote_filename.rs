@@ -0,0 +1,389 @@
+//! 根据响应头确定本地文件名
+//!
+//! `/download?id=123` 这类直链从 URL 推不出有意义的文件名。HEAD/GET 探测拿到响应后，
+//! 优先使用 `Content-Disposition`（RFC 6266 `filename*`，UTF-8 / GBK 兜底），
+//! 否则按 `Content-Type` 补上扩展名。只有自动生成的文件名才会被改写。
+
+#![deny(clippy::unwrap_used, clippy::expect_used)]
+
+use percent_encoding::percent_decode;
+use reqwest::header::{HeaderMap, CONTENT_DISPOSITION, CONTENT_TYPE};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+use crate::utils::file_utils::sanitize_filename;
+
+/// 目标文件名的来源，决定响应头能否改写它
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FilenameOrigin {
+    /// 用户指定或已经按响应头确定过，保持不变（续传时文件名必须稳定）
+    #[default]
+    Fixed,
+    /// 由任务标题生成：保留标题，只补扩展名
+    Title,
+    /// 由 URL 路径生成：可整体替换为 Content-Disposition 中的文件名
+    Url,
+}
+
+const MIME_EXTENSIONS: &[(&str, &str)] = &[
+    ("video/mp4", "mp4"),
+    ("video/x-m4v", "m4v"),
+    ("video/webm", "webm"),
+    ("video/x-matroska", "mkv"),
+    ("video/quicktime", "mov"),
+    ("video/x-msvideo", "avi"),
+    ("video/x-flv", "flv"),
+    ("video/x-ms-wmv", "wmv"),
+    ("video/mp2t", "ts"),
+    ("video/mpeg", "mpg"),
+    ("video/3gpp", "3gp"),
+    ("audio/mpeg", "mp3"),
+    ("audio/mp4", "m4a"),
+    ("audio/x-m4a", "m4a"),
+    ("audio/aac", "aac"),
+    ("audio/ogg", "ogg"),
+    ("audio/flac", "flac"),
+    ("audio/wav", "wav"),
+    ("audio/x-wav", "wav"),
+    ("application/vnd.apple.mpegurl", "m3u8"),
+    ("application/x-mpegurl", "m3u8"),
+    ("application/dash+xml", "mpd"),
+    ("application/pdf", "pdf"),
+    ("application/zip", "zip"),
+    ("application/x-7z-compressed", "7z"),
+    ("application/x-rar-compressed", "rar"),
+    ("application/vnd.rar", "rar"),
+    ("image/jpeg", "jpg"),
+    ("image/png", "png"),
+    ("image/gif", "gif"),
+    ("image/webp", "webp"),
+    ("text/plain", "txt"),
+    ("application/json", "json"),
+];
+
+/// 动态页面的"扩展名"不代表文件类型，可以被替换
+const SCRIPT_EXTENSIONS: &[&str] = &[
+    "php", "asp", "aspx", "jsp", "cgi", "do", "action", "html", "htm", "ashx",
+];
+
+/// 从 `Content-Type` 推断扩展名（不认识或 `application/octet-stream` 时返回 None）
+pub fn extension_for_content_type(content_type: &str) -> Option<&'static str> {
+    let mime = content_type
+        .split(';')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_ascii_lowercase();
+    MIME_EXTENSIONS
+        .iter()
+        .find(|(candidate, _)| *candidate == mime)
+        .map(|(_, extension)| *extension)
+}
+
+/// 解析 `Content-Disposition` 中的文件名：`filename*` 优先于 `filename`，
+/// 结果只保留最后一段路径并做文件名清理
+pub fn filename_from_content_disposition(value: &[u8]) -> Option<String> {
+    let params = split_params(value);
+    let extended = params
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case("filename*"))
+        .and_then(|(_, value)| decode_ext_value(value));
+    let plain = || {
+        params
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case("filename"))
+            .map(|(_, value)| decode_plain_value(value))
+    };
+    extended
+        .or_else(plain)
+        .and_then(|filename| clean_filename(&filename))
+}
+
+/// 按响应头重新确定文件名；返回 None 表示保持 `current` 不变
+pub fn resolve_response_filename(
+    current: &str,
+    origin: FilenameOrigin,
+    headers: &HeaderMap,
+) -> Option<String> {
+    if origin == FilenameOrigin::Fixed {
+        return None;
+    }
+    let disposition = headers
+        .get(CONTENT_DISPOSITION)
+        .and_then(|value| filename_from_content_disposition(value.as_bytes()));
+    let type_extension = headers
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(extension_for_content_type);
+
+    let resolved = match (origin, disposition) {
+        (FilenameOrigin::Url, Some(filename)) if meaningful_extension(&filename).is_some() => {
+            filename
+        }
+        (FilenameOrigin::Url, Some(filename)) => with_extension(&filename, type_extension),
+        (_, disposition) => {
+            let extension = disposition
+                .as_deref()
+                .and_then(meaningful_extension)
+                .or(type_extension.map(str::to_string));
+            with_extension(current, extension.as_deref())
+        }
+    };
+    (resolved != current).then_some(resolved)
+}
+
+/// 同名文件已存在时依次尝试 `name (2).ext`、`name (3).ext`…
+pub fn unique_filename(dir: &Path, filename: &str) -> String {
+    if !dir.join(filename).exists() {
+        return filename.to_string();
+    }
+    let path = Path::new(filename);
+    let stem = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_else(|| filename.to_string());
+    let extension = path
+        .extension()
+        .map(|extension| format!(".{}", extension.to_string_lossy()))
+        .unwrap_or_default();
+    (2..)
+        .map(|index| format!("{} ({}){}", stem, index, extension))
+        .find(|candidate| !dir.join(candidate).exists())
+        .unwrap_or_else(|| filename.to_string())
+}
+
+fn meaningful_extension(filename: &str) -> Option<String> {
+    let extension = Path::new(filename)
+        .extension()?
+        .to_str()?
+        .to_ascii_lowercase();
+    (extension.len() <= 5
+        && extension.chars().all(|ch| ch.is_ascii_alphanumeric())
+        && !SCRIPT_EXTENSIONS.contains(&extension.as_str()))
+    .then_some(extension)
+}
+
+fn with_extension(filename: &str, extension: Option<&str>) -> String {
+    let Some(extension) = extension else {
+        return filename.to_string();
+    };
+    if meaningful_extension(filename).is_some() {
+        return filename.to_string();
+    }
+    let stem = match Path::new(filename).extension().and_then(|ext| ext.to_str()) {
+        // download.php -> download.mp4
+        Some(script) if SCRIPT_EXTENSIONS.contains(&script.to_ascii_lowercase().as_str()) => {
+            &filename[..filename.len() - script.len() - 1]
+        }
+        _ => filename,
+    };
+    format!("{}.{}", stem, extension)
+}
+
+fn clean_filename(raw: &str) -> Option<String> {
+    let last_segment = raw.rsplit(['/', '\\']).next().unwrap_or(raw).trim();
+    if last_segment.is_empty() || last_segment.trim_matches('.').is_empty() {
+        return None;
+    }
+    Some(sanitize_filename(last_segment))
+}
+
+/// 拆出 `name=value` 参数（第一个 `;` 之前是 disposition 类型），处理引号与转义
+fn split_params(value: &[u8]) -> Vec<(String, Vec<u8>)> {
+    let mut params = Vec::new();
+    let mut index = match value.iter().position(|byte| *byte == b';') {
+        Some(position) => position + 1,
+        // 个别服务器省略 disposition 类型，直接给 filename=
+        None if value.contains(&b'=') => 0,
+        None => return params,
+    };
+    while index < value.len() {
+        while index < value.len() && matches!(value[index], b' ' | b'\t' | b';') {
+            index += 1;
+        }
+        let name_start = index;
+        while index < value.len() && !matches!(value[index], b'=' | b';') {
+            index += 1;
+        }
+        let name = String::from_utf8_lossy(&value[name_start..index])
+            .trim()
+            .to_string();
+        if index >= value.len() || value[index] == b';' {
+            continue;
+        }
+        index += 1;
+        while index < value.len() && matches!(value[index], b' ' | b'\t') {
+            index += 1;
+        }
+
+        let mut param = Vec::new();
+        if value.get(index) == Some(&b'"') {
+            index += 1;
+            while index < value.len() && value[index] != b'"' {
+                if value[index] == b'\\' && index + 1 < value.len() {
+                    index += 1;
+                }
+                param.push(value[index]);
+                index += 1;
+            }
+            index += 1;
+        } else {
+            while index < value.len() && value[index] != b';' {
+                param.push(value[index]);
+                index += 1;
+            }
+            while param.last().is_some_and(|byte| byte.is_ascii_whitespace()) {
+                param.pop();
+            }
+        }
+        if !name.is_empty() {
+            params.push((name, param));
+        }
+    }
+    params
+}
+
+/// RFC 5987 `charset'lang'pct-encoded`
+fn decode_ext_value(value: &[u8]) -> Option<String> {
+    let value = std::str::from_utf8(value).ok()?;
+    let mut parts = value.splitn(3, '\'');
+    let charset = parts.next()?.trim().to_ascii_lowercase();
+    let _language = parts.next()?;
+    let encoded = parts.next()?;
+    let bytes: Vec<u8> = percent_decode(encoded.as_bytes()).collect();
+    Some(decode_bytes(&bytes, Some(&charset)))
+}
+
+/// 普通 `filename=`：可能是原始 UTF-8/GBK 字节，也可能被百分号编码
+fn decode_plain_value(value: &[u8]) -> String {
+    let bytes: Vec<u8> = if value.contains(&b'%') {
+        let decoded: Vec<u8> = percent_decode(value).collect();
+        if std::str::from_utf8(&decoded).is_ok() {
+            decoded
+        } else {
+            value.to_vec()
+        }
+    } else {
+        value.to_vec()
+    };
+    decode_bytes(&bytes, None)
+}
+
+fn decode_bytes(bytes: &[u8], charset: Option<&str>) -> String {
+    match charset {
+        Some("gbk" | "gb2312" | "gb18030") => encoding_rs::GB18030.decode(bytes).0.into_owned(),
+        Some("iso-8859-1" | "latin1") => encoding_rs::WINDOWS_1252.decode(bytes).0.into_owned(),
+        _ => match std::str::from_utf8(bytes) {
+            Ok(text) => text.to_string(),
+            Err(_) => encoding_rs::GB18030.decode(bytes).0.into_owned(),
+        },
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::expect_used)]
+mod tests {
+    use super::*;
+    use reqwest::header::HeaderValue;
+
+    fn headers(disposition: Option<&[u8]>, content_type: Option<&str>) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        if let Some(disposition) = disposition {
+            headers.insert(
+                CONTENT_DISPOSITION,
+                HeaderValue::from_bytes(disposition).expect("header bytes"),
+            );
+        }
+        if let Some(content_type) = content_type {
+            headers.insert(
+                CONTENT_TYPE,
+                HeaderValue::from_str(content_type).expect("content type"),
+            );
+        }
+        headers
+    }
+
+    #[test]
+    fn parses_rfc6266_and_legacy_encodings() {
+        assert_eq!(
+            filename_from_content_disposition(
+                b"attachment; filename=\"fallback.mp4\"; filename*=UTF-8''%E7%AC%AC1%E8%AF%BE.mp4"
+            )
+            .as_deref(),
+            Some("第1课.mp4")
+        );
+        assert_eq!(
+            filename_from_content_disposition(b"attachment; filename*=GBK''%B5%DA1%BF%CE.mp4")
+                .as_deref(),
+            Some("第1课.mp4")
+        );
+        // 原始 GBK 字节 / 百分号编码的 UTF-8
+        let (gbk, _, _) = encoding_rs::GBK.encode("课程 2.mp4");
+        let raw = [b"attachment; filename=\"".as_slice(), &gbk, b"\""].concat();
+        assert_eq!(
+            filename_from_content_disposition(&raw).as_deref(),
+            Some("课程 2.mp4")
+        );
+        assert_eq!(
+            filename_from_content_disposition(b"inline; filename=%E8%A7%86%E9%A2%91.webm")
+                .as_deref(),
+            Some("视频.webm")
+        );
+        // 路径穿越与空文件名
+        assert_eq!(
+            filename_from_content_disposition(b"attachment; filename=\"../../etc/a\\\\b.mp4\"")
+                .as_deref(),
+            Some("b.mp4")
+        );
+        assert_eq!(
+            filename_from_content_disposition(b"attachment; filename=\"..\""),
+            None
+        );
+        assert_eq!(filename_from_content_disposition(b"attachment"), None);
+    }
+
+    #[test]
+    fn resolves_names_by_origin() {
+        let disposition = headers(
+            Some(b"attachment; filename=\"lesson.mp4\""),
+            Some("video/mp4"),
+        );
+        assert_eq!(
+            resolve_response_filename("download", FilenameOrigin::Url, &disposition).as_deref(),
+            Some("lesson.mp4")
+        );
+        assert_eq!(
+            resolve_response_filename("第一课", FilenameOrigin::Title, &disposition).as_deref(),
+            Some("第一课.mp4")
+        );
+        assert_eq!(
+            resolve_response_filename("mine.bin", FilenameOrigin::Fixed, &disposition),
+            None
+        );
+
+        let typed = headers(None, Some("video/x-matroska; charset=binary"));
+        assert_eq!(
+            resolve_response_filename("download.php", FilenameOrigin::Url, &typed).as_deref(),
+            Some("download.mkv")
+        );
+        assert_eq!(
+            resolve_response_filename("clip.mp4", FilenameOrigin::Url, &typed),
+            None
+        );
+        let opaque = headers(None, Some("application/octet-stream"));
+        assert_eq!(
+            resolve_response_filename("download", FilenameOrigin::Url, &opaque),
+            None
+        );
+    }
+
+    #[test]
+    fn unique_filename_skips_existing_files() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        assert_eq!(unique_filename(dir.path(), "a.mp4"), "a.mp4");
+        std::fs::write(dir.path().join("a.mp4"), b"x").expect("write");
+        std::fs::write(dir.path().join("a (2).mp4"), b"x").expect("write");
+        assert_eq!(unique_filename(dir.path(), "a.mp4"), "a (3).mp4");
+    }
+}
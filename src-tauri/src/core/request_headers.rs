@@ -0,0 +1,217 @@
+//! 按主机匹配的请求头规则
+//!
+//! 全局请求头（`DownloadConfig.headers`）先生效，再按主机规则覆盖，
+//! 越具体的主机规则优先级越高（`cdn.example.com` 覆盖 `example.com`）。
+
+#![deny(clippy::unwrap_used, clippy::expect_used)]
+
+use anyhow::{Context, Result};
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use crate::core::models::{DownloadConfig, HostHeaderRule};
+
+/// 已校验的请求头规则集合
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HeaderRules {
+    global: Vec<(String, String)>,
+    /// 按具体程度升序排列，后匹配的覆盖先匹配的
+    host_rules: Vec<CompiledHostRule>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct CompiledHostRule {
+    host: String,
+    headers: Vec<(String, String)>,
+}
+
+impl HeaderRules {
+    pub fn from_download_config(config: &DownloadConfig) -> Result<Self> {
+        Self::new(&config.headers, &config.host_headers)
+    }
+
+    pub fn new(
+        global: &std::collections::HashMap<String, String>,
+        rules: &[HostHeaderRule],
+    ) -> Result<Self> {
+        let mut global_headers: Vec<(String, String)> = global
+            .iter()
+            .map(|(name, value)| validate_header(name, value))
+            .collect::<Result<_>>()?;
+        global_headers.sort_by(|a, b| a.0.cmp(&b.0));
+
+        let mut host_rules = Vec::with_capacity(rules.len());
+        for rule in rules {
+            let host = normalize_host_pattern(&rule.host);
+            if host.is_empty() {
+                anyhow::bail!("Header rule host must not be empty");
+            }
+            let mut headers = Vec::new();
+            if let Some(user_agent) = &rule.user_agent {
+                upsert(&mut headers, validate_header("User-Agent", user_agent)?);
+            }
+            if let Some(referer) = &rule.referer {
+                upsert(&mut headers, validate_header("Referer", referer)?);
+            }
+            if let Some(origin) = &rule.origin {
+                upsert(&mut headers, validate_header("Origin", origin)?);
+            }
+            let mut extra: Vec<_> = rule.headers.iter().collect();
+            extra.sort_by(|a, b| a.0.cmp(b.0));
+            for (name, value) in extra {
+                upsert(&mut headers, validate_header(name, value)?);
+            }
+            host_rules.push(CompiledHostRule { host, headers });
+        }
+        // 稳定排序：相同具体程度时保持配置顺序
+        host_rules.sort_by_key(|rule| rule.host.split('.').count());
+
+        Ok(Self {
+            global: global_headers,
+            host_rules,
+        })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.global.is_empty() && self.host_rules.is_empty()
+    }
+
+    /// 目标 URL 最终需要附带的请求头（名称保持配置中的写法）
+    pub fn headers_for_url(&self, url: &str) -> Vec<(String, String)> {
+        let mut headers = self.global.clone();
+        if self.host_rules.is_empty() {
+            return headers;
+        }
+
+        let Some(host) = Url::parse(url)
+            .ok()
+            .and_then(|parsed| parsed.host_str().map(|host| host.to_ascii_lowercase()))
+        else {
+            return headers;
+        };
+        for rule in self
+            .host_rules
+            .iter()
+            .filter(|rule| host_matches(&host, &rule.host))
+        {
+            for header in &rule.headers {
+                upsert(&mut headers, header.clone());
+            }
+        }
+        headers
+    }
+
+    pub fn header_map_for_url(&self, url: &str) -> HeaderMap {
+        let mut map = HeaderMap::new();
+        for (name, value) in self.headers_for_url(url) {
+            // 构建规则时已校验，这里不会失败
+            if let (Ok(name), Ok(value)) = (
+                HeaderName::from_bytes(name.as_bytes()),
+                HeaderValue::from_str(&value),
+            ) {
+                map.insert(name, value);
+            }
+        }
+        map
+    }
+}
+
+fn validate_header(name: &str, value: &str) -> Result<(String, String)> {
+    let name = name.trim();
+    HeaderName::from_bytes(name.as_bytes())
+        .with_context(|| format!("Invalid header name: {:?}", name))?;
+    HeaderValue::from_str(value.trim())
+        .with_context(|| format!("Invalid value for header {}", name))?;
+    Ok((name.to_string(), value.trim().to_string()))
+}
+
+fn upsert(headers: &mut Vec<(String, String)>, header: (String, String)) {
+    match headers
+        .iter_mut()
+        .find(|(name, _)| name.eq_ignore_ascii_case(&header.0))
+    {
+        Some(existing) => *existing = header,
+        None => headers.push(header),
+    }
+}
+
+pub(crate) fn normalize_host_pattern(pattern: &str) -> String {
+    let trimmed = pattern.trim();
+    let host = Url::parse(trimmed)
+        .ok()
+        .and_then(|url| url.host_str().map(str::to_string))
+        .unwrap_or_else(|| trimmed.to_string());
+    host.trim_start_matches("*.")
+        .trim_start_matches('.')
+        .trim_end_matches('.')
+        .to_ascii_lowercase()
+}
+
+pub(crate) fn host_matches(host: &str, pattern: &str) -> bool {
+    host == pattern || host.ends_with(&format!(".{}", pattern))
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::expect_used)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn rule(host: &str) -> HostHeaderRule {
+        HostHeaderRule {
+            host: host.to_string(),
+            ..HostHeaderRule::default()
+        }
+    }
+
+    #[test]
+    fn more_specific_host_rules_override_global_headers() {
+        let global = HashMap::from([
+            ("Referer".to_string(), "https://global.example/".to_string()),
+            ("X-Team".to_string(), "video".to_string()),
+        ]);
+        let rules = vec![
+            HostHeaderRule {
+                referer: Some("https://cdn.course.com/player".to_string()),
+                ..rule("cdn.course.com")
+            },
+            HostHeaderRule {
+                referer: Some("https://course.com/".to_string()),
+                origin: Some("https://course.com".to_string()),
+                user_agent: Some("CourseAgent/1.0".to_string()),
+                ..rule("*.course.com")
+            },
+        ];
+        let header_rules = HeaderRules::new(&global, &rules).unwrap();
+
+        let headers = header_rules.headers_for_url("https://cdn.course.com/v/1.m3u8");
+        let lookup = |name: &str| {
+            headers
+                .iter()
+                .find(|(key, _)| key.eq_ignore_ascii_case(name))
+                .map(|(_, value)| value.as_str())
+        };
+        assert_eq!(lookup("referer"), Some("https://cdn.course.com/player"));
+        assert_eq!(lookup("origin"), Some("https://course.com"));
+        assert_eq!(lookup("user-agent"), Some("CourseAgent/1.0"));
+        assert_eq!(lookup("x-team"), Some("video"));
+
+        let other = header_rules.header_map_for_url("https://other.org/a.mp4");
+        assert_eq!(other.get("referer").unwrap(), "https://global.example/");
+        assert!(other.get("origin").is_none());
+    }
+
+    #[test]
+    fn rejects_invalid_header_names_and_values() {
+        let global = HashMap::from([("Bad Header".to_string(), "x".to_string())]);
+        assert!(HeaderRules::new(&global, &[]).is_err());
+
+        let rules = vec![HostHeaderRule {
+            headers: HashMap::from([("X-Ok".to_string(), "line\nbreak".to_string())]),
+            ..rule("example.com")
+        }];
+        assert!(HeaderRules::new(&HashMap::new(), &rules).is_err());
+        assert!(HeaderRules::new(&HashMap::new(), &[rule("  ")]).is_err());
+    }
+}
@@ -9,21 +9,26 @@
 
 use anyhow::{bail, Context, Result};
 use futures_util::StreamExt;
-use reqwest::{Client, StatusCode};
+use reqwest::StatusCode;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::path::{Path, PathBuf};
 use std::sync::{
     atomic::{AtomicBool, Ordering},
     Arc,
 };
-use std::time::{Duration, SystemTime};
+use std::time::{Duration, Instant, SystemTime};
 use tokio::sync::RwLock;
 
 use crate::core::downloader::BandwidthController;
+use crate::core::http_client::HttpClientHandle;
+use crate::core::mirror_pool::{MirrorFailure, MirrorLease, MirrorPool};
 use crate::core::part_file::{derive_part_path, PartFileWriter};
 
-const CURRENT_RESUME_SCHEMA_VERSION: u32 = 2;
+const CURRENT_RESUME_SCHEMA_VERSION: u32 = 3;
+
+/// 远端资源在两次请求之间发生变化（ETag / Last-Modified / 大小不一致）
+const RESOURCE_CHANGED: &str = "resource_changed";
 
 /// 下载进度回调：参数为 (task_id, delta_bytes, total_size)
 pub type ResumeProgressCallback = Arc<dyn Fn(&str, u64, u64) + Send + Sync>;
@@ -45,6 +50,9 @@ pub struct ChunkInfo {
     pub retry_count: usize,
     /// 最后更新时间
     pub last_update: SystemTime,
+    /// 下载该分片的镜像地址
+    #[serde(default)]
+    pub source: Option<String>,
 }
 
 impl ChunkInfo {
@@ -57,6 +65,7 @@ impl ChunkInfo {
             status: ChunkStatus::Pending,
             retry_count: 0,
             last_update: SystemTime::now(),
+            source: None,
         }
     }
 
@@ -128,6 +137,12 @@ pub struct ResumeInfo {
     pub original_url: String,
     /// 服务器支持能力
     pub server_capabilities: ServerCapabilities,
+    /// 服务器返回的 ETag（schema v3）
+    #[serde(default)]
+    pub etag: Option<String>,
+    /// 服务器返回的 Last-Modified 原始值（schema v3）
+    #[serde(default)]
+    pub remote_last_modified: Option<String>,
 }
 
 impl ResumeInfo {
@@ -150,6 +165,8 @@ impl ResumeInfo {
                 detected_at: SystemTime::now(),
                 server_info: None,
             },
+            etag: None,
+            remote_last_modified: None,
         }
     }
 
@@ -171,7 +188,11 @@ impl ResumeInfo {
 
     /// 更新分片进度
     pub fn update_chunk_progress(&mut self, chunk_index: usize, downloaded: u64) {
-        if let Some(chunk) = self.chunks.get_mut(chunk_index) {
+        if let Some(chunk) = self
+            .chunks
+            .iter_mut()
+            .find(|chunk| chunk.index == chunk_index)
+        {
             chunk.downloaded = downloaded;
             chunk.last_update = SystemTime::now();
             if chunk.is_completed() {
@@ -193,6 +214,50 @@ impl ResumeInfo {
         }
     }
 
+    /// If-Range 使用的校验值：优先强 ETag，其次 Last-Modified
+    pub fn if_range_validator(&self) -> Option<&str> {
+        self.etag
+            .as_deref()
+            .filter(|etag| !etag.starts_with("W/"))
+            .or(self.remote_last_modified.as_deref())
+    }
+
+    /// 与最新探测结果比较，资源已变化时返回原因
+    fn detect_resource_change(&self, remote: &RemoteResource) -> Option<String> {
+        if let (Some(stored), Some(current)) = (&self.etag, &remote.etag) {
+            if stored != current {
+                return Some(format!("ETag changed from {} to {}", stored, current));
+            }
+        } else if let (Some(stored), Some(current)) =
+            (&self.remote_last_modified, &remote.last_modified)
+        {
+            if stored != current {
+                return Some(format!(
+                    "Last-Modified changed from {} to {}",
+                    stored, current
+                ));
+            }
+        }
+
+        match remote.content_length {
+            Some(size) if self.total_size > 0 && size != self.total_size => Some(format!(
+                "Content-Length changed from {} to {}",
+                self.total_size, size
+            )),
+            _ => None,
+        }
+    }
+
+    /// 记录探测到的校验值；响应缺少某项时保留已存的值，避免 If-Range 失效
+    fn record_validators(&mut self, remote: &RemoteResource) {
+        if let Some(etag) = &remote.etag {
+            self.etag = Some(etag.clone());
+        }
+        if let Some(last_modified) = &remote.last_modified {
+            self.remote_last_modified = Some(last_modified.clone());
+        }
+    }
+
     pub fn part_file_path(&self) -> PathBuf {
         self.part_file_path
             .as_ref()
@@ -211,6 +276,48 @@ fn derive_part_file_path(file_path: &str) -> String {
         .to_string()
 }
 
+/// HEAD 探测得到的远端资源信息
+#[derive(Debug, Clone, Default)]
+struct RemoteResource {
+    success: bool,
+    accepts_ranges: bool,
+    content_length: Option<u64>,
+    etag: Option<String>,
+    last_modified: Option<String>,
+}
+
+impl RemoteResource {
+    /// 镜像与基准资源不一致的原因（大小、ETag、Last-Modified 或不支持 Range）
+    fn mirror_mismatch(&self, mirror: &RemoteResource) -> Option<String> {
+        if !mirror.success {
+            return Some("镜像探测请求失败".to_string());
+        }
+        if mirror.content_length.is_none() || mirror.content_length != self.content_length {
+            return Some(format!(
+                "镜像大小 {:?} 与基准 {:?} 不一致",
+                mirror.content_length, self.content_length
+            ));
+        }
+        if let (Some(expected), Some(actual)) = (&self.etag, &mirror.etag) {
+            if expected != actual {
+                return Some(format!("镜像 ETag {} 与基准 {} 不一致", actual, expected));
+            }
+        }
+        if let (Some(expected), Some(actual)) = (&self.last_modified, &mirror.last_modified) {
+            if expected != actual {
+                return Some(format!(
+                    "镜像 Last-Modified {} 与基准 {} 不一致",
+                    actual, expected
+                ));
+            }
+        }
+        if !mirror.accepts_ranges {
+            return Some("镜像不支持 Range 请求".to_string());
+        }
+        None
+    }
+}
+
 /// 断点续传下载器配置
 #[derive(Debug, Clone)]
 pub struct ResumeDownloaderConfig {
@@ -228,6 +335,8 @@ pub struct ResumeDownloaderConfig {
     pub resume_info_dir: PathBuf,
     /// 服务器能力缓存过期时间
     pub server_cache_ttl: Duration,
+    /// 拆分进行中分片时每一半的最小大小 (默认 1MB)
+    pub min_split_size: u64,
 }
 
 impl Default for ResumeDownloaderConfig {
@@ -240,14 +349,191 @@ impl Default for ResumeDownloaderConfig {
             retry_delay: Duration::from_secs(2),
             resume_info_dir: std::env::temp_dir().join("video_downloader_resume"),
             server_cache_ttl: Duration::from_secs(24 * 60 * 60), // 24小时
+            min_split_size: 1024 * 1024,                         // 1MB
         }
     }
 }
 
+/// 分片调度器
+///
+/// 空闲连接优先领取未开始的分片；没有可领取的分片时，拆分剩余字节最多的进行中分片，
+/// 接管其后半段（work stealing），拆分结果直接写回分片列表，暂停后可按新分片续传。
+struct ChunkScheduler {
+    state: parking_lot::Mutex<SchedulerState>,
+    allow_split: bool,
+    min_split_size: u64,
+}
+
+struct SchedulerState {
+    chunks: Vec<ChunkInfo>,
+    active: HashSet<usize>,
+    aborted: bool,
+}
+
+impl SchedulerState {
+    /// 按 `ChunkInfo::index` 查找；旧版本或被改写的续传信息里索引不一定等于位置
+    fn chunk(&self, index: usize) -> Option<&ChunkInfo> {
+        self.chunks.iter().find(|chunk| chunk.index == index)
+    }
+
+    fn chunk_mut(&mut self, index: usize) -> Option<&mut ChunkInfo> {
+        self.chunks.iter_mut().find(|chunk| chunk.index == index)
+    }
+}
+
+impl ChunkScheduler {
+    /// 分片索引重复时无法区分各连接负责的分片，直接拒绝续传
+    fn new(chunks: Vec<ChunkInfo>, allow_split: bool, min_split_size: u64) -> Result<Self> {
+        let mut seen = HashSet::new();
+        if let Some(duplicate) = chunks.iter().find(|chunk| !seen.insert(chunk.index)) {
+            bail!("断点续传信息中的分片索引重复: {}", duplicate.index);
+        }
+        Ok(Self {
+            state: parking_lot::Mutex::new(SchedulerState {
+                chunks,
+                active: HashSet::new(),
+                aborted: false,
+            }),
+            allow_split,
+            min_split_size: min_split_size.max(1),
+        })
+    }
+
+    /// 领取下一个分片，返回分片索引
+    fn claim(&self) -> Option<usize> {
+        let mut state = self.state.lock();
+        if state.aborted {
+            return None;
+        }
+
+        let next = state
+            .chunks
+            .iter()
+            .find(|chunk| !chunk.is_completed() && !state.active.contains(&chunk.index))
+            .map(|chunk| chunk.index);
+        if let Some(index) = next {
+            state.active.insert(index);
+            if let Some(chunk) = state.chunk_mut(index) {
+                chunk.status = ChunkStatus::Downloading;
+            }
+            return Some(index);
+        }
+
+        if !self.allow_split {
+            return None;
+        }
+        self.split_largest_active(&mut state)
+    }
+
+    fn split_largest_active(&self, state: &mut SchedulerState) -> Option<usize> {
+        let victim = state
+            .chunks
+            .iter()
+            .filter(|chunk| state.active.contains(&chunk.index) && !chunk.is_completed())
+            .filter(|chunk| chunk.remaining() >= self.min_split_size * 2)
+            .max_by_key(|chunk| chunk.remaining())
+            .map(|chunk| chunk.index)?;
+
+        let new_index = state
+            .chunks
+            .iter()
+            .map(|chunk| chunk.index + 1)
+            .max()
+            .unwrap_or(0);
+        let chunk = state.chunk_mut(victim)?;
+        let split_at = chunk.start + chunk.downloaded + chunk.remaining() / 2;
+        let mut stolen = ChunkInfo::new(new_index, split_at, chunk.end);
+        stolen.status = ChunkStatus::Downloading;
+        chunk.end = split_at - 1;
+
+        tracing::debug!(
+            "拆分分片 {}: 新分片 {} 接管 {}-{}",
+            victim,
+            new_index,
+            stolen.start,
+            stolen.end
+        );
+
+        state.chunks.push(stolen);
+        state.active.insert(new_index);
+        Some(new_index)
+    }
+
+    fn chunk(&self, index: usize) -> Option<ChunkInfo> {
+        self.state.lock().chunk(index).cloned()
+    }
+
+    fn chunk_count(&self) -> usize {
+        self.state.lock().chunks.len()
+    }
+
+    fn set_source(&self, index: usize, url: &str) {
+        if let Some(chunk) = self.state.lock().chunk_mut(index) {
+            chunk.source = Some(url.to_string());
+        }
+    }
+
+    fn downloaded(&self, index: usize) -> u64 {
+        self.state
+            .lock()
+            .chunk(index)
+            .map(|chunk| chunk.downloaded)
+            .unwrap_or(0)
+    }
+
+    /// 本分片还能写入的字节数（分片可能已被拆走后半段）
+    fn writable(&self, index: usize, len: u64) -> u64 {
+        self.state
+            .lock()
+            .chunk(index)
+            .map(|chunk| chunk.remaining().min(len))
+            .unwrap_or(0)
+    }
+
+    /// 记录已落盘的字节，返回 (实际计入的字节数, 分片是否已完成)
+    fn commit(&self, index: usize, len: u64) -> (u64, bool) {
+        let mut state = self.state.lock();
+        let Some(chunk) = state.chunk_mut(index) else {
+            return (0, true);
+        };
+        let committed = chunk.remaining().min(len);
+        chunk.downloaded += committed;
+        chunk.last_update = SystemTime::now();
+        (committed, chunk.downloaded >= chunk.size())
+    }
+
+    /// 归还分片，并根据进度设置状态
+    fn release(&self, index: usize, retry_count: usize, failed: bool) {
+        let mut state = self.state.lock();
+        state.active.remove(&index);
+        if let Some(chunk) = state.chunk_mut(index) {
+            chunk.retry_count = retry_count;
+            chunk.status = if chunk.downloaded >= chunk.size() {
+                ChunkStatus::Completed
+            } else if failed {
+                ChunkStatus::Failed
+            } else if chunk.downloaded > 0 {
+                ChunkStatus::Paused
+            } else {
+                ChunkStatus::Pending
+            };
+        }
+    }
+
+    /// 出现不可恢复的错误后停止派发新分片
+    fn abort(&self) {
+        self.state.lock().aborted = true;
+    }
+
+    fn snapshot(&self) -> Vec<ChunkInfo> {
+        self.state.lock().chunks.clone()
+    }
+}
+
 /// 增强的断点续传下载器
 pub struct ResumeDownloader {
     config: ResumeDownloaderConfig,
-    client: Client,
+    client: HttpClientHandle,
     server_capabilities_cache: Arc<RwLock<HashMap<String, ServerCapabilities>>>,
     _active_chunks: Arc<RwLock<HashMap<String, Arc<AtomicBool>>>>,
     resume_info_cache: Arc<RwLock<HashMap<String, ResumeInfo>>>,
@@ -299,10 +585,14 @@ impl ResumeDownloader {
         err.to_string().contains("download_cancelled")
     }
 
+    fn is_resource_changed_error(err: &anyhow::Error) -> bool {
+        err.to_string().contains(RESOURCE_CHANGED)
+    }
+
     /// 创建新的断点续传下载器
     pub fn new(
         config: ResumeDownloaderConfig,
-        client: Client,
+        client: impl Into<HttpClientHandle>,
         bandwidth_controller: BandwidthController,
     ) -> Result<Self> {
         // 确保断点信息目录存在
@@ -310,7 +600,7 @@ impl ResumeDownloader {
 
         Ok(Self {
             config,
-            client,
+            client: client.into(),
             server_capabilities_cache: Arc::new(RwLock::new(HashMap::new())),
             _active_chunks: Arc::new(RwLock::new(HashMap::new())),
             resume_info_cache: Arc::new(RwLock::new(HashMap::new())),
@@ -342,8 +632,7 @@ impl ResumeDownloader {
         // 发送HEAD请求检测Range支持
         let response = self
             .client
-            .head(url)
-            .send()
+            .send(self.client.head(url))
             .await
             .with_context(|| format!("Failed to send HEAD request to {}", url))?;
 
@@ -398,10 +687,42 @@ impl ResumeDownloader {
         cancel_flag: Option<Arc<AtomicBool>>,
         pause_flag: Option<Arc<AtomicBool>>,
     ) -> Result<ResumeInfo> {
+        self.download_from_mirrors(
+            task_id,
+            task_id,
+            Arc::new(MirrorPool::single(url)),
+            file_path,
+            total_size,
+            progress_callback,
+            cancel_flag,
+            pause_flag,
+        )
+        .await
+    }
+
+    /// 从多个镜像开始或恢复下载，分片按镜像实测速度分配
+    ///
+    /// `resume_key` 定位断点文件；`task_id` 是下载任务 ID，用于按任务限速和进度回调
+    #[allow(clippy::too_many_arguments)]
+    pub async fn download_from_mirrors(
+        &self,
+        resume_key: &str,
+        task_id: &str,
+        sources: Arc<MirrorPool>,
+        file_path: &Path,
+        total_size: Option<u64>,
+        progress_callback: Option<ResumeProgressCallback>,
+        cancel_flag: Option<Arc<AtomicBool>>,
+        pause_flag: Option<Arc<AtomicBool>>,
+    ) -> Result<ResumeInfo> {
+        let url = sources
+            .primary_url()
+            .ok_or_else(|| anyhow::anyhow!("没有可用的下载地址"))?;
+
         // 尝试加载已有的断点信息
-        let mut resume_info = self.load_resume_info(task_id).await?.unwrap_or_else(|| {
+        let mut resume_info = self.load_resume_info(resume_key).await?.unwrap_or_else(|| {
             ResumeInfo::new(
-                task_id.to_string(),
+                resume_key.to_string(),
                 file_path.to_string_lossy().to_string(),
                 url.to_string(),
                 total_size.unwrap_or(0),
@@ -410,13 +731,35 @@ impl ResumeDownloader {
         resume_info.file_path = file_path.to_string_lossy().to_string();
         resume_info.ensure_current_schema();
 
-        // 如果没有总大小信息，尝试获取
-        if resume_info.total_size == 0 {
-            if let Some(size) = self.get_content_length(url).await? {
-                resume_info.total_size = size;
-            } else {
-                bail!("无法获取文件大小，不支持断点续传");
+        // 探测远端大小与校验值；已知大小时探测失败不阻断下载
+        let remote = match self.probe_sources(&sources).await {
+            Ok(remote) => Some(remote),
+            Err(e) if resume_info.total_size > 0 => {
+                tracing::warn!("探测远端资源失败，跳过变更检测: {}", e);
+                None
             }
+            Err(e) => return Err(e),
+        };
+
+        if let Some(remote) = &remote {
+            let has_progress =
+                resume_info.downloaded_total > 0 || resume_info.part_file_path().exists();
+            if has_progress {
+                if let Some(reason) = resume_info.detect_resource_change(remote) {
+                    self.discard_stale_progress(&mut resume_info, &reason)
+                        .await?;
+                    resume_info.total_size = 0;
+                }
+            }
+            if resume_info.total_size == 0 {
+                resume_info.total_size = remote.content_length.unwrap_or(0);
+            }
+            resume_info.record_validators(remote);
+        }
+
+        // 如果没有总大小信息，无法续传
+        if resume_info.total_size == 0 {
+            bail!("无法获取文件大小，不支持断点续传");
         }
 
         if Self::should_interrupt(&cancel_flag, &pause_flag) {
@@ -428,8 +771,9 @@ impl ResumeDownloader {
             tokio::fs::create_dir_all(parent).await?;
         }
 
-        // 检测服务器支持能力
-        resume_info.server_capabilities = self.detect_server_capabilities(url).await?;
+        // 检测服务器支持能力（主镜像可能在探测时被剔除）
+        let url = sources.primary_url().unwrap_or(url);
+        resume_info.server_capabilities = self.detect_server_capabilities(&url).await?;
 
         // 如果文件不存在或大小不匹配，重新开始
         if !self.validate_existing_state(&resume_info).await? {
@@ -439,25 +783,45 @@ impl ResumeDownloader {
             self.clear_chunk_progress(&mut resume_info);
         }
 
-        // 创建分片策略
-        if resume_info.chunks.is_empty() {
-            resume_info.chunks = self.create_chunks(&resume_info).await?;
-        }
+        let mut restarted = false;
+        let part_writer = loop {
+            // 创建分片策略
+            if resume_info.chunks.is_empty() {
+                resume_info.chunks = self.create_chunks(&resume_info).await?;
+            }
 
-        let part_writer = self.prepare_storage(file_path, &mut resume_info).await?;
-        self.sync_chunks_with_part_file(&mut resume_info).await?;
-        self.save_resume_info(&resume_info).await?;
+            let part_writer = self.prepare_storage(file_path, &mut resume_info).await?;
+            self.sync_chunks_with_part_file(&mut resume_info).await?;
+            self.save_resume_info(&resume_info).await?;
 
-        // 开始下载
-        self.download_chunks(
-            url,
-            &mut resume_info,
-            &part_writer,
-            progress_callback.clone(),
-            cancel_flag.clone(),
-            pause_flag.clone(),
-        )
-        .await?;
+            // 开始下载
+            match self
+                .download_chunks(
+                    task_id,
+                    &sources,
+                    &mut resume_info,
+                    &part_writer,
+                    progress_callback.clone(),
+                    cancel_flag.clone(),
+                    pause_flag.clone(),
+                )
+                .await
+            {
+                Ok(()) => break part_writer,
+                // 服务器对 If-Range 返回了完整内容：资源在下载过程中被替换，只重来一次
+                Err(e) if Self::is_resource_changed_error(&e) && !restarted => {
+                    restarted = true;
+                    self.discard_stale_progress(&mut resume_info, &e.to_string())
+                        .await?;
+                    let remote = self.probe_sources(&sources).await?;
+                    resume_info.total_size = remote
+                        .content_length
+                        .ok_or_else(|| anyhow::anyhow!("无法获取文件大小，不支持断点续传"))?;
+                    resume_info.record_validators(&remote);
+                }
+                Err(e) => return Err(e),
+            }
+        };
 
         if Self::should_interrupt(&cancel_flag, &pause_flag) {
             // 保存当前进度，保证后续能继续
@@ -501,20 +865,18 @@ impl ResumeDownloader {
     }
 
     /// 下载所有分片
+    #[allow(clippy::too_many_arguments)]
     async fn download_chunks(
         &self,
-        url: &str,
+        task_id: &str,
+        sources: &Arc<MirrorPool>,
         resume_info: &mut ResumeInfo,
         part_writer: &PartFileWriter,
         progress_callback: Option<ResumeProgressCallback>,
         cancel_flag: Option<Arc<AtomicBool>>,
         pause_flag: Option<Arc<AtomicBool>>,
     ) -> Result<()> {
-        let pending_chunks: Vec<usize> = resume_info
-            .pending_chunks()
-            .iter()
-            .map(|chunk| chunk.index)
-            .collect();
+        let pending_count = resume_info.pending_chunks().len();
 
         let bandwidth_controller = self.bandwidth_controller.clone();
 
@@ -525,89 +887,97 @@ impl ResumeDownloader {
             )));
         }
 
-        if pending_chunks.is_empty() {
+        if pending_count == 0 {
             tracing::info!("所有分片已完成");
             return Ok(());
         }
 
-        let max_concurrent = std::cmp::min(
-            pending_chunks.len(),
-            resume_info.server_capabilities.max_concurrent,
-        );
+        // 只有按多分片规划的下载才允许拆分，单分片的小文件保持单连接
+        let allow_split = resume_info.server_capabilities.supports_ranges
+            && resume_info.server_capabilities.supports_concurrent
+            && resume_info.chunks.len() > 1;
+        let max_concurrent = if allow_split {
+            resume_info.server_capabilities.max_concurrent.max(1)
+        } else {
+            std::cmp::min(
+                pending_count,
+                resume_info.server_capabilities.max_concurrent,
+            )
+            .max(1)
+        };
 
         tracing::info!(
-            "开始下载 {} 个分片，最大并发: {}",
-            pending_chunks.len(),
-            max_concurrent
+            "开始下载 {} 个分片，最大并发: {}，动态拆分: {}",
+            pending_count,
+            max_concurrent,
+            allow_split
         );
 
-        // 创建信号量控制并发数
-        let semaphore = Arc::new(tokio::sync::Semaphore::new(max_concurrent));
+        let scheduler = Arc::new(ChunkScheduler::new(
+            resume_info.chunks.clone(),
+            allow_split,
+            self.config.min_split_size,
+        )?);
+        let task_id = Arc::new(task_id.to_string());
+        let total_size = resume_info.total_size;
         let mut handles = Vec::new();
 
-        for chunk_index in pending_chunks {
-            if Self::should_interrupt(&cancel_flag, &pause_flag) {
-                // 在退出前保存进度，便于下次继续
-                self.save_resume_info(resume_info).await.ok();
-                return Err(anyhow::anyhow!(Self::interrupt_reason(
-                    &cancel_flag,
-                    &pause_flag
-                )));
-            }
-
-            let semaphore = Arc::clone(&semaphore);
-            let url = url.to_string();
+        // 固定数量的连接各自循环领取分片，空闲时拆分其他连接的剩余部分
+        for _ in 0..max_concurrent {
+            let scheduler = Arc::clone(&scheduler);
+            let sources = Arc::clone(sources);
             let client = self.client.clone();
             let config = self.config.clone();
             let resume_info_clone = resume_info.clone();
             let part_writer = part_writer.clone();
-            let task_id = Arc::new(resume_info_clone.task_id.clone());
-            let total_size = resume_info_clone.total_size;
+            let task_id = Arc::clone(&task_id);
             let progress_callback = progress_callback.clone();
             let controller = bandwidth_controller.clone();
             let cancel_flag = cancel_flag.clone();
             let pause_flag = pause_flag.clone();
 
             let handle = tokio::spawn(async move {
-                let _permit = semaphore
-                    .acquire()
+                while let Some(chunk_index) = scheduler.claim() {
+                    if let Err(e) = Self::download_chunk_static(
+                        &client,
+                        &config,
+                        &sources,
+                        &resume_info_clone,
+                        &scheduler,
+                        &part_writer,
+                        chunk_index,
+                        Arc::clone(&task_id),
+                        total_size,
+                        progress_callback.clone(),
+                        controller.clone(),
+                        cancel_flag.clone(),
+                        pause_flag.clone(),
+                    )
                     .await
-                    .map_err(|_| anyhow::anyhow!("chunk concurrency semaphore closed"))?;
+                    {
+                        scheduler.abort();
+                        return Err(e);
+                    }
 
-                if ResumeDownloader::should_interrupt(&cancel_flag, &pause_flag) {
-                    return Err(anyhow::anyhow!(Self::interrupt_reason(
-                        &cancel_flag,
-                        &pause_flag
-                    )));
+                    if ResumeDownloader::should_interrupt(&cancel_flag, &pause_flag) {
+                        return Err(anyhow::anyhow!(Self::interrupt_reason(
+                            &cancel_flag,
+                            &pause_flag
+                        )));
+                    }
                 }
-
-                Self::download_chunk_static(
-                    &client,
-                    &config,
-                    &url,
-                    &resume_info_clone,
-                    &part_writer,
-                    chunk_index,
-                    task_id,
-                    total_size,
-                    progress_callback,
-                    controller.clone(),
-                    cancel_flag.clone(),
-                    pause_flag.clone(),
-                )
-                .await
+                Ok(())
             });
 
             handles.push(handle);
         }
 
-        // 等待所有分片下载完成：
+        // 等待所有连接结束：
         // 不在首个错误处提前返回，先回收全部任务并尽可能同步本地进度，避免状态落后导致“回退”。
-        let mut chunk_results = Vec::new();
         let mut first_error: Option<anyhow::Error> = None;
         for handle in handles {
             match handle.await {
-                Ok(Ok(chunk_info)) => chunk_results.push(chunk_info),
+                Ok(Ok(())) => {}
                 Ok(Err(e)) => {
                     if Self::is_pause_error(&e) {
                         tracing::info!("分片下载已暂停: {}", e);
@@ -629,12 +999,8 @@ impl ResumeDownloader {
             }
         }
 
-        // 更新resume_info中的分片信息
-        for updated_chunk in chunk_results {
-            if let Some(chunk) = resume_info.chunks.get_mut(updated_chunk.index) {
-                *chunk = updated_chunk;
-            }
-        }
+        // 回写分片信息（包含运行中拆分出的新分片）
+        resume_info.chunks = scheduler.snapshot();
 
         // 重新计算总下载量
         resume_info.downloaded_total = resume_info.chunks.iter().map(|c| c.downloaded).sum();
@@ -650,13 +1016,14 @@ impl ResumeDownloader {
         Ok(())
     }
 
-    /// 静态方法下载单个分片
+    /// 静态方法下载单个分片（失败时按配置重试，暂停时归还分片后正常返回）
     #[allow(clippy::too_many_arguments)]
     async fn download_chunk_static(
-        client: &Client,
+        client: &HttpClientHandle,
         config: &ResumeDownloaderConfig,
-        url: &str,
+        sources: &MirrorPool,
         resume_info: &ResumeInfo,
+        scheduler: &ChunkScheduler,
         part_writer: &PartFileWriter,
         chunk_index: usize,
         task_id: Arc<String>,
@@ -665,41 +1032,34 @@ impl ResumeDownloader {
         bandwidth_controller: BandwidthController,
         cancel_flag: Option<Arc<AtomicBool>>,
         pause_flag: Option<Arc<AtomicBool>>,
-    ) -> Result<ChunkInfo> {
-        let mut chunk = resume_info
-            .chunks
-            .get(chunk_index)
-            .ok_or_else(|| anyhow::anyhow!("分片索引无效: {}", chunk_index))?
-            .clone();
-
-        if chunk.is_completed() {
-            return Ok(chunk);
-        }
-
-        if Self::should_interrupt(&cancel_flag, &pause_flag) {
-            return Err(anyhow::anyhow!(Self::interrupt_reason(
-                &cancel_flag,
-                &pause_flag
-            )));
-        }
-
+    ) -> Result<()> {
         let mut retry_count = 0;
 
         while retry_count <= config.max_retries {
             if Self::should_interrupt(&cancel_flag, &pause_flag) {
+                scheduler.release(chunk_index, retry_count, false);
                 return Err(anyhow::anyhow!(Self::interrupt_reason(
                     &cancel_flag,
                     &pause_flag
                 )));
             }
 
-            match Self::download_chunk_attempt(
+            let Some(lease) = sources.acquire() else {
+                scheduler.release(chunk_index, retry_count, true);
+                bail!("没有可用的下载镜像");
+            };
+            let started = Instant::now();
+            let downloaded_before = scheduler.downloaded(chunk_index);
+
+            let result = Self::download_chunk_attempt(
                 client,
                 config,
-                url,
+                sources,
+                &lease,
                 resume_info,
+                scheduler,
                 part_writer,
-                &mut chunk,
+                chunk_index,
                 &task_id,
                 total_size,
                 progress_callback.clone(),
@@ -707,57 +1067,78 @@ impl ResumeDownloader {
                 cancel_flag.clone(),
                 pause_flag.clone(),
             )
-            .await
-            {
+            .await;
+            let transferred = scheduler
+                .downloaded(chunk_index)
+                .saturating_sub(downloaded_before);
+
+            match result {
                 Ok(_) => {
-                    chunk.status = ChunkStatus::Completed;
-                    tracing::debug!("分片 {} 下载完成", chunk.index);
-                    return Ok(chunk);
+                    sources.finish(lease.index, transferred, started.elapsed(), true);
+                    scheduler.release(chunk_index, retry_count, false);
+                    tracing::debug!("分片 {} 下载完成 ({})", chunk_index, lease.url);
+                    return Ok(());
                 }
                 Err(e) => {
                     if Self::is_pause_error(&e) {
-                        if chunk.downloaded > 0 {
-                            chunk.status = ChunkStatus::Paused;
-                        } else {
-                            chunk.status = ChunkStatus::Pending;
-                        }
-                        return Ok(chunk);
+                        sources.finish(lease.index, transferred, started.elapsed(), false);
+                        scheduler.release(chunk_index, retry_count, false);
+                        return Ok(());
                     }
                     if Self::is_cancel_error(&e) {
+                        sources.finish(lease.index, transferred, started.elapsed(), false);
+                        scheduler.release(chunk_index, retry_count, false);
+                        return Err(e);
+                    }
+                    if Self::is_resource_changed_error(&e) {
+                        // 只是某个镜像的内容不一致时剔除该镜像，换源继续
+                        let dropped = sources.drop_mirror(lease.index, &e.to_string());
+                        sources.release(lease.index);
+                        if dropped {
+                            continue;
+                        }
+                        scheduler.release(chunk_index, retry_count, false);
                         return Err(e);
                     }
+                    if sources.report_failure(lease.index, &e.to_string()) == MirrorFailure::Dropped
+                    {
+                        tracing::warn!("分片 {} 换用其他镜像重试: {}", chunk_index, e);
+                        continue;
+                    }
                     retry_count += 1;
-                    chunk.retry_count = retry_count;
-                    chunk.status = ChunkStatus::Failed;
 
                     if retry_count <= config.max_retries {
                         tracing::warn!(
                             "分片 {} 下载失败，第 {} 次重试: {}",
-                            chunk.index,
+                            chunk_index,
                             retry_count,
                             e
                         );
                         tokio::time::sleep(config.retry_delay).await;
                     } else {
-                        tracing::error!("分片 {} 下载失败，已达最大重试次数: {}", chunk.index, e);
+                        scheduler.release(chunk_index, retry_count, true);
+                        tracing::error!("分片 {} 下载失败，已达最大重试次数: {}", chunk_index, e);
                         return Err(e);
                     }
                 }
             }
         }
 
-        Err(anyhow::anyhow!("分片 {} 下载失败", chunk.index))
+        scheduler.release(chunk_index, retry_count, true);
+        Err(anyhow::anyhow!("分片 {} 下载失败", chunk_index))
     }
 
     /// 尝试下载分片
     #[allow(clippy::too_many_arguments)]
     async fn download_chunk_attempt(
-        client: &Client,
+        client: &HttpClientHandle,
         _config: &ResumeDownloaderConfig,
-        url: &str,
+        sources: &MirrorPool,
+        lease: &MirrorLease,
         resume_info: &ResumeInfo,
+        scheduler: &ChunkScheduler,
         part_writer: &PartFileWriter,
-        chunk: &mut ChunkInfo,
+        chunk_index: usize,
         task_id: &Arc<String>,
         total_size: u64,
         progress_callback: Option<ResumeProgressCallback>,
@@ -765,34 +1146,42 @@ impl ResumeDownloader {
         cancel_flag: Option<Arc<AtomicBool>>,
         pause_flag: Option<Arc<AtomicBool>>,
     ) -> Result<()> {
-        chunk.status = ChunkStatus::Downloading;
-
         if Self::should_interrupt(&cancel_flag, &pause_flag) {
             bail!(Self::interrupt_reason(&cancel_flag, &pause_flag));
         }
 
+        let chunk = scheduler
+            .chunk(chunk_index)
+            .ok_or_else(|| anyhow::anyhow!("分片索引无效: {}", chunk_index))?;
+
         // 计算实际需要下载的范围
         let range_start = chunk.start + chunk.downloaded;
         let range_end = chunk.end;
 
         if range_start > range_end {
-            chunk.downloaded = chunk.size();
             return Ok(());
         }
+        scheduler.set_source(chunk_index, &lease.url);
 
         // 构建 Range 请求：
         // - 多分片下载始终使用 Range
         // - 单分片在断点续传（downloaded > 0）时也必须使用 Range，避免从头内容被追加写入
-        let mut request = client.get(url);
+        let mut request = client.get(&lease.url);
         let using_range_request = resume_info.server_capabilities.supports_ranges
-            && (resume_info.chunks.len() > 1 || chunk.downloaded > 0);
+            && (scheduler.chunk_count() > 1 || chunk.downloaded > 0);
 
+        let if_range = resume_info
+            .if_range_validator()
+            .filter(|_| using_range_request);
         if using_range_request {
             let range_header = format!("bytes={}-{}", range_start, range_end);
             request = request.header("Range", range_header);
         }
+        if let Some(validator) = if_range {
+            request = request.header("If-Range", validator);
+        }
 
-        let response = request.send().await?;
+        let response = client.send(request).await?;
 
         // 检查响应状态
         let status = response.status();
@@ -800,50 +1189,129 @@ impl ResumeDownloader {
             bail!("HTTP错误: {}", status);
         }
         if using_range_request && status != StatusCode::PARTIAL_CONTENT {
+            if if_range.is_some() && status == StatusCode::OK {
+                bail!("{}: 服务器忽略 If-Range 并返回完整内容", RESOURCE_CHANGED);
+            }
             bail!("服务器未返回分片响应(206)，无法安全续传: {}", status);
         }
+        if let (true, Some(expected), Some(actual)) = (
+            using_range_request,
+            resume_info.etag.as_deref(),
+            response
+                .headers()
+                .get(reqwest::header::ETAG)
+                .and_then(|value| value.to_str().ok()),
+        ) {
+            if expected != actual {
+                bail!("{}: ETag 从 {} 变为 {}", RESOURCE_CHANGED, expected, actual);
+            }
+        }
 
         // 在共享 .part 文件上进行偏移写入，每个分片使用独立文件句柄避免 seek 互相干扰。
         let mut file = part_writer.open_chunk_writer(range_start).await?;
 
         // 下载数据流
         let mut stream = response.bytes_stream();
+        let mut completed = false;
         while let Some(chunk_data) = stream.next().await {
             if Self::should_interrupt(&cancel_flag, &pause_flag) {
                 let _ = file.flush_and_sync().await;
                 bail!(Self::interrupt_reason(&cancel_flag, &pause_flag));
             }
             let chunk_data = chunk_data?;
-            file.write_all(&chunk_data).await?;
-            bandwidth_controller.throttle(chunk_data.len() as u64).await;
-
-            chunk.downloaded = chunk
-                .downloaded
-                .saturating_add(chunk_data.len() as u64)
-                .min(chunk.size());
-            chunk.last_update = SystemTime::now();
 
-            if let Some(callback) = &progress_callback {
-                callback(task_id.as_str(), chunk_data.len() as u64, total_size);
+            // 后半段可能已被其他连接接管，只写入仍属于本分片的部分
+            let writable = scheduler.writable(chunk_index, chunk_data.len() as u64);
+            file.write_all(&chunk_data[..writable as usize]).await?;
+            let (committed, done) = scheduler.commit(chunk_index, writable);
+            sources.record_bytes(lease.index, committed);
+            bandwidth_controller
+                .throttle_task(task_id.as_str(), &lease.url, committed)
+                .await;
+
+            if committed > 0 {
+                if let Some(callback) = &progress_callback {
+                    callback(task_id.as_str(), committed, total_size);
+                }
+            }
+            if done {
+                completed = true;
+                break;
             }
         }
 
         file.flush_and_sync().await?;
 
+        if !completed {
+            bail!("分片 {} 数据不完整，连接提前结束", chunk_index);
+        }
+
         Ok(())
     }
 
-    /// 获取内容长度
-    async fn get_content_length(&self, url: &str) -> Result<Option<u64>> {
-        let response = self.client.head(url).send().await?;
+    /// 获取内容长度及 ETag / Last-Modified
+    async fn probe_remote_resource(&self, url: &str) -> Result<RemoteResource> {
+        let response = self.client.send(self.client.head(url)).await?;
+        let success = response.status().is_success();
+        let header = |name: reqwest::header::HeaderName| {
+            response
+                .headers()
+                .get(name)
+                .and_then(|v| v.to_str().ok())
+                .map(|v| v.trim().to_string())
+                .filter(|v| !v.is_empty())
+        };
 
-        let content_length = response
-            .headers()
-            .get("content-length")
-            .and_then(|v| v.to_str().ok())
-            .and_then(|v| v.parse::<u64>().ok());
+        Ok(RemoteResource {
+            success,
+            accepts_ranges: header(reqwest::header::ACCEPT_RANGES)
+                .map(|v| v.to_ascii_lowercase().contains("bytes"))
+                .unwrap_or(false),
+            content_length: header(reqwest::header::CONTENT_LENGTH)
+                .and_then(|v| v.parse::<u64>().ok()),
+            etag: header(reqwest::header::ETAG),
+            last_modified: header(reqwest::header::LAST_MODIFIED),
+        })
+    }
+
+    /// 探测所有镜像，以第一个返回有效大小的镜像为基准，剔除大小或校验值不一致的镜像
+    async fn probe_sources(&self, sources: &MirrorPool) -> Result<RemoteResource> {
+        let available = sources.available();
+        let probes = futures::future::join_all(
+            available
+                .iter()
+                .map(|(_, url)| self.probe_remote_resource(url)),
+        )
+        .await;
+
+        let reference_pos = probes
+            .iter()
+            .position(|probe| {
+                matches!(probe, Ok(remote) if remote.success && remote.content_length.is_some())
+            })
+            .or_else(|| probes.iter().position(Result::is_ok));
+        let Some(reference) = reference_pos.and_then(|pos| probes[pos].as_ref().ok().cloned())
+        else {
+            return Err(probes
+                .into_iter()
+                .find_map(Result::err)
+                .unwrap_or_else(|| anyhow::anyhow!("没有可用的下载地址")));
+        };
 
-        Ok(content_length)
+        for (pos, ((index, _), probe)) in available.iter().zip(&probes).enumerate() {
+            if Some(pos) == reference_pos {
+                continue;
+            }
+            let mismatch = match probe {
+                Ok(remote) => reference.mirror_mismatch(remote),
+                Err(e) => Some(e.to_string()),
+            };
+            if let Some(reason) = mismatch {
+                sources.drop_mirror(*index, &reason);
+            }
+        }
+
+        Ok(reference)
     }
 
     async fn has_any_chunk_files(&self, task_id: &str) -> Result<bool> {
@@ -894,7 +1362,54 @@ impl ResumeDownloader {
         self.has_any_chunk_files(&resume_info.task_id).await
     }
 
+    /// 远端资源已变化：隔离旧的 .part，清空分片进度后从头下载，避免拼接出混合文件
+    async fn discard_stale_progress(
+        &self,
+        resume_info: &mut ResumeInfo,
+        reason: &str,
+    ) -> Result<()> {
+        tracing::warn!(
+            "远端资源已变化，放弃已下载内容并重新开始: {} ({})",
+            resume_info.original_url,
+            reason
+        );
+
+        self.quarantine_part_file(&resume_info.part_file_path(), "stale", reason)
+            .await?;
+
+        // legacy 布局下未完成的内容直接写在目标文件里
+        let final_path = PathBuf::from(&resume_info.file_path);
+        if let Ok(metadata) = tokio::fs::metadata(&final_path).await {
+            if metadata.is_file() && metadata.len() < resume_info.total_size {
+                self.quarantine_part_file(&final_path, "stale", reason)
+                    .await?;
+            }
+        }
+        for chunk in &resume_info.chunks {
+            let temp_path =
+                Self::get_chunk_temp_path(&self.config, &resume_info.task_id, chunk.index);
+            tokio::fs::remove_file(temp_path).await.ok();
+        }
+
+        resume_info.chunks.clear();
+        resume_info.downloaded_total = 0;
+        resume_info.etag = None;
+        resume_info.remote_last_modified = None;
+        resume_info.last_modified = SystemTime::now();
+        Ok(())
+    }
+
     async fn quarantine_corrupt_part_file(&self, part_path: &Path, reason: &str) -> Result<()> {
+        self.quarantine_part_file(part_path, "corrupt", reason)
+            .await
+    }
+
+    async fn quarantine_part_file(
+        &self,
+        part_path: &Path,
+        label: &str,
+        reason: &str,
+    ) -> Result<()> {
         if !part_path.exists() {
             return Ok(());
         }
@@ -907,10 +1422,11 @@ impl ResumeDownloader {
             .file_name()
             .map(|name| name.to_string_lossy().to_string())
             .unwrap_or_else(|| "download.part".to_string());
-        let quarantine_path = part_path.with_file_name(format!("{file_name}.corrupt-{stamp}"));
+        let quarantine_path = part_path.with_file_name(format!("{file_name}.{label}-{stamp}"));
 
         tracing::warn!(
-            "Quarantining corrupt part file {} to {} ({})",
+            "Quarantining {} part file {} to {} ({})",
+            label,
             part_path.display(),
             quarantine_path.display(),
             reason
@@ -1216,6 +1732,7 @@ impl ResumeDownloader {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use reqwest::Client;
     use tempfile::tempdir;
 
     #[tokio::test]
@@ -1464,6 +1981,444 @@ mod tests {
         assert_eq!(resume_info.downloaded_total, 5);
     }
 
+    async fn spawn_versioned_server(
+        body: &'static [u8],
+        etag: &'static str,
+        head_etag: &'static str,
+    ) -> String {
+        spawn_test_server(body, etag, head_etag, false).await
+    }
+
+    /// 简易 HTTP 服务：支持 HEAD、Range 与 If-Range；`head_etag` 可与实际内容不同以模拟缓存过期，
+    /// `fail_gets` 为 true 时 HEAD 正常但所有 GET 返回 503
+    async fn spawn_test_server(
+        body: &'static [u8],
+        etag: &'static str,
+        head_etag: &'static str,
+        fail_gets: bool,
+    ) -> String {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            loop {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let mut buffer = [0u8; 2048];
+                let bytes_read = socket.read(&mut buffer).await.unwrap();
+                let request = String::from_utf8_lossy(&buffer[..bytes_read]).to_string();
+                let header = |name: &str| {
+                    request.lines().find_map(|line| {
+                        let (key, value) = line.split_once(':')?;
+                        key.trim()
+                            .eq_ignore_ascii_case(name)
+                            .then(|| value.trim().to_string())
+                    })
+                };
+
+                if request.starts_with("HEAD") {
+                    let response = format!(
+                        "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nAccept-Ranges: bytes\r\nETag: {}\r\nConnection: close\r\n\r\n",
+                        body.len(),
+                        head_etag
+                    );
+                    socket.write_all(response.as_bytes()).await.unwrap();
+                    continue;
+                }
+                if fail_gets {
+                    socket
+                        .write_all(b"HTTP/1.1 503 Service Unavailable\r\nContent-Length: 0\r\nConnection: close\r\n\r\n")
+                        .await
+                        .unwrap();
+                    continue;
+                }
+
+                let range = header("range").and_then(|value| {
+                    let (start, end) = value.strip_prefix("bytes=")?.split_once('-')?;
+                    Some((start.parse::<usize>().ok()?, end.parse::<usize>().ok()?))
+                });
+                let if_range_matches = header("if-range").is_none_or(|value| value == etag);
+                let (status, payload) = match range {
+                    Some((start, end)) if if_range_matches => (
+                        "206 Partial Content",
+                        &body[start..=end.min(body.len() - 1)],
+                    ),
+                    _ => ("200 OK", body),
+                };
+                let response = format!(
+                    "HTTP/1.1 {}\r\nContent-Length: {}\r\nETag: {}\r\nConnection: close\r\n\r\n",
+                    status,
+                    payload.len(),
+                    etag
+                );
+                socket.write_all(response.as_bytes()).await.unwrap();
+                socket.write_all(payload).await.unwrap();
+                socket.shutdown().await.ok();
+            }
+        });
+        format!("http://{}/video.mp4", addr)
+    }
+
+    async fn seed_stale_progress(
+        downloader: &ResumeDownloader,
+        task_id: &str,
+        final_path: &Path,
+        url: &str,
+    ) {
+        let writer = PartFileWriter::new(final_path);
+        writer.prepare(None).await.unwrap();
+        let mut chunk_writer = writer.open_chunk_writer(0).await.unwrap();
+        chunk_writer.write_all(b"OLD-").await.unwrap();
+        chunk_writer.flush_and_sync().await.unwrap();
+
+        let mut resume_info = ResumeInfo::new(
+            task_id.to_string(),
+            final_path.to_string_lossy().to_string(),
+            url.to_string(),
+            8,
+        );
+        resume_info.server_capabilities.supports_ranges = true;
+        resume_info.chunks = vec![ChunkInfo::new(0, 0, 7)];
+        resume_info.update_chunk_progress(0, 4);
+        resume_info.etag = Some("\"v1\"".to_string());
+        downloader.save_resume_info(&resume_info).await.unwrap();
+    }
+
+    fn has_stale_file(dir: &Path) -> bool {
+        std::fs::read_dir(dir)
+            .unwrap()
+            .filter_map(|entry| entry.ok())
+            .any(|entry| entry.file_name().to_string_lossy().contains(".part.stale-"))
+    }
+
+    #[tokio::test]
+    async fn test_changed_etag_quarantines_stale_part_and_restarts() {
+        let temp_dir = tempdir().unwrap();
+        let config = ResumeDownloaderConfig {
+            resume_info_dir: temp_dir.path().join("resume"),
+            ..ResumeDownloaderConfig::default()
+        };
+        let downloader =
+            ResumeDownloader::new(config, Client::new(), BandwidthController::new()).unwrap();
+        let url = spawn_versioned_server(b"NEWDATA!", "\"v2\"", "\"v2\"").await;
+        let final_path = temp_dir.path().join("video.mp4");
+        seed_stale_progress(&downloader, "etag-task", &final_path, &url).await;
+
+        let resume_info = downloader
+            .download_with_resume("etag-task", &url, &final_path, Some(8), None, None, None)
+            .await
+            .unwrap();
+
+        assert_eq!(tokio::fs::read(&final_path).await.unwrap(), b"NEWDATA!");
+        assert_eq!(resume_info.etag.as_deref(), Some("\"v2\""));
+        assert!(has_stale_file(temp_dir.path()));
+    }
+
+    #[tokio::test]
+    async fn test_if_range_mismatch_restarts_instead_of_splicing() {
+        let temp_dir = tempdir().unwrap();
+        let config = ResumeDownloaderConfig {
+            resume_info_dir: temp_dir.path().join("resume"),
+            retry_delay: Duration::from_millis(10),
+            ..ResumeDownloaderConfig::default()
+        };
+        let downloader =
+            ResumeDownloader::new(config, Client::new(), BandwidthController::new()).unwrap();
+        // HEAD 仍返回旧 ETag，只有带 If-Range 的分片请求能发现内容已替换
+        let url = spawn_versioned_server(b"NEWDATA!", "\"v2\"", "\"v1\"").await;
+        let final_path = temp_dir.path().join("video.mp4");
+        seed_stale_progress(&downloader, "if-range-task", &final_path, &url).await;
+
+        downloader
+            .download_with_resume(
+                "if-range-task",
+                &url,
+                &final_path,
+                Some(8),
+                None,
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(tokio::fs::read(&final_path).await.unwrap(), b"NEWDATA!");
+        assert!(has_stale_file(temp_dir.path()));
+    }
+
+    #[test]
+    fn test_legacy_resume_info_without_validators_still_loads() {
+        let mut value = serde_json::to_value(ResumeInfo::new(
+            "legacy".to_string(),
+            "/tmp/legacy.mp4".to_string(),
+            "http://example.com/legacy.mp4".to_string(),
+            16,
+        ))
+        .unwrap();
+        let object = value.as_object_mut().unwrap();
+        object.remove("etag");
+        object.remove("remote_last_modified");
+        object.insert("schema_version".to_string(), serde_json::json!(2));
+
+        let mut info: ResumeInfo = serde_json::from_value(value).unwrap();
+        info.ensure_current_schema();
+        assert_eq!(info.schema_version, CURRENT_RESUME_SCHEMA_VERSION);
+        assert!(info.if_range_validator().is_none());
+
+        info.etag = Some("W/\"weak\"".to_string());
+        info.remote_last_modified = Some("Wed, 21 Oct 2015 07:28:00 GMT".to_string());
+        assert_eq!(
+            info.if_range_validator(),
+            Some("Wed, 21 Oct 2015 07:28:00 GMT")
+        );
+    }
+
+    #[test]
+    fn test_probe_without_validators_keeps_stored_ones() {
+        let mut info = ResumeInfo::new(
+            "keep".to_string(),
+            "/tmp/keep.mp4".to_string(),
+            "http://example.com/keep.mp4".to_string(),
+            16,
+        );
+        info.etag = Some("\"v1\"".to_string());
+        info.remote_last_modified = Some("Wed, 21 Oct 2015 07:28:00 GMT".to_string());
+
+        info.record_validators(&RemoteResource {
+            success: true,
+            accepts_ranges: true,
+            content_length: Some(16),
+            etag: None,
+            last_modified: None,
+        });
+        assert_eq!(info.if_range_validator(), Some("\"v1\""));
+
+        info.record_validators(&RemoteResource {
+            success: true,
+            accepts_ranges: true,
+            content_length: Some(16),
+            etag: Some("\"v2\"".to_string()),
+            last_modified: None,
+        });
+        assert_eq!(info.etag.as_deref(), Some("\"v2\""));
+        assert_eq!(
+            info.remote_last_modified.as_deref(),
+            Some("Wed, 21 Oct 2015 07:28:00 GMT")
+        );
+    }
+
+    #[test]
+    fn test_scheduler_splits_largest_active_chunk() {
+        let scheduler = ChunkScheduler::new(
+            vec![ChunkInfo::new(0, 0, 99), ChunkInfo::new(1, 100, 199)],
+            true,
+            10,
+        )
+        .unwrap();
+        assert_eq!(scheduler.claim(), Some(0));
+        assert_eq!(scheduler.claim(), Some(1));
+        assert_eq!(scheduler.commit(0, 20), (20, false));
+        assert_eq!(scheduler.commit(1, 60), (60, false));
+
+        // 分片 0 剩余 80 字节最多，从剩余部分的中点拆开
+        assert_eq!(scheduler.claim(), Some(2));
+        let stolen = scheduler.chunk(2).unwrap();
+        assert_eq!((stolen.start, stolen.end), (60, 99));
+        assert_eq!(scheduler.chunk(0).unwrap().end, 59);
+
+        // 原连接只能写到新的结束位置
+        assert_eq!(scheduler.writable(0, 100), 40);
+        assert_eq!(scheduler.commit(0, 100), (40, true));
+        scheduler.release(0, 0, false);
+        scheduler.release(2, 0, false);
+
+        let chunks = scheduler.snapshot();
+        assert_eq!(chunks.len(), 3);
+        assert_eq!(chunks[0].status, ChunkStatus::Completed);
+        assert_eq!(chunks[2].status, ChunkStatus::Pending);
+        assert_eq!(chunks.iter().map(ChunkInfo::size).sum::<u64>(), 200);
+    }
+
+    #[test]
+    fn test_scheduler_looks_up_chunks_by_index_not_position() {
+        // 旧版续传信息：分片顺序被改写，索引不等于位置
+        let scheduler = ChunkScheduler::new(
+            vec![ChunkInfo::new(3, 100, 199), ChunkInfo::new(1, 0, 99)],
+            true,
+            10,
+        )
+        .unwrap();
+        assert_eq!(scheduler.claim(), Some(3));
+        assert_eq!(scheduler.claim(), Some(1));
+        assert_eq!(scheduler.commit(1, 100), (100, true));
+        assert_eq!(scheduler.chunk(1).unwrap().downloaded, 100);
+        assert_eq!(scheduler.chunk(3).unwrap().downloaded, 0);
+        scheduler.release(1, 0, false);
+
+        // 拆分出的新分片不能与已有索引冲突
+        assert_eq!(scheduler.claim(), Some(4));
+        assert_eq!(scheduler.chunk(4).unwrap().start, 150);
+
+        assert!(ChunkScheduler::new(
+            vec![ChunkInfo::new(0, 0, 99), ChunkInfo::new(0, 100, 199)],
+            true,
+            10,
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_scheduler_does_not_split_small_or_single_connection_chunks() {
+        let scheduler = ChunkScheduler::new(vec![ChunkInfo::new(0, 0, 99)], false, 10).unwrap();
+        assert_eq!(scheduler.claim(), Some(0));
+        assert_eq!(scheduler.claim(), None);
+
+        let scheduler = ChunkScheduler::new(
+            vec![ChunkInfo::new(0, 0, 29), ChunkInfo::new(1, 30, 59)],
+            true,
+            10,
+        )
+        .unwrap();
+        assert_eq!(scheduler.claim(), Some(0));
+        assert_eq!(scheduler.claim(), Some(1));
+        scheduler.commit(0, 15);
+        scheduler.commit(1, 15);
+        // 剩余 15 字节不足两倍最小拆分大小
+        assert_eq!(scheduler.claim(), None);
+
+        scheduler.abort();
+        scheduler.release(0, 0, false);
+        assert_eq!(scheduler.claim(), None);
+    }
+
+    #[tokio::test]
+    async fn test_download_with_work_stealing_produces_complete_file() {
+        const BODY: &[u8] =
+            b"0123456789abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ-_0123456789abcdef";
+        let temp_dir = tempdir().unwrap();
+        let config = ResumeDownloaderConfig {
+            resume_info_dir: temp_dir.path().join("resume"),
+            chunk_size: 16,
+            large_file_threshold: 0,
+            max_concurrent_chunks: 4,
+            min_split_size: 2,
+            ..ResumeDownloaderConfig::default()
+        };
+        let downloader =
+            ResumeDownloader::new(config, Client::new(), BandwidthController::new()).unwrap();
+        let url = spawn_versioned_server(BODY, "\"v1\"", "\"v1\"").await;
+        let final_path = temp_dir.path().join("stolen.mp4");
+
+        let resume_info = downloader
+            .download_with_resume("steal-task", &url, &final_path, None, None, None, None)
+            .await
+            .unwrap();
+
+        assert_eq!(tokio::fs::read(&final_path).await.unwrap(), BODY);
+        // 拆分后的分片首尾相接，完整覆盖整个文件
+        let mut ranges: Vec<_> = resume_info
+            .chunks
+            .iter()
+            .map(|chunk| (chunk.start, chunk.end))
+            .collect();
+        ranges.sort();
+        assert_eq!(ranges.first().unwrap().0, 0);
+        assert_eq!(ranges.last().unwrap().1, BODY.len() as u64 - 1);
+        assert!(ranges.windows(2).all(|pair| pair[0].1 + 1 == pair[1].0));
+    }
+
+    #[tokio::test]
+    async fn test_failing_mirror_is_dropped_without_failing_download() {
+        const BODY: &[u8] =
+            b"mirror-payload-0123456789abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ!";
+        let temp_dir = tempdir().unwrap();
+        let config = ResumeDownloaderConfig {
+            resume_info_dir: temp_dir.path().join("resume"),
+            chunk_size: 16,
+            large_file_threshold: 0,
+            max_concurrent_chunks: 2,
+            retry_delay: Duration::from_millis(10),
+            ..ResumeDownloaderConfig::default()
+        };
+        let downloader =
+            ResumeDownloader::new(config, Client::new(), BandwidthController::new()).unwrap();
+
+        let flaky = spawn_test_server(BODY, "\"v1\"", "\"v1\"", true).await;
+        let healthy = spawn_versioned_server(BODY, "\"v1\"", "\"v1\"").await;
+        let other_version = spawn_versioned_server(b"short", "\"v9\"", "\"v9\"").await;
+        let sources = Arc::new(MirrorPool::new(vec![
+            flaky.clone(),
+            healthy.clone(),
+            other_version.clone(),
+        ]));
+        let final_path = temp_dir.path().join("mirrored.mp4");
+
+        let resume_info = downloader
+            .download_from_mirrors(
+                "mirror-task",
+                "mirror-task",
+                Arc::clone(&sources),
+                &final_path,
+                None,
+                None,
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(tokio::fs::read(&final_path).await.unwrap(), BODY);
+        let stats = sources.stats();
+        // 大小不一致的镜像在探测时剔除，GET 持续失败的镜像在下载中剔除
+        assert!(stats[2].disabled);
+        assert!(stats[0].disabled);
+        assert_eq!(stats[0].downloaded_bytes, 0);
+        assert_eq!(stats[1].downloaded_bytes, BODY.len() as u64);
+        assert!(resume_info
+            .chunks
+            .iter()
+            .all(|chunk| chunk.source.as_deref() == Some(healthy.as_str())));
+    }
+
+    #[tokio::test]
+    async fn test_per_task_cap_throttles_chunked_download_by_task_id() {
+        let body: &'static [u8] = Box::leak(vec![b'x'; 64 * 1024].into_boxed_slice());
+        let temp_dir = tempdir().unwrap();
+        let config = ResumeDownloaderConfig {
+            resume_info_dir: temp_dir.path().join("resume"),
+            chunk_size: 16 * 1024,
+            large_file_threshold: 0,
+            max_concurrent_chunks: 2,
+            ..ResumeDownloaderConfig::default()
+        };
+        let controller = BandwidthController::new();
+        // 上限登记在任务 ID 上，断点文件用的是另一个 key
+        controller.register_task("capped-task", 128, Some(64 * 1024));
+        let downloader = ResumeDownloader::new(config, Client::new(), controller).unwrap();
+        let url = spawn_versioned_server(body, "\"v1\"", "\"v1\"").await;
+        let final_path = temp_dir.path().join("capped.mp4");
+
+        let started = Instant::now();
+        downloader
+            .download_from_mirrors(
+                "resume-key-sha",
+                "capped-task",
+                Arc::new(MirrorPool::single(&url)),
+                &final_path,
+                None,
+                None,
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+        let elapsed = started.elapsed();
+
+        assert_eq!(tokio::fs::read(&final_path).await.unwrap(), body);
+        // 64 KB/s 上限下 64KB 扣除突发额度后仍需约 0.8 秒
+        assert!(elapsed >= Duration::from_millis(500), "{:?}", elapsed);
+    }
+
     #[tokio::test]
     async fn test_server_capabilities() {
         let temp_dir = tempdir().unwrap();
@@ -4,13 +4,16 @@
 //! the concurrency slots full without blocking UI threads.
 
 use std::sync::Arc;
+use std::time::Duration;
 
 use tokio::runtime::Handle;
 use tokio::sync::{mpsc, oneshot, RwLock};
 use tracing::{debug, instrument};
 
+use crate::core::bandwidth_schedule::RateLimitStatus;
+use crate::core::hls_variant::VariantPolicy;
 use crate::core::manager::{DownloadEvent, DownloadManager};
-use crate::core::models::{AppError, AppResult, DownloadConfig, VideoTask};
+use crate::core::models::{AppError, AppResult, AudioExtraction, DownloadConfig, VideoTask};
 
 /// Commands understood by the runtime router.
 #[derive(Debug)]
@@ -23,6 +26,20 @@ pub enum RuntimeCommand {
         updates: Vec<(String, String)>,
         respond_to: oneshot::Sender<AppResult<Vec<VideoTask>>>,
     },
+    SetTaskVariantPolicy {
+        task_id: String,
+        policy: Option<VariantPolicy>,
+        respond_to: oneshot::Sender<AppResult<VideoTask>>,
+    },
+    SetTaskAudioExtraction {
+        task_id: String,
+        extraction: Option<AudioExtraction>,
+        respond_to: oneshot::Sender<AppResult<VideoTask>>,
+    },
+    StopLiveRecording {
+        task_id: String,
+        respond_to: oneshot::Sender<AppResult<bool>>,
+    },
     RemoveTasks {
         task_ids: Vec<String>,
         respond_to: oneshot::Sender<AppResult<usize>>,
@@ -34,13 +51,26 @@ pub enum RuntimeCommand {
         respond_to: oneshot::Sender<AppResult<usize>>,
     },
     UpdateConfig {
-        config: DownloadConfig,
+        config: Box<DownloadConfig>,
         respond_to: oneshot::Sender<AppResult<()>>,
     },
     SetRateLimit {
         bytes_per_second: Option<u64>,
+        /// None = manual limit stays until cleared
+        expires_in: Option<Duration>,
         respond_to: oneshot::Sender<AppResult<Option<u64>>>,
     },
+    SetTaskRateLimit {
+        task_id: String,
+        bytes_per_second: Option<u64>,
+        respond_to: oneshot::Sender<AppResult<VideoTask>>,
+    },
+    ClearRateLimitOverride {
+        respond_to: oneshot::Sender<AppResult<RateLimitStatus>>,
+    },
+    RefreshRateLimit {
+        respond_to: oneshot::Sender<AppResult<RateLimitStatus>>,
+    },
     Start {
         task_id: String,
         respond_to: oneshot::Sender<AppResult<()>>,
@@ -112,6 +142,40 @@ impl DownloadRuntimeHandle {
         .await
     }
 
+    pub async fn set_task_variant_policy(
+        &self,
+        task_id: String,
+        policy: Option<VariantPolicy>,
+    ) -> AppResult<VideoTask> {
+        self.send_command(|tx| RuntimeCommand::SetTaskVariantPolicy {
+            task_id,
+            policy,
+            respond_to: tx,
+        })
+        .await
+    }
+
+    pub async fn set_task_audio_extraction(
+        &self,
+        task_id: String,
+        extraction: Option<AudioExtraction>,
+    ) -> AppResult<VideoTask> {
+        self.send_command(|tx| RuntimeCommand::SetTaskAudioExtraction {
+            task_id,
+            extraction,
+            respond_to: tx,
+        })
+        .await
+    }
+
+    pub async fn stop_live_recording(&self, task_id: String) -> AppResult<bool> {
+        self.send_command(|tx| RuntimeCommand::StopLiveRecording {
+            task_id,
+            respond_to: tx,
+        })
+        .await
+    }
+
     pub async fn remove_tasks(&self, task_ids: Vec<String>) -> AppResult<usize> {
         self.send_command(|tx| RuntimeCommand::RemoveTasks {
             task_ids,
@@ -132,20 +196,53 @@ impl DownloadRuntimeHandle {
 
     pub async fn update_config(&self, config: DownloadConfig) -> AppResult<()> {
         self.send_command(|tx| RuntimeCommand::UpdateConfig {
-            config,
+            config: Box::new(config),
             respond_to: tx,
         })
         .await
     }
 
     pub async fn set_rate_limit(&self, bytes_per_second: Option<u64>) -> AppResult<Option<u64>> {
+        self.set_rate_limit_override(bytes_per_second, None).await
+    }
+
+    /// Manual limit that takes precedence over the bandwidth schedule until it expires
+    pub async fn set_rate_limit_override(
+        &self,
+        bytes_per_second: Option<u64>,
+        expires_in: Option<Duration>,
+    ) -> AppResult<Option<u64>> {
         self.send_command(|tx| RuntimeCommand::SetRateLimit {
+            bytes_per_second,
+            expires_in,
+            respond_to: tx,
+        })
+        .await
+    }
+
+    pub async fn set_task_rate_limit(
+        &self,
+        task_id: String,
+        bytes_per_second: Option<u64>,
+    ) -> AppResult<VideoTask> {
+        self.send_command(|tx| RuntimeCommand::SetTaskRateLimit {
+            task_id,
             bytes_per_second,
             respond_to: tx,
         })
         .await
     }
 
+    pub async fn clear_rate_limit_override(&self) -> AppResult<RateLimitStatus> {
+        self.send_command(|tx| RuntimeCommand::ClearRateLimitOverride { respond_to: tx })
+            .await
+    }
+
+    pub async fn refresh_rate_limit(&self) -> AppResult<RateLimitStatus> {
+        self.send_command(|tx| RuntimeCommand::RefreshRateLimit { respond_to: tx })
+            .await
+    }
+
     pub async fn start_task(&self, task_id: String) -> AppResult<()> {
         self.send_command(|tx| RuntimeCommand::Start {
             task_id,
@@ -224,6 +321,24 @@ pub fn spawn_router_loop(
     tracing::info!("[RUNTIME] Router loop spawned successfully");
 }
 
+/// How often the bandwidth schedule is re-evaluated
+const BANDWIDTH_SCHEDULE_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Periodically re-apply the bandwidth schedule (and expire manual overrides) through the router.
+pub fn spawn_bandwidth_schedule_loop(runtime: DownloadRuntimeHandle) {
+    tauri::async_runtime::spawn(async move {
+        let mut interval = tokio::time::interval(BANDWIDTH_SCHEDULE_INTERVAL);
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        loop {
+            interval.tick().await;
+            if let Err(err) = runtime.refresh_rate_limit().await {
+                debug!("[RUNTIME] Bandwidth schedule loop stopped: {}", err);
+                break;
+            }
+        }
+    });
+}
+
 /// Legacy function for backwards compatibility - creates and immediately spawns.
 /// Prefer using create_download_runtime_handle + spawn_router_loop for better control.
 pub fn spawn_download_runtime(manager: Arc<RwLock<DownloadManager>>) -> DownloadRuntimeHandle {
@@ -285,6 +400,32 @@ async fn handle_command(manager: &Arc<RwLock<DownloadManager>>, command: Runtime
             let result = DownloadManager::runtime_update_task_output_paths(manager, updates).await;
             let _ = respond_to.send(result);
         }
+        RuntimeCommand::SetTaskVariantPolicy {
+            task_id,
+            policy,
+            respond_to,
+        } => {
+            let result =
+                DownloadManager::runtime_set_task_variant_policy(manager, &task_id, policy).await;
+            let _ = respond_to.send(result);
+        }
+        RuntimeCommand::SetTaskAudioExtraction {
+            task_id,
+            extraction,
+            respond_to,
+        } => {
+            let result =
+                DownloadManager::runtime_set_task_audio_extraction(manager, &task_id, extraction)
+                    .await;
+            let _ = respond_to.send(result);
+        }
+        RuntimeCommand::StopLiveRecording {
+            task_id,
+            respond_to,
+        } => {
+            let result = DownloadManager::runtime_stop_live_recording(manager, &task_id).await;
+            let _ = respond_to.send(result);
+        }
         RuntimeCommand::RemoveTasks {
             task_ids,
             respond_to,
@@ -301,14 +442,35 @@ async fn handle_command(manager: &Arc<RwLock<DownloadManager>>, command: Runtime
             let _ = respond_to.send(result);
         }
         RuntimeCommand::UpdateConfig { config, respond_to } => {
-            let result = DownloadManager::runtime_update_config(manager, config).await;
+            let result = DownloadManager::runtime_update_config(manager, *config).await;
             let _ = respond_to.send(result);
         }
         RuntimeCommand::SetRateLimit {
+            bytes_per_second,
+            expires_in,
+            respond_to,
+        } => {
+            let result =
+                DownloadManager::runtime_set_rate_limit(manager, bytes_per_second, expires_in)
+                    .await;
+            let _ = respond_to.send(result);
+        }
+        RuntimeCommand::SetTaskRateLimit {
+            task_id,
             bytes_per_second,
             respond_to,
         } => {
-            let result = DownloadManager::runtime_set_rate_limit(manager, bytes_per_second).await;
+            let result =
+                DownloadManager::runtime_set_task_rate_limit(manager, &task_id, bytes_per_second)
+                    .await;
+            let _ = respond_to.send(result);
+        }
+        RuntimeCommand::ClearRateLimitOverride { respond_to } => {
+            let result = DownloadManager::runtime_clear_rate_limit_override(manager).await;
+            let _ = respond_to.send(result);
+        }
+        RuntimeCommand::RefreshRateLimit { respond_to } => {
+            let result = DownloadManager::runtime_refresh_rate_limit(manager).await;
             let _ = respond_to.send(result);
         }
         RuntimeCommand::Start {
@@ -379,6 +541,15 @@ mod tests {
         VideoTask {
             id: id.to_string(),
             url: url.to_string(),
+            mirrors: Vec::new(),
+            rate_limit: None,
+            variant_policy: None,
+            ytdlp_format: None,
+            hls_variant: None,
+            recording: None,
+            segment_gaps: Vec::new(),
+            sidecar_files: Vec::new(),
+            audio_extraction: None,
             title: title.to_string(),
             output_path: output_path.to_string(),
             resolved_path: None,
@@ -487,6 +658,23 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn runtime_clear_rate_limit_override_falls_back_to_schedule() {
+        let (runtime, manager, _temp_dir) = create_runtime_handle();
+
+        runtime
+            .set_rate_limit_override(Some(256 * 1024), Some(Duration::from_secs(600)))
+            .await
+            .expect("set override");
+        let status = runtime
+            .clear_rate_limit_override()
+            .await
+            .expect("clear override");
+
+        assert_eq!(status.bytes_per_second, None);
+        assert_eq!(manager.read().await.get_rate_limit().await, None);
+    }
+
     #[tokio::test]
     async fn runtime_remove_tasks_routes_removal_through_runtime() {
         let (runtime, manager, _temp_dir) = create_runtime_handle();
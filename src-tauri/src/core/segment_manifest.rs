@@ -0,0 +1,229 @@
+//! M3U8 分片清单
+//!
+//! 每个 M3U8 任务在临时目录下保存 `<task_id>.manifest.json`，记录已按顺序写入
+//! 输出 `.part` 的连续前缀：提交的片段数、字节数、SHA-256 以及其中被跳过的缺口。
+//! 暂停、失败重试或应用崩溃后再次开始时，前缀校验通过就截断到该位置续写，
+//! 只下载之后的片段。
+
+#![deny(clippy::unwrap_used, clippy::expect_used)]
+
+use crate::core::models::SegmentGap;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+use tokio::io::AsyncReadExt;
+
+const CURRENT_MANIFEST_SCHEMA_VERSION: u32 = 2;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SegmentManifest {
+    pub schema_version: u32,
+    pub task_id: String,
+    /// 实际下载的媒体播放列表（master playlist 时为选中档位的地址）
+    pub playlist_url: String,
+    /// 正在写入的输出文件（`.part`）
+    #[serde(default)]
+    pub part_path: Option<PathBuf>,
+    /// 已按顺序提交的片段数（含被跳过的缺口）
+    #[serde(default)]
+    pub committed_segments: usize,
+    /// `.part` 中已提交的字节数
+    #[serde(default)]
+    pub committed_bytes: u64,
+    /// 已提交前缀的 SHA-256（小写十六进制）
+    #[serde(default)]
+    pub committed_sha256: String,
+    /// 前缀末尾生效的 fMP4 初始化段
+    #[serde(default)]
+    pub current_init: Option<usize>,
+    /// 前缀中被跳过的片段
+    #[serde(default)]
+    pub gaps: Vec<SegmentGap>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl SegmentManifest {
+    pub fn new(task_id: &str, playlist_url: &str) -> Self {
+        Self {
+            schema_version: CURRENT_MANIFEST_SCHEMA_VERSION,
+            task_id: task_id.to_string(),
+            playlist_url: playlist_url.to_string(),
+            part_path: None,
+            committed_segments: 0,
+            committed_bytes: 0,
+            committed_sha256: String::new(),
+            current_init: None,
+            gaps: Vec::new(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    pub fn path(temp_root: &Path, task_id: &str) -> PathBuf {
+        temp_root.join(format!("{}.manifest.json", task_id))
+    }
+
+    /// 读取清单，不检查播放列表地址（取消已暂停的任务时用来找到 `.part`）
+    pub async fn load(temp_root: &Path, task_id: &str) -> Option<Self> {
+        let content = tokio::fs::read_to_string(Self::path(temp_root, task_id))
+            .await
+            .ok()?;
+        serde_json::from_str(&content).ok()
+    }
+
+    /// 读取清单；不存在、无法解析、版本不符或播放列表地址已变化时返回一份空清单
+    pub async fn load_or_new(temp_root: &Path, task_id: &str, playlist_url: &str) -> Self {
+        let path = Self::path(temp_root, task_id);
+        let content = match tokio::fs::read_to_string(&path).await {
+            Ok(content) => content,
+            Err(_) => return Self::new(task_id, playlist_url),
+        };
+
+        match serde_json::from_str::<Self>(&content) {
+            Ok(manifest)
+                if manifest.playlist_url == playlist_url
+                    && manifest.schema_version == CURRENT_MANIFEST_SCHEMA_VERSION =>
+            {
+                manifest
+            }
+            Ok(_) => {
+                tracing::info!(
+                    "播放列表或清单格式已变化，丢弃旧的分片清单: {}",
+                    path.display()
+                );
+                Self::new(task_id, playlist_url)
+            }
+            Err(e) => {
+                tracing::warn!("分片清单损坏，重新下载全部片段: {} - {}", path.display(), e);
+                Self::new(task_id, playlist_url)
+            }
+        }
+    }
+
+    /// 先写临时文件再替换，避免崩溃时留下半截 JSON
+    pub async fn save(&self, temp_root: &Path) -> Result<()> {
+        tokio::fs::create_dir_all(temp_root).await?;
+        let path = Self::path(temp_root, &self.task_id);
+        let staging = path.with_extension("json.tmp");
+        let content = serde_json::to_string(self).context("序列化分片清单失败")?;
+        tokio::fs::write(&staging, content).await?;
+        tokio::fs::rename(&staging, &path).await?;
+        Ok(())
+    }
+
+    pub async fn remove(temp_root: &Path, task_id: &str) -> Result<()> {
+        let path = Self::path(temp_root, task_id);
+        if path.exists() {
+            tokio::fs::remove_file(path).await?;
+        }
+        Ok(())
+    }
+
+    /// 记录新的已提交前缀
+    pub fn commit(
+        &mut self,
+        segments: usize,
+        bytes: u64,
+        sha256: String,
+        current_init: Option<usize>,
+        gaps: &[SegmentGap],
+    ) {
+        self.committed_segments = segments;
+        self.committed_bytes = bytes;
+        self.committed_sha256 = sha256;
+        self.current_init = current_init;
+        self.gaps = gaps.to_vec();
+        self.updated_at = Utc::now();
+    }
+
+    /// 清空已提交前缀，从头开始写
+    pub fn reset(&mut self) {
+        self.commit(0, 0, String::new(), None, &[]);
+    }
+
+    /// 校验 `part_path` 的已提交前缀：文件不短于前缀且前缀 SHA-256 一致时返回
+    /// 续写用的哈希状态；否则清空前缀并返回 None
+    pub async fn verify_prefix(&mut self, part_path: &Path) -> Option<Sha256> {
+        if self.committed_segments == 0 || self.part_path.as_deref() != Some(part_path) {
+            self.reset();
+            return None;
+        }
+        match Self::hash_prefix(part_path, self.committed_bytes).await {
+            Ok(hasher) if hex::encode(hasher.clone().finalize()) == self.committed_sha256 => {
+                Some(hasher)
+            }
+            result => {
+                tracing::debug!(
+                    "已提交前缀校验失败，从头写入: {} ({:?})",
+                    part_path.display(),
+                    result.err()
+                );
+                self.reset();
+                None
+            }
+        }
+    }
+
+    async fn hash_prefix(path: &Path, len: u64) -> Result<Sha256> {
+        let file = tokio::fs::File::open(path).await?;
+        if file.metadata().await?.len() < len {
+            anyhow::bail!("文件短于已提交前缀");
+        }
+        let mut reader = file.take(len);
+        let mut hasher = Sha256::new();
+        let mut buffer = vec![0u8; 256 * 1024];
+        loop {
+            let read = reader.read(&mut buffer).await?;
+            if read == 0 {
+                break;
+            }
+            hasher.update(&buffer[..read]);
+        }
+        Ok(hasher)
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::expect_used)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[tokio::test]
+    async fn manifest_round_trips_and_verifies_committed_prefix() {
+        let temp_dir = tempdir().unwrap();
+        let root = temp_dir.path();
+        let part = root.join("lesson.ts.part");
+        tokio::fs::write(&part, b"[0][1]partial").await.unwrap();
+
+        let mut manifest = SegmentManifest::new("task", "https://example.com/a.m3u8");
+        manifest.part_path = Some(part.clone());
+        manifest.commit(2, 6, hex::encode(Sha256::digest(b"[0][1]")), None, &[]);
+        manifest.save(root).await.unwrap();
+
+        let mut loaded =
+            SegmentManifest::load_or_new(root, "task", "https://example.com/a.m3u8").await;
+        assert_eq!(loaded.committed_segments, 2);
+        let hasher = loaded.verify_prefix(&part).await.unwrap();
+        assert_eq!(
+            hex::encode(hasher.finalize()),
+            hex::encode(Sha256::digest(b"[0][1]"))
+        );
+
+        // 前缀内容被改动时校验失败，前缀被清空
+        tokio::fs::write(&part, b"[0][X]partial").await.unwrap();
+        assert!(loaded.verify_prefix(&part).await.is_none());
+        assert_eq!(loaded.committed_segments, 0);
+        assert_eq!(loaded.committed_bytes, 0);
+
+        // 换了播放列表地址的清单不复用，但仍能按任务找到 .part
+        let other = SegmentManifest::load_or_new(root, "task", "https://example.com/b.m3u8").await;
+        assert_eq!(other.committed_segments, 0);
+        let raw = SegmentManifest::load(root, "task").await.unwrap();
+        assert_eq!(raw.part_path.as_deref(), Some(part.as_path()));
+
+        SegmentManifest::remove(root, "task").await.unwrap();
+        assert!(!SegmentManifest::path(root, "task").exists());
+    }
+}
@@ -10,6 +10,7 @@ use serde::{Deserialize, Serialize};
 use std::cmp::Ordering;
 use std::collections::HashMap;
 use std::path::{Path, PathBuf};
+use std::str::FromStr;
 use std::sync::Arc;
 use std::time::Instant;
 use tokio::fs;
@@ -83,17 +84,57 @@ impl Default for YoutubeDownloaderConfig {
 }
 
 /// Video quality options for YouTube downloads
-#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum VideoQuality {
     Low,
     Medium,
     High,
     Best,
     Worst,
+    /// Best stream no taller than this many pixels
+    Height(u32),
+}
+
+impl VideoQuality {
+    /// Height cap in pixels (None for best/worst)
+    pub fn max_height(self) -> Option<u32> {
+        match self {
+            VideoQuality::Low => Some(480),
+            VideoQuality::Medium => Some(720),
+            VideoQuality::High => Some(1080),
+            VideoQuality::Height(height) => Some(height),
+            VideoQuality::Best | VideoQuality::Worst => None,
+        }
+    }
+}
+
+/// Parses config values such as "best", "high" or "720p"
+impl FromStr for VideoQuality {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let value = value.trim().to_ascii_lowercase();
+        match value.as_str() {
+            "low" => Ok(VideoQuality::Low),
+            "medium" => Ok(VideoQuality::Medium),
+            "high" => Ok(VideoQuality::High),
+            "best" => Ok(VideoQuality::Best),
+            "worst" => Ok(VideoQuality::Worst),
+            _ => value
+                .trim_end_matches('p')
+                .parse::<u32>()
+                .ok()
+                .filter(|height| *height > 0)
+                .map(VideoQuality::Height)
+                .ok_or_else(|| anyhow::anyhow!("Invalid video quality: {}", value)),
+        }
+    }
 }
 
 /// Video codec preferences for YouTube downloads
-#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum VideoCodecPreference {
     AVC1, // H.264
     VP9,  // VP9
@@ -101,8 +142,23 @@ pub enum VideoCodecPreference {
     Any,
 }
 
+impl FromStr for VideoCodecPreference {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "avc1" | "avc" | "h264" => Ok(VideoCodecPreference::AVC1),
+            "vp9" | "vp09" => Ok(VideoCodecPreference::VP9),
+            "av01" | "av1" => Ok(VideoCodecPreference::AV01),
+            "any" => Ok(VideoCodecPreference::Any),
+            other => Err(anyhow::anyhow!("Invalid video codec: {}", other)),
+        }
+    }
+}
+
 /// Audio quality options for YouTube downloads
-#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum AudioQuality {
     Low,
     Medium,
@@ -111,8 +167,24 @@ pub enum AudioQuality {
     Worst,
 }
 
+impl FromStr for AudioQuality {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "low" => Ok(AudioQuality::Low),
+            "medium" => Ok(AudioQuality::Medium),
+            "high" => Ok(AudioQuality::High),
+            "best" => Ok(AudioQuality::Best),
+            "worst" => Ok(AudioQuality::Worst),
+            other => Err(anyhow::anyhow!("Invalid audio quality: {}", other)),
+        }
+    }
+}
+
 /// Audio codec preferences for YouTube downloads
-#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 #[allow(clippy::upper_case_acronyms)]
 pub enum AudioCodecPreference {
     AAC,
@@ -132,7 +204,8 @@ pub enum DownloadPriority {
 }
 
 /// YouTube video download format options
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(tag = "kind", rename_all = "snake_case")]
 pub enum YoutubeDownloadFormat {
     /// Complete video with audio
     CompleteVideo {
@@ -1011,6 +1084,7 @@ impl YoutubeDownloader {
             VideoQuality::High => ytdl_selector::VideoQuality::High,
             VideoQuality::Best => ytdl_selector::VideoQuality::Best,
             VideoQuality::Worst => ytdl_selector::VideoQuality::Worst,
+            VideoQuality::Height(height) => ytdl_selector::VideoQuality::CustomHeight(height),
         }
     }
 
@@ -14,26 +14,100 @@ use tokio::io::AsyncReadExt;
 use tokio::sync::mpsc;
 use tokio::time::{interval, timeout, Duration};
 
+use crate::core::bandwidth::BandwidthController;
+use crate::core::cookie_jar::TempCookieFile;
 use crate::core::downloader::{DownloadStats, DownloadTask};
-use crate::core::models::{ExternalVideoInfo, SourcePlatform};
+use crate::core::http_client::HttpClientHandle;
+use crate::core::models::{
+    ExternalVideoInfo, PlaylistExpansion, SourcePlatform, VideoTask, YtDlpSidecarOptions,
+};
+use crate::core::platform_registry;
 use crate::core::ytdlp_support::{
-    build_download_args, build_probe_args, classify_error, detect_platform, emit_committing,
-    emit_progress, env_path, external_info_from_json, sanitize_filename, sidecar_path,
-    spawn_line_reader,
+    build_download_args, build_expand_args, build_probe_args, classify_error, detect_platform,
+    discover_sidecar_files, emit_committing, emit_progress, env_path, external_info_from_json,
+    is_postprocessing_line, is_sidecar_file_name, parse_format_line, playlist_expansion_from_json,
+    playlist_tasks, rate_limit_needs_restart, sanitize_filename, sidecar_path, spawn_line_reader,
+    YtDlpFormatSelection,
+};
+pub use crate::core::ytdlp_support::{
+    parse_progress_line, YtDlpDownloaderConfig, YtDlpNetworkOptions,
 };
-pub use crate::core::ytdlp_support::{parse_progress_line, YtDlpDownloaderConfig};
 use crate::utils::process::hidden_command;
 
 const PROBE_VIDEO_INFO_TIMEOUT: Duration = Duration::from_secs(15);
+/// 大频道的扁平列表要翻很多页，比单个视频探测慢得多
+const EXPAND_PLAYLIST_TIMEOUT: Duration = Duration::from_secs(120);
+/// 重新计算任务份额的间隔，需短于限速器的活跃窗口
+const RATE_RECHECK_INTERVAL: Duration = Duration::from_secs(1);
+/// 两次因限速变化重启 yt-dlp 的最小间隔
+const RATE_RESTART_MIN_INTERVAL: Duration = Duration::from_secs(10);
 
 #[derive(Debug, Clone)]
 pub struct YtDlpDownloader {
     config: YtDlpDownloaderConfig,
+    http_client: Option<HttpClientHandle>,
+    bandwidth_controller: Option<BandwidthController>,
 }
 
 impl YtDlpDownloader {
     pub fn new(config: YtDlpDownloaderConfig) -> Self {
-        Self { config }
+        Self {
+            config,
+            http_client: None,
+            bandwidth_controller: None,
+        }
+    }
+
+    /// 与 HTTP 下载器共享网络配置，代理变更后下一次启动 yt-dlp 即生效
+    pub fn with_http_client(mut self, http_client: HttpClientHandle) -> Self {
+        self.http_client = Some(http_client);
+        self
+    }
+
+    /// 与 HTTP 下载共用限速器：按任务份额传 `--limit-rate`
+    pub fn with_bandwidth_controller(mut self, bandwidth_controller: BandwidthController) -> Self {
+        self.bandwidth_controller = Some(bandwidth_controller);
+        self
+    }
+
+    async fn task_rate_limit(&self, task: &DownloadTask) -> Option<u64> {
+        match &self.bandwidth_controller {
+            Some(controller) => controller.task_rate(&task.id, &task.url).await,
+            None => None,
+        }
+    }
+
+    fn network_options(&self, url: &str) -> YtDlpNetworkOptions {
+        YtDlpNetworkOptions {
+            proxy: self
+                .http_client
+                .as_ref()
+                .and_then(|client| client.proxy_url_for(url)),
+            headers: self
+                .http_client
+                .as_ref()
+                .map(|client| client.headers_for_url(url))
+                .unwrap_or_default(),
+            rate_limit: None,
+            auth: self
+                .http_client
+                .as_ref()
+                .and_then(|client| client.external_auth_for(url)),
+            cookies_file: None,
+            extra_args: platform_registry::current().ytdlp_args_for(url),
+        }
+    }
+
+    /// 把共享 cookie jar 导出为临时文件；返回值需要活到 yt-dlp 进程结束
+    fn export_cookies(&self) -> Option<TempCookieFile> {
+        let jar = self.http_client.as_ref()?.cookies();
+        match jar.export_temp_file() {
+            Ok(file) => file,
+            Err(err) => {
+                tracing::warn!("🍪 Failed to export cookies for yt-dlp: {}", err);
+                None
+            }
+        }
     }
 
     pub fn default_with_user_agent(user_agent: String) -> Self {
@@ -48,7 +122,7 @@ impl YtDlpDownloader {
     }
 
     pub fn build_probe_args(url: &str) -> Vec<String> {
-        build_probe_args(url, None)
+        build_probe_args(url, None, &YtDlpNetworkOptions::default())
     }
 
     pub fn build_download_args(
@@ -57,7 +131,16 @@ impl YtDlpDownloader {
         output_template: &str,
         ffmpeg_path: Option<&Path>,
     ) -> Vec<String> {
-        build_download_args(url, output_dir, output_template, ffmpeg_path, None)
+        build_download_args(
+            url,
+            output_dir,
+            output_template,
+            ffmpeg_path,
+            None,
+            &YtDlpNetworkOptions::default(),
+            &YtDlpFormatSelection::default(),
+            &YtDlpSidecarOptions::default(),
+        )
     }
 
     pub async fn probe_video_info(&self, url: &str) -> Result<ExternalVideoInfo> {
@@ -70,16 +153,64 @@ impl YtDlpDownloader {
         url: &str,
         probe_timeout: Duration,
     ) -> Result<ExternalVideoInfo> {
+        let json = self
+            .run_json_probe(url, probe_timeout, |js_runtime, network| {
+                build_probe_args(url, js_runtime, network)
+            })
+            .await?;
+        Ok(Self::external_info_from_json(&json, url))
+    }
+
+    /// 把播放列表、频道或课程展开为条目列表（不下载），顺序与上限按 YouTube 配置
+    pub async fn expand_playlist(
+        &self,
+        url: &str,
+        reverse: bool,
+        max_items: Option<usize>,
+    ) -> Result<PlaylistExpansion> {
+        self.expand_playlist_with_timeout(url, reverse, max_items, EXPAND_PLAYLIST_TIMEOUT)
+            .await
+    }
+
+    pub(crate) async fn expand_playlist_with_timeout(
+        &self,
+        url: &str,
+        reverse: bool,
+        max_items: Option<usize>,
+        probe_timeout: Duration,
+    ) -> Result<PlaylistExpansion> {
+        let json = self
+            .run_json_probe(url, probe_timeout, |js_runtime, network| {
+                build_expand_args(url, js_runtime, network, max_items)
+            })
+            .await?;
+        Ok(playlist_expansion_from_json(&json, url, reverse, max_items))
+    }
+
+    /// 运行一次只输出 JSON 的 yt-dlp（探测/展开），超时后结束整个进程组
+    async fn run_json_probe(
+        &self,
+        url: &str,
+        probe_timeout: Duration,
+        build_args: impl FnOnce(Option<&Path>, &YtDlpNetworkOptions) -> Vec<String>,
+    ) -> Result<Value> {
         crate::utils::validation::assert_http_url(url)?;
         let tool = self.resolve_ytdlp_command();
         let js_runtime = self.resolve_deno_command();
+        let cookies = self.export_cookies();
         let mut command = hidden_command(&tool);
         #[cfg(unix)]
         {
             command.process_group(0);
         }
         command
-            .args(build_probe_args(url, js_runtime.as_deref()))
+            .args(build_args(
+                js_runtime.as_deref(),
+                &YtDlpNetworkOptions {
+                    cookies_file: cookies.as_ref().map(|file| file.path().to_path_buf()),
+                    ..self.network_options(url)
+                },
+            ))
             .stdout(Stdio::piped())
             .stderr(Stdio::piped());
         let mut child = command.spawn().map_err(|err| {
@@ -105,9 +236,7 @@ impl YtDlpDownloader {
                 terminate_external_child(&mut child).await;
                 stdout_task.abort();
                 stderr_task.abort();
-                return Err(anyhow::anyhow!(
-                    "probe_timeout: yt-dlp video info probe timed out"
-                ));
+                return Err(anyhow::anyhow!("probe_timeout: yt-dlp probe timed out"));
             }
             Ok(Ok(status)) => status,
             Ok(Err(err)) => return Err(anyhow::anyhow!("external_tool_failed: {}", err)),
@@ -125,9 +254,7 @@ impl YtDlpDownloader {
             let stderr = String::from_utf8_lossy(&stderr);
             return Err(anyhow::anyhow!(classify_error(&stderr)));
         }
-        let json: Value = serde_json::from_slice(&stdout)
-            .map_err(|err| anyhow::anyhow!("json_parse_failed: {}", err))?;
-        Ok(Self::external_info_from_json(&json, url))
+        serde_json::from_slice(&stdout).map_err(|err| anyhow::anyhow!("json_parse_failed: {}", err))
     }
 
     pub async fn download(
@@ -154,35 +281,19 @@ impl YtDlpDownloader {
         } else {
             format!("{}.%(ext)s", safe_name)
         };
-        let args = build_download_args(
-            &task.url,
-            Path::new(&task.output_path),
-            &output_template,
-            Some(&ffmpeg),
-            js_runtime.as_deref(),
-        );
-
-        let mut command = hidden_command(&ytdlp);
-        #[cfg(unix)]
-        {
-            command.process_group(0);
-        }
-        let mut child = command
-            .args(args)
-            .stdout(Stdio::piped())
-            .stderr(Stdio::piped())
-            .spawn()
-            .map_err(|err| {
-                if err.kind() == std::io::ErrorKind::NotFound {
-                    anyhow::anyhow!("external_tool_missing: yt-dlp not found")
-                } else {
-                    anyhow::anyhow!("external_tool_failed: {}", err)
-                }
-            })?;
-
-        let (line_tx, mut line_rx) = mpsc::unbounded_channel::<String>();
-        spawn_line_reader(child.stdout.take(), line_tx.clone());
-        spawn_line_reader(child.stderr.take(), line_tx);
+        let cookies = self.export_cookies();
+        let network = YtDlpNetworkOptions {
+            cookies_file: cookies.as_ref().map(|file| file.path().to_path_buf()),
+            ..self.network_options(&task.url)
+        };
+        let mut rate_limit = self.task_rate_limit(task).await;
+        let format =
+            YtDlpFormatSelection::new(&task.ytdlp_format, task.ytdlp_merge_format.as_deref())
+                .with_audio_extraction(
+                    task.audio_extraction
+                        .as_ref()
+                        .or(task.ytdlp_audio_extraction.as_ref()),
+                );
 
         let started = Instant::now();
         let mut stderr = String::new();
@@ -190,42 +301,94 @@ impl YtDlpDownloader {
         let mut partial_name_prefix = (!use_extractor_title_template).then(|| safe_name.clone());
         let mut progress_tick = interval(Duration::from_millis(150));
         progress_tick.tick().await;
-        let mut output_closed = false;
-        let exit_status = loop {
-            if let Some(status) = child.try_wait()? {
-                break status;
-            }
+        let (exit_status, mut line_rx) = 'process: loop {
+            let args = build_download_args(
+                &task.url,
+                Path::new(&task.output_path),
+                &output_template,
+                Some(&ffmpeg),
+                js_runtime.as_deref(),
+                &YtDlpNetworkOptions {
+                    rate_limit,
+                    ..network.clone()
+                },
+                &format,
+                &task.ytdlp_sidecars,
+            );
+            let (mut child, mut line_rx) = spawn_ytdlp(&ytdlp, args)?;
+            let spawned_at = Instant::now();
+            let mut rate_check = interval(RATE_RECHECK_INTERVAL);
+            rate_check.tick().await;
+            let mut output_closed = false;
+            let mut post_processing = false;
+            let exit_status = loop {
+                if let Some(status) = child.try_wait()? {
+                    break status;
+                }
 
-            tokio::select! {
-                maybe_line = line_rx.recv(), if !output_closed => {
-                    if let Some(line) = maybe_line {
-                        handle_ytdlp_line(
-                            line,
-                            task,
-                            started,
-                            progress_tx.as_ref(),
-                            &mut final_path,
-                            &mut partial_name_prefix,
-                            &mut stderr,
-                        );
-                    } else {
-                        output_closed = true;
+                tokio::select! {
+                    maybe_line = line_rx.recv(), if !output_closed => {
+                        if let Some(line) = maybe_line {
+                            post_processing |= is_postprocessing_line(&line);
+                            handle_ytdlp_line(
+                                line,
+                                task,
+                                started,
+                                progress_tx.as_ref(),
+                                &mut final_path,
+                                &mut partial_name_prefix,
+                                &mut stderr,
+                            );
+                        } else {
+                            output_closed = true;
+                        }
                     }
-                }
-                _ = progress_tick.tick() => {
-                    if let Some(prefix) = partial_name_prefix.as_deref() {
-                        emit_filesystem_progress(task, prefix, started, progress_tx.as_ref());
+                    _ = progress_tick.tick() => {
+                        if let Some(prefix) = partial_name_prefix.as_deref() {
+                            emit_filesystem_progress(task, prefix, started, progress_tx.as_ref());
+                        }
+                        if cancel_flag.load(Ordering::Relaxed) || pause_flag.load(Ordering::Relaxed) {
+                            terminate_external_child(&mut child).await;
+                            return Err(if cancel_flag.load(Ordering::Relaxed) {
+                                anyhow::anyhow!("download_cancelled")
+                            } else {
+                                anyhow::anyhow!("download_paused")
+                            });
+                        }
                     }
-                    if cancel_flag.load(Ordering::Relaxed) || pause_flag.load(Ordering::Relaxed) {
-                        terminate_external_child(&mut child).await;
-                        return Err(if cancel_flag.load(Ordering::Relaxed) {
-                            anyhow::anyhow!("download_cancelled")
-                        } else {
-                            anyhow::anyhow!("download_paused")
-                        });
+                    _ = rate_check.tick(), if self.bandwidth_controller.is_some() => {
+                        let next = self.task_rate_limit(task).await;
+                        // yt-dlp 只在启动时读取 --limit-rate：份额明显变化时重启进程，.part 文件会续传
+                        if !post_processing
+                            && spawned_at.elapsed() >= RATE_RESTART_MIN_INTERVAL
+                            && rate_limit_needs_restart(rate_limit, next)
+                        {
+                            tracing::info!(
+                                "🚦 Restarting yt-dlp for task {} with rate limit {:?} -> {:?}",
+                                task.id,
+                                rate_limit,
+                                next
+                            );
+                            terminate_external_child(&mut child).await;
+                            while let Ok(line) = line_rx.try_recv() {
+                                handle_ytdlp_line(
+                                    line,
+                                    task,
+                                    started,
+                                    progress_tx.as_ref(),
+                                    &mut final_path,
+                                    &mut partial_name_prefix,
+                                    &mut stderr,
+                                );
+                            }
+                            stderr.clear();
+                            rate_limit = next;
+                            continue 'process;
+                        }
                     }
                 }
-            }
+            };
+            break (exit_status, line_rx);
         };
         while let Ok(line) = line_rx.try_recv() {
             handle_ytdlp_line(
@@ -255,12 +418,16 @@ impl YtDlpDownloader {
         }
 
         let expected_path = Path::new(&task.output_path).join(&task.filename);
+        let skip_sidecars = task.ytdlp_sidecars.any();
         let final_path = final_path
             .filter(|path| path.exists())
-            .or_else(|| discover_output_file(Path::new(&task.output_path), &safe_name))
             .or_else(|| {
-                use_extractor_title_template
-                    .then(|| discover_latest_output_file(Path::new(&task.output_path)))?
+                discover_output_file(Path::new(&task.output_path), &safe_name, skip_sidecars)
+            })
+            .or_else(|| {
+                use_extractor_title_template.then(|| {
+                    discover_latest_output_file(Path::new(&task.output_path), skip_sidecars)
+                })?
             })
             .or_else(|| expected_path.exists().then_some(expected_path));
         let Some(final_path) = final_path else {
@@ -274,6 +441,13 @@ impl YtDlpDownloader {
         if let Some(name) = final_path.file_name().and_then(|name| name.to_str()) {
             task.filename = name.to_string();
         }
+        if let Some(tx) = progress_tx.as_ref().filter(|_| skip_sidecars) {
+            let stats = DownloadStats {
+                sidecar_files: Some(discover_sidecar_files(&final_path)),
+                ..DownloadStats::default()
+            };
+            let _ = tx.send((task.id.clone(), stats));
+        }
         emit_committing(task, progress_tx.as_ref());
         Ok(())
     }
@@ -286,6 +460,14 @@ impl YtDlpDownloader {
         classify_error(message)
     }
 
+    pub fn playlist_tasks(
+        playlist: &PlaylistExpansion,
+        selected: Option<&[u32]>,
+        output_path: &str,
+    ) -> Vec<VideoTask> {
+        playlist_tasks(playlist, selected, output_path)
+    }
+
     fn resolve_ytdlp_command(&self) -> PathBuf {
         self.config
             .yt_dlp_path
@@ -330,7 +512,11 @@ fn should_use_extractor_title_template(safe_name: &str) -> bool {
     normalized.starts_with("任务_") || normalized.starts_with("任务-")
 }
 
-fn discover_output_file(output_dir: &Path, safe_name: &str) -> Option<PathBuf> {
+fn discover_output_file(
+    output_dir: &Path,
+    safe_name: &str,
+    skip_sidecars: bool,
+) -> Option<PathBuf> {
     let prefix = format!("{safe_name}.");
     let mut matches = std::fs::read_dir(output_dir)
         .ok()?
@@ -344,7 +530,9 @@ fn discover_output_file(output_dir: &Path, safe_name: &str) -> Option<PathBuf> {
             let file_name = path.file_name()?.to_string_lossy();
             if file_name == safe_name || file_name.starts_with(&prefix) {
                 let ignored = [".part", ".ytdl", ".tmp", ".temp"];
-                if ignored.iter().any(|suffix| file_name.ends_with(suffix)) {
+                if ignored.iter().any(|suffix| file_name.ends_with(suffix))
+                    || (skip_sidecars && is_sidecar_file_name(&file_name))
+                {
                     return None;
                 }
                 return Some((path, metadata.modified().ok()));
@@ -357,7 +545,7 @@ fn discover_output_file(output_dir: &Path, safe_name: &str) -> Option<PathBuf> {
     matches.into_iter().map(|(path, _)| path).next()
 }
 
-fn discover_latest_output_file(output_dir: &Path) -> Option<PathBuf> {
+fn discover_latest_output_file(output_dir: &Path, skip_sidecars: bool) -> Option<PathBuf> {
     let mut matches = std::fs::read_dir(output_dir)
         .ok()?
         .filter_map(Result::ok)
@@ -369,7 +557,9 @@ fn discover_latest_output_file(output_dir: &Path) -> Option<PathBuf> {
             }
             let file_name = path.file_name()?.to_string_lossy();
             let ignored = [".part", ".ytdl", ".tmp", ".temp", ".vdstate"];
-            if ignored.iter().any(|suffix| file_name.ends_with(suffix)) {
+            if ignored.iter().any(|suffix| file_name.ends_with(suffix))
+                || (skip_sidecars && is_sidecar_file_name(&file_name))
+            {
                 return None;
             }
             Some((path, metadata.modified().ok()))
@@ -380,6 +570,34 @@ fn discover_latest_output_file(output_dir: &Path) -> Option<PathBuf> {
     matches.into_iter().map(|(path, _)| path).next()
 }
 
+fn spawn_ytdlp(
+    ytdlp: &Path,
+    args: Vec<String>,
+) -> Result<(tokio::process::Child, mpsc::UnboundedReceiver<String>)> {
+    let mut command = hidden_command(ytdlp);
+    #[cfg(unix)]
+    {
+        command.process_group(0);
+    }
+    let mut child = command
+        .args(args)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|err| {
+            if err.kind() == std::io::ErrorKind::NotFound {
+                anyhow::anyhow!("external_tool_missing: yt-dlp not found")
+            } else {
+                anyhow::anyhow!("external_tool_failed: {}", err)
+            }
+        })?;
+
+    let (line_tx, line_rx) = mpsc::unbounded_channel::<String>();
+    spawn_line_reader(child.stdout.take(), line_tx.clone());
+    spawn_line_reader(child.stderr.take(), line_tx);
+    Ok((child, line_rx))
+}
+
 fn handle_ytdlp_line(
     line: String,
     task: &mut DownloadTask,
@@ -392,6 +610,14 @@ fn handle_ytdlp_line(
     if let Some(path_start) = line.find("filepath:") {
         let path = &line[path_start + "filepath:".len()..];
         *final_path = Some(PathBuf::from(path.trim()));
+    } else if let Some(format) = parse_format_line(&line) {
+        if let Some(tx) = progress_tx {
+            let stats = DownloadStats {
+                selected_format: Some(format),
+                ..DownloadStats::default()
+            };
+            let _ = tx.send((task.id.clone(), stats));
+        }
     } else if let Some(destination) = parse_destination_line(&line) {
         if let Some(file_name) = destination.file_name().and_then(|name| name.to_str()) {
             *partial_name_prefix = Some(file_name.to_string());
@@ -7,11 +7,23 @@ use std::sync::{
     Arc,
 };
 
-use crate::core::models::{SourcePlatform, TaskStatus};
+use crate::core::credentials::ExternalAuth;
+use crate::core::models::{
+    AudioExtraction, AudioFormat, SelectedFormat, SidecarKind, SourcePlatform, TaskStatus,
+    YtDlpSidecarOptions,
+};
+use crate::core::youtube_downloader::{
+    AudioCodecPreference, AudioQuality, VideoCodecPreference, VideoQuality, YoutubeDownloadFormat,
+};
 use crate::core::{
     downloader::DownloadTask,
     ytdlp_downloader::{parse_progress_line, YtDlpDownloader, YtDlpDownloaderConfig},
-    ytdlp_support::{emit_progress, platform_host_rules, ParsedYtDlpProgress},
+    ytdlp_support::{
+        build_download_args, build_expand_args, build_probe_args, discover_sidecar_files,
+        emit_progress, is_postprocessing_line, parse_format_line, platform_host_rules,
+        playlist_expansion_from_json, rate_limit_needs_restart, ParsedYtDlpProgress,
+        YtDlpFormatSelection, YtDlpNetworkOptions,
+    },
 };
 
 #[test]
@@ -96,6 +108,439 @@ fn builds_safe_probe_and_download_args() {
         ]));
 }
 
+#[test]
+fn builds_format_selectors_and_sort_keys_from_preferences() {
+    let capped = YtDlpFormatSelection::new(
+        &YoutubeDownloadFormat::CompleteVideo {
+            video_quality: VideoQuality::Height(720),
+            video_codec: VideoCodecPreference::AVC1,
+            audio_quality: AudioQuality::Medium,
+            audio_codec: AudioCodecPreference::Any,
+        },
+        Some("mp4"),
+    );
+    assert_eq!(
+        capped.selector,
+        "bv*[height<=720]+ba/b[height<=720]/bv*+ba/b"
+    );
+    assert_eq!(
+        capped.sort,
+        ["res:720", "vcodec:h264", "abr:128", "ext:mp4:m4a"]
+    );
+
+    let args = build_download_args(
+        "https://youtu.be/abc",
+        Path::new("/tmp/out"),
+        "video.%(ext)s",
+        None,
+        None,
+        &YtDlpNetworkOptions::default(),
+        &YtDlpFormatSelection::new(&YoutubeDownloadFormat::BestAvailable, Some("MKV")),
+        &YtDlpSidecarOptions::default(),
+    );
+    assert!(args.windows(2).any(|pair| pair == ["--format", "bv*+ba/b"]));
+    assert!(args
+        .windows(2)
+        .any(|pair| pair == ["--merge-output-format", "mkv"]));
+    assert!(args.iter().all(|arg| arg != "--format-sort"));
+    assert!(args
+        .windows(2)
+        .any(|pair| pair == ["--print", "before_dl:format:%(format_id)s\t%(format_note)s"]));
+
+    let audio = YtDlpFormatSelection::new(
+        &YoutubeDownloadFormat::AudioOnly {
+            quality: AudioQuality::Worst,
+            codec: AudioCodecPreference::Opus,
+        },
+        Some("webm"),
+    );
+    assert_eq!(audio.selector, "ba/b");
+    assert_eq!(audio.sort, ["acodec:opus", "+abr"]);
+
+    let specific = YtDlpFormatSelection::new(
+        &YoutubeDownloadFormat::SpecificFormat {
+            format_id: "137+140".to_string(),
+        },
+        Some("3gp"),
+    );
+    assert_eq!(specific.selector, "137+140");
+    assert!(specific.sort.is_empty());
+    assert_eq!(specific.merge_format, "mp4");
+}
+
+#[test]
+fn builds_audio_extraction_args() {
+    let format = YtDlpFormatSelection::new(
+        &YoutubeDownloadFormat::CompleteVideo {
+            video_quality: VideoQuality::Height(720),
+            video_codec: VideoCodecPreference::AVC1,
+            audio_quality: AudioQuality::High,
+            audio_codec: AudioCodecPreference::Any,
+        },
+        Some("mkv"),
+    )
+    .with_audio_extraction(Some(&AudioExtraction {
+        format: AudioFormat::Mp3,
+        bitrate_kbps: Some(192),
+    }));
+    assert_eq!(format.selector, "ba/b");
+    assert_eq!(format.sort, ["abr:192"]);
+
+    let args = build_download_args(
+        "https://youtu.be/abc",
+        Path::new("/tmp/out"),
+        "video.%(ext)s",
+        None,
+        None,
+        &YtDlpNetworkOptions::default(),
+        &format,
+        &YtDlpSidecarOptions::default(),
+    );
+    assert!(args.iter().any(|arg| arg == "--extract-audio"));
+    assert!(args
+        .windows(2)
+        .any(|pair| pair == ["--audio-format", "mp3"]));
+    assert!(args
+        .windows(2)
+        .any(|pair| pair == ["--audio-quality", "192K"]));
+    assert!(args.iter().all(|arg| arg != "--merge-output-format"));
+
+    let lossless = YtDlpFormatSelection::default().with_audio_extraction(Some(&AudioExtraction {
+        format: AudioFormat::Flac,
+        bitrate_kbps: Some(320),
+    }));
+    let args = build_download_args(
+        "https://youtu.be/abc",
+        Path::new("/tmp/out"),
+        "video.%(ext)s",
+        None,
+        None,
+        &YtDlpNetworkOptions::default(),
+        &lossless,
+        &YtDlpSidecarOptions::default(),
+    );
+    assert!(args
+        .windows(2)
+        .any(|pair| pair == ["--audio-format", "flac"]));
+    assert!(args.iter().all(|arg| arg != "--audio-quality"));
+
+    assert_eq!("aac".parse::<AudioFormat>().unwrap(), AudioFormat::M4a);
+    assert!("ogg".parse::<AudioFormat>().is_err());
+}
+
+#[test]
+fn parses_selected_format_line_and_format_preferences() {
+    assert_eq!(
+        parse_format_line("format:137+140\t1080p"),
+        Some(SelectedFormat {
+            format_id: "137+140".to_string(),
+            format_note: Some("1080p".to_string()),
+        })
+    );
+    assert_eq!(
+        parse_format_line("format:hls-720\tNA").and_then(|format| format.format_note),
+        None
+    );
+    assert_eq!(parse_format_line("format:NA\tNA"), None);
+    assert_eq!(parse_format_line("filesize:1024\tNA"), None);
+
+    assert_eq!(
+        "720p".parse::<VideoQuality>().unwrap(),
+        VideoQuality::Height(720)
+    );
+    assert_eq!("Best".parse::<VideoQuality>().unwrap(), VideoQuality::Best);
+    assert!("0p".parse::<VideoQuality>().is_err());
+    assert_eq!(
+        "h264".parse::<VideoCodecPreference>().unwrap(),
+        VideoCodecPreference::AVC1
+    );
+
+    let task_format: YoutubeDownloadFormat = serde_json::from_value(json!({
+        "kind": "video_only",
+        "quality": { "height": 480 },
+        "codec": "vp9",
+    }))
+    .unwrap();
+    assert_eq!(
+        task_format,
+        YoutubeDownloadFormat::VideoOnly {
+            quality: VideoQuality::Height(480),
+            codec: VideoCodecPreference::VP9,
+        }
+    );
+    assert_eq!(
+        YtDlpFormatSelection::new(&task_format, None).selector,
+        "bv[height<=480]/bv"
+    );
+}
+
+#[test]
+fn builds_sidecar_args_and_discovers_sidecar_files() {
+    let sidecars = YtDlpSidecarOptions {
+        subtitles: true,
+        subtitle_languages: vec!["en".to_string(), " zh-Hans ".to_string()],
+        auto_subtitles: true,
+        subtitle_format: Some("srt".to_string()),
+        thumbnail: true,
+        description: true,
+        info_json: true,
+    };
+    let args = build_download_args(
+        "https://youtu.be/abc",
+        Path::new("/tmp/out"),
+        "video.%(ext)s",
+        None,
+        None,
+        &YtDlpNetworkOptions::default(),
+        &YtDlpFormatSelection::default(),
+        &sidecars,
+    );
+    for flag in [
+        "--write-subs",
+        "--write-auto-subs",
+        "--write-thumbnail",
+        "--write-description",
+        "--write-info-json",
+    ] {
+        assert!(args.iter().any(|arg| arg == flag), "missing {flag}");
+    }
+    assert!(args
+        .windows(2)
+        .any(|pair| pair == ["--sub-langs", "en,zh-Hans"]));
+    assert!(args
+        .windows(2)
+        .any(|pair| pair == ["--convert-subs", "srt"]));
+    assert!(args
+        .windows(2)
+        .any(|pair| pair == ["--convert-thumbnails", "jpg"]));
+    assert_eq!(
+        args.last().map(String::as_str),
+        Some("https://youtu.be/abc")
+    );
+
+    let temp_dir = tempfile::tempdir().unwrap();
+    for name in [
+        "Talk.mp4",
+        "Talk.en.srt",
+        "Talk.zh-Hans.vtt",
+        "Talk.jpg",
+        "Talk.description",
+        "Talk.info.json",
+        "Talk.f137.mp4.part",
+        "Talk 2.mp4",
+        "Other.jpg",
+    ] {
+        std::fs::write(temp_dir.path().join(name), b"x").unwrap();
+    }
+    let files = discover_sidecar_files(&temp_dir.path().join("Talk.mp4"));
+    let summary = files
+        .iter()
+        .map(|file| {
+            (
+                file.kind,
+                Path::new(&file.path)
+                    .file_name()
+                    .unwrap()
+                    .to_string_lossy()
+                    .to_string(),
+                file.language.clone(),
+            )
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        summary,
+        vec![
+            (
+                SidecarKind::Description,
+                "Talk.description".to_string(),
+                None
+            ),
+            (
+                SidecarKind::Subtitle,
+                "Talk.en.srt".to_string(),
+                Some("en".to_string())
+            ),
+            (SidecarKind::InfoJson, "Talk.info.json".to_string(), None),
+            (SidecarKind::Thumbnail, "Talk.jpg".to_string(), None),
+            (
+                SidecarKind::Subtitle,
+                "Talk.zh-Hans.vtt".to_string(),
+                Some("zh-Hans".to_string())
+            ),
+        ]
+    );
+}
+
+#[test]
+fn passes_proxy_to_probe_and_download_args() {
+    let network = YtDlpNetworkOptions {
+        proxy: Some("socks5h://user:pw@proxy.corp:1080".to_string()),
+        ..YtDlpNetworkOptions::default()
+    };
+    let probe = build_probe_args("https://youtu.be/abc", None, &network);
+    let download = build_download_args(
+        "https://youtu.be/abc",
+        Path::new("/tmp/out"),
+        "video.%(ext)s",
+        None,
+        None,
+        &network,
+        &YtDlpFormatSelection::default(),
+        &YtDlpSidecarOptions::default(),
+    );
+    for args in [&probe, &download] {
+        assert!(args
+            .windows(2)
+            .any(|pair| pair == ["--proxy", "socks5h://user:pw@proxy.corp:1080"]));
+        assert_eq!(
+            args.last().map(String::as_str),
+            Some("https://youtu.be/abc")
+        );
+    }
+
+    let direct = build_probe_args(
+        "https://youtu.be/abc",
+        None,
+        &YtDlpNetworkOptions::default(),
+    );
+    assert!(!direct.iter().any(|arg| arg == "--proxy"));
+}
+
+#[test]
+fn passes_header_rules_as_add_header_args() {
+    let network = YtDlpNetworkOptions {
+        headers: vec![
+            ("Referer".to_string(), "https://course.com/".to_string()),
+            ("User-Agent".to_string(), "CourseAgent/1.0".to_string()),
+        ],
+        ..YtDlpNetworkOptions::default()
+    };
+    let args = build_probe_args("https://course.com/v/1", None, &network);
+    assert!(args
+        .windows(2)
+        .any(|pair| pair == ["--add-header", "Referer:https://course.com/"]));
+    assert!(args
+        .windows(2)
+        .any(|pair| pair == ["--add-header", "User-Agent:CourseAgent/1.0"]));
+    assert_eq!(
+        args.last().map(String::as_str),
+        Some("https://course.com/v/1")
+    );
+}
+
+#[test]
+fn passes_matched_credentials_as_login_or_video_password() {
+    let login = YtDlpNetworkOptions {
+        auth: Some(ExternalAuth::Login {
+            username: "alice".to_string(),
+            password: "hunter2".to_string(),
+        }),
+        ..YtDlpNetworkOptions::default()
+    };
+    let args = build_probe_args("https://vimeo.com/1", None, &login);
+    assert!(args.windows(2).any(|pair| pair == ["--username", "alice"]));
+    assert!(args
+        .windows(2)
+        .any(|pair| pair == ["--password", "hunter2"]));
+    assert!(!format!("{:?}", login).contains("hunter2"));
+
+    let video_password = YtDlpNetworkOptions {
+        auth: Some(ExternalAuth::VideoPassword("letmein".to_string())),
+        ..YtDlpNetworkOptions::default()
+    };
+    let args = build_download_args(
+        "https://vimeo.com/1",
+        Path::new("/tmp/out"),
+        "video.%(ext)s",
+        None,
+        None,
+        &video_password,
+        &YtDlpFormatSelection::default(),
+        &YtDlpSidecarOptions::default(),
+    );
+    assert!(args
+        .windows(2)
+        .any(|pair| pair == ["--video-password", "letmein"]));
+    assert_eq!(args.last().map(String::as_str), Some("https://vimeo.com/1"));
+}
+
+#[test]
+fn passes_exported_cookie_jar_to_probe_and_download() {
+    let jar = crate::core::cookie_jar::CookieJar::in_memory();
+    jar.import_netscape(".course.com\tTRUE\t/\tTRUE\t0\tsid\tabc\n", None)
+        .expect("import");
+    let exported = jar
+        .export_temp_file()
+        .expect("export")
+        .expect("non-empty jar");
+    let network = YtDlpNetworkOptions {
+        cookies_file: Some(exported.path().to_path_buf()),
+        ..YtDlpNetworkOptions::default()
+    };
+    let cookies_path = exported.path().to_string_lossy().to_string();
+
+    let probe = build_probe_args("https://course.com/v/1", None, &network);
+    let download = build_download_args(
+        "https://course.com/v/1",
+        Path::new("/tmp/out"),
+        "video.%(ext)s",
+        None,
+        None,
+        &network,
+        &YtDlpFormatSelection::default(),
+        &YtDlpSidecarOptions::default(),
+    );
+    for args in [probe, download] {
+        assert!(args
+            .windows(2)
+            .any(|pair| pair[0] == "--cookies" && pair[1] == cookies_path));
+    }
+    assert!(build_probe_args(
+        "https://course.com/v/1",
+        None,
+        &YtDlpNetworkOptions::default()
+    )
+    .iter()
+    .all(|arg| arg != "--cookies"));
+}
+
+#[test]
+fn passes_rate_limit_share_and_restarts_only_on_real_changes() {
+    let network = YtDlpNetworkOptions {
+        rate_limit: Some(512 * 1024),
+        ..YtDlpNetworkOptions::default()
+    };
+    let args = build_download_args(
+        "https://youtu.be/abc",
+        Path::new("/tmp/out"),
+        "video.%(ext)s",
+        None,
+        None,
+        &network,
+        &YtDlpFormatSelection::default(),
+        &YtDlpSidecarOptions::default(),
+    );
+    assert!(args
+        .windows(2)
+        .any(|pair| pair == ["--limit-rate", "524288"]));
+    assert_eq!(
+        args.last().map(String::as_str),
+        Some("https://youtu.be/abc")
+    );
+
+    assert!(!rate_limit_needs_restart(None, None));
+    assert!(!rate_limit_needs_restart(Some(1000), Some(1100)));
+    assert!(rate_limit_needs_restart(Some(1000), Some(1300)));
+    assert!(rate_limit_needs_restart(Some(1000), None));
+    assert!(rate_limit_needs_restart(None, Some(1000)));
+    assert!(is_postprocessing_line(
+        "[Merger] Merging formats into \"a.mp4\""
+    ));
+    assert!(!is_postprocessing_line(
+        "[download] Destination: a.f137.mp4"
+    ));
+}
+
 #[test]
 fn parses_ytdlp_progress_template_lines() {
     let parsed =
@@ -224,6 +669,131 @@ fn maps_probe_json_to_external_info() {
     assert!(!info.requires_auth);
 }
 
+#[test]
+fn builds_flat_playlist_expand_args() {
+    let args = build_expand_args(
+        "https://www.youtube.com/playlist?list=PL1",
+        None,
+        &YtDlpNetworkOptions::default(),
+        Some(20),
+    );
+
+    assert!(args.contains(&"--flat-playlist".to_string()));
+    assert!(args.contains(&"--dump-single-json".to_string()));
+    assert!(!args.contains(&"--no-playlist".to_string()));
+    assert!(args.windows(2).any(|pair| pair == ["--playlist-end", "20"]));
+    assert_eq!(
+        args.last().map(String::as_str),
+        Some("https://www.youtube.com/playlist?list=PL1")
+    );
+}
+
+#[test]
+fn expands_flat_playlist_entries_and_builds_ordered_tasks() {
+    let json = json!({
+        "_type": "playlist",
+        "id": "PL1",
+        "title": "Course",
+        "extractor": "youtube:tab",
+        "webpage_url": "https://www.youtube.com/playlist?list=PL1",
+        "entries": [
+            {"_type": "url", "ie_key": "Youtube", "id": "a", "url": "https://www.youtube.com/watch?v=a", "title": "One", "duration": 61.0},
+            null,
+            {"_type": "url", "ie_key": "Youtube", "id": "c", "url": "c", "title": "Three"},
+            {"_type": "playlist", "id": "nested", "title": "Shorts"},
+            {"_type": "url", "ie_key": "Youtube", "id": "e", "url": "https://www.youtube.com/watch?v=e"}
+        ]
+    });
+
+    let playlist =
+        playlist_expansion_from_json(&json, "https://youtube.com/playlist?list=PL1", false, None);
+    assert_eq!(playlist.playlist_id.as_deref(), Some("PL1"));
+    assert_eq!(playlist.source_platform, SourcePlatform::Youtube);
+    let indexes: Vec<u32> = playlist.entries.iter().map(|entry| entry.index).collect();
+    assert_eq!(indexes, vec![1, 3, 5]);
+    assert_eq!(playlist.entries[0].duration_seconds, Some(61.0));
+    assert_eq!(playlist.entries[1].url, "https://www.youtube.com/watch?v=c");
+
+    // 与 yt-dlp 一致：先截取前 N 项再倒序
+    let reversed = playlist_expansion_from_json(
+        &json,
+        "https://youtube.com/playlist?list=PL1",
+        true,
+        Some(2),
+    );
+    let indexes: Vec<u32> = reversed.entries.iter().map(|entry| entry.index).collect();
+    assert_eq!(indexes, vec![3, 1]);
+
+    let tasks = YtDlpDownloader::playlist_tasks(&playlist, Some(&[5, 1]), "/downloads");
+    assert_eq!(tasks.len(), 2);
+    assert_eq!(tasks[0].title, "One");
+    assert_eq!(tasks[1].title, "Course #5");
+    assert!(tasks[0].created_at < tasks[1].created_at);
+    let info = tasks[1].external_info.as_ref().unwrap();
+    assert_eq!(info.playlist_id.as_deref(), Some("PL1"));
+    assert_eq!(info.playlist_index, Some(5));
+    assert_eq!(
+        tasks[1].downloader_type,
+        Some(crate::core::models::DownloaderType::YtDlp)
+    );
+}
+
+#[test]
+fn expanding_a_single_video_returns_one_entry() {
+    let playlist = playlist_expansion_from_json(
+        &json!({"id": "abc", "title": "Solo", "webpage_url": "https://www.youtube.com/watch?v=abc"}),
+        "https://youtu.be/abc",
+        false,
+        None,
+    );
+
+    assert_eq!(playlist.playlist_id, None);
+    assert_eq!(playlist.entries.len(), 1);
+    assert_eq!(
+        playlist.entries[0].url,
+        "https://www.youtube.com/watch?v=abc"
+    );
+    assert_eq!(playlist.entries[0].title.as_deref(), Some("Solo"));
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn fake_sidecar_expands_playlist() {
+    use tempfile::tempdir;
+
+    let temp = tempdir().unwrap();
+    let ytdlp = temp.path().join("yt-dlp");
+    let args_log = temp.path().join("args.txt");
+    write_executable(
+        &ytdlp,
+        &format!(
+            r#"#!/usr/bin/env sh
+echo "$@" > "{}"
+printf '%s' '{{"_type":"playlist","id":"PL9","title":"Nine","entries":[{{"url":"https://www.youtube.com/watch?v=x","title":"X"}}]}}'
+"#,
+            args_log.display()
+        ),
+    );
+
+    let downloader = YtDlpDownloader::new(YtDlpDownloaderConfig {
+        yt_dlp_path: Some(ytdlp),
+        ffmpeg_path: None,
+        deno_path: None,
+        user_agent: "test".to_string(),
+    });
+    let playlist = downloader
+        .expand_playlist("https://www.youtube.com/playlist?list=PL9", false, Some(5))
+        .await
+        .unwrap();
+
+    assert_eq!(playlist.playlist_id.as_deref(), Some("PL9"));
+    assert_eq!(playlist.entries.len(), 1);
+    assert_eq!(playlist.entries[0].title.as_deref(), Some("X"));
+    let args = std::fs::read_to_string(args_log).unwrap();
+    assert!(args.contains("--flat-playlist"));
+    assert!(args.contains("--playlist-end 5"));
+}
+
 #[cfg(unix)]
 #[tokio::test]
 async fn fake_sidecar_probe_times_out() {
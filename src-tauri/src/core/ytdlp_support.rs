@@ -4,8 +4,17 @@ use std::time::Instant;
 use tokio::io::{AsyncBufReadExt, BufReader};
 use tokio::sync::mpsc;
 
+use crate::core::credentials::ExternalAuth;
 use crate::core::downloader::{DownloadStats, DownloadTask};
-use crate::core::models::{ExternalVideoInfo, SourcePlatform, TaskStatus};
+use crate::core::models::{
+    AudioExtraction, DownloaderType, ExternalVideoInfo, PlaylistEntry, PlaylistExpansion,
+    SelectedFormat, SidecarFile, SidecarKind, SourcePlatform, TaskStatus, VideoTask,
+    YtDlpSidecarOptions,
+};
+use crate::core::platform_registry;
+use crate::core::youtube_downloader::{
+    AudioCodecPreference, AudioQuality, VideoCodecPreference, VideoQuality, YoutubeDownloadFormat,
+};
 pub use crate::utils::file_utils::sanitize_filename;
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -25,6 +34,174 @@ pub struct YtDlpDownloaderConfig {
     pub user_agent: String,
 }
 
+/// 传给 yt-dlp 的网络参数（与 reqwest 客户端共用同一份配置）
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct YtDlpNetworkOptions {
+    pub proxy: Option<String>,
+    pub headers: Vec<(String, String)>,
+    /// `--limit-rate`，由限速器按任务份额计算（bytes/sec）
+    pub rate_limit: Option<u64>,
+    /// 凭据库中匹配的账号、视频密码或令牌
+    pub auth: Option<ExternalAuth>,
+    /// `--cookies`，由共享 cookie jar 导出的临时 Netscape 文件
+    pub cookies_file: Option<PathBuf>,
+    /// 平台规则里配置的额外参数，放在其它网络参数之后
+    pub extra_args: Vec<String>,
+}
+
+/// yt-dlp 支持的合并容器（`--merge-output-format`）
+const MERGE_FORMATS: [&str; 6] = ["mp4", "mkv", "webm", "mov", "avi", "flv"];
+
+/// 格式选择：`--format` 选择器、`--format-sort` 排序键和合并容器
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct YtDlpFormatSelection {
+    pub selector: String,
+    pub sort: Vec<String>,
+    pub merge_format: String,
+    /// 仅音频模式：`-x --audio-format` 转码，不再合并视频
+    pub audio_extraction: Option<AudioExtraction>,
+}
+
+impl Default for YtDlpFormatSelection {
+    fn default() -> Self {
+        Self::new(&YoutubeDownloadFormat::BestAvailable, None)
+    }
+}
+
+impl YtDlpFormatSelection {
+    /// 画质上限同时写进选择器（带回退）和 `res` 排序键，编码和音质只影响排序；
+    /// 明确指定容器时优先选同容器的流，省去合并时的转封装
+    pub fn new(format: &YoutubeDownloadFormat, merge_format: Option<&str>) -> Self {
+        let merge_format = match merge_format.map(|value| value.trim().to_ascii_lowercase()) {
+            Some(value) if MERGE_FORMATS.contains(&value.as_str()) => Some(value),
+            Some(value) => {
+                tracing::warn!("yt-dlp 不支持合并为 {}，改用 mp4", value);
+                None
+            }
+            None => None,
+        };
+        let mut sort = Vec::new();
+        let selector = match format {
+            YoutubeDownloadFormat::BestAvailable => "bv*+ba/b".to_string(),
+            YoutubeDownloadFormat::CompleteVideo {
+                video_quality,
+                video_codec,
+                audio_quality,
+                audio_codec,
+            } => {
+                push_video_sort_keys(&mut sort, *video_quality, *video_codec);
+                push_audio_sort_keys(&mut sort, *audio_quality, *audio_codec);
+                match (video_quality, video_quality.max_height()) {
+                    (VideoQuality::Worst, _) => "wv*+ba/w".to_string(),
+                    (_, Some(height)) => {
+                        format!("bv*[height<={h}]+ba/b[height<={h}]/bv*+ba/b", h = height)
+                    }
+                    (_, None) => "bv*+ba/b".to_string(),
+                }
+            }
+            YoutubeDownloadFormat::VideoOnly { quality, codec } => {
+                push_video_sort_keys(&mut sort, *quality, *codec);
+                match (quality, quality.max_height()) {
+                    (VideoQuality::Worst, _) => "wv".to_string(),
+                    (_, Some(height)) => format!("bv[height<={}]/bv", height),
+                    (_, None) => "bv".to_string(),
+                }
+            }
+            YoutubeDownloadFormat::AudioOnly { quality, codec } => {
+                push_audio_sort_keys(&mut sort, *quality, *codec);
+                "ba/b".to_string()
+            }
+            YoutubeDownloadFormat::SpecificFormat { format_id } => format_id.clone(),
+        };
+        let has_video = !matches!(
+            format,
+            YoutubeDownloadFormat::AudioOnly { .. } | YoutubeDownloadFormat::SpecificFormat { .. }
+        );
+        match merge_format.as_deref() {
+            Some("mp4") if has_video => sort.push("ext:mp4:m4a".to_string()),
+            Some("webm") if has_video => sort.push("ext:webm:webm".to_string()),
+            _ => {}
+        }
+
+        Self {
+            selector,
+            sort,
+            merge_format: merge_format.unwrap_or_else(|| "mp4".to_string()),
+            audio_extraction: None,
+        }
+    }
+
+    /// 仅音频模式：改选最好的音频流（站点没有独立音频时退回完整文件），只保留音频排序键
+    pub fn with_audio_extraction(mut self, extraction: Option<&AudioExtraction>) -> Self {
+        if let Some(extraction) = extraction {
+            self.selector = "ba/b".to_string();
+            self.sort
+                .retain(|key| key.starts_with("acodec:") || key.contains("abr"));
+            self.audio_extraction = Some(extraction.clone());
+        }
+        self
+    }
+}
+
+fn push_video_sort_keys(
+    sort: &mut Vec<String>,
+    quality: VideoQuality,
+    codec: VideoCodecPreference,
+) {
+    if let Some(height) = quality.max_height() {
+        sort.push(format!("res:{}", height));
+    }
+    let codec = match codec {
+        VideoCodecPreference::AVC1 => Some("h264"),
+        VideoCodecPreference::VP9 => Some("vp9"),
+        VideoCodecPreference::AV01 => Some("av01"),
+        VideoCodecPreference::Any => None,
+    };
+    if let Some(codec) = codec {
+        sort.push(format!("vcodec:{}", codec));
+    }
+}
+
+fn push_audio_sort_keys(
+    sort: &mut Vec<String>,
+    quality: AudioQuality,
+    codec: AudioCodecPreference,
+) {
+    let codec = match codec {
+        AudioCodecPreference::AAC => Some("aac"),
+        AudioCodecPreference::MP3 => Some("mp3"),
+        AudioCodecPreference::Opus => Some("opus"),
+        AudioCodecPreference::FLAC => Some("flac"),
+        AudioCodecPreference::Any => None,
+    };
+    if let Some(codec) = codec {
+        sort.push(format!("acodec:{}", codec));
+    }
+    match quality {
+        AudioQuality::Low => sort.push("abr:64".to_string()),
+        AudioQuality::Medium => sort.push("abr:128".to_string()),
+        AudioQuality::High => sort.push("abr:192".to_string()),
+        AudioQuality::Worst => sort.push("+abr".to_string()),
+        AudioQuality::Best => {}
+    }
+}
+
+/// 解析 `before_dl:format:` 打印的格式 ID 和说明
+pub fn parse_format_line(line: &str) -> Option<SelectedFormat> {
+    let payload = line.trim().strip_prefix("format:")?;
+    let (format_id, format_note) = payload.split_once('\t').unwrap_or((payload, ""));
+    let format_id = format_id.trim();
+    if format_id.is_empty() || format_id.eq_ignore_ascii_case("NA") {
+        return None;
+    }
+    let format_note = format_note.trim();
+    Some(SelectedFormat {
+        format_id: format_id.to_string(),
+        format_note: (!format_note.is_empty() && !format_note.eq_ignore_ascii_case("NA"))
+            .then(|| format_note.to_string()),
+    })
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct ParsedYtDlpProgress {
     pub downloaded_bytes: u64,
@@ -38,24 +215,29 @@ pub struct ParsedYtDlpProgress {
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct PlatformHostRule {
     pub platform: SourcePlatform,
+    pub display_name: &'static str,
     pub hosts: &'static [&'static str],
 }
 
 const PLATFORM_HOST_RULES: &[PlatformHostRule] = &[
     PlatformHostRule {
         platform: SourcePlatform::Youtube,
+        display_name: "YouTube",
         hosts: &["youtube.com", "youtu.be", "youtube-nocookie.com"],
     },
     PlatformHostRule {
         platform: SourcePlatform::Tiktok,
+        display_name: "TikTok",
         hosts: &["tiktok.com"],
     },
     PlatformHostRule {
         platform: SourcePlatform::Instagram,
+        display_name: "Instagram",
         hosts: &["instagram.com"],
     },
     PlatformHostRule {
         platform: SourcePlatform::Facebook,
+        display_name: "Facebook",
         hosts: &["facebook.com", "fb.watch", "fb.com"],
     },
 ];
@@ -282,6 +464,28 @@ fn parse_eta_seconds(value: &str) -> Option<u64> {
     }
 }
 
+/// 下载完成后的合并/修复阶段：此时重启 yt-dlp 会重做后处理
+pub fn is_postprocessing_line(line: &str) -> bool {
+    const STAGES: [&str; 6] = [
+        "[Merger]",
+        "[FixupM3u8]",
+        "[FixupM4a]",
+        "[ExtractAudio]",
+        "[VideoConvertor]",
+        "[VideoRemuxer]",
+    ];
+    let line = line.trim_start();
+    STAGES.iter().any(|stage| line.starts_with(stage))
+}
+
+/// 限速取消/新增，或速率变化超过 20% 时才值得重启 yt-dlp
+pub fn rate_limit_needs_restart(current: Option<u64>, next: Option<u64>) -> bool {
+    match (current, next) {
+        (Some(current), Some(next)) => current.abs_diff(next) * 5 > current,
+        (current, next) => current != next,
+    }
+}
+
 pub fn spawn_line_reader<R>(reader: Option<R>, tx: mpsc::UnboundedSender<String>)
 where
     R: tokio::io::AsyncRead + Unpin + Send + 'static,
@@ -300,24 +504,15 @@ pub fn platform_host_rules() -> &'static [PlatformHostRule] {
     PLATFORM_HOST_RULES
 }
 
+/// 按当前平台规则表（内置 + 配置）识别来源平台
 pub fn detect_platform(url: &str) -> SourcePlatform {
-    let host = url::Url::parse(url)
-        .ok()
-        .and_then(|parsed| parsed.host_str().map(|host| host.to_lowercase()))
-        .unwrap_or_default();
-
-    platform_host_rules()
-        .iter()
-        .find(|rule| {
-            rule.hosts
-                .iter()
-                .any(|candidate| host_matches(&host, candidate))
-        })
-        .map(|rule| rule.platform.clone())
+    platform_registry::current()
+        .match_url(url)
+        .map(|rule| SourcePlatform::from_id(&rule.id))
         .unwrap_or(SourcePlatform::Generic)
 }
 
-fn host_matches(host: &str, candidate: &str) -> bool {
+pub(crate) fn host_matches(host: &str, candidate: &str) -> bool {
     host == candidate || host.ends_with(&format!(".{candidate}"))
 }
 
@@ -336,32 +531,88 @@ pub fn is_direct_media_url(url: &str) -> bool {
         .unwrap_or(false)
 }
 
-pub fn build_probe_args(url: &str, js_runtime_path: Option<&Path>) -> Vec<String> {
+pub fn build_probe_args(
+    url: &str,
+    js_runtime_path: Option<&Path>,
+    network: &YtDlpNetworkOptions,
+) -> Vec<String> {
     let mut args = vec![
         "--no-playlist".into(),
         "--newline".into(),
         "--dump-single-json".into(),
     ];
     append_js_runtime_args(&mut args, js_runtime_path);
+    append_network_args(&mut args, network);
+    args.push(url.into());
+    args
+}
+
+/// 展开播放列表/频道：`--flat-playlist` 只列条目不解析每个视频；
+/// `max_items` 对应 `--playlist-end`，与 yt-dlp 一样先截取再倒序
+pub fn build_expand_args(
+    url: &str,
+    js_runtime_path: Option<&Path>,
+    network: &YtDlpNetworkOptions,
+    max_items: Option<usize>,
+) -> Vec<String> {
+    let mut args = vec![
+        "--flat-playlist".into(),
+        "--yes-playlist".into(),
+        "--dump-single-json".into(),
+    ];
+    if let Some(max_items) = max_items.filter(|max| *max > 0) {
+        args.push("--playlist-end".into());
+        args.push(max_items.to_string());
+    }
+    append_js_runtime_args(&mut args, js_runtime_path);
+    append_network_args(&mut args, network);
     args.push(url.into());
     args
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn build_download_args(
     url: &str,
     output_dir: &Path,
     output_template: &str,
     ffmpeg_path: Option<&Path>,
     js_runtime_path: Option<&Path>,
+    network: &YtDlpNetworkOptions,
+    format: &YtDlpFormatSelection,
+    sidecars: &YtDlpSidecarOptions,
 ) -> Vec<String> {
-    let mut args = vec![
+    let mut args: Vec<String> = vec![
         "--no-playlist".into(),
         "--newline".into(),
         "--progress".into(),
         "--format".into(),
-        "bv*+ba/b".into(),
-        "--merge-output-format".into(),
-        "mp4".into(),
+        format.selector.clone(),
+    ];
+    if !format.sort.is_empty() {
+        args.push("--format-sort".into());
+        args.push(format.sort.join(","));
+    }
+    match &format.audio_extraction {
+        Some(extraction) => {
+            args.extend([
+                "--extract-audio".into(),
+                "--audio-format".into(),
+                extraction.format.extension().into(),
+            ]);
+            if let Some(kbps) = extraction
+                .bitrate_kbps
+                .filter(|_| !extraction.format.is_lossless())
+            {
+                args.push("--audio-quality".into());
+                args.push(format!("{}K", kbps));
+            }
+        }
+        None => {
+            args.push("--merge-output-format".into());
+            args.push(format.merge_format.clone());
+        }
+    }
+    args.extend([
         "--paths".into(),
         output_dir.to_string_lossy().to_string(),
         "--output".into(),
@@ -371,17 +622,162 @@ pub fn build_download_args(
         "--print".into(),
         "before_dl:filesize:%(filesize)s\t%(filesize_approx)s".into(),
         "--print".into(),
+        "before_dl:format:%(format_id)s\t%(format_note)s".into(),
+        "--print".into(),
         "after_move:filepath:%(filepath)s".into(),
-    ];
+    ]);
     if let Some(path) = ffmpeg_path {
         args.push("--ffmpeg-location".into());
         args.push(path.to_string_lossy().to_string());
     }
+    append_sidecar_args(&mut args, sidecars);
     append_js_runtime_args(&mut args, js_runtime_path);
+    append_network_args(&mut args, network);
     args.push(url.into());
     args
 }
 
+fn append_sidecar_args(args: &mut Vec<String>, sidecars: &YtDlpSidecarOptions) {
+    if sidecars.subtitles {
+        args.push("--write-subs".into());
+        if sidecars.auto_subtitles {
+            args.push("--write-auto-subs".into());
+        }
+        let languages = sidecars
+            .subtitle_languages
+            .iter()
+            .map(|language| language.trim())
+            .filter(|language| !language.is_empty())
+            .collect::<Vec<_>>();
+        if !languages.is_empty() {
+            args.push("--sub-langs".into());
+            args.push(languages.join(","));
+        }
+        if let Some(format) = &sidecars.subtitle_format {
+            args.push("--convert-subs".into());
+            args.push(format.clone());
+        }
+    }
+    if sidecars.thumbnail {
+        args.push("--write-thumbnail".into());
+        args.push("--convert-thumbnails".into());
+        args.push("jpg".into());
+    }
+    if sidecars.description {
+        args.push("--write-description".into());
+    }
+    if sidecars.info_json {
+        args.push("--write-info-json".into());
+    }
+}
+
+const SUBTITLE_EXTENSIONS: [&str; 6] = ["vtt", "srt", "ass", "lrc", "ttml", "srv3"];
+const THUMBNAIL_EXTENSIONS: [&str; 4] = ["jpg", "jpeg", "png", "webp"];
+
+/// 按 yt-dlp 的命名规则识别视频 `<stem>.<ext>` 旁的附属文件：`<stem>.<lang>.vtt`、
+/// `<stem>.jpg`、`<stem>.description`、`<stem>.info.json`
+pub fn sidecar_kind(video_stem: &str, file_name: &str) -> Option<(SidecarKind, Option<String>)> {
+    let rest = file_name.strip_prefix(video_stem)?.strip_prefix('.')?;
+    match rest {
+        "description" => return Some((SidecarKind::Description, None)),
+        "info.json" => return Some((SidecarKind::InfoJson, None)),
+        _ => {}
+    }
+    let (head, ext) = match rest.rsplit_once('.') {
+        Some((head, ext)) => (Some(head), ext.to_ascii_lowercase()),
+        None => (None, rest.to_ascii_lowercase()),
+    };
+    match head {
+        None if THUMBNAIL_EXTENSIONS.contains(&ext.as_str()) => {
+            Some((SidecarKind::Thumbnail, None))
+        }
+        Some(language)
+            if !language.is_empty()
+                && !language.contains('.')
+                && SUBTITLE_EXTENSIONS.contains(&ext.as_str()) =>
+        {
+            Some((SidecarKind::Subtitle, Some(language.to_string())))
+        }
+        _ => None,
+    }
+}
+
+/// 文件名像附属文件（找不到最终文件、只能按目录猜测时用来排除）
+pub fn is_sidecar_file_name(file_name: &str) -> bool {
+    let lower = file_name.to_ascii_lowercase();
+    lower.ends_with(".description")
+        || lower.ends_with(".info.json")
+        || lower.rsplit_once('.').is_some_and(|(_, ext)| {
+            SUBTITLE_EXTENSIONS.contains(&ext) || THUMBNAIL_EXTENSIONS.contains(&ext)
+        })
+}
+
+/// 列出视频旁的附属文件（按文件名排序）
+pub fn discover_sidecar_files(video_path: &Path) -> Vec<SidecarFile> {
+    let (Some(dir), Some(stem), Some(video_name)) = (
+        video_path.parent(),
+        video_path.file_stem().and_then(|stem| stem.to_str()),
+        video_path.file_name(),
+    ) else {
+        return Vec::new();
+    };
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut files = entries
+        .filter_map(Result::ok)
+        .filter(|entry| entry.file_name() != video_name)
+        .filter(|entry| entry.file_type().is_ok_and(|kind| kind.is_file()))
+        .filter_map(|entry| {
+            let (kind, language) = sidecar_kind(stem, entry.file_name().to_str()?)?;
+            Some(SidecarFile {
+                kind,
+                path: entry.path().to_string_lossy().to_string(),
+                language,
+            })
+        })
+        .collect::<Vec<_>>();
+    files.sort_by(|a, b| a.path.cmp(&b.path));
+    files
+}
+
+fn append_network_args(args: &mut Vec<String>, network: &YtDlpNetworkOptions) {
+    if let Some(proxy) = &network.proxy {
+        args.push("--proxy".into());
+        args.push(proxy.clone());
+    }
+    for (name, value) in &network.headers {
+        args.push("--add-header".into());
+        args.push(format!("{}:{}", name, value));
+    }
+    match &network.auth {
+        Some(ExternalAuth::Login { username, password }) => {
+            args.push("--username".into());
+            args.push(username.clone());
+            args.push("--password".into());
+            args.push(password.clone());
+        }
+        Some(ExternalAuth::VideoPassword(password)) => {
+            args.push("--video-password".into());
+            args.push(password.clone());
+        }
+        Some(ExternalAuth::Bearer(token)) => {
+            args.push("--add-header".into());
+            args.push(format!("Authorization:Bearer {}", token));
+        }
+        None => {}
+    }
+    if let Some(cookies_file) = &network.cookies_file {
+        args.push("--cookies".into());
+        args.push(cookies_file.to_string_lossy().into_owned());
+    }
+    if let Some(rate_limit) = network.rate_limit {
+        args.push("--limit-rate".into());
+        args.push(rate_limit.to_string());
+    }
+    args.extend(network.extra_args.iter().cloned());
+}
+
 fn append_js_runtime_args(args: &mut Vec<String>, js_runtime_path: Option<&Path>) {
     if let Some(path) = js_runtime_path {
         args.push("--js-runtimes".into());
@@ -422,9 +818,180 @@ pub fn external_info_from_json(json: &Value, original_url: &str) -> ExternalVide
             .and_then(Value::as_str)
             .map(str::to_string),
         requires_auth: false,
+        playlist_id: json
+            .get("playlist_id")
+            .and_then(Value::as_str)
+            .map(str::to_string),
+        playlist_title: json
+            .get("playlist_title")
+            .and_then(Value::as_str)
+            .map(str::to_string),
+        playlist_index: json
+            .get("playlist_index")
+            .and_then(Value::as_u64)
+            .map(|index| index as u32),
     }
 }
 
+/// 把 `--flat-playlist --dump-single-json` 的输出转成条目列表；
+/// 不是播放列表的地址返回只含它自己的一项
+pub fn playlist_expansion_from_json(
+    json: &Value,
+    original_url: &str,
+    reverse: bool,
+    max_items: Option<usize>,
+) -> PlaylistExpansion {
+    let webpage_url = json
+        .get("webpage_url")
+        .and_then(Value::as_str)
+        .unwrap_or(original_url)
+        .to_string();
+    let string_field = |key: &str| json.get(key).and_then(Value::as_str).map(str::to_string);
+
+    let items = json.get("entries").and_then(Value::as_array);
+    let mut entries: Vec<PlaylistEntry> = match items {
+        Some(items) => items
+            .iter()
+            .enumerate()
+            .filter_map(|(position, item)| playlist_entry_from_json(item, position))
+            .collect(),
+        None => vec![PlaylistEntry {
+            index: 1,
+            url: webpage_url.clone(),
+            id: string_field("id"),
+            title: string_field("title"),
+            duration_seconds: json.get("duration").and_then(Value::as_f64),
+            thumbnail: string_field("thumbnail"),
+        }],
+    };
+    if let Some(max_items) = max_items.filter(|max| *max > 0) {
+        entries.truncate(max_items);
+    }
+    if reverse {
+        entries.reverse();
+    }
+
+    PlaylistExpansion {
+        source_platform: detect_platform(&webpage_url),
+        playlist_id: items.and_then(|_| string_field("id")),
+        title: string_field("title"),
+        extractor: string_field("extractor"),
+        webpage_url,
+        entries,
+    }
+}
+
+fn playlist_entry_from_json(item: &Value, position: usize) -> Option<PlaylistEntry> {
+    // 已删除/私有的条目为 null，嵌套的播放列表（频道标签页）不展开
+    if item.is_null() || item.get("_type").and_then(Value::as_str) == Some("playlist") {
+        return None;
+    }
+    let string_field = |key: &str| item.get(key).and_then(Value::as_str).map(str::to_string);
+    let id = string_field("id");
+    let url = string_field("webpage_url")
+        .or_else(|| string_field("url").filter(|url| url.starts_with("http")))
+        .or_else(|| {
+            // 旧版 yt-dlp 的 YouTube 扁平条目只给视频 ID
+            id.as_ref()
+                .filter(|_| item.get("ie_key").and_then(Value::as_str) == Some("Youtube"))
+                .map(|id| format!("https://www.youtube.com/watch?v={}", id))
+        })?;
+
+    Some(PlaylistEntry {
+        index: item
+            .get("playlist_index")
+            .and_then(Value::as_u64)
+            .map(|index| index as u32)
+            .unwrap_or(position as u32 + 1),
+        url,
+        id,
+        title: string_field("title"),
+        duration_seconds: item.get("duration").and_then(Value::as_f64),
+        thumbnail: string_field("thumbnail").or_else(|| {
+            item.get("thumbnails")
+                .and_then(Value::as_array)
+                .and_then(|thumbnails| thumbnails.last())
+                .and_then(|thumbnail| thumbnail.get("url"))
+                .and_then(Value::as_str)
+                .map(str::to_string)
+        }),
+    })
+}
+
+/// 每个选中的条目建一个 yt-dlp 任务；`selected` 为空时取全部。
+/// 按播放列表顺序递增 created_at，队列按同样的顺序开始下载
+pub fn playlist_tasks(
+    playlist: &PlaylistExpansion,
+    selected: Option<&[u32]>,
+    output_path: &str,
+) -> Vec<VideoTask> {
+    let now = chrono::Utc::now();
+    playlist
+        .entries
+        .iter()
+        .filter(|entry| selected.is_none_or(|selected| selected.contains(&entry.index)))
+        .enumerate()
+        .map(|(position, entry)| {
+            let created_at = now + chrono::Duration::milliseconds(position as i64);
+            VideoTask {
+                id: uuid::Uuid::new_v4().to_string(),
+                url: entry.url.clone(),
+                mirrors: Vec::new(),
+                rate_limit: None,
+                variant_policy: None,
+                ytdlp_format: None,
+                hls_variant: None,
+                recording: None,
+                segment_gaps: Vec::new(),
+                sidecar_files: Vec::new(),
+                audio_extraction: None,
+                title: entry
+                    .title
+                    .clone()
+                    .unwrap_or_else(|| format!("{} #{}", playlist_label(playlist), entry.index)),
+                output_path: output_path.to_string(),
+                resolved_path: None,
+                status: TaskStatus::Pending,
+                progress: 0.0,
+                file_size: None,
+                downloaded_size: 0,
+                speed: 0.0,
+                display_speed_bps: 0,
+                eta: None,
+                error_message: None,
+                created_at,
+                updated_at: created_at,
+                paused_at: None,
+                paused_from_active: false,
+                downloader_type: Some(DownloaderType::YtDlp),
+                video_info: None,
+                external_info: Some(ExternalVideoInfo {
+                    source_platform: detect_platform(&entry.url),
+                    extractor: playlist.extractor.clone(),
+                    webpage_url: Some(entry.url.clone()),
+                    title: entry.title.clone(),
+                    thumbnail: entry.thumbnail.clone(),
+                    duration_seconds: entry.duration_seconds,
+                    format_id: None,
+                    format_note: None,
+                    requires_auth: false,
+                    playlist_id: playlist.playlist_id.clone(),
+                    playlist_title: playlist.title.clone(),
+                    playlist_index: Some(entry.index),
+                }),
+            }
+        })
+        .collect()
+}
+
+fn playlist_label(playlist: &PlaylistExpansion) -> &str {
+    playlist
+        .title
+        .as_deref()
+        .or(playlist.playlist_id.as_deref())
+        .unwrap_or("playlist")
+}
+
 pub fn classify_error(message: &str) -> String {
     let normalized = message.to_lowercase();
     if normalized.contains("ffmpeg")
@@ -436,7 +1003,7 @@ pub fn classify_error(message: &str) -> String {
         || normalized.contains("private")
         || normalized.contains("age-restricted")
     {
-        "authentication_required: public-only downloads do not use cookies or login state".into()
+        "authentication_required: sign-in required; import a cookies.txt or add credentials for this site".into()
     } else if normalized.contains("not available in your country")
         || normalized.contains("geo")
         || normalized.contains("copyright")
@@ -47,8 +47,49 @@ pub fn spawn_download_event_bridge(
                 DownloadEvent::TaskCommitting { task_id } => {
                     emit_status_change(&app_handle, task_id, "Committing", None, false);
                 }
-                DownloadEvent::TaskCompleted { task_id, .. } => {
-                    emit_status_change(&app_handle, task_id, "Completed", None, false);
+                DownloadEvent::TaskFileResolved { task_id, file_path } => {
+                    let _ = emit_download_event(
+                        &app_handle,
+                        "task.file_resolved",
+                        &json!({ "task_id": task_id, "file_path": file_path }),
+                    );
+                }
+                DownloadEvent::TaskVariantSelected { task_id, variant } => {
+                    let _ = emit_download_event(
+                        &app_handle,
+                        "task.variant_selected",
+                        &json!({ "task_id": task_id, "variant": variant }),
+                    );
+                }
+                DownloadEvent::TaskSegmentGaps { task_id, gaps } => {
+                    let _ = emit_download_event(
+                        &app_handle,
+                        "task.segment_gaps",
+                        &json!({ "task_id": task_id, "gaps": gaps }),
+                    );
+                }
+                DownloadEvent::TaskFormatSelected { task_id, format } => {
+                    let _ = emit_download_event(
+                        &app_handle,
+                        "task.format_selected",
+                        &json!({
+                            "task_id": task_id,
+                            "format_id": format.format_id,
+                            "format_note": format.format_note,
+                        }),
+                    );
+                }
+                DownloadEvent::TaskSidecarFiles { task_id, files } => {
+                    let _ = emit_download_event(
+                        &app_handle,
+                        "task.sidecar_files",
+                        &json!({ "task_id": task_id, "files": files }),
+                    );
+                }
+                DownloadEvent::TaskCompleted {
+                    task_id, warning, ..
+                } => {
+                    emit_status_change(&app_handle, task_id, "Completed", warning, false);
                 }
                 DownloadEvent::TaskFailed { task_id, error } => {
                     emit_status_change(&app_handle, task_id, "Failed", Some(error), false);
@@ -65,6 +106,9 @@ pub fn spawn_download_event_bridge(
                 DownloadEvent::StatsUpdated { stats } => {
                     let _ = emit_download_event(&app_handle, "task.stats_updated", &stats);
                 }
+                DownloadEvent::RateLimitChanged { status } => {
+                    let _ = emit_download_event(&app_handle, "rate_limit.changed", &status);
+                }
                 _ => {}
             }
         }
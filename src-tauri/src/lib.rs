@@ -49,7 +49,7 @@ pub struct AppState {
 impl AppState {
     pub fn new() -> anyhow::Result<Self> {
         let mut config = core::app_bootstrap::load_or_initialize_config();
-        let download_config = config.download.clone();
+        let download_config = config.effective_download_config();
 
         let downloader_config =
             core::app_bootstrap::downloader_config_from_download_config(&download_config);
@@ -58,9 +58,14 @@ impl AppState {
 
         core::app_bootstrap::ensure_optional_config_defaults(&mut config);
 
-        let download_manager = Arc::new(tokio::sync::RwLock::new(DownloadManager::new(
-            download_config,
-        )?));
+        let credentials = core::app_bootstrap::open_credential_store();
+        http_downloader.set_credential_store(credentials.clone());
+        let cookies = core::app_bootstrap::open_cookie_jar();
+        http_downloader.set_cookie_jar(cookies.clone());
+        let download_manager = DownloadManager::new(download_config)?;
+        download_manager.set_credential_store(credentials);
+        download_manager.set_cookie_jar(cookies);
+        let download_manager = Arc::new(tokio::sync::RwLock::new(download_manager));
         let download_runtime = spawn_download_runtime(download_manager.clone());
         let task_engine = spawn_task_engine(Arc::new(download_runtime.clone()));
 
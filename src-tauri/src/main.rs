@@ -24,7 +24,10 @@ use core::{
     downloader::HttpDownloader,
     models::{AppError, DownloadConfig},
     queue_scheduler::spawn_queue_scheduler,
-    runtime::{create_download_runtime_handle, spawn_router_loop, DownloadRuntimeHandle},
+    runtime::{
+        create_download_runtime_handle, spawn_bandwidth_schedule_loop, spawn_router_loop,
+        DownloadRuntimeHandle,
+    },
     AppConfig, DownloadManager,
 };
 use engine::task_engine::{spawn_task_engine, TaskEngineHandle};
@@ -68,11 +71,16 @@ impl AppState {
     fn try_new() -> Result<Self, String> {
         // 使用更安全的方式优先加载本地配置，失败时再回退到默认值
         let mut config = core::app_bootstrap::load_or_initialize_config();
-        let download_config = config.download.clone();
+        let download_config = config.effective_download_config();
 
         // 简化DownloadManager创建
         let download_manager = DownloadManager::new(download_config.clone())
             .map_err(|e| format!("DownloadManager creation failed: {}", e))?;
+        // 管理器与命令层的下载器共用同一个凭据库
+        let credentials = core::app_bootstrap::open_credential_store();
+        download_manager.set_credential_store(credentials.clone());
+        let cookies = core::app_bootstrap::open_cookie_jar();
+        download_manager.set_cookie_jar(cookies.clone());
         let download_manager = Arc::new(RwLock::new(download_manager));
 
         // 创建 runtime handle 但不立即 spawn router（等待 Tauri runtime）
@@ -88,6 +96,9 @@ impl AppState {
 
         core::app_bootstrap::ensure_optional_config_defaults(&mut config);
 
+        http_downloader.set_credential_store(credentials);
+        http_downloader.set_cookie_jar(cookies);
+
         Ok(Self {
             download_manager,
             http_downloader: Arc::new(RwLock::new(http_downloader)),
@@ -103,7 +114,7 @@ impl AppState {
         let mut config = core::app_bootstrap::load_or_initialize_config();
 
         // 如果DownloadManager创建失败，使用更简单的配置
-        let download_manager = match DownloadManager::new(config.download.clone()) {
+        let download_manager = match DownloadManager::new(config.effective_download_config()) {
             Ok(manager) => manager,
             Err(error) => {
                 warn!(
@@ -123,6 +134,10 @@ impl AppState {
                 manager
             }
         };
+        let credentials = core::app_bootstrap::open_credential_store();
+        download_manager.set_credential_store(credentials.clone());
+        let cookies = core::app_bootstrap::open_cookie_jar();
+        download_manager.set_cookie_jar(cookies.clone());
         let download_manager = Arc::new(RwLock::new(download_manager));
         let (download_runtime, router_rx) =
             create_download_runtime_handle(download_manager.clone());
@@ -131,6 +146,8 @@ impl AppState {
         let downloader_config = core::app_bootstrap::fallback_downloader_config();
         let http_downloader = core::app_bootstrap::create_http_downloader(downloader_config)
             .map_err(|error| format!("Cannot create fallback HttpDownloader: {}", error))?;
+        http_downloader.set_credential_store(credentials);
+        http_downloader.set_cookie_jar(cookies);
 
         Ok(Self {
             download_manager,
@@ -185,7 +202,12 @@ fn main() {
         .invoke_handler(tauri::generate_handler![
             // 下载相关命令
             add_download_tasks,
+            expand_playlist,
+            add_playlist_tasks,
             update_task_output_paths,
+            set_task_variant_policy,
+            set_task_audio_extraction,
+            stop_live_recording,
             start_download,
             pause_download,
             resume_download,
@@ -200,6 +222,9 @@ fn main() {
             retry_failed_tasks,
             set_rate_limit,
             get_rate_limit,
+            clear_rate_limit_override,
+            set_task_rate_limit,
+            get_rate_limit_status,
             // 导入相关命令
             import_file,
             import_csv_file,
@@ -213,6 +238,14 @@ fn main() {
             reset_config,
             export_config,
             import_config,
+            get_platform_rules,
+            // 凭据与 cookie 相关命令
+            list_credentials,
+            save_credential,
+            delete_credential,
+            import_cookies,
+            list_cookie_domains,
+            clear_cookies,
             // 系统相关命令
             open_download_folder,
             reveal_path_in_folder,
@@ -242,6 +275,7 @@ fn main() {
             } else {
                 warn!("⚠️ Router receiver already taken or not available");
             }
+            spawn_bandwidth_schedule_loop(app_state.download_runtime.clone());
 
             if logging::local_logging_enabled() {
                 // Emit a bootstrap log so frontend diagnostics file exists even before UI mounts
@@ -1,80 +1,665 @@
-//! M3U8 playlist parsing utilities
+//! HLS (RFC 8216) playlist parsing
+//!
+//! 下载器和预览共用的唯一播放列表模型。语法解析交给 `m3u8-rs`，这里负责
+//! 补齐它不处理的语义：EXT-X-KEY / EXT-X-MAP 对后续所有片段生效、相对地址、
+//! 省略偏移的 BYTERANGE、媒体序号和直播判断，以及把 master playlist 转成
+//! `HlsVariant` / `HlsRendition`。
 
-use anyhow::{anyhow, Result};
-use serde::{Deserialize, Serialize};
+use crate::core::hls_variant::{HlsRendition, HlsVariant, RenditionKind};
+use anyhow::{anyhow, bail, Result};
+use chrono::{DateTime, FixedOffset};
+use m3u8_rs::{AlternativeMediaType, Key, KeyMethod, MediaPlaylistType, Playlist};
+use std::path::PathBuf;
 use url::Url;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct M3u8Segment {
+/// M3U8播放列表信息
+#[derive(Debug, Clone)]
+pub struct M3U8Playlist {
+    /// 播放列表URL
     pub url: String,
-    pub duration: Option<f64>,
-    pub sequence: Option<u64>,
+    /// 基础URL（用于解析相对路径）
+    pub base_url: String,
+    /// 片段列表
+    pub segments: Vec<M3U8Segment>,
+    /// 总时长（秒）
+    pub duration: f64,
+    /// 是否为Live流（没有 EXT-X-ENDLIST 且不是 VOD 类型）
+    pub is_live: bool,
+    /// EXT-X-MEDIA-SEQUENCE
+    pub media_sequence: u64,
+    /// EXT-X-DISCONTINUITY-SEQUENCE
+    pub discontinuity_sequence: u64,
+    /// 目标时长
+    pub target_duration: f64,
+    /// 版本
+    pub version: u32,
+    /// 加密信息（列表末尾生效的 EXT-X-KEY）
+    pub encryption: Option<M3U8Encryption>,
+    /// EXT-X-MAP 初始化段（fMP4/CMAF 流），按出现顺序
+    pub init_sections: Vec<M3U8InitSection>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct M3u8Playlist {
-    pub segments: Vec<M3u8Segment>,
-    pub is_live: bool,
-    pub target_duration: Option<f64>,
+impl M3U8Playlist {
+    /// 带 EXT-X-MAP 的播放列表视为 fMP4 分片
+    pub fn is_fmp4(&self) -> bool {
+        !self.init_sections.is_empty()
+    }
+}
+
+/// EXT-X-MAP 声明的媒体初始化段
+#[derive(Debug, Clone)]
+pub struct M3U8InitSection {
+    pub url: String,
+    /// 字节范围（起止，含）
+    pub byte_range: Option<(u64, u64)>,
+    /// 声明 EXT-X-MAP 时生效的 EXT-X-KEY
+    pub encryption: Option<M3U8Encryption>,
+}
+
+/// M3U8片段信息
+#[derive(Debug, Clone)]
+pub struct M3U8Segment {
+    /// 片段索引（本次下载内的顺序，也用于临时文件名）
+    pub index: usize,
+    /// 媒体序号（EXT-X-MEDIA-SEQUENCE + 位置），直播增量录制和默认 IV 都以它为准
+    pub sequence: u64,
+    /// 片段URL
+    pub url: String,
+    /// 时长（秒）
+    pub duration: f64,
+    /// 字节范围（可选）
+    pub byte_range: Option<(u64, u64)>,
+    /// 片段前有 EXT-X-DISCONTINUITY
+    pub discontinuity: bool,
+    /// EXT-X-PROGRAM-DATE-TIME（只在声明它的片段上）
+    pub program_date_time: Option<DateTime<FixedOffset>>,
+    /// 是否已下载
+    pub downloaded: bool,
+    /// 本地文件路径
+    pub local_path: Option<PathBuf>,
+    /// 片段加密信息
+    pub encryption: Option<M3U8Encryption>,
+    /// 所属初始化段在 `M3U8Playlist::init_sections` 中的下标
+    pub init_section: Option<usize>,
+}
+
+/// M3U8加密信息
+#[derive(Debug, Clone)]
+pub struct M3U8Encryption {
+    /// 加密方法
+    pub method: String,
+    /// 密钥URL
+    pub key_url: Option<String>,
+    /// 初始化向量
+    pub iv: Option<String>,
+    /// 密钥数据
+    pub key_data: Option<Vec<u8>>,
+    /// KEYFORMAT；缺省即 "identity"（密钥地址直接返回 16 字节密钥）
+    pub key_format: Option<String>,
+    /// KEYFORMATVERSIONS
+    pub key_format_versions: Option<String>,
+}
+
+impl M3U8Encryption {
+    /// 是否为可直接获取的明文密钥；其他 KEYFORMAT 是 DRM 系统的密钥
+    pub fn is_identity_key(&self) -> bool {
+        self.key_format
+            .as_deref()
+            .is_none_or(|format| format.eq_ignore_ascii_case("identity"))
+    }
+}
+
+/// master playlist 中的档位和音轨/字幕
+#[derive(Debug, Clone)]
+pub struct HlsMasterPlaylist {
+    pub variants: Vec<HlsVariant>,
+    pub renditions: Vec<HlsRendition>,
+}
+
+#[derive(Debug, Clone)]
+pub enum HlsPlaylist {
+    Master(HlsMasterPlaylist),
+    Media(Box<M3U8Playlist>),
 }
 
-/// Parse M3U8 playlist content
-pub fn parse_m3u8_content(content: &str, base_url: Option<&str>) -> Result<M3u8Playlist> {
-    let lines: Vec<&str> = content.lines().collect();
-    let mut segments = Vec::new();
-    let mut is_live = false;
-    let mut target_duration = None;
-    let mut current_duration = None;
-    let mut sequence_counter = 0u64;
+/// 解析任意播放列表，相对地址按 `playlist_url` 解析
+pub fn parse_hls_playlist(playlist_url: &str, content: &str) -> Result<HlsPlaylist> {
+    let content = normalize(content)?;
+    match m3u8_rs::parse_playlist_res(content.as_bytes()) {
+        Ok(Playlist::MasterPlaylist(master)) => {
+            Ok(HlsPlaylist::Master(convert_master(playlist_url, master)?))
+        }
+        Ok(Playlist::MediaPlaylist(media)) => Ok(HlsPlaylist::Media(Box::new(convert_media(
+            playlist_url,
+            media,
+        )?))),
+        Err(_) => bail!("无效的M3U8文件格式"),
+    }
+}
 
-    for line in lines {
-        let line = line.trim();
+/// 解析媒体播放列表；拿到 master playlist 时报错
+pub fn parse_media_playlist(playlist_url: &str, content: &str) -> Result<M3U8Playlist> {
+    match parse_hls_playlist(playlist_url, content)? {
+        HlsPlaylist::Media(playlist) => Ok(*playlist),
+        HlsPlaylist::Master(_) => {
+            bail!("期望媒体播放列表，实际是 master playlist: {}", playlist_url)
+        }
+    }
+}
 
-        if let Some(stripped) = line.strip_prefix("#EXT-X-TARGETDURATION:") {
-            if let Ok(duration) = stripped.parse::<f64>() {
-                target_duration = Some(duration);
+/// 解析 master playlist；拿到媒体播放列表时报错
+pub fn parse_master_playlist(playlist_url: &str, content: &str) -> Result<HlsMasterPlaylist> {
+    match parse_hls_playlist(playlist_url, content)? {
+        HlsPlaylist::Master(master) => Ok(master),
+        HlsPlaylist::Media(_) => {
+            bail!("期望 master playlist，实际是媒体播放列表: {}", playlist_url)
+        }
+    }
+}
+
+/// 是否为 master playlist（第一个可识别的标签属于 master playlist）
+pub fn is_master_playlist(content: &str) -> bool {
+    normalize(content).is_ok_and(|content| m3u8_rs::is_master_playlist(content.as_bytes()))
+}
+
+/// 按播放列表地址解析相对地址；已是绝对地址时原样返回
+pub fn resolve_playlist_url(playlist_url: &str, uri: &str) -> Result<String> {
+    let base = Url::parse(playlist_url).map_err(|e| anyhow!("无效的播放列表地址: {}", e))?;
+    Ok(base.join(uri.trim())?.to_string())
+}
+
+/// 去掉 BOM 并补上结尾换行（`m3u8-rs` 的部分标签要求行尾）
+fn normalize(content: &str) -> Result<String> {
+    let content = content.trim_start_matches('\u{feff}').trim_start();
+    if !content.starts_with("#EXTM3U") {
+        bail!("无效的M3U8文件格式");
+    }
+    let mut content = content.to_string();
+    if !content.ends_with('\n') {
+        content.push('\n');
+    }
+    Ok(content)
+}
+
+fn convert_media(playlist_url: &str, media: m3u8_rs::MediaPlaylist) -> Result<M3U8Playlist> {
+    let mut playlist = M3U8Playlist {
+        url: playlist_url.to_string(),
+        base_url: Url::parse(playlist_url)?.to_string(),
+        segments: Vec::with_capacity(media.segments.len()),
+        duration: 0.0,
+        // 没有 ENDLIST 的 EVENT/直播列表还会继续追加片段
+        is_live: !media.end_list && media.playlist_type != Some(MediaPlaylistType::Vod),
+        media_sequence: media.media_sequence,
+        discontinuity_sequence: media.discontinuity_sequence,
+        target_duration: exact_seconds(media.target_duration),
+        version: media.version.unwrap_or(1) as u32,
+        encryption: None,
+        init_sections: Vec::new(),
+    };
+
+    // m3u8-rs 只把 KEY/MAP 挂在紧随其后的第一个片段上，按规范它们对后续片段一直生效
+    let mut current_encryption: Option<M3U8Encryption> = None;
+    let mut current_init: Option<usize> = None;
+    let mut last_byte_range_end: Option<u64> = None;
+
+    for (index, segment) in media.segments.into_iter().enumerate() {
+        for tag in &segment.unknown_tags {
+            let rest = tag.rest.as_deref().unwrap_or_default();
+            match tag.tag.as_str() {
+                // 不带 IV 的 METHOD=NONE 会被 m3u8-rs 当作未知标签
+                "X-KEY" if is_method_none(rest) => current_encryption = None,
+                "X-KEY" => bail!("无法解析的 EXT-X-KEY: {}", rest),
+                "X-MAP" => bail!("无法解析的 EXT-X-MAP: {}", rest),
+                _ => {}
             }
-        } else if line.starts_with("#EXTINF:") {
-            if let Some(duration_str) = line.strip_prefix("#EXTINF:") {
-                if let Some(comma_pos) = duration_str.find(',') {
-                    if let Ok(duration) = duration_str[..comma_pos].parse::<f64>() {
-                        current_duration = Some(duration);
-                    }
+        }
+        if let Some(key) = &segment.key {
+            current_encryption = convert_key(playlist_url, key)?;
+        }
+        // 初始化段可在 DISCONTINUITY 处更换；与 KEY 同在一个片段前时按 KEY 在前处理
+        if let Some(map) = &segment.map {
+            let byte_range = map
+                .byte_range
+                .as_ref()
+                .and_then(|range| inclusive_range(range.length, range.offset.unwrap_or(0)));
+            playlist.init_sections.push(M3U8InitSection {
+                url: resolve_playlist_url(playlist_url, &map.uri)?,
+                byte_range,
+                encryption: current_encryption.clone(),
+            });
+            current_init = Some(playlist.init_sections.len() - 1);
+        }
+
+        let byte_range = match &segment.byte_range {
+            Some(range) => {
+                // 省略偏移时紧接上一个片段的范围
+                let start = range
+                    .offset
+                    .or_else(|| last_byte_range_end.map(|end| end + 1))
+                    .unwrap_or(0);
+                let byte_range = inclusive_range(range.length, start);
+                if byte_range.is_none() {
+                    tracing::warn!("检测到长度为 0 的 EXT-X-BYTERANGE: {}", segment.uri);
                 }
+                byte_range
             }
-        } else if let Some(playlist_type) = line.strip_prefix("#EXT-X-PLAYLIST-TYPE:") {
-            is_live = playlist_type != "VOD";
-        } else if !line.starts_with('#') && !line.is_empty() {
-            // This is a segment URL
-            let segment_url = if let Some(base) = base_url {
-                resolve_url(base, line)?
-            } else {
-                line.to_string()
-            };
-
-            segments.push(M3u8Segment {
-                url: segment_url,
-                duration: current_duration.take(),
-                sequence: Some(sequence_counter),
-            });
+            None => None,
+        };
+        if let Some((_, end)) = byte_range {
+            last_byte_range_end = Some(end);
+        }
+
+        let duration = exact_seconds(segment.duration);
+        playlist.duration += duration;
+        playlist.segments.push(M3U8Segment {
+            index,
+            sequence: playlist.media_sequence + index as u64,
+            url: resolve_playlist_url(playlist_url, &segment.uri)?,
+            duration,
+            byte_range,
+            discontinuity: segment.discontinuity,
+            program_date_time: segment.program_date_time,
+            downloaded: false,
+            local_path: None,
+            encryption: current_encryption.clone(),
+            init_section: current_init,
+        });
+    }
+    playlist.encryption = current_encryption;
 
-            sequence_counter += 1;
+    tracing::debug!(
+        "解析完成: {} 个片段, 总时长: {:.2}秒",
+        playlist.segments.len(),
+        playlist.duration
+    );
+    Ok(playlist)
+}
+
+fn convert_master(
+    playlist_url: &str,
+    master: m3u8_rs::MasterPlaylist,
+) -> Result<HlsMasterPlaylist> {
+    // 解析失败的 STREAM-INF 会落到未知标签里，它后面的 URI 还会覆盖上一个档位的地址
+    if let Some(tag) = master
+        .unknown_tags
+        .iter()
+        .find(|tag| tag.tag == "X-STREAM-INF")
+    {
+        bail!(
+            "无法解析的 EXT-X-STREAM-INF（缺少 BANDWIDTH 或属性格式错误）: {}",
+            tag.rest.as_deref().unwrap_or_default()
+        );
+    }
+
+    let mut variants = Vec::new();
+    for stream in master.variants {
+        // I-frame 档位只用于快进预览；没有 URI 行的档位无法下载
+        if stream.is_i_frame || stream.uri.trim().is_empty() {
+            continue;
         }
+        variants.push(HlsVariant {
+            uri: resolve_playlist_url(playlist_url, &stream.uri)?,
+            bandwidth: stream.bandwidth,
+            average_bandwidth: stream.average_bandwidth,
+            width: stream
+                .resolution
+                .and_then(|resolution| u32::try_from(resolution.width).ok()),
+            height: stream
+                .resolution
+                .and_then(|resolution| u32::try_from(resolution.height).ok()),
+            codecs: stream.codecs,
+            frame_rate: stream.frame_rate,
+            audio_group: stream.audio,
+            subtitles_group: stream.subtitles,
+        });
+    }
+    if variants.is_empty() {
+        bail!("master playlist 中没有可用的档位");
+    }
+
+    for tag in master
+        .unknown_tags
+        .iter()
+        .filter(|tag| tag.tag == "X-MEDIA")
+    {
+        tracing::warn!(
+            "忽略无法解析的 EXT-X-MEDIA: {}",
+            tag.rest.as_deref().unwrap_or_default()
+        );
+    }
+    let mut renditions = Vec::new();
+    for media in master.alternatives {
+        // VIDEO / CLOSED-CAPTIONS 不单独下载
+        let kind = match media.media_type {
+            AlternativeMediaType::Audio => RenditionKind::Audio,
+            AlternativeMediaType::Subtitles => RenditionKind::Subtitles,
+            _ => continue,
+        };
+        renditions.push(HlsRendition {
+            kind,
+            group_id: media.group_id,
+            name: media.name,
+            language: media.language,
+            uri: media
+                .uri
+                .map(|uri| resolve_playlist_url(playlist_url, &uri))
+                .transpose()?,
+            default: media.default,
+        });
     }
 
-    Ok(M3u8Playlist {
-        segments,
-        is_live,
-        target_duration,
+    Ok(HlsMasterPlaylist {
+        variants,
+        renditions,
     })
 }
 
-fn resolve_url(base: &str, relative: &str) -> Result<String> {
-    let base_url = Url::parse(base).map_err(|e| anyhow!("Invalid base URL: {}", e))?;
+fn convert_key(playlist_url: &str, key: &Key) -> Result<Option<M3U8Encryption>> {
+    let method = match &key.method {
+        KeyMethod::None => return Ok(None),
+        KeyMethod::AES128 => "AES-128".to_string(),
+        KeyMethod::SampleAES => "SAMPLE-AES".to_string(),
+        KeyMethod::Other(method) => method.clone(),
+    };
+    Ok(Some(M3U8Encryption {
+        method,
+        key_url: key
+            .uri
+            .as_deref()
+            .map(|uri| resolve_playlist_url(playlist_url, uri))
+            .transpose()?,
+        iv: key.iv.clone(),
+        key_data: None,
+        key_format: key.keyformat.clone(),
+        key_format_versions: key.keyformatversions.clone(),
+    }))
+}
+
+fn is_method_none(attributes: &str) -> bool {
+    attribute_pairs(attributes)
+        .into_iter()
+        .any(|(name, value)| name == "METHOD" && value == "NONE")
+}
+
+/// 拆分 `NAME=VALUE,...` 属性列表；引号内的逗号属于取值本身
+fn attribute_pairs(attributes: &str) -> Vec<(&str, &str)> {
+    let mut items = Vec::new();
+    let mut start = 0;
+    let mut in_quotes = false;
+    for (index, ch) in attributes.char_indices() {
+        match ch {
+            '"' => in_quotes = !in_quotes,
+            ',' if !in_quotes => {
+                items.push(&attributes[start..index]);
+                start = index + 1;
+            }
+            _ => {}
+        }
+    }
+    items.push(&attributes[start..]);
+    items
+        .into_iter()
+        .filter_map(|item| item.split_once('='))
+        .map(|(name, value)| (name.trim(), value.trim()))
+        .collect()
+}
+
+/// `长度@起点` -> 含两端的 (起, 止)；长度为 0 时没有范围
+fn inclusive_range(length: u64, start: u64) -> Option<(u64, u64)> {
+    (length > 0).then(|| (start, start.saturating_add(length - 1)))
+}
+
+/// m3u8-rs 用 f32 保存时长，按最短十进制表示转回 f64（9.009 而不是 9.008999824523926）
+fn exact_seconds(value: f32) -> f64 {
+    value.to_string().parse().unwrap_or(value as f64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const APPLE_MASTER: &str = include_str!("testdata/hls/apple_master_media_groups.m3u8");
+    const FMP4_BYTERANGE: &str = include_str!("testdata/hls/fmp4_single_file_byterange.m3u8");
+    const AES_KEY_ROTATION: &str = include_str!("testdata/hls/aes128_key_rotation.m3u8");
+    const LIVE_WINDOW: &str = include_str!("testdata/hls/live_sliding_window.m3u8");
+    const SAMPLE_AES_DRM: &str = include_str!("testdata/hls/sample_aes_fairplay.m3u8");
+    const KEY_NONE_QUOTED_COMMAS: &str = include_str!("testdata/hls/key_none_quoted_commas.m3u8");
+
+    fn media(url: &str, content: &str) -> M3U8Playlist {
+        parse_media_playlist(url, content).unwrap()
+    }
+
+    #[test]
+    fn corpus_master_playlist_with_media_groups() {
+        let url = "https://devstreaming.example.com/videos/advanced/master.m3u8";
+        assert!(is_master_playlist(APPLE_MASTER));
+        let master = parse_master_playlist(url, APPLE_MASTER).unwrap();
+
+        // I-frame 档位不参与下载
+        assert_eq!(master.variants.len(), 6);
+        let top = master
+            .variants
+            .iter()
+            .max_by_key(|variant| variant.bandwidth)
+            .unwrap();
+        assert_eq!(
+            top.uri,
+            "https://devstreaming.example.com/videos/advanced/v9/prog_index.m3u8"
+        );
+        assert_eq!((top.width, top.height), (Some(1920), Some(1080)));
+        assert_eq!(top.codecs.as_deref(), Some("avc1.64002a,mp4a.40.2"));
+        assert_eq!(top.frame_rate, Some(60.0));
+        assert_eq!(top.average_bandwidth, Some(7_968_416));
+        assert_eq!(top.audio_group.as_deref(), Some("aac"));
+        assert_eq!(top.subtitles_group.as_deref(), Some("subs"));
+
+        let audio: Vec<_> = master
+            .renditions
+            .iter()
+            .filter(|rendition| rendition.kind == RenditionKind::Audio)
+            .collect();
+        assert_eq!(audio.len(), 3);
+        assert!(audio[0].default);
+        assert_eq!(audio[1].language.as_deref(), Some("es"));
+        assert_eq!(
+            audio[1].uri.as_deref(),
+            Some("https://devstreaming.example.com/videos/advanced/a1/es/prog_index.m3u8")
+        );
+        // 引号内带逗号的 NAME 保持完整
+        assert_eq!(audio[2].name, "Commentary, Director");
+        let subtitles: Vec<_> = master
+            .renditions
+            .iter()
+            .filter(|rendition| rendition.kind == RenditionKind::Subtitles)
+            .map(|rendition| rendition.language.as_deref().unwrap_or_default())
+            .collect();
+        assert_eq!(subtitles, vec!["en", "fr", "ja"]);
+    }
+
+    #[test]
+    fn corpus_fmp4_single_file_with_implicit_byterange_offsets() {
+        let playlist = media("https://cdn.example.com/cmaf/main.m3u8", FMP4_BYTERANGE);
+        assert!(playlist.is_fmp4());
+        assert!(!playlist.is_live);
+        assert_eq!(playlist.version, 7);
+        assert_eq!(playlist.target_duration, 6.0);
+        assert_eq!(playlist.init_sections.len(), 1);
+        assert_eq!(
+            playlist.init_sections[0].url,
+            "https://cdn.example.com/cmaf/main.mp4"
+        );
+        assert_eq!(playlist.init_sections[0].byte_range, Some((0, 1117)));
+
+        let ranges: Vec<_> = playlist
+            .segments
+            .iter()
+            .map(|segment| segment.byte_range)
+            .collect();
+        assert_eq!(
+            ranges,
+            vec![
+                Some((1118, 327_817)),
+                Some((327_818, 655_931)),
+                Some((655_932, 983_399)),
+                Some((983_400, 1_101_215)),
+            ]
+        );
+        assert!(playlist.segments.iter().all(|segment| segment.url
+            == "https://cdn.example.com/cmaf/main.mp4"
+            && segment.init_section == Some(0)));
+        assert!((playlist.duration - 19.019).abs() < 1e-9);
+    }
+
+    #[test]
+    fn corpus_aes128_key_rotation_keyformat_and_quoted_commas() {
+        let playlist = media(
+            "https://vod.example.com/course/7/index.m3u8",
+            AES_KEY_ROTATION,
+        );
+        assert_eq!(playlist.media_sequence, 7794);
+
+        let first = playlist.segments[0].encryption.as_ref().unwrap();
+        assert_eq!(first.method, "AES-128");
+        // URI 的查询参数里有逗号
+        assert_eq!(
+            first.key_url.as_deref(),
+            Some("https://keys.example.com/hls/key?id=42,43&sig=ab%2Ccd")
+        );
+        assert_eq!(
+            first.iv.as_deref(),
+            Some("0x00000000000000000000000000001E72")
+        );
+        assert_eq!(first.key_format.as_deref(), Some("identity"));
+        assert_eq!(first.key_format_versions.as_deref(), Some("1"));
+        assert!(first.is_identity_key());
+
+        let key_urls: Vec<_> = playlist
+            .segments
+            .iter()
+            .map(|segment| {
+                segment
+                    .encryption
+                    .as_ref()
+                    .and_then(|encryption| encryption.key_url.as_deref())
+            })
+            .collect();
+        assert_eq!(
+            key_urls,
+            vec![
+                Some("https://keys.example.com/hls/key?id=42,43&sig=ab%2Ccd"),
+                Some("https://keys.example.com/hls/key?id=42,43&sig=ab%2Ccd"),
+                Some("https://vod.example.com/course/7/keys/rotated.bin"),
+                None,
+                None,
+                Some("https://vod.example.com/course/7/keys/rotated.bin"),
+            ]
+        );
+        // 不带 IV 的 KEY 由媒体序号推导 IV
+        assert!(playlist.segments[2]
+            .encryption
+            .as_ref()
+            .is_some_and(|encryption| encryption.iv.is_none()));
+        assert_eq!(playlist.segments[5].sequence, 7799);
+        assert!(playlist.encryption.is_some());
+    }
 
-    let resolved = base_url
-        .join(relative)
-        .map_err(|e| anyhow!("Failed to resolve URL: {}", e))?;
+    #[test]
+    fn corpus_live_window_with_discontinuity_and_program_date_time() {
+        let playlist = media("https://live.example.com/ch1/chunklist.m3u8", LIVE_WINDOW);
+        assert!(playlist.is_live);
+        assert_eq!(playlist.media_sequence, 2_680);
+        assert_eq!(playlist.discontinuity_sequence, 13);
+        assert_eq!(playlist.target_duration, 4.0);
 
-    Ok(resolved.to_string())
+        let sequences: Vec<u64> = playlist.segments.iter().map(|s| s.sequence).collect();
+        assert_eq!(sequences, vec![2_680, 2_681, 2_682, 2_683, 2_684]);
+        let discontinuities: Vec<bool> =
+            playlist.segments.iter().map(|s| s.discontinuity).collect();
+        assert_eq!(discontinuities, vec![false, false, true, false, false]);
+
+        let first_pdt = playlist.segments[0].program_date_time.unwrap();
+        assert_eq!(first_pdt.to_rfc3339(), "2026-03-14T09:26:40.120+08:00");
+        let ad_pdt = playlist.segments[2].program_date_time.unwrap();
+        assert_eq!(ad_pdt.timestamp(), first_pdt.timestamp() + 8);
+        assert!(playlist.segments[1].program_date_time.is_none());
+        assert_eq!(
+            playlist.segments[2].url,
+            "https://ads.example.com/break/0001.ts?token=a,b"
+        );
+    }
+
+    #[test]
+    fn corpus_sample_aes_with_drm_keyformat() {
+        let playlist = media("https://drm.example.com/title/index.m3u8", SAMPLE_AES_DRM);
+        let encryption = playlist.segments[0].encryption.as_ref().unwrap();
+        assert_eq!(encryption.method, "SAMPLE-AES");
+        assert_eq!(
+            encryption.key_url.as_deref(),
+            Some("skd://drm.example.com/key?asset=title,1")
+        );
+        assert_eq!(
+            encryption.key_format.as_deref(),
+            Some("com.apple.streamingkeydelivery")
+        );
+        assert!(!encryption.is_identity_key());
+        assert!(playlist.is_fmp4());
+    }
+
+    #[test]
+    fn corpus_method_none_key_with_comma_in_quoted_uri() {
+        let playlist = media(
+            "https://vod.example.com/show/index.m3u8",
+            KEY_NONE_QUOTED_COMMAS,
+        );
+        let first = playlist.segments[0].encryption.as_ref().unwrap();
+        assert_eq!(first.method, "AES-128");
+        assert_eq!(
+            first.key_url.as_deref(),
+            Some("https://keys.example.com/k?ids=7,8&alg=METHOD=NONE")
+        );
+        assert!(playlist.segments[1].encryption.is_none());
+        assert!(playlist.segments[2].encryption.is_none());
+
+        // "METHOD=NONE" 只出现在引号内的 URI 里，不能当作明文
+        assert_eq!(
+            attribute_pairs(r#"URI="https://k.example.com/?a,METHOD=NONE,b",IV=0x01"#),
+            vec![
+                ("URI", r#""https://k.example.com/?a,METHOD=NONE,b""#),
+                ("IV", "0x01"),
+            ]
+        );
+        assert!(parse_media_playlist(
+            "http://example.com/x.m3u8",
+            "#EXTM3U\n#EXT-X-KEY:URI=\"k?a,METHOD=NONE,b\"\n#EXTINF:1,\na.ts\n"
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn tolerates_bom_crlf_comments_and_missing_trailing_newline() {
+        let content = format!(
+            "\u{feff}{}",
+            "#EXTM3U\n#EXT-X-VERSION:3\n# 注释行\n\n#EXT-X-TARGETDURATION:10\n\
+             #EXT-X-KEY:METHOD=NONE\n#EXTINF:9.009,第一段\n  seg-0.ts  \n\
+             #EXT-X-VENDOR-TAG:foo=bar\n#EXTINF:3.003\nseg-1.ts\n#EXT-X-ENDLIST"
+                .replace('\n', "\r\n")
+        );
+        let playlist = media("http://example.com/a/playlist.m3u8", &content);
+        assert_eq!(playlist.segments.len(), 2);
+        assert_eq!(playlist.segments[0].url, "http://example.com/a/seg-0.ts");
+        assert_eq!(playlist.segments[1].duration, 3.003);
+        assert!(playlist.segments.iter().all(|s| s.encryption.is_none()));
+        assert!(!playlist.is_live);
+
+        let no_newline = "#EXTM3U\n#EXT-X-PLAYLIST-TYPE:VOD\n#EXTINF:2.0,\na.ts";
+        assert!(!media("http://example.com/v.m3u8", no_newline).is_live);
+
+        assert!(parse_media_playlist("http://example.com/x.m3u8", "<html></html>").is_err());
+        assert!(parse_media_playlist(
+            "http://example.com/x.m3u8",
+            "#EXTM3U\n#EXT-X-KEY:URI=\"k\"\n#EXTINF:1,\na.ts\n"
+        )
+        .is_err());
+        assert!(parse_master_playlist("http://example.com/x.m3u8", no_newline).is_err());
+    }
 }
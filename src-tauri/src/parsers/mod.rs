@@ -5,8 +5,10 @@
 pub mod csv_parser;
 pub mod excel_parser;
 pub mod m3u8_parser;
+pub mod mpd_parser;
 
 // Re-export commonly used parsers
 pub use csv_parser::*;
 pub use excel_parser::*;
 pub use m3u8_parser::*;
+pub use mpd_parser::*;
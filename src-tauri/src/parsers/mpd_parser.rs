@@ -0,0 +1,964 @@
+//! MPEG-DASH (ISO/IEC 23009-1) MPD parsing
+//!
+//! 只覆盖点播下载需要的部分：Period / AdaptationSet / Representation、逐级继承的
+//! BaseURL 和分片属性，以及三种分片寻址方式——SegmentTemplate（`$Number$` 或
+//! SegmentTimeline）、SegmentList 和 SegmentBase。SegmentBase 的分片索引在媒体文件
+//! 的 sidx box 里，下载前先取回 `index_range` 再用 `parse_sidx` 展开为字节范围。
+
+#![deny(clippy::unwrap_used, clippy::expect_used)]
+
+use crate::core::hls_variant::StreamQuality;
+use anyhow::{anyhow, bail, Context, Result};
+use quick_xml::escape::resolve_predefined_entity;
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+use url::Url;
+
+/// 解析后的 MPD
+#[derive(Debug, Clone, PartialEq)]
+pub struct DashManifest {
+    /// `type="dynamic"`（直播）
+    pub is_dynamic: bool,
+    /// mediaPresentationDuration（秒）
+    pub duration: Option<f64>,
+    pub periods: Vec<DashPeriod>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DashPeriod {
+    pub id: Option<String>,
+    /// 时长（秒）：Period@duration，或由下一个 Period 的 start / 总时长推算
+    pub duration: Option<f64>,
+    pub adaptation_sets: Vec<DashAdaptationSet>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DashContentKind {
+    Video,
+    Audio,
+    Text,
+    Other,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DashAdaptationSet {
+    pub id: Option<String>,
+    pub kind: DashContentKind,
+    pub language: Option<String>,
+    /// `<Role value="main">`
+    pub is_main: bool,
+    /// 声明了 ContentProtection（DRM）
+    pub protected: bool,
+    pub representations: Vec<DashRepresentation>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DashRepresentation {
+    pub id: String,
+    /// bits/s
+    pub bandwidth: u64,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub codecs: Option<String>,
+    pub mime_type: Option<String>,
+    pub frame_rate: Option<f64>,
+    pub segments: DashSegments,
+}
+
+impl StreamQuality for DashRepresentation {
+    fn bandwidth(&self) -> u64 {
+        self.bandwidth
+    }
+
+    fn height(&self) -> Option<u32> {
+        self.height
+    }
+}
+
+/// Representation 的分片寻址结果
+#[derive(Debug, Clone, PartialEq)]
+pub enum DashSegments {
+    /// 地址已展开的分片（SegmentTemplate、SegmentList，或只有 BaseURL 的单文件）
+    Listed {
+        init: Option<DashSegment>,
+        segments: Vec<DashSegment>,
+    },
+    /// SegmentBase：分片索引在 `index_range` 指向的 sidx box 里
+    Indexed {
+        url: String,
+        /// 初始化段的字节范围（起止，含）；未声明时为文件开头到 sidx 之前
+        init_range: Option<(u64, u64)>,
+        index_range: (u64, u64),
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DashSegment {
+    pub url: String,
+    /// 字节范围（起止，含）
+    pub byte_range: Option<(u64, u64)>,
+    /// 时长（秒）
+    pub duration: f64,
+}
+
+/// 解析 MPD 文本；相对地址按 `mpd_url` 和各级 BaseURL 解析为绝对地址
+pub fn parse_mpd(mpd_url: &str, content: &str) -> Result<DashManifest> {
+    let root = parse_tree(content)?;
+    if root.name != "MPD" {
+        bail!("不是 DASH 清单: 根元素为 <{}>", root.name);
+    }
+
+    let is_dynamic = root.attr("type") == Some("dynamic");
+    let total = root
+        .attr("mediaPresentationDuration")
+        .map(parse_iso_duration)
+        .transpose()?;
+    let base = join_base_url(
+        &Url::parse(mpd_url).with_context(|| format!("无效的 MPD 地址: {}", mpd_url))?,
+        &root,
+    )?;
+
+    let period_elements: Vec<&Element> = root.children("Period").collect();
+    let starts = period_elements
+        .iter()
+        .map(|period| period.attr("start").map(parse_iso_duration).transpose())
+        .collect::<Result<Vec<_>>>()?;
+
+    let mut periods = Vec::with_capacity(period_elements.len());
+    for (index, period) in period_elements.iter().enumerate() {
+        let start = starts[index].unwrap_or(0.0);
+        let duration = match period.attr("duration") {
+            Some(duration) => Some(parse_iso_duration(duration)?),
+            None => match starts.get(index + 1).copied().flatten() {
+                Some(next) => Some(next - start),
+                None => total.map(|total| total - start),
+            },
+        };
+        periods.push(parse_period(&base, period, duration)?);
+    }
+
+    Ok(DashManifest {
+        is_dynamic,
+        duration: total,
+        periods,
+    })
+}
+
+fn parse_period(base: &Url, period: &Element, duration: Option<f64>) -> Result<DashPeriod> {
+    let base = join_base_url(base, period)?;
+    let mut adaptation_sets = Vec::new();
+    for set in period.children("AdaptationSet") {
+        let set_base = join_base_url(&base, set)?;
+        let mut representations = Vec::new();
+        for (index, representation) in set.children("Representation").enumerate() {
+            let levels = [period, set, representation];
+            let inherited = |name: &str| {
+                representation
+                    .attr(name)
+                    .or_else(|| set.attr(name))
+                    .map(str::to_string)
+            };
+            let id = representation
+                .attr("id")
+                .map(str::to_string)
+                .unwrap_or_else(|| index.to_string());
+            let bandwidth = representation
+                .attr("bandwidth")
+                .and_then(|value| value.parse().ok())
+                .unwrap_or(0);
+            let url = join_base_url(&set_base, representation)?;
+            let segments = parse_segments(&url, &levels, &id, bandwidth, duration)
+                .with_context(|| format!("Representation {} 的分片信息无效", id))?;
+            representations.push(DashRepresentation {
+                bandwidth,
+                width: inherited("width").and_then(|value| value.parse().ok()),
+                height: inherited("height").and_then(|value| value.parse().ok()),
+                codecs: inherited("codecs"),
+                mime_type: inherited("mimeType"),
+                frame_rate: inherited("frameRate").as_deref().and_then(parse_frame_rate),
+                segments,
+                id,
+            });
+        }
+
+        adaptation_sets.push(DashAdaptationSet {
+            id: set.attr("id").map(str::to_string),
+            kind: content_kind(set, &representations),
+            language: set.attr("lang").map(str::to_string),
+            is_main: set
+                .children("Role")
+                .any(|role| role.attr("value") == Some("main")),
+            protected: set.child("ContentProtection").is_some()
+                || set
+                    .children("Representation")
+                    .any(|representation| representation.child("ContentProtection").is_some()),
+            representations,
+        });
+    }
+
+    Ok(DashPeriod {
+        id: period.attr("id").map(str::to_string),
+        duration,
+        adaptation_sets,
+    })
+}
+
+/// contentType 优先，其次按 mimeType 前缀判断
+fn content_kind(set: &Element, representations: &[DashRepresentation]) -> DashContentKind {
+    let declared = set.attr("contentType").map(str::to_string).or_else(|| {
+        set.attr("mimeType")
+            .map(str::to_string)
+            .or_else(|| representations.first()?.mime_type.clone())
+    });
+    let codecs = set
+        .attr("codecs")
+        .map(str::to_string)
+        .or_else(|| representations.first()?.codecs.clone())
+        .unwrap_or_default();
+    match declared.as_deref() {
+        Some(kind) if kind.starts_with("video") => DashContentKind::Video,
+        Some(kind) if kind.starts_with("audio") => DashContentKind::Audio,
+        Some(kind) if kind.starts_with("text") || kind == "application/ttml+xml" => {
+            DashContentKind::Text
+        }
+        Some("application/mp4") if codecs.starts_with("stpp") || codecs.starts_with("wvtt") => {
+            DashContentKind::Text
+        }
+        _ => DashContentKind::Other,
+    }
+}
+
+/// 按 Period / AdaptationSet / Representation 的顺序查找分片信息，越靠后越优先
+fn parse_segments(
+    url: &Url,
+    levels: &[&Element; 3],
+    id: &str,
+    bandwidth: u64,
+    period_duration: Option<f64>,
+) -> Result<DashSegments> {
+    let template = Inherited::declared(levels, "SegmentTemplate");
+    if !template.0.is_empty() {
+        return parse_segment_template(url, &template, id, bandwidth, period_duration);
+    }
+    let list = Inherited::declared(levels, "SegmentList");
+    if !list.0.is_empty() {
+        return parse_segment_list(url, &list);
+    }
+
+    let base = Inherited::declared(levels, "SegmentBase");
+    let init_range = base
+        .child("Initialization")
+        .and_then(|init| init.attr("range"))
+        .map(parse_byte_range)
+        .transpose()?;
+    if let Some(index_range) = base.attr("indexRange") {
+        return Ok(DashSegments::Indexed {
+            url: url.to_string(),
+            init_range,
+            index_range: parse_byte_range(index_range)?,
+        });
+    }
+
+    // 没有任何分片信息：BaseURL 就是完整的媒体文件
+    Ok(DashSegments::Listed {
+        init: None,
+        segments: vec![DashSegment {
+            url: url.to_string(),
+            byte_range: None,
+            duration: period_duration.unwrap_or(0.0),
+        }],
+    })
+}
+
+fn parse_segment_template(
+    url: &Url,
+    template: &Inherited,
+    id: &str,
+    bandwidth: u64,
+    period_duration: Option<f64>,
+) -> Result<DashSegments> {
+    let timescale = template.number("timescale")?.unwrap_or(1).max(1);
+    let start_number = template.number("startNumber")?.unwrap_or(1);
+    let media = template
+        .attr("media")
+        .ok_or_else(|| anyhow!("SegmentTemplate 缺少 media 属性"))?;
+    let resolve = |pattern: &str, number: u64, time: u64| -> Result<String> {
+        let expanded = expand_template(pattern, id, number, time, bandwidth)?;
+        Ok(url.join(&expanded)?.to_string())
+    };
+
+    let init = template
+        .attr("initialization")
+        .map(|pattern| {
+            Ok::<_, anyhow::Error>(DashSegment {
+                url: resolve(pattern, start_number, 0)?,
+                byte_range: None,
+                duration: 0.0,
+            })
+        })
+        .transpose()?;
+    let period_end = period_duration.map(|duration| (duration * timescale as f64).round() as u64);
+
+    // (t, d) 列表，时间单位为 timescale
+    let mut timeline: Vec<(u64, u64)> = Vec::new();
+    if let Some(entries) = template.child("SegmentTimeline") {
+        let entries: Vec<&Element> = entries.children("S").collect();
+        let mut time = 0u64;
+        for (index, entry) in entries.iter().enumerate() {
+            if let Some(t) = entry.attr("t") {
+                time = t.parse().with_context(|| format!("无效的 S@t: {}", t))?;
+            }
+            let duration: u64 = entry
+                .attr("d")
+                .ok_or_else(|| anyhow!("SegmentTimeline 的 S 缺少 d 属性"))?
+                .parse()
+                .context("无效的 S@d")?;
+            if duration == 0 {
+                bail!("SegmentTimeline 的 S@d 不能为 0");
+            }
+            let repeat: i64 = entry
+                .attr("r")
+                .map(|r| r.parse().context("无效的 S@r"))
+                .transpose()?
+                .unwrap_or(0);
+            let count = if repeat >= 0 {
+                repeat as u64 + 1
+            } else {
+                // r=-1：重复到下一个 S@t 或 Period 结束
+                let until = entries
+                    .get(index + 1)
+                    .and_then(|next| next.attr("t"))
+                    .and_then(|t| t.parse().ok())
+                    .or(period_end)
+                    .ok_or_else(|| anyhow!("S@r=-1 但 Period 时长未知"))?;
+                until.saturating_sub(time).div_ceil(duration)
+            };
+            for _ in 0..count {
+                timeline.push((time, duration));
+                time += duration;
+            }
+        }
+    } else {
+        let duration = template
+            .number("duration")?
+            .filter(|duration| *duration > 0)
+            .ok_or_else(|| anyhow!("SegmentTemplate 既没有 SegmentTimeline 也没有 duration"))?;
+        let total = period_end.ok_or_else(|| anyhow!("Period 时长未知，无法推算分片数"))?;
+        let mut time = 0u64;
+        while time < total {
+            timeline.push((time, duration.min(total - time)));
+            time += duration;
+        }
+    }
+
+    let segments = timeline
+        .into_iter()
+        .enumerate()
+        .map(|(offset, (time, duration))| {
+            Ok(DashSegment {
+                url: resolve(media, start_number + offset as u64, time)?,
+                byte_range: None,
+                duration: duration as f64 / timescale as f64,
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+    Ok(DashSegments::Listed { init, segments })
+}
+
+fn parse_segment_list(url: &Url, list: &Inherited) -> Result<DashSegments> {
+    let timescale = list.number("timescale")?.unwrap_or(1).max(1);
+    let duration = list.number("duration")?.unwrap_or(0) as f64 / timescale as f64;
+    let resolve = |source: Option<&str>| -> Result<String> {
+        Ok(match source {
+            Some(source) => url.join(source)?.to_string(),
+            None => url.to_string(),
+        })
+    };
+
+    let init = list
+        .child("Initialization")
+        .map(|init| {
+            Ok::<_, anyhow::Error>(DashSegment {
+                url: resolve(init.attr("sourceURL"))?,
+                byte_range: init.attr("range").map(parse_byte_range).transpose()?,
+                duration: 0.0,
+            })
+        })
+        .transpose()?;
+    let segments = list
+        .child_list("SegmentURL")
+        .into_iter()
+        .map(|segment| {
+            Ok(DashSegment {
+                url: resolve(segment.attr("media"))?,
+                byte_range: segment
+                    .attr("mediaRange")
+                    .map(parse_byte_range)
+                    .transpose()?,
+                duration,
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+    Ok(DashSegments::Listed { init, segments })
+}
+
+/// 展开 SegmentTemplate 中的 `$RepresentationID$`、`$Number$`、`$Time$`、
+/// `$Bandwidth$`（可带 `%0<width>d` 格式）和 `$$`
+pub fn expand_template(
+    template: &str,
+    representation_id: &str,
+    number: u64,
+    time: u64,
+    bandwidth: u64,
+) -> Result<String> {
+    let mut expanded = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find('$') {
+        expanded.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        let end = after
+            .find('$')
+            .ok_or_else(|| anyhow!("SegmentTemplate 中的 $ 未闭合: {}", template))?;
+        let token = &after[..end];
+        rest = &after[end + 1..];
+
+        let (name, format) = match token.split_once('%') {
+            Some((name, format)) => (name, Some(format)),
+            None => (token, None),
+        };
+        let value = match name {
+            "" => {
+                expanded.push('$');
+                continue;
+            }
+            "RepresentationID" => {
+                expanded.push_str(representation_id);
+                continue;
+            }
+            "Number" => number,
+            "Time" => time,
+            "Bandwidth" => bandwidth,
+            _ => bail!("不支持的 SegmentTemplate 标识符: ${}$", token),
+        };
+        let width = match format {
+            None => 0,
+            Some(format) => format
+                .strip_prefix('0')
+                .and_then(|format| format.strip_suffix('d'))
+                .and_then(|width| width.parse().ok())
+                .ok_or_else(|| anyhow!("不支持的 SegmentTemplate 格式: ${}$", token))?,
+        };
+        expanded.push_str(&format!("{:0width$}", value, width = width));
+    }
+    expanded.push_str(rest);
+    Ok(expanded)
+}
+
+/// ISO 8601 时长（`PT1H2M3.5S`、`P1DT2H`）转为秒；不支持年、月
+pub fn parse_iso_duration(value: &str) -> Result<f64> {
+    let invalid = || anyhow!("无效的 ISO 8601 时长: {}", value);
+    let body = value.trim().strip_prefix('P').ok_or_else(invalid)?;
+    let (date, time) = body.split_once('T').unwrap_or((body, ""));
+
+    let mut seconds = 0.0;
+    for (part, units) in [
+        (date, &[('D', 86_400.0)][..]),
+        (time, &[('H', 3_600.0), ('M', 60.0), ('S', 1.0)][..]),
+    ] {
+        let mut number = String::new();
+        for ch in part.chars() {
+            if ch.is_ascii_digit() || ch == '.' {
+                number.push(ch);
+                continue;
+            }
+            let scale = units
+                .iter()
+                .find(|(unit, _)| *unit == ch)
+                .map(|(_, scale)| *scale)
+                .ok_or_else(invalid)?;
+            seconds += number.parse::<f64>().map_err(|_| invalid())? * scale;
+            number.clear();
+        }
+        if !number.is_empty() {
+            return Err(invalid());
+        }
+    }
+    Ok(seconds)
+}
+
+/// 解析 sidx box，返回各分片的字节范围和时长。`sidx_offset` 为 box 在文件中的起始位置，
+/// `data` 从该位置开始（即按 indexRange 取回的内容）
+pub fn parse_sidx(url: &str, data: &[u8], sidx_offset: u64) -> Result<Vec<DashSegment>> {
+    let read = |at: usize, len: usize| -> Result<u64> {
+        let bytes = data
+            .get(at..at + len)
+            .ok_or_else(|| anyhow!("sidx 数据不完整"))?;
+        Ok(bytes
+            .iter()
+            .fold(0u64, |value, byte| (value << 8) | *byte as u64))
+    };
+
+    let (mut box_size, header) = (read(0, 4)?, 8usize);
+    if data.get(4..8) != Some(b"sidx".as_slice()) {
+        bail!("indexRange 处不是 sidx box");
+    }
+    let header = if box_size == 1 {
+        box_size = read(8, 8)?;
+        header + 8
+    } else {
+        header
+    };
+
+    let version = read(header, 1)?;
+    let timescale = read(header + 8, 4)?.max(1);
+    let (first_offset, mut at) = if version == 0 {
+        (read(header + 16, 4)?, header + 20)
+    } else {
+        (read(header + 20, 8)?, header + 28)
+    };
+    let reference_count = read(at + 2, 2)?;
+    at += 4;
+
+    let mut offset = sidx_offset + box_size + first_offset;
+    let mut segments = Vec::with_capacity(reference_count as usize);
+    for _ in 0..reference_count {
+        let reference = read(at, 4)?;
+        if reference >> 31 == 1 {
+            bail!("不支持多级 sidx 索引");
+        }
+        let size = reference & 0x7fff_ffff;
+        let duration = read(at + 4, 4)?;
+        segments.push(DashSegment {
+            url: url.to_string(),
+            byte_range: Some((offset, offset + size - 1)),
+            duration: duration as f64 / timescale as f64,
+        });
+        offset += size;
+        at += 12;
+    }
+    Ok(segments)
+}
+
+/// `"500-999"` -> (500, 999)
+fn parse_byte_range(value: &str) -> Result<(u64, u64)> {
+    let (start, end) = value
+        .split_once('-')
+        .ok_or_else(|| anyhow!("无效的字节范围: {}", value))?;
+    let range = (start.trim().parse()?, end.trim().parse()?);
+    if range.1 < range.0 {
+        bail!("无效的字节范围: {}", value);
+    }
+    Ok(range)
+}
+
+/// `"25"` / `"30000/1001"`
+fn parse_frame_rate(value: &str) -> Option<f64> {
+    match value.split_once('/') {
+        Some((numerator, denominator)) => {
+            let denominator: f64 = denominator.parse().ok()?;
+            (denominator > 0.0).then_some(numerator.parse::<f64>().ok()? / denominator)
+        }
+        None => value.parse().ok(),
+    }
+}
+
+/// 以 `element` 的第一个 BaseURL 更新基础地址
+fn join_base_url(base: &Url, element: &Element) -> Result<Url> {
+    match element.child("BaseURL").map(|url| url.text.trim()) {
+        Some(url) if !url.is_empty() => base
+            .join(url)
+            .with_context(|| format!("无效的 BaseURL: {}", url)),
+        _ => Ok(base.clone()),
+    }
+}
+
+/// 多级声明的同名分片元素：属性和子元素取最具体（最后）的一级
+struct Inherited<'a>(Vec<&'a Element>);
+
+impl<'a> Inherited<'a> {
+    fn declared(levels: &[&'a Element], name: &str) -> Self {
+        Self(
+            levels
+                .iter()
+                .filter_map(|level| level.child(name))
+                .collect(),
+        )
+    }
+
+    fn attr(&self, name: &str) -> Option<&'a str> {
+        self.0.iter().rev().find_map(|element| element.attr(name))
+    }
+
+    fn number(&self, name: &str) -> Result<Option<u64>> {
+        self.attr(name)
+            .map(|value| {
+                value
+                    .parse()
+                    .with_context(|| format!("无效的 {} 属性: {}", name, value))
+            })
+            .transpose()
+    }
+
+    fn child(&self, name: &str) -> Option<&'a Element> {
+        self.0.iter().rev().find_map(|element| element.child(name))
+    }
+
+    fn child_list(&self, name: &str) -> Vec<&'a Element> {
+        self.0
+            .iter()
+            .rev()
+            .map(|element| {
+                element
+                    .children
+                    .iter()
+                    .filter(|child| child.name == name)
+                    .collect::<Vec<_>>()
+            })
+            .find(|children| !children.is_empty())
+            .unwrap_or_default()
+    }
+}
+
+/// 最小的 XML 元素树（名称去掉命名空间前缀）
+#[derive(Debug, Default)]
+struct Element {
+    name: String,
+    attributes: Vec<(String, String)>,
+    children: Vec<Element>,
+    text: String,
+}
+
+impl Element {
+    fn attr(&self, name: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    fn child(&self, name: &str) -> Option<&Element> {
+        self.children.iter().find(|child| child.name == name)
+    }
+
+    fn children<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a Element> + 'a {
+        self.children.iter().filter(move |child| child.name == name)
+    }
+}
+
+fn parse_tree(content: &str) -> Result<Element> {
+    let mut reader = Reader::from_str(content.trim_start_matches('\u{feff}'));
+    reader.config_mut().trim_text(true);
+    let decoder = reader.decoder();
+
+    let start_element = |start: &BytesStart| -> Result<Element> {
+        let mut attributes = Vec::new();
+        for attribute in start.attributes() {
+            let attribute = attribute?;
+            attributes.push((
+                String::from_utf8_lossy(attribute.key.local_name().as_ref()).into_owned(),
+                attribute.decode_and_unescape_value(decoder)?.into_owned(),
+            ));
+        }
+        Ok(Element {
+            name: String::from_utf8_lossy(start.local_name().as_ref()).into_owned(),
+            attributes,
+            ..Element::default()
+        })
+    };
+
+    let mut stack: Vec<Element> = Vec::new();
+    loop {
+        let event = reader
+            .read_event()
+            .with_context(|| format!("MPD 不是合法的 XML (位置 {})", reader.buffer_position()))?;
+        match event {
+            Event::Start(start) => stack.push(start_element(&start)?),
+            Event::Empty(start) => {
+                let element = start_element(&start)?;
+                match stack.last_mut() {
+                    Some(parent) => parent.children.push(element),
+                    None => return Ok(element),
+                }
+            }
+            Event::End(_) => {
+                let element = stack.pop().ok_or_else(|| anyhow!("MPD 结构不完整"))?;
+                match stack.last_mut() {
+                    Some(parent) => parent.children.push(element),
+                    None => return Ok(element),
+                }
+            }
+            Event::Text(text) => {
+                if let Some(element) = stack.last_mut() {
+                    element.text.push_str(&text.decode()?);
+                }
+            }
+            Event::CData(text) => {
+                if let Some(element) = stack.last_mut() {
+                    element.text.push_str(&text.decode()?);
+                }
+            }
+            Event::GeneralRef(reference) => {
+                if let Some(element) = stack.last_mut() {
+                    match reference.resolve_char_ref()? {
+                        Some(ch) => element.text.push(ch),
+                        None => {
+                            let name = reference.decode()?;
+                            let resolved = resolve_predefined_entity(&name)
+                                .ok_or_else(|| anyhow!("未知的 XML 实体: &{};", name))?;
+                            element.text.push_str(resolved);
+                        }
+                    }
+                }
+            }
+            Event::Eof => bail!("MPD 为空或结构不完整"),
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::expect_used)]
+mod tests {
+    use super::*;
+
+    const MPD_URL: &str = "https://cdn.example.com/course/lesson/manifest.mpd";
+
+    fn listed(representation: &DashRepresentation) -> (&Option<DashSegment>, &Vec<DashSegment>) {
+        match &representation.segments {
+            DashSegments::Listed { init, segments } => (init, segments),
+            other => panic!("expected listed segments, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn parses_iso_durations_and_templates() {
+        assert_eq!(parse_iso_duration("PT1H2M3.5S").unwrap(), 3723.5);
+        assert_eq!(parse_iso_duration("P1DT0.25S").unwrap(), 86_400.25);
+        assert_eq!(parse_iso_duration("PT0S").unwrap(), 0.0);
+        assert!(parse_iso_duration("1H").is_err());
+        assert!(parse_iso_duration("P1Y").is_err());
+
+        assert_eq!(
+            expand_template(
+                "$RepresentationID$/seg-$Number%05d$-$Time$_$Bandwidth$$$.m4s",
+                "v1",
+                7,
+                90_000,
+                800_000
+            )
+            .unwrap(),
+            "v1/seg-00007-90000_800000$.m4s"
+        );
+        assert!(expand_template("seg-$Number", "v1", 1, 0, 0).is_err());
+        assert!(expand_template("seg-$SubNumber$", "v1", 1, 0, 0).is_err());
+    }
+
+    #[test]
+    fn expands_segment_template_with_number_and_inherited_base_urls() {
+        let manifest = parse_mpd(
+            MPD_URL,
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<MPD xmlns="urn:mpeg:dash:schema:mpd:2011" type="static" mediaPresentationDuration="PT9.5S">
+  <BaseURL>media/</BaseURL>
+  <Period id="p0">
+    <AdaptationSet contentType="video" mimeType="video/mp4" frameRate="30000/1001">
+      <BaseURL>video/</BaseURL>
+      <SegmentTemplate timescale="1000" duration="4000" startNumber="1"
+        initialization="$RepresentationID$/init.mp4" media="$RepresentationID$/$Number%03d$.m4s?token=a&amp;b=1"/>
+      <Representation id="720p" bandwidth="2800000" width="1280" height="720" codecs="avc1.4d401f"/>
+      <Representation id="360p" bandwidth="800000" width="640" height="360"/>
+    </AdaptationSet>
+    <AdaptationSet mimeType="audio/mp4" lang="en">
+      <Role schemeIdUri="urn:mpeg:dash:role:2011" value="main"/>
+      <Representation id="aac" bandwidth="128000">
+        <SegmentTemplate timescale="1" duration="5" media="audio/$Number$.m4s"/>
+      </Representation>
+    </AdaptationSet>
+  </Period>
+</MPD>"#,
+        )
+        .unwrap();
+
+        assert!(!manifest.is_dynamic);
+        assert_eq!(manifest.duration, Some(9.5));
+        let period = &manifest.periods[0];
+        assert_eq!(period.duration, Some(9.5));
+
+        let video = &period.adaptation_sets[0];
+        assert_eq!(video.kind, DashContentKind::Video);
+        let hd = &video.representations[0];
+        assert_eq!(
+            (hd.width, hd.height, hd.bandwidth),
+            (Some(1280), Some(720), 2_800_000)
+        );
+        assert_eq!(hd.mime_type.as_deref(), Some("video/mp4"));
+        assert!((hd.frame_rate.unwrap() - 29.97).abs() < 0.01);
+
+        let (init, segments) = listed(hd);
+        assert_eq!(
+            init.as_ref().unwrap().url,
+            "https://cdn.example.com/course/lesson/media/video/720p/init.mp4"
+        );
+        let urls: Vec<&str> = segments
+            .iter()
+            .map(|segment| segment.url.as_str())
+            .collect();
+        assert_eq!(
+            urls,
+            vec![
+                "https://cdn.example.com/course/lesson/media/video/720p/001.m4s?token=a&b=1",
+                "https://cdn.example.com/course/lesson/media/video/720p/002.m4s?token=a&b=1",
+                "https://cdn.example.com/course/lesson/media/video/720p/003.m4s?token=a&b=1",
+            ]
+        );
+        assert_eq!(segments[2].duration, 1.5);
+
+        let audio = &period.adaptation_sets[1];
+        assert_eq!(audio.kind, DashContentKind::Audio);
+        assert_eq!(audio.language.as_deref(), Some("en"));
+        assert!(audio.is_main && !audio.protected);
+        let (init, segments) = listed(&audio.representations[0]);
+        assert!(init.is_none());
+        assert_eq!(segments.len(), 2);
+        assert_eq!(
+            segments[1].url,
+            "https://cdn.example.com/course/lesson/media/audio/2.m4s"
+        );
+    }
+
+    #[test]
+    fn expands_segment_timeline_with_repeats() {
+        let manifest = parse_mpd(
+            MPD_URL,
+            r#"<MPD type="static" mediaPresentationDuration="PT10S">
+  <Period>
+    <AdaptationSet contentType="video">
+      <ContentProtection schemeIdUri="urn:mpeg:dash:mp4protection:2011" value="cenc"/>
+      <SegmentTemplate timescale="90000" startNumber="10" initialization="init-$Bandwidth$.mp4" media="t-$Time$-n$Number$.m4s">
+        <SegmentTimeline>
+          <S t="0" d="180000" r="1"/>
+          <S d="90000"/>
+          <S t="450000" d="180000" r="-1"/>
+        </SegmentTimeline>
+      </SegmentTemplate>
+      <Representation id="v" bandwidth="500000"/>
+    </AdaptationSet>
+  </Period>
+</MPD>"#,
+        )
+        .unwrap();
+
+        let set = &manifest.periods[0].adaptation_sets[0];
+        assert!(set.protected);
+        let (init, segments) = listed(&set.representations[0]);
+        assert!(init.as_ref().unwrap().url.ends_with("/init-500000.mp4"));
+        let names: Vec<&str> = segments
+            .iter()
+            .map(|segment| segment.url.rsplit('/').next().unwrap())
+            .collect();
+        // r=-1 填满到 Period 结束：450000 起每段 2 秒，到 900000 为止
+        assert_eq!(
+            names,
+            vec![
+                "t-0-n10.m4s",
+                "t-180000-n11.m4s",
+                "t-360000-n12.m4s",
+                "t-450000-n13.m4s",
+                "t-630000-n14.m4s",
+                "t-810000-n15.m4s",
+            ]
+        );
+        assert_eq!(segments[2].duration, 1.0);
+    }
+
+    #[test]
+    fn parses_segment_list_and_segment_base() {
+        let manifest = parse_mpd(
+            MPD_URL,
+            r#"<MPD type="static" mediaPresentationDuration="PT6S">
+  <Period duration="PT6S">
+    <AdaptationSet mimeType="video/webm">
+      <Representation id="list" bandwidth="1000">
+        <BaseURL>https://media.example.com/list/video.webm</BaseURL>
+        <SegmentList timescale="10" duration="30">
+          <Initialization range="0-99"/>
+          <SegmentURL mediaRange="100-199"/>
+          <SegmentURL media="part2.webm" mediaRange="0-49"/>
+        </SegmentList>
+      </Representation>
+      <Representation id="base" bandwidth="2000">
+        <BaseURL>base.mp4</BaseURL>
+        <SegmentBase indexRange="862-1000">
+          <Initialization range="0-861"/>
+        </SegmentBase>
+      </Representation>
+      <Representation id="single" bandwidth="3000">
+        <BaseURL>single.mp4</BaseURL>
+      </Representation>
+    </AdaptationSet>
+  </Period>
+</MPD>"#,
+        )
+        .unwrap();
+
+        let representations = &manifest.periods[0].adaptation_sets[0].representations;
+        let (init, segments) = listed(&representations[0]);
+        assert_eq!(
+            init.as_ref().unwrap(),
+            &DashSegment {
+                url: "https://media.example.com/list/video.webm".to_string(),
+                byte_range: Some((0, 99)),
+                duration: 0.0,
+            }
+        );
+        assert_eq!(segments[0].byte_range, Some((100, 199)));
+        assert_eq!(segments[1].url, "https://media.example.com/list/part2.webm");
+        assert_eq!(segments[1].duration, 3.0);
+
+        assert_eq!(
+            representations[1].segments,
+            DashSegments::Indexed {
+                url: "https://cdn.example.com/course/lesson/base.mp4".to_string(),
+                init_range: Some((0, 861)),
+                index_range: (862, 1000),
+            }
+        );
+
+        let (init, segments) = listed(&representations[2]);
+        assert!(init.is_none());
+        assert_eq!(segments.len(), 1);
+        assert_eq!(segments[0].duration, 6.0);
+
+        assert!(parse_mpd(MPD_URL, "<html><body/></html>").is_err());
+        assert!(parse_mpd(MPD_URL, "<MPD><Period>").is_err());
+    }
+
+    #[test]
+    fn parses_sidx_references_into_byte_ranges() {
+        let mut sidx = Vec::new();
+        sidx.extend_from_slice(&0u32.to_be_bytes()); // 大小稍后回填
+        sidx.extend_from_slice(b"sidx");
+        sidx.extend_from_slice(&[0, 0, 0, 0]); // version 0, flags
+        sidx.extend_from_slice(&1u32.to_be_bytes()); // reference_ID
+        sidx.extend_from_slice(&1000u32.to_be_bytes()); // timescale
+        sidx.extend_from_slice(&0u32.to_be_bytes()); // earliest_presentation_time
+        sidx.extend_from_slice(&10u32.to_be_bytes()); // first_offset
+        sidx.extend_from_slice(&[0, 0, 0, 2]); // reserved, reference_count
+        for (size, duration) in [(500u32, 2000u32), (300, 1500)] {
+            sidx.extend_from_slice(&size.to_be_bytes());
+            sidx.extend_from_slice(&duration.to_be_bytes());
+            sidx.extend_from_slice(&0x9000_0000u32.to_be_bytes());
+        }
+        let size = sidx.len() as u32;
+        sidx[..4].copy_from_slice(&size.to_be_bytes());
+
+        let segments = parse_sidx("https://cdn.example.com/a.mp4", &sidx, 862).unwrap();
+        let first = 862 + size as u64 + 10;
+        assert_eq!(segments.len(), 2);
+        assert_eq!(segments[0].byte_range, Some((first, first + 499)));
+        assert_eq!(segments[1].byte_range, Some((first + 500, first + 799)));
+        assert_eq!(segments[1].duration, 1.5);
+
+        assert!(parse_sidx("https://cdn.example.com/a.mp4", b"\0\0\0\x08moov", 0).is_err());
+    }
+}
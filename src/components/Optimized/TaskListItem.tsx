@@ -1,15 +1,51 @@
 import React, { useEffect, useMemo, useRef, useState } from 'react';
 import toast from 'react-hot-toast';
-import { ClipboardDocumentIcon, FolderOpenIcon } from '@heroicons/react/24/outline';
+import {
+  CheckIcon,
+  ClipboardDocumentIcon,
+  FolderOpenIcon,
+  StopCircleIcon,
+} from '@heroicons/react/24/outline';
 
 import { revealPathInFolderCommand } from '../../features/downloads/api/systemCommands';
+import { stopLiveRecordingCommand } from '../../features/downloads/api/taskMutations';
 import { buildTaskSupportBundle } from '../../features/downloads/model/downloadDiagnostics';
-import type { VideoTask } from '../../types';
+import { useDownloadStore } from '../../stores/downloadStore';
+import type { AudioExtraction, HlsVariantPolicy, SidecarFile, VideoTask } from '../../types';
 import { formatSpeed } from '../../utils/format';
 
 const buttonFocusClass =
   'focus-visible:outline-none focus-visible:ring-2 focus-visible:ring-ring focus-visible:ring-offset-2 focus-visible:ring-offset-background';
 
+const menuItemClass = `flex w-full items-center gap-2 px-3 py-2 text-left text-sm text-gray-700 hover:bg-gray-100 dark:text-gray-200 dark:hover:bg-gray-800 ${buttonFocusClass}`;
+
+const menuSectionClass =
+  'px-3 pt-2 pb-1 text-[11px] font-medium text-gray-400 dark:text-gray-500 border-t border-gray-100 dark:border-gray-800';
+
+// 下载开始前可以调整的单任务选项
+const EDITABLE_STATUSES: VideoTask['status'][] = ['pending', 'paused', 'failed', 'cancelled'];
+
+// HLS/DASH 档位；null 表示沿用配置中的默认策略
+const VARIANT_CHOICES: { label: string; policy: HlsVariantPolicy | null }[] = [
+  { label: '默认', policy: null },
+  { label: '最高画质', policy: { kind: 'highest' } },
+  { label: '接近 1080p', policy: { kind: 'closest_height', height: 1080 } },
+  { label: '接近 720p', policy: { kind: 'closest_height', height: 720 } },
+  { label: '最低画质', policy: { kind: 'lowest' } },
+];
+
+// 仅音频模式；null 表示保留视频
+const AUDIO_CHOICES: { label: string; extraction: AudioExtraction | null }[] = [
+  { label: '保留视频', extraction: null },
+  { label: 'MP3', extraction: { format: 'mp3' } },
+  { label: 'M4A', extraction: { format: 'm4a' } },
+  { label: 'Opus', extraction: { format: 'opus' } },
+  { label: 'FLAC', extraction: { format: 'flac' } },
+];
+
+const sameChoice = (left: unknown, right: unknown) =>
+  JSON.stringify(left ?? null) === JSON.stringify(right ?? null);
+
 const copyTextToClipboard = async (text: string): Promise<void> => {
   if (navigator.clipboard?.writeText) {
     await navigator.clipboard.writeText(text);
@@ -40,6 +76,19 @@ function formatTime(seconds: number): string {
 const getRevealPath = (task: VideoTask): string =>
   (task.resolved_path?.trim() || task.output_path.trim()).trim();
 
+const sidecarLabel = (file: SidecarFile): string => {
+  switch (file.kind) {
+    case 'subtitle':
+      return file.language ? `字幕（${file.language}）` : '字幕';
+    case 'thumbnail':
+      return '缩略图';
+    case 'description':
+      return '视频简介';
+    case 'info_json':
+      return '元数据';
+  }
+};
+
 export const TaskItem = React.memo<{
   task: VideoTask;
   style: React.CSSProperties;
@@ -51,6 +100,12 @@ export const TaskItem = React.memo<{
   const [menuPosition, setMenuPosition] = useState<{ x: number; y: number } | null>(null);
   const menuRef = useRef<HTMLDivElement>(null);
   const canReveal = task.status === 'completed' && getRevealPath(task).length > 0;
+  const isRecording = task.status === 'downloading' && Boolean(task.recording);
+  const canEditOptions = EDITABLE_STATUSES.includes(task.status);
+  const canChooseVariant =
+    canEditOptions && (task.downloader_type === 'm3u8' || task.downloader_type === 'dash');
+  const setTaskVariantPolicy = useDownloadStore(state => state.setTaskVariantPolicy);
+  const setTaskAudioExtraction = useDownloadStore(state => state.setTaskAudioExtraction);
 
   useEffect(() => {
     if (!menuPosition) return;
@@ -87,14 +142,28 @@ export const TaskItem = React.memo<{
     }
   };
 
+  // 结束直播录制：已录制的内容照常收尾为完成文件
+  const handleStopRecording = async (event: React.MouseEvent<HTMLButtonElement>) => {
+    event.stopPropagation();
+    try {
+      const stopped = await stopLiveRecordingCommand(task.id);
+      if (stopped) {
+        toast.success('正在结束录制');
+      } else {
+        toast.error('任务当前不在录制直播');
+      }
+    } catch (error) {
+      toast.error(error instanceof Error ? error.message : '结束录制失败');
+    }
+  };
+
   const handleContextMenu = (event: React.MouseEvent<HTMLDivElement>) => {
-    if (!canReveal) return;
+    if (!canReveal && !canEditOptions) return;
     event.preventDefault();
     setMenuPosition({ x: event.clientX, y: event.clientY });
   };
 
-  const handleRevealInFolder = async () => {
-    const path = getRevealPath(task);
+  const handleRevealInFolder = async (path: string = getRevealPath(task)) => {
     setMenuPosition(null);
 
     if (!path) {
@@ -162,6 +231,18 @@ export const TaskItem = React.memo<{
             {task.title}
           </h4>
           <div className='flex items-center gap-2 shrink-0'>
+            {isRecording && (
+              <button
+                type='button'
+                onClick={handleStopRecording}
+                className={`p-1 rounded-md text-red-500 hover:text-red-700 hover:bg-red-50 dark:hover:bg-red-900/30 ${buttonFocusClass}`}
+                title='结束录制'
+                aria-label={`结束录制：${task.title}`}
+                data-testid='stop-recording'
+              >
+                <StopCircleIcon className='w-4 h-4' />
+              </button>
+            )}
             <button
               type='button'
               onClick={handleCopyDiagnostic}
@@ -193,7 +274,16 @@ export const TaskItem = React.memo<{
         </div>
 
         <div className='flex items-center space-x-4 text-xs text-gray-500 dark:text-gray-400'>
-          {task.status === 'downloading' && (
+          {isRecording && task.recording && (
+            <>
+              <span className='w-16 text-red-500'>录制中</span>
+              <span className='w-24 font-mono tabular-nums whitespace-nowrap'>
+                {formatSpeed(displaySpeed)}
+              </span>
+              <span>已录: {formatTime(task.recording.recorded_secs)}</span>
+            </>
+          )}
+          {task.status === 'downloading' && !isRecording && (
             <>
               <span className='w-16'>{progressPercentage}%</span>
               <span className='w-24 font-mono tabular-nums whitespace-nowrap'>
@@ -214,6 +304,15 @@ export const TaskItem = React.memo<{
           {task.status !== 'downloading' && task.status !== 'committing' && (
             <span className='truncate text-gray-400'>{task.output_path}</span>
           )}
+          {task.status === 'completed' && task.error_message && (
+            <span
+              className='truncate text-amber-600 dark:text-amber-400'
+              title={task.error_message}
+              data-testid='task-warning'
+            >
+              {task.error_message}
+            </span>
+          )}
         </div>
 
         <div
@@ -240,15 +339,84 @@ export const TaskItem = React.memo<{
           style={{ left: menuPosition.x, top: menuPosition.y }}
           role='menu'
         >
-          <button
-            type='button'
-            className={`flex w-full items-center gap-2 px-3 py-2 text-left text-sm text-gray-700 hover:bg-gray-100 dark:text-gray-200 dark:hover:bg-gray-800 ${buttonFocusClass}`}
-            onClick={handleRevealInFolder}
-            role='menuitem'
-          >
-            <FolderOpenIcon className='h-4 w-4' />
-            在目录中显示
-          </button>
+          {canReveal && (
+            <>
+              <button
+                type='button'
+                className={menuItemClass}
+                onClick={() => handleRevealInFolder()}
+                role='menuitem'
+              >
+                <FolderOpenIcon className='h-4 w-4' />
+                在目录中显示
+              </button>
+              {task.sidecar_files?.map(file => (
+                <button
+                  key={file.path}
+                  type='button'
+                  className={menuItemClass}
+                  onClick={() => handleRevealInFolder(file.path)}
+                  role='menuitem'
+                  title={file.path}
+                >
+                  <FolderOpenIcon className='h-4 w-4' />
+                  显示{sidecarLabel(file)}
+                </button>
+              ))}
+            </>
+          )}
+          {canChooseVariant && (
+            <>
+              <div className={menuSectionClass}>清晰度档位</div>
+              {VARIANT_CHOICES.map(choice => (
+                <button
+                  key={choice.label}
+                  type='button'
+                  className={menuItemClass}
+                  onClick={() => {
+                    setMenuPosition(null);
+                    void setTaskVariantPolicy(task.id, choice.policy);
+                  }}
+                  role='menuitemradio'
+                  aria-checked={sameChoice(task.variant_policy, choice.policy)}
+                >
+                  <CheckIcon
+                    className={`h-4 w-4 ${
+                      sameChoice(task.variant_policy, choice.policy) ? '' : 'invisible'
+                    }`}
+                  />
+                  {choice.label}
+                </button>
+              ))}
+            </>
+          )}
+          {canEditOptions && (
+            <>
+              <div className={menuSectionClass}>仅音频</div>
+              {AUDIO_CHOICES.map(choice => {
+                const checked = sameChoice(
+                  task.audio_extraction?.format,
+                  choice.extraction?.format
+                );
+                return (
+                  <button
+                    key={choice.label}
+                    type='button'
+                    className={menuItemClass}
+                    onClick={() => {
+                      setMenuPosition(null);
+                      void setTaskAudioExtraction(task.id, choice.extraction);
+                    }}
+                    role='menuitemradio'
+                    aria-checked={checked}
+                  >
+                    <CheckIcon className={`h-4 w-4 ${checked ? '' : 'invisible'}`} />
+                    {choice.label}
+                  </button>
+                );
+              })}
+            </>
+          )}
         </div>
       )}
     </div>
@@ -1,4 +1,4 @@
-import React, { useState } from 'react';
+import React, { useEffect, useState } from 'react';
 import {
   PlusIcon,
   LinkIcon,
@@ -10,6 +10,7 @@ import {
   FolderOpenIcon,
   ArrowDownTrayIcon,
   SparklesIcon,
+  QueueListIcon,
 } from '@heroicons/react/24/outline';
 import { useDownloadStore } from '../../stores/downloadStore';
 import { useConfigStore } from '../../stores/configStore';
@@ -19,7 +20,17 @@ import {
   readClipboardTextCommand,
   selectOutputDirectoryCommand,
 } from '../../features/downloads/api/systemCommands';
-import type { DownloaderType, ExternalVideoInfo, SourcePlatform, VideoTask } from '../../types';
+import { getPlatformRulesCommand } from '../../features/downloads/api/configCommands';
+import { expandPlaylistCommand } from '../../features/downloads/api/taskCreation';
+import type {
+  DownloaderType,
+  ExternalVideoInfo,
+  PlatformRule,
+  PlaylistExpansion,
+  SourcePlatform,
+  VideoTask,
+} from '../../types';
+import { PlaylistPreview } from './PlaylistPreview';
 
 interface ManualUrlEntry {
   id: string;
@@ -32,28 +43,60 @@ interface ManualUrlEntry {
   error?: string;
 }
 
-const detectSourcePlatform = (url: string): SourcePlatform => {
+// 与后端内置规则一致；后端规则表读取失败时使用
+const BUILTIN_PLATFORM_RULES: PlatformRule[] = [
+  {
+    id: 'youtube',
+    display_name: 'YouTube',
+    hosts: ['youtube.com', 'youtu.be', 'youtube-nocookie.com'],
+    route: 'ytdlp',
+    ytdlp_args: [],
+  },
+  { id: 'tiktok', display_name: 'TikTok', hosts: ['tiktok.com'], route: 'ytdlp', ytdlp_args: [] },
+  {
+    id: 'instagram',
+    display_name: 'Instagram',
+    hosts: ['instagram.com'],
+    route: 'ytdlp',
+    ytdlp_args: [],
+  },
+  {
+    id: 'facebook',
+    display_name: 'Facebook',
+    hosts: ['facebook.com', 'fb.watch', 'fb.com'],
+    route: 'ytdlp',
+    ytdlp_args: [],
+  },
+];
+
+// 域名后缀匹配，规则按顺序取第一条
+const matchPlatformRule = (url: string, rules: PlatformRule[]): PlatformRule | undefined => {
   try {
     const host = new URL(url).hostname.toLowerCase();
-    if (host.includes('youtube.com') || host === 'youtu.be' || host.endsWith('.youtu.be')) {
-      return 'youtube';
-    }
-    if (host.includes('tiktok.com')) return 'tiktok';
-    if (host.includes('instagram.com')) return 'instagram';
-    if (host.includes('facebook.com') || host.includes('fb.watch') || host.includes('fb.com')) {
-      return 'facebook';
-    }
+    return rules.find(rule =>
+      rule.hosts.some(candidate => host === candidate || host.endsWith(`.${candidate}`))
+    );
   } catch {
-    return 'generic';
+    return undefined;
   }
-  return 'generic';
 };
 
+const detectSourcePlatform = (url: string, rules: PlatformRule[]): SourcePlatform =>
+  matchPlatformRule(url, rules)?.id ?? 'generic';
+
 const isM3u8Url = (url: string) => {
   const lower = url.toLowerCase();
   return lower.includes('.m3u8') || lower.includes('m3u8');
 };
 
+const isDashUrl = (url: string) => {
+  try {
+    return new URL(url).pathname.toLowerCase().endsWith('.mpd');
+  } catch {
+    return false;
+  }
+};
+
 const isDirectMediaUrl = (url: string) => {
   try {
     const path = new URL(url).pathname.toLowerCase();
@@ -77,27 +120,18 @@ const isDirectMediaUrl = (url: string) => {
   }
 };
 
-const inferDownloaderType = (url: string): DownloaderType => {
+const inferDownloaderType = (url: string, rules: PlatformRule[]): DownloaderType => {
+  const rule = matchPlatformRule(url, rules);
+  if (rule) return rule.route;
+  if (isDashUrl(url)) return 'dash';
   if (isM3u8Url(url)) return 'm3u8';
-  if (detectSourcePlatform(url) !== 'generic') return 'ytdlp';
   return isDirectMediaUrl(url) ? 'http' : 'ytdlp';
 };
 
-const platformLabel = (platform?: SourcePlatform) => {
-  switch (platform) {
-    case 'youtube':
-      return 'YouTube';
-    case 'tiktok':
-      return 'TikTok';
-    case 'instagram':
-      return 'Instagram';
-    case 'facebook':
-      return 'Facebook';
-    case 'generic':
-      return '网页';
-    default:
-      return undefined;
-  }
+const platformLabel = (rules: PlatformRule[], platform?: SourcePlatform) => {
+  if (!platform) return undefined;
+  if (platform === 'generic') return '网页';
+  return rules.find(rule => rule.id === platform)?.display_name ?? platform;
 };
 
 const AUTO_INFO_PROBE_TIMEOUT_MS = 6000;
@@ -125,8 +159,15 @@ export const ManualInputPanel: React.FC = () => {
   const [outputDir, setOutputDir] = useState<string>('');
   const [isValidatingUrls, setIsValidatingUrls] = useState(false);
   const [isPreparingDownload, setIsPreparingDownload] = useState(false);
+  const [expandingEntryId, setExpandingEntryId] = useState<string | null>(null);
+  const [playlistPreview, setPlaylistPreview] = useState<{
+    entryId: string;
+    playlist: PlaylistExpansion;
+  } | null>(null);
+  const [isAddingPlaylist, setIsAddingPlaylist] = useState(false);
 
   const addTasks = useDownloadStore(state => state.addTasks);
+  const addPlaylistTasks = useDownloadStore(state => state.addPlaylistTasks);
   const enqueueDownloads = useDownloadStore(state => state.enqueueDownloads);
   const recordRecentImport = useDownloadStore(state => state.recordRecentImport);
   const setFilterStatus = useDownloadStore(state => state.setFilterStatus);
@@ -134,6 +175,25 @@ export const ManualInputPanel: React.FC = () => {
   const defaultOutputDirFromConfig = useConfigStore(
     state => state.config.download.output_directory
   );
+  const configuredPlatforms = useConfigStore(state => state.config.platforms);
+  const [platformRules, setPlatformRules] = useState<PlatformRule[]>(BUILTIN_PLATFORM_RULES);
+
+  // 配置中的平台规则变化后重新读取后端生效的规则表
+  useEffect(() => {
+    let cancelled = false;
+    getPlatformRulesCommand()
+      .then(rules => {
+        if (!cancelled) setPlatformRules(rules);
+      })
+      .catch(() => {
+        if (!cancelled) {
+          setPlatformRules([...(configuredPlatforms ?? []), ...BUILTIN_PLATFORM_RULES]);
+        }
+      });
+    return () => {
+      cancelled = true;
+    };
+  }, [configuredPlatforms]);
 
   const addNewUrlEntry = () => {
     if (newUrlInput.trim()) {
@@ -189,13 +249,13 @@ export const ManualInputPanel: React.FC = () => {
   ): Promise<ManualUrlEntry> => {
     const isValidUrl = /^https?:\/\//.test(entry.url);
     let title = entry.title;
-    let platform = entry.platform ?? detectSourcePlatform(entry.url);
+    let platform = entry.platform ?? detectSourcePlatform(entry.url, platformRules);
     let externalInfo = entry.externalInfo;
 
     if (
       isValidUrl &&
       (!entry.title || !entry.externalInfo) &&
-      inferDownloaderType(entry.url) === 'ytdlp'
+      inferDownloaderType(entry.url, platformRules) === 'ytdlp'
     ) {
       try {
         const infoProbe = getVideoInfoCommand<any>({ url: entry.url });
@@ -257,6 +317,48 @@ export const ManualInputPanel: React.FC = () => {
     }
   };
 
+  const expandPlaylist = async (entry: ManualUrlEntry) => {
+    setExpandingEntryId(entry.id);
+    try {
+      const playlist = await expandPlaylistCommand(entry.url);
+      if (playlist.entries.length === 0) {
+        notify.error('没有可下载的条目', '该链接未展开出任何视频');
+        return;
+      }
+      setPlaylistPreview({ entryId: entry.id, playlist });
+    } catch (error) {
+      notify.error('展开播放列表失败', String(error));
+    } finally {
+      setExpandingEntryId(null);
+    }
+  };
+
+  const addSelectedPlaylistEntries = async (selected: number[] | null) => {
+    if (!playlistPreview) return;
+    const targetDir = outputDir || defaultOutputDirFromConfig || './downloads';
+
+    try {
+      setIsAddingPlaylist(true);
+      const addedTasks = await addPlaylistTasks(playlistPreview.playlist, selected, targetDir);
+
+      recordRecentImport(
+        addedTasks.map(task => task.id),
+        addedTasks
+      );
+      setFilterStatus('all');
+      setSearchQuery('');
+      enqueueDownloads(addedTasks.map(task => task.id));
+
+      notify.success('任务已添加', `从播放列表添加了 ${addedTasks.length} 个任务到队列`);
+      setManualUrls(current => current.filter(entry => entry.id !== playlistPreview.entryId));
+      setPlaylistPreview(null);
+    } catch (error) {
+      notify.error('添加播放列表失败', String(error));
+    } finally {
+      setIsAddingPlaylist(false);
+    }
+  };
+
   const startDownload = async () => {
     const validUrls = manualUrls.filter(entry => entry.isValid !== false); // Allow undefined (not validated yet)
     if (validUrls.length === 0) {
@@ -290,7 +392,7 @@ export const ManualInputPanel: React.FC = () => {
         speed: 0,
         created_at: new Date().toISOString(),
         updated_at: new Date().toISOString(),
-        downloader_type: inferDownloaderType(entry.url),
+        downloader_type: inferDownloaderType(entry.url, platformRules),
         video_info: {
           zl_id: entry.id,
           zl_name: '手动添加',
@@ -299,7 +401,7 @@ export const ManualInputPanel: React.FC = () => {
           kc_name: entry.title || '手动添加下载',
         },
         external_info: entry.externalInfo ?? {
-          source_platform: entry.platform ?? detectSourcePlatform(entry.url),
+          source_platform: entry.platform ?? detectSourcePlatform(entry.url, platformRules),
           webpage_url: entry.url,
           title: entry.title,
           requires_auth: false,
@@ -413,13 +515,30 @@ export const ManualInputPanel: React.FC = () => {
                   )}
                   {entry.platform && (
                     <div className='text-xs text-gray-500 mt-0.5'>
-                      {platformLabel(entry.platform)} ·{' '}
-                      {inferDownloaderType(entry.url) === 'ytdlp' ? 'yt-dlp' : '原生下载器'}
+                      {platformLabel(platformRules, entry.platform)} ·{' '}
+                      {inferDownloaderType(entry.url, platformRules) === 'ytdlp'
+                        ? 'yt-dlp'
+                        : '原生下载器'}
                     </div>
                   )}
                   {entry.error && <div className='text-xs text-red-500 mt-0.5'>{entry.error}</div>}
                 </div>
                 <div className='flex items-center gap-2'>
+                  {inferDownloaderType(entry.url, platformRules) === 'ytdlp' && (
+                    <button
+                      onClick={() => expandPlaylist(entry)}
+                      disabled={expandingEntryId !== null}
+                      className='text-gray-400 hover:text-blue-500 disabled:opacity-50'
+                      title='展开播放列表/频道'
+                      data-testid='expand-playlist'
+                    >
+                      {expandingEntryId === entry.id ? (
+                        <ArrowPathIcon className='w-4 h-4 animate-spin' />
+                      ) : (
+                        <QueueListIcon className='w-4 h-4' />
+                      )}
+                    </button>
+                  )}
                   {entry.isProcessing && (
                     <ArrowPathIcon className='w-4 h-4 text-blue-500 animate-spin' />
                   )}
@@ -438,6 +557,18 @@ export const ManualInputPanel: React.FC = () => {
             ))}
           </div>
 
+          {playlistPreview && (
+            <div className='mt-4'>
+              <PlaylistPreview
+                key={playlistPreview.entryId}
+                playlist={playlistPreview.playlist}
+                isAdding={isAddingPlaylist}
+                onConfirm={addSelectedPlaylistEntries}
+                onCancel={() => setPlaylistPreview(null)}
+              />
+            </div>
+          )}
+
           <div className='mt-4 pt-4 border-t border-gray-200 dark:border-gray-700 flex flex-col sm:flex-row gap-3 items-center'>
             <div className='flex-1 w-full relative'>
               <FolderOpenIcon className='w-5 h-5 text-gray-400 absolute left-3 top-2.5' />
@@ -0,0 +1,127 @@
+import React, { useState } from 'react';
+import { ArrowPathIcon, QueueListIcon, XMarkIcon } from '@heroicons/react/24/outline';
+import type { PlaylistExpansion } from '../../types';
+
+const formatDuration = (seconds?: number | null) => {
+  if (!seconds || seconds <= 0) return undefined;
+  const total = Math.round(seconds);
+  const hours = Math.floor(total / 3600);
+  const minutes = Math.floor((total % 3600) / 60);
+  const secs = total % 60;
+  const mmss = `${String(minutes).padStart(2, '0')}:${String(secs).padStart(2, '0')}`;
+  return hours > 0 ? `${hours}:${mmss}` : mmss;
+};
+
+interface PlaylistPreviewProps {
+  playlist: PlaylistExpansion;
+  isAdding: boolean;
+  // selected 为 null 表示全部条目
+  onConfirm: (selected: number[] | null) => void;
+  onCancel: () => void;
+}
+
+// 展开后的播放列表/频道：按下载顺序列出条目，勾选后再建任务
+export const PlaylistPreview: React.FC<PlaylistPreviewProps> = ({
+  playlist,
+  isAdding,
+  onConfirm,
+  onCancel,
+}) => {
+  const [selected, setSelected] = useState<Set<number>>(
+    () => new Set(playlist.entries.map(entry => entry.index))
+  );
+
+  const toggleEntry = (index: number) => {
+    setSelected(current => {
+      const next = new Set(current);
+      if (next.has(index)) {
+        next.delete(index);
+      } else {
+        next.add(index);
+      }
+      return next;
+    });
+  };
+
+  const allSelected = selected.size === playlist.entries.length;
+
+  const handleConfirm = () => {
+    onConfirm(
+      allSelected
+        ? null
+        : playlist.entries.map(entry => entry.index).filter(index => selected.has(index))
+    );
+  };
+
+  return (
+    <div
+      className='bg-white dark:bg-gray-800 rounded-xl border border-blue-200 dark:border-blue-800 p-4 shadow-sm'
+      data-testid='playlist-preview'
+    >
+      <div className='flex items-center justify-between mb-3'>
+        <div className='flex items-center gap-2 min-w-0'>
+          <QueueListIcon className='w-5 h-5 text-blue-500 shrink-0' />
+          <span className='text-sm font-medium text-gray-900 dark:text-gray-100 truncate'>
+            {playlist.title || playlist.webpage_url}
+          </span>
+          <span className='text-xs text-gray-500 shrink-0'>
+            已选 {selected.size} / {playlist.entries.length}
+          </span>
+        </div>
+        <div className='flex items-center gap-3 shrink-0'>
+          <button
+            onClick={() =>
+              setSelected(
+                allSelected ? new Set() : new Set(playlist.entries.map(entry => entry.index))
+              )
+            }
+            className='text-xs text-blue-600 hover:text-blue-700'
+          >
+            {allSelected ? '全不选' : '全选'}
+          </button>
+          <button onClick={onCancel} className='text-gray-400 hover:text-red-500' title='取消'>
+            <XMarkIcon className='w-4 h-4' />
+          </button>
+        </div>
+      </div>
+
+      <div className='space-y-1 max-h-72 overflow-y-auto custom-scrollbar pr-1'>
+        {playlist.entries.map(entry => (
+          <label
+            key={entry.index}
+            className='flex items-center gap-3 px-2 py-1.5 rounded hover:bg-gray-50 dark:hover:bg-gray-700/50 cursor-pointer'
+            data-testid='playlist-entry'
+          >
+            <input
+              type='checkbox'
+              checked={selected.has(entry.index)}
+              onChange={() => toggleEntry(entry.index)}
+              className='w-4 h-4 text-blue-600 border-gray-300 rounded'
+            />
+            <span className='text-xs text-gray-400 w-8 text-right'>{entry.index}</span>
+            <span className='flex-1 min-w-0 text-sm text-gray-800 dark:text-gray-200 truncate'>
+              {entry.title || entry.url}
+            </span>
+            {formatDuration(entry.duration_seconds) && (
+              <span className='text-xs text-gray-500 tabular-nums'>
+                {formatDuration(entry.duration_seconds)}
+              </span>
+            )}
+          </label>
+        ))}
+      </div>
+
+      <div className='mt-3 flex justify-end'>
+        <button
+          onClick={handleConfirm}
+          disabled={selected.size === 0 || isAdding}
+          data-testid='confirm-playlist'
+          className='px-4 py-2 bg-green-600 hover:bg-green-700 disabled:bg-gray-400 text-white rounded-lg text-sm font-medium shadow-sm transition-colors flex items-center'
+        >
+          {isAdding && <ArrowPathIcon className='w-4 h-4 mr-2 animate-spin' />}
+          添加选中条目 ({selected.size})
+        </button>
+      </div>
+    </div>
+  );
+};
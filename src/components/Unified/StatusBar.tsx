@@ -10,6 +10,8 @@ import { formatBytes, formatSpeed } from '../../utils/format';
 export const StatusBar: React.FC = () => {
   const tasks = useDownloadStore(state => state.tasks);
   const stats = useDownloadStore(state => state.stats);
+  const rateLimitStatus = useDownloadStore(state => state.rateLimitStatus);
+  const resumeRateLimitSchedule = useDownloadStore(state => state.resumeRateLimitSchedule);
 
   // Calculate real-time stats
   const activeStats = React.useMemo(() => {
@@ -39,6 +41,27 @@ export const StatusBar: React.FC = () => {
     [tasks]
   );
 
+  // 限速来源：时段规则显示规则名，手动限速显示到期时间
+  const rateLimitDetail = React.useMemo(() => {
+    if (!rateLimitStatus) {
+      return null;
+    }
+    if (rateLimitStatus.source === 'schedule') {
+      return rateLimitStatus.rule ? `时段规则 · ${rateLimitStatus.rule}` : '时段规则';
+    }
+    if (rateLimitStatus.source === 'manual') {
+      if (!rateLimitStatus.override_expires_at) {
+        return '手动';
+      }
+      const expiresAt = new Date(rateLimitStatus.override_expires_at).toLocaleTimeString([], {
+        hour: '2-digit',
+        minute: '2-digit',
+      });
+      return `手动 · 至 ${expiresAt}`;
+    }
+    return null;
+  }, [rateLimitStatus]);
+
   return (
     <div
       className='bg-white dark:bg-gray-800 border-t border-gray-200 dark:border-gray-700 px-4 py-2 text-xs text-gray-600 dark:text-gray-400 select-none shrink-0 z-20'
@@ -112,6 +135,32 @@ export const StatusBar: React.FC = () => {
                   : '--'}
               </span>
             </div>
+
+            <div
+              data-testid='rate-limit-status'
+              className='col-span-2 flex min-w-0 items-center gap-4 rounded bg-white px-3 py-1.5 dark:bg-gray-800/80'
+            >
+              <span className='truncate text-gray-500 dark:text-gray-400'>全局限速</span>
+              {rateLimitDetail && (
+                <span className='truncate text-[11px] text-gray-400 dark:text-gray-500'>
+                  {rateLimitDetail}
+                </span>
+              )}
+              {rateLimitStatus?.source === 'manual' && (
+                <button
+                  type='button'
+                  onClick={() => void resumeRateLimitSchedule()}
+                  className='text-[11px] text-blue-600 hover:underline dark:text-blue-400'
+                >
+                  恢复时段限速
+                </button>
+              )}
+              <span className='ml-auto inline-flex min-w-[5.75rem] justify-end font-semibold tabular-nums text-gray-800 dark:text-gray-100'>
+                {rateLimitStatus?.bytes_per_second
+                  ? formatSpeed(rateLimitStatus.bytes_per_second)
+                  : '不限速'}
+              </span>
+            </div>
           </div>
         </section>
 
@@ -9,6 +9,14 @@ const systemCommandMocks = vi.hoisted(() => ({
   selectOutputDirectoryCommand: vi.fn(),
 }));
 
+const configCommandMocks = vi.hoisted(() => ({
+  getPlatformRulesCommand: vi.fn(),
+}));
+
+const taskCreationMocks = vi.hoisted(() => ({
+  expandPlaylistCommand: vi.fn(),
+}));
+
 const notifyMocks = vi.hoisted(() => ({
   notify: {
     success: vi.fn(),
@@ -18,6 +26,7 @@ const notifyMocks = vi.hoisted(() => ({
 
 const downloadStoreActions = vi.hoisted(() => ({
   addTasks: vi.fn(),
+  addPlaylistTasks: vi.fn(),
   enqueueDownloads: vi.fn(),
   recordRecentImport: vi.fn(),
   setFilterStatus: vi.fn(),
@@ -45,11 +54,14 @@ vi.mock('../../../stores/configStore', () => ({
 
 vi.mock('../../../stores/uiStore', () => notifyMocks);
 vi.mock('../../../features/downloads/api/systemCommands', () => systemCommandMocks);
+vi.mock('../../../features/downloads/api/configCommands', () => configCommandMocks);
+vi.mock('../../../features/downloads/api/taskCreation', () => taskCreationMocks);
 
 describe('ManualInputPanel', () => {
   beforeEach(() => {
     vi.clearAllMocks();
     configState.config.download.output_directory = '/default-downloads';
+    configCommandMocks.getPlatformRulesCommand.mockRejectedValue(new Error('not available'));
   });
 
   it('selects output directory through the shared system command seam', async () => {
@@ -112,4 +124,75 @@ describe('ManualInputPanel', () => {
       expect.any(String)
     );
   });
+
+  it('routes and labels links with platform rules loaded from config', async () => {
+    const user = userEvent.setup();
+    downloadStoreActions.addTasks.mockImplementation(async tasks => tasks);
+    configCommandMocks.getPlatformRulesCommand.mockResolvedValue([
+      {
+        id: 'courses',
+        display_name: '内部课程',
+        hosts: ['learn.corp.example'],
+        route: 'm3u8',
+        ytdlp_args: [],
+      },
+    ]);
+
+    render(<ManualInputPanel />);
+
+    await waitFor(() => {
+      expect(configCommandMocks.getPlatformRulesCommand).toHaveBeenCalledTimes(1);
+    });
+    await user.type(screen.getByTestId('url-input'), 'https://learn.corp.example/lesson/7');
+    await user.click(screen.getByTestId('add-url'));
+    await user.click(screen.getByTestId('confirm-import'));
+
+    await waitFor(() => {
+      expect(downloadStoreActions.addTasks).toHaveBeenCalledTimes(1);
+    });
+
+    expect(systemCommandMocks.getVideoInfoCommand).not.toHaveBeenCalled();
+    expect(downloadStoreActions.addTasks.mock.calls[0][0][0]).toMatchObject({
+      url: 'https://learn.corp.example/lesson/7',
+      downloader_type: 'm3u8',
+      external_info: { source_platform: 'courses' },
+    });
+  });
+
+  it('previews an expanded playlist and adds only the selected entries', async () => {
+    const user = userEvent.setup();
+    const playlist = {
+      source_platform: 'youtube',
+      webpage_url: 'https://www.youtube.com/playlist?list=PL1',
+      title: 'Course',
+      entries: [
+        { index: 1, url: 'https://www.youtube.com/watch?v=a', title: 'Intro' },
+        { index: 2, url: 'https://www.youtube.com/watch?v=b', title: 'Lesson 2' },
+      ],
+    };
+    taskCreationMocks.expandPlaylistCommand.mockResolvedValue(playlist);
+    downloadStoreActions.addPlaylistTasks.mockResolvedValue([{ id: 'task-b' }]);
+
+    render(<ManualInputPanel />);
+
+    await user.type(screen.getByTestId('url-input'), playlist.webpage_url);
+    await user.click(screen.getByTestId('add-url'));
+    await user.click(screen.getByTestId('expand-playlist'));
+
+    expect(await screen.findByTestId('playlist-preview')).toHaveTextContent('已选 2 / 2');
+    expect(screen.getAllByTestId('playlist-entry')).toHaveLength(2);
+
+    await user.click(screen.getByText('Intro'));
+    await user.click(screen.getByTestId('confirm-playlist'));
+
+    await waitFor(() => {
+      expect(downloadStoreActions.addPlaylistTasks).toHaveBeenCalledWith(
+        playlist,
+        [2],
+        '/default-downloads'
+      );
+    });
+    expect(downloadStoreActions.enqueueDownloads).toHaveBeenCalledWith(['task-b']);
+    expect(downloadStoreActions.addTasks).not.toHaveBeenCalled();
+  });
 });
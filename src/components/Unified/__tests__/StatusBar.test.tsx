@@ -33,6 +33,13 @@ describe('Unified StatusBar', () => {
         commit_warning_count: 2,
         commit_elevated_warning_count: 0,
       },
+      rateLimitStatus: {
+        bytes_per_second: 1024 * 1024,
+        source: 'schedule',
+        rule: 'office-hours',
+        override_expires_at: null,
+      },
+      resumeRateLimitSchedule: vi.fn(),
     };
 
     mockUseDownloadStore.mockImplementation((selector?: unknown) =>
@@ -42,6 +49,15 @@ describe('Unified StatusBar', () => {
     );
   });
 
+  it('shows the active bandwidth schedule rule and its limit', () => {
+    render(<StatusBar />);
+
+    const rateLimit = screen.getByTestId('rate-limit-status');
+    expect(rateLimit).toHaveTextContent('时段规则 · office-hours');
+    expect(rateLimit).toHaveTextContent('1 MB/s');
+    expect(screen.queryByText('恢复时段限速')).not.toBeInTheDocument();
+  });
+
   it('renders total speed from store stats display_total_speed_bps on the authoritative mainline status bar', () => {
     render(<StatusBar />);
 
@@ -8,13 +8,21 @@ import {
   cancelDownloadCommand,
   startAllDownloadsCommand,
   pauseAllDownloadsCommand,
+  clearRateLimitOverrideCommand,
 } from '../downloadCommands';
 import {
   removeTasksCommand,
   clearCompletedTasksCommand,
   updateTaskOutputPathsCommand,
+  stopLiveRecordingCommand,
+  setTaskVariantPolicyCommand,
+  setTaskAudioExtractionCommand,
 } from '../taskMutations';
-import { addDownloadTasksCommand } from '../taskCreation';
+import {
+  addDownloadTasksCommand,
+  addPlaylistTasksCommand,
+  expandPlaylistCommand,
+} from '../taskCreation';
 import {
   importRawFileCommand,
   importStructuredFileCommand,
@@ -42,7 +50,11 @@ import {
   resetConfigCommand,
   updateConfigCommand,
 } from '../configCommands';
-import { getDownloadStatsCommand, getDownloadTasksCommand } from '../runtimeQueries';
+import {
+  getDownloadStatsCommand,
+  getDownloadTasksCommand,
+  getRateLimitStatusCommand,
+} from '../runtimeQueries';
 
 describe('downloads api command seams', () => {
   beforeEach(() => {
@@ -108,6 +120,41 @@ describe('downloads api command seams', () => {
     });
   });
 
+  it('wraps the live recording stop command', async () => {
+    vi.mocked(invoke).mockResolvedValueOnce(true as never);
+
+    await expect(stopLiveRecordingCommand('live-1')).resolves.toBe(true);
+
+    expect(invoke).toHaveBeenCalledWith('stop_live_recording', {
+      taskId: 'live-1',
+      task_id: 'live-1',
+    });
+  });
+
+  it('wraps the per-task variant policy command', async () => {
+    const policy = { kind: 'closest_height' as const, height: 720 };
+
+    await setTaskVariantPolicyCommand('hls-1', policy);
+
+    expect(invoke).toHaveBeenCalledWith('set_task_variant_policy', {
+      taskId: 'hls-1',
+      task_id: 'hls-1',
+      policy,
+    });
+  });
+
+  it('wraps the per-task audio extraction command', async () => {
+    const extraction = { format: 'mp3' as const, bitrate_kbps: 192 };
+
+    await setTaskAudioExtractionCommand('task-1', extraction);
+
+    expect(invoke).toHaveBeenCalledWith('set_task_audio_extraction', {
+      taskId: 'task-1',
+      task_id: 'task-1',
+      extraction,
+    });
+  });
+
   it('wraps task creation command', async () => {
     const tasks = [{ url: 'https://example.com/video.mp4', title: 'Example' }];
 
@@ -116,6 +163,29 @@ describe('downloads api command seams', () => {
     expect(invoke).toHaveBeenNthCalledWith(1, 'add_download_tasks', { tasks });
   });
 
+  it('wraps playlist expansion and playlist task creation commands', async () => {
+    const playlist = {
+      source_platform: 'youtube' as const,
+      webpage_url: 'https://www.youtube.com/playlist?list=PL1',
+      playlist_id: 'PL1',
+      entries: [{ index: 1, url: 'https://www.youtube.com/watch?v=a' }],
+    };
+    vi.mocked(invoke)
+      .mockResolvedValueOnce(playlist as never)
+      .mockResolvedValueOnce([] as never);
+
+    await expect(expandPlaylistCommand(playlist.webpage_url)).resolves.toEqual(playlist);
+    await addPlaylistTasksCommand(playlist, [1], '/downloads');
+
+    expect(invoke).toHaveBeenNthCalledWith(1, 'expand_playlist', { url: playlist.webpage_url });
+    expect(invoke).toHaveBeenNthCalledWith(2, 'add_playlist_tasks', {
+      playlist,
+      selected: [1],
+      outputPath: '/downloads',
+      output_path: '/downloads',
+    });
+  });
+
   it('wraps import preview and structured-file command seams used by import surfaces', async () => {
     vi.mocked(invoke)
       .mockResolvedValueOnce({
@@ -311,4 +381,12 @@ describe('downloads api command seams', () => {
     expect(invoke).toHaveBeenNthCalledWith(1, 'get_download_tasks');
     expect(invoke).toHaveBeenNthCalledWith(2, 'get_download_stats');
   });
+
+  it('wraps rate limit status and schedule override command seams', async () => {
+    await getRateLimitStatusCommand();
+    await clearRateLimitOverrideCommand();
+
+    expect(invoke).toHaveBeenNthCalledWith(1, 'get_rate_limit_status');
+    expect(invoke).toHaveBeenNthCalledWith(2, 'clear_rate_limit_override');
+  });
 });
@@ -0,0 +1,26 @@
+import type { CookieDomainSummary, CredentialInput, CredentialSummary } from '../../../types';
+import { invokeTauri } from '../../../utils/tauriBridge';
+
+export const listCredentialsCommand = async (): Promise<CredentialSummary[]> =>
+  invokeTauri<CredentialSummary[]>('list_credentials');
+
+export const saveCredentialCommand = async (
+  credential: CredentialInput
+): Promise<CredentialSummary> =>
+  invokeTauri<CredentialSummary>('save_credential', { credential });
+
+export const deleteCredentialCommand = async (id: string): Promise<boolean> =>
+  invokeTauri<boolean>('delete_credential', { id });
+
+export const importCookiesCommand = async (filePath: string, domain?: string): Promise<number> =>
+  invokeTauri<number>('import_cookies', {
+    filePath,
+    file_path: filePath,
+    domain: domain ?? null,
+  });
+
+export const listCookieDomainsCommand = async (): Promise<CookieDomainSummary[]> =>
+  invokeTauri<CookieDomainSummary[]>('list_cookie_domains');
+
+export const clearCookiesCommand = async (domain: string): Promise<number> =>
+  invokeTauri<number>('clear_cookies', { domain });
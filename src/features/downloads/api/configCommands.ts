@@ -1,4 +1,4 @@
-import type { AppConfig } from '../../../types';
+import type { AppConfig, PlatformRule } from '../../../types';
 import { invokeTauri } from '../../../utils/tauriBridge';
 
 export const getConfigCommand = async (): Promise<AppConfig> =>
@@ -24,3 +24,7 @@ export const importConfigCommand = async (filePath: string): Promise<AppConfig>
     filePath,
     file_path: filePath,
   });
+
+// 当前生效的平台规则（自定义规则在前，随后是内置平台）
+export const getPlatformRulesCommand = async (): Promise<PlatformRule[]> =>
+  invokeTauri<PlatformRule[]>('get_platform_rules');
@@ -22,3 +22,7 @@ export const startAllDownloadsCommand = async (): Promise<number> =>
 
 export const pauseAllDownloadsCommand = async (): Promise<number> =>
   invokeTauri<number>('pause_all_downloads');
+
+// 取消手动限速，恢复按时段限速
+export const clearRateLimitOverrideCommand = async (): Promise<unknown> =>
+  invokeTauri<unknown>('clear_rate_limit_override');
@@ -8,3 +8,7 @@ export const getDownloadTasksCommand = async <TTask>(): Promise<TTask[]> => {
 
 export const getDownloadStatsCommand = async (): Promise<DownloadStats> =>
   invokeTauri<DownloadStats>('get_download_stats');
+
+// 当前生效的全局限速（启动时加载，之后由 rate_limit.changed 事件更新）
+export const getRateLimitStatusCommand = async (): Promise<unknown> =>
+  invokeTauri<unknown>('get_rate_limit_status');
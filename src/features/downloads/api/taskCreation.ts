@@ -1,4 +1,23 @@
+import type { VideoTask } from '../../../schemas';
+import type { PlaylistExpansion } from '../../../types';
 import { invokeTauri } from '../../../utils/tauriBridge';
 
 export const addDownloadTasksCommand = async <T>(tasks: unknown[]): Promise<T> =>
   invokeTauri<T>('add_download_tasks', { tasks });
+
+// 展开播放列表/频道/课程，只列条目不下载
+export const expandPlaylistCommand = async (url: string): Promise<PlaylistExpansion> =>
+  invokeTauri<PlaylistExpansion>('expand_playlist', { url });
+
+// selected 为空时为全部条目各建一个任务
+export const addPlaylistTasksCommand = async (
+  playlist: PlaylistExpansion,
+  selected: number[] | null,
+  outputPath: string
+): Promise<VideoTask[]> =>
+  invokeTauri<VideoTask[]>('add_playlist_tasks', {
+    playlist,
+    selected,
+    outputPath,
+    output_path: outputPath,
+  });
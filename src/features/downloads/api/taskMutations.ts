@@ -1,4 +1,5 @@
 import type { VideoTask } from '../../../schemas';
+import type { AudioExtraction, HlsVariantPolicy } from '../../../types';
 import { invokeTauri } from '../../../utils/tauriBridge';
 import { buildTaskIdsPayload } from '../../../utils/tauriPayloads';
 
@@ -20,3 +21,21 @@ export const updateTaskOutputPathsCommand = async (
     taskUpdates,
     task_updates: taskUpdates,
   });
+
+// 结束直播录制并保留已录内容；任务不在录制中时返回 false
+export const stopLiveRecordingCommand = async (taskId: string): Promise<boolean> =>
+  invokeTauri<boolean>('stop_live_recording', { taskId, task_id: taskId });
+
+// policy 为 null 时恢复使用配置中的默认策略
+export const setTaskVariantPolicyCommand = async (
+  taskId: string,
+  policy: HlsVariantPolicy | null
+): Promise<VideoTask> =>
+  invokeTauri<VideoTask>('set_task_variant_policy', { taskId, task_id: taskId, policy });
+
+// extraction 为 null 时保留视频（yt-dlp 任务沿用 YouTube 配置）
+export const setTaskAudioExtractionCommand = async (
+  taskId: string,
+  extraction: AudioExtraction | null
+): Promise<VideoTask> =>
+  invokeTauri<VideoTask>('set_task_audio_extraction', { taskId, task_id: taskId, extraction });
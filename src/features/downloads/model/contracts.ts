@@ -1,3 +1,11 @@
+import type { HlsVariant, LiveRecordingProgress, SegmentGap, SidecarFile } from '../../../types';
+import {
+  HlsVariantSchema,
+  LiveRecordingProgressSchema,
+  SegmentGapSchema,
+  SidecarFileSchema,
+} from '../../../schemas';
+
 export interface DownloadEventEnvelope<T = unknown> {
   schema_version: number;
   event_id: string;
@@ -6,7 +14,16 @@ export interface DownloadEventEnvelope<T = unknown> {
   payload: T;
 }
 
-export type DownloadEventType = 'task.progressed' | 'task.status_changed' | 'task.stats_updated';
+export type DownloadEventType =
+  | 'task.progressed'
+  | 'task.status_changed'
+  | 'task.stats_updated'
+  | 'task.file_resolved'
+  | 'task.variant_selected'
+  | 'task.segment_gaps'
+  | 'task.format_selected'
+  | 'task.sidecar_files'
+  | 'rate_limit.changed';
 
 export interface TaskProgressedPayload {
   task_id: string;
@@ -16,6 +33,7 @@ export interface TaskProgressedPayload {
   display_speed_bps?: number;
   eta?: number;
   progress?: number;
+  recording?: LiveRecordingProgress;
 }
 
 export interface TaskStatusChangedPayload {
@@ -24,6 +42,35 @@ export interface TaskStatusChangedPayload {
   error_message?: string | null;
 }
 
+// 下载器按 Content-Disposition / Content-Type 确定的最终文件路径
+export interface TaskFileResolvedPayload {
+  task_id: string;
+  file_path: string;
+}
+
+// HLS 下载从 master playlist 选中的档位
+export interface TaskVariantSelectedPayload {
+  task_id: string;
+  variant: HlsVariant;
+}
+
+// HLS/DASH 下载完成时跳过的片段（空数组表示没有缺口）
+export interface TaskSegmentGapsPayload {
+  task_id: string;
+  gaps: SegmentGap[];
+}
+
+export interface TaskSidecarFilesPayload {
+  task_id: string;
+  files: SidecarFile[];
+}
+
+export interface TaskFormatSelectedPayload {
+  task_id: string;
+  format_id: string;
+  format_note?: string;
+}
+
 export interface TaskStatsUpdatedPayload {
   total_tasks?: number;
   completed_tasks?: number;
@@ -41,10 +88,28 @@ export interface TaskStatsUpdatedPayload {
   commit_elevated_warning_count?: number;
 }
 
+export type RateLimitSource = 'unlimited' | 'schedule' | 'manual';
+
+// 当前生效的全局限速（手动限速优先于时段规则）
+export interface RateLimitChangedPayload {
+  bytes_per_second: number | null;
+  source: RateLimitSource;
+  rule: string | null;
+  override_expires_at: string | null;
+}
+
 export const SUPPORTED_DOWNLOAD_EVENT_SCHEMA = 1;
 
 export const isSupportedDownloadEventType = (value: unknown): value is DownloadEventType =>
-  value === 'task.progressed' || value === 'task.status_changed' || value === 'task.stats_updated';
+  value === 'task.progressed' ||
+  value === 'task.status_changed' ||
+  value === 'task.stats_updated' ||
+  value === 'task.file_resolved' ||
+  value === 'task.variant_selected' ||
+  value === 'task.segment_gaps' ||
+  value === 'task.format_selected' ||
+  value === 'task.sidecar_files' ||
+  value === 'rate_limit.changed';
 
 const isNonEmptyString = (value: unknown): value is string =>
   typeof value === 'string' && value.trim().length > 0;
@@ -128,6 +193,7 @@ export const parseTaskProgressedPayload = (
       display_speed_bps: asFiniteNumber(candidate.display_speed_bps),
       eta: asFiniteNumber(candidate.eta),
       progress: asFiniteNumber(candidate.progress),
+      recording: LiveRecordingProgressSchema.safeParse(candidate.recording).data,
     },
   };
 };
@@ -201,3 +267,116 @@ export const parseTaskStatsUpdatedPayload = (
     },
   };
 };
+
+export const parseTaskFileResolvedPayload = (
+  payload: unknown
+): { success: true; data: TaskFileResolvedPayload } | { success: false; error: string } => {
+  if (!payload || typeof payload !== 'object') {
+    return { success: false, error: 'task.file_resolved payload must be an object' };
+  }
+
+  const candidate = payload as Record<string, unknown>;
+  if (!isNonEmptyString(candidate.task_id) || !isNonEmptyString(candidate.file_path)) {
+    return { success: false, error: 'task.file_resolved requires task_id and file_path' };
+  }
+
+  return {
+    success: true,
+    data: { task_id: candidate.task_id, file_path: candidate.file_path },
+  };
+};
+
+export const parseTaskVariantSelectedPayload = (
+  payload: unknown
+): { success: true; data: TaskVariantSelectedPayload } | { success: false; error: string } => {
+  if (!payload || typeof payload !== 'object') {
+    return { success: false, error: 'task.variant_selected payload must be an object' };
+  }
+
+  const candidate = payload as Record<string, unknown>;
+  const variant = HlsVariantSchema.safeParse(candidate.variant);
+  if (!isNonEmptyString(candidate.task_id) || !variant.success) {
+    return { success: false, error: 'task.variant_selected requires task_id and variant' };
+  }
+
+  return { success: true, data: { task_id: candidate.task_id, variant: variant.data } };
+};
+
+export const parseTaskSegmentGapsPayload = (
+  payload: unknown
+): { success: true; data: TaskSegmentGapsPayload } | { success: false; error: string } => {
+  if (!payload || typeof payload !== 'object') {
+    return { success: false, error: 'task.segment_gaps payload must be an object' };
+  }
+
+  const candidate = payload as Record<string, unknown>;
+  const gaps = SegmentGapSchema.array().safeParse(candidate.gaps);
+  if (!isNonEmptyString(candidate.task_id) || !gaps.success) {
+    return { success: false, error: 'task.segment_gaps requires task_id and gaps' };
+  }
+
+  return { success: true, data: { task_id: candidate.task_id, gaps: gaps.data } };
+};
+
+export const parseTaskFormatSelectedPayload = (
+  payload: unknown
+): { success: true; data: TaskFormatSelectedPayload } | { success: false; error: string } => {
+  if (!payload || typeof payload !== 'object') {
+    return { success: false, error: 'task.format_selected payload must be an object' };
+  }
+
+  const candidate = payload as Record<string, unknown>;
+  if (!isNonEmptyString(candidate.task_id) || !isNonEmptyString(candidate.format_id)) {
+    return { success: false, error: 'task.format_selected requires task_id and format_id' };
+  }
+
+  return {
+    success: true,
+    data: {
+      task_id: candidate.task_id,
+      format_id: candidate.format_id,
+      format_note: isNonEmptyString(candidate.format_note) ? candidate.format_note : undefined,
+    },
+  };
+};
+
+export const parseTaskSidecarFilesPayload = (
+  payload: unknown
+): { success: true; data: TaskSidecarFilesPayload } | { success: false; error: string } => {
+  if (!payload || typeof payload !== 'object') {
+    return { success: false, error: 'task.sidecar_files payload must be an object' };
+  }
+
+  const candidate = payload as Record<string, unknown>;
+  const files = SidecarFileSchema.array().safeParse(candidate.files);
+  if (!isNonEmptyString(candidate.task_id) || !files.success) {
+    return { success: false, error: 'task.sidecar_files requires task_id and files' };
+  }
+
+  return { success: true, data: { task_id: candidate.task_id, files: files.data } };
+};
+
+export const parseRateLimitChangedPayload = (
+  payload: unknown
+): { success: true; data: RateLimitChangedPayload } | { success: false; error: string } => {
+  if (!payload || typeof payload !== 'object') {
+    return { success: false, error: 'rate_limit.changed payload must be an object' };
+  }
+
+  const candidate = payload as Record<string, unknown>;
+  const source = candidate.source;
+  if (source !== 'unlimited' && source !== 'schedule' && source !== 'manual') {
+    return { success: false, error: 'rate_limit.changed has invalid source' };
+  }
+
+  return {
+    success: true,
+    data: {
+      bytes_per_second: asFiniteNumber(candidate.bytes_per_second) ?? null,
+      source,
+      rule: typeof candidate.rule === 'string' ? candidate.rule : null,
+      override_expires_at:
+        typeof candidate.override_expires_at === 'string' ? candidate.override_expires_at : null,
+    },
+  };
+};
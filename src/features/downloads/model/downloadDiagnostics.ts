@@ -179,7 +179,7 @@ export const toDownloadDiagnostic = (error: unknown): DownloadDiagnostic => {
       return {
         code,
         severity: 'warning',
-        message: '该内容需要登录、年龄确认或私有权限；当前仅支持公开内容下载。',
+        message: '该内容需要登录、年龄确认或私有权限；请导入该站点的 cookies.txt 或添加账号凭据后重试。',
       };
     case 'geo_or_policy_restricted':
       return {
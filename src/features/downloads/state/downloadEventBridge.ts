@@ -3,6 +3,12 @@ import { listen } from '@tauri-apps/api/event';
 import { ensureDownloadStats, calculateStatsFromTasks } from '../../../utils/downloadStats';
 import {
   parseDownloadEventEnvelope,
+  parseRateLimitChangedPayload,
+  parseTaskFileResolvedPayload,
+  parseTaskVariantSelectedPayload,
+  parseTaskSegmentGapsPayload,
+  parseTaskFormatSelectedPayload,
+  parseTaskSidecarFilesPayload,
   parseTaskProgressedPayload,
   parseTaskStatsUpdatedPayload,
   parseTaskStatusChangedPayload,
@@ -148,6 +154,72 @@ export const initializeDownloadEventBridge = async () => {
             }));
             break;
           }
+          case 'task.file_resolved': {
+            const parsedPayload = parseTaskFileResolvedPayload(envelope.payload);
+            if (parsedPayload.success === false) return;
+            const { task_id, file_path } = parsedPayload.data;
+            useDownloadStore.setState(state => ({
+              tasks: state.tasks.map(task =>
+                task.id === task_id ? { ...task, resolved_path: file_path } : task
+              ),
+            }));
+            break;
+          }
+          case 'task.variant_selected': {
+            const parsedPayload = parseTaskVariantSelectedPayload(envelope.payload);
+            if (parsedPayload.success === false) return;
+            const { task_id, variant } = parsedPayload.data;
+            useDownloadStore.setState(state => ({
+              tasks: state.tasks.map(task =>
+                task.id === task_id ? { ...task, hls_variant: variant } : task
+              ),
+            }));
+            break;
+          }
+          case 'task.segment_gaps': {
+            const parsedPayload = parseTaskSegmentGapsPayload(envelope.payload);
+            if (parsedPayload.success === false) return;
+            const { task_id, gaps } = parsedPayload.data;
+            useDownloadStore.setState(state => ({
+              tasks: state.tasks.map(task =>
+                task.id === task_id ? { ...task, segment_gaps: gaps } : task
+              ),
+            }));
+            break;
+          }
+          case 'task.format_selected': {
+            const parsedPayload = parseTaskFormatSelectedPayload(envelope.payload);
+            if (parsedPayload.success === false) return;
+            const { task_id, format_id, format_note } = parsedPayload.data;
+            useDownloadStore.setState(state => ({
+              tasks: state.tasks.map(task =>
+                task.id === task_id && task.external_info
+                  ? {
+                      ...task,
+                      external_info: { ...task.external_info, format_id, format_note },
+                    }
+                  : task
+              ),
+            }));
+            break;
+          }
+          case 'task.sidecar_files': {
+            const parsedPayload = parseTaskSidecarFilesPayload(envelope.payload);
+            if (parsedPayload.success === false) return;
+            const { task_id, files } = parsedPayload.data;
+            useDownloadStore.setState(state => ({
+              tasks: state.tasks.map(task =>
+                task.id === task_id ? { ...task, sidecar_files: files } : task
+              ),
+            }));
+            break;
+          }
+          case 'rate_limit.changed': {
+            const parsedPayload = parseRateLimitChangedPayload(envelope.payload);
+            if (parsedPayload.success === false) return;
+            useDownloadStore.setState({ rateLimitStatus: parsedPayload.data });
+            break;
+          }
         }
       });
 
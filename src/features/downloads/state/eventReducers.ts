@@ -1,4 +1,5 @@
 import type { TaskStatus, VideoTask } from '../../../schemas';
+import type { LiveRecordingProgress } from '../../../types';
 
 export interface ProgressEventPayload {
   task_id: string;
@@ -8,6 +9,7 @@ export interface ProgressEventPayload {
   display_speed_bps?: number;
   eta?: number | null;
   progress?: number;
+  recording?: LiveRecordingProgress;
 }
 
 export interface StatusEventPayload {
@@ -102,6 +104,7 @@ export const reduceTasksWithProgressUpdate = (
       display_speed_bps: normalizedDisplaySpeed,
       eta: etaValue,
       progress,
+      recording: update.recording ?? task.recording,
       updated_at: new Date().toISOString(),
     };
   });
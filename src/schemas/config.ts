@@ -1,5 +1,10 @@
 import { z } from 'zod';
 import { LogLevelSchema, ThemeTypeSchema } from './enums';
+import { HlsRenditionPreferencesSchema, HlsVariantPolicySchema } from './tasks';
+
+const TimeOfDaySchema = z
+  .string()
+  .regex(/^(([01]\d|2[0-3]):[0-5]\d|24:00)$/, '时间格式应为 HH:MM');
 
 export const DownloadConfigSchema = z
   .object({
@@ -8,7 +13,43 @@ export const DownloadConfigSchema = z
     timeout_seconds: z.number().int().min(10).max(300, '超时时间应在10-300秒之间'),
     user_agent: z.string().min(1, 'User-Agent不能为空'),
     proxy: z.string().optional().nullable(),
+    no_proxy: z.array(z.string()).optional(),
     headers: z.record(z.string(), z.string()),
+    host_headers: z
+      .array(
+        z.object({
+          host: z.string().min(1),
+          user_agent: z.string().optional().nullable(),
+          referer: z.string().optional().nullable(),
+          origin: z.string().optional().nullable(),
+          headers: z.record(z.string(), z.string()).optional(),
+        })
+      )
+      .optional(),
+    bandwidth_schedule: z
+      .array(
+        z.object({
+          start: TimeOfDaySchema,
+          end: TimeOfDaySchema,
+          bytes_per_second: z.number().int().positive().optional().nullable(),
+          label: z.string().optional().nullable(),
+        })
+      )
+      .optional(),
+    per_task_rate_limit: z.number().int().positive().optional().nullable(),
+    host_rate_limits: z
+      .array(
+        z.object({
+          host: z.string().min(1),
+          bytes_per_second: z.number().int().positive(),
+        })
+      )
+      .optional(),
+    hls_variant_policy: HlsVariantPolicySchema.optional(),
+    live_max_duration_secs: z.number().int().positive().optional().nullable(),
+    live_max_bytes: z.number().int().positive().optional().nullable(),
+    hls_renditions: HlsRenditionPreferencesSchema.optional(),
+    hls_max_missing_segments: z.number().int().nonnegative().optional(),
     output_directory: z.string().min(1, '输出目录不能为空'),
     auto_verify_integrity: z.boolean(),
     integrity_algorithm: z.string().min(1).optional().nullable(),
@@ -58,12 +99,18 @@ export const SystemConfigSchema = z.object({
 export const YoutubeConfigSchema = z.object({
   default_quality: z.string().optional().nullable(),
   default_format: z.string().optional().nullable(),
+  default_video_codec: z.enum(['any', 'avc1', 'vp9', 'av01']).optional().nullable(),
+  default_audio_quality: z.enum(['best', 'high', 'medium', 'low', 'worst']).optional().nullable(),
   extract_audio: z.boolean(),
   audio_format: z.string().optional().nullable(),
+  audio_bitrate_kbps: z.number().int().min(32).max(512).optional().nullable(),
   download_subtitles: z.boolean(),
   subtitle_languages: z.array(z.string()),
+  auto_subtitles: z.boolean().optional(),
+  subtitle_format: z.enum(['srt', 'vtt', 'ass', 'lrc']).optional().nullable(),
   download_thumbnail: z.boolean(),
   download_description: z.boolean(),
+  write_info_json: z.boolean().optional(),
   playlist_reverse: z.boolean(),
   playlist_max_items: z.number().int().positive().optional().nullable(),
 });
@@ -74,7 +121,7 @@ export const AdvancedConfigSchema = z.object({
   max_log_files: z.number().int().min(1).max(50),
   cleanup_on_exit: z.boolean(),
   enable_proxy: z.boolean(),
-  proxy_type: z.enum(['http', 'https', 'socks4', 'socks5']),
+  proxy_type: z.enum(['http', 'https', 'socks4', 'socks5', 'socks5h']),
   proxy_host: z.string().optional(),
   proxy_port: z.number().int().positive().optional(),
   proxy_username: z.string().optional(),
@@ -85,12 +132,32 @@ export const AdvancedConfigSchema = z.object({
   statistics_retention_days: z.number().int().min(1).max(365),
 });
 
+export const PlatformRuleSchema = z.object({
+  id: z
+    .string()
+    .regex(/^[a-z0-9_-]+$/, '平台 ID 只能包含小写字母、数字、- 和 _')
+    .refine(id => id !== 'generic', '平台 ID generic 为保留值'),
+  display_name: z.string().trim().min(1, '请填写平台名称'),
+  hosts: z.array(z.string().trim().min(1)).min(1, '至少需要一个域名'),
+  route: z.enum(['ytdlp', 'http', 'm3u8']).default('ytdlp'),
+  ytdlp_args: z.array(z.string().trim().min(1)).default([]),
+  default_quality: z.string().optional().nullable(),
+  max_concurrent: z.number().int().positive().optional().nullable(),
+});
+
 export const AppConfigSchema = z.object({
   download: DownloadConfigSchema,
   ui: UIConfigSchema.optional().nullable(),
   system: SystemConfigSchema.optional().nullable(),
   youtube: YoutubeConfigSchema.optional().nullable(),
   advanced: AdvancedConfigSchema,
+  platforms: z
+    .array(PlatformRuleSchema)
+    .optional()
+    .refine(
+      rules => !rules || new Set(rules.map(rule => rule.id)).size === rules.length,
+      '平台 ID 不能重复'
+    ),
 });
 
 export type DownloadConfig = z.infer<typeof DownloadConfigSchema>;
@@ -98,4 +165,5 @@ export type UIConfig = z.infer<typeof UIConfigSchema>;
 export type SystemConfig = z.infer<typeof SystemConfigSchema>;
 export type YoutubeConfig = z.infer<typeof YoutubeConfigSchema>;
 export type AdvancedConfig = z.infer<typeof AdvancedConfigSchema>;
+export type PlatformRule = z.infer<typeof PlatformRuleSchema>;
 export type AppConfig = z.infer<typeof AppConfigSchema>;
@@ -12,7 +12,7 @@ export const TaskStatusSchema = z.enum([
 
 export const DownloaderTypeSchema = z.preprocess(
   value => (value === 'youtube' ? 'ytdlp' : value),
-  z.enum(['http', 'm3u8', 'ytdlp'])
+  z.enum(['http', 'm3u8', 'dash', 'ytdlp'])
 );
 export const ViewTypeSchema = z.enum(['dashboard', 'import', 'settings', 'about']);
 export const NotificationTypeSchema = z.enum(['success', 'error', 'warning', 'info']);
@@ -26,13 +26,8 @@ export const VideoInfoSchema = z
     }
   );
 
-export const SourcePlatformSchema = z.enum([
-  'youtube',
-  'tiktok',
-  'instagram',
-  'facebook',
-  'generic',
-]);
+// 内置平台或配置中自定义平台的 id
+export const SourcePlatformSchema = z.string().min(1);
 
 export const ExternalVideoInfoSchema = z.object({
   source_platform: SourcePlatformSchema,
@@ -44,11 +39,104 @@ export const ExternalVideoInfoSchema = z.object({
   format_id: z.string().nullable().optional(),
   format_note: z.string().nullable().optional(),
   requires_auth: z.boolean().optional().default(false),
+  playlist_id: z.string().nullable().optional(),
+  playlist_title: z.string().nullable().optional(),
+  playlist_index: z.number().int().positive().nullable().optional(),
+});
+
+// master playlist 档位选择策略；bandwidth 单位为 bits/s
+export const HlsVariantPolicySchema = z.discriminatedUnion('kind', [
+  z.object({ kind: z.literal('highest') }),
+  z.object({ kind: z.literal('lowest') }),
+  z.object({ kind: z.literal('closest_height'), height: z.number().int().positive() }),
+  z.object({ kind: z.literal('max_bandwidth'), bandwidth: z.number().int().positive() }),
+]);
+
+const YtDlpVideoQualitySchema = z.union([
+  z.enum(['low', 'medium', 'high', 'best', 'worst']),
+  z.object({ height: z.number().int().positive() }),
+]);
+const YtDlpVideoCodecSchema = z.enum(['avc1', 'vp9', 'av01', 'any']);
+const YtDlpAudioQualitySchema = z.enum(['low', 'medium', 'high', 'best', 'worst']);
+const YtDlpAudioCodecSchema = z.enum(['aac', 'mp3', 'opus', 'flac', 'any']);
+
+export const YoutubeDownloadFormatSchema = z.discriminatedUnion('kind', [
+  z.object({
+    kind: z.literal('complete_video'),
+    video_quality: YtDlpVideoQualitySchema,
+    video_codec: YtDlpVideoCodecSchema,
+    audio_quality: YtDlpAudioQualitySchema,
+    audio_codec: YtDlpAudioCodecSchema,
+  }),
+  z.object({
+    kind: z.literal('video_only'),
+    quality: YtDlpVideoQualitySchema,
+    codec: YtDlpVideoCodecSchema,
+  }),
+  z.object({
+    kind: z.literal('audio_only'),
+    quality: YtDlpAudioQualitySchema,
+    codec: YtDlpAudioCodecSchema,
+  }),
+  z.object({ kind: z.literal('best_available') }),
+  z.object({ kind: z.literal('specific_format'), format_id: z.string().min(1) }),
+]);
+
+export const AudioExtractionSchema = z.object({
+  format: z.enum(['mp3', 'm4a', 'opus', 'flac', 'wav']),
+  bitrate_kbps: z.number().int().min(32).max(512).nullable().optional(),
+});
+
+export const HlsVariantSchema = z.object({
+  uri: z.string(),
+  bandwidth: z.number().nonnegative(),
+  average_bandwidth: z.number().nonnegative().nullable().optional(),
+  width: z.number().nullable().optional(),
+  height: z.number().nullable().optional(),
+  codecs: z.string().nullable().optional(),
+  frame_rate: z.number().nullable().optional(),
+  audio_group: z.string().nullable().optional(),
+  subtitles_group: z.string().nullable().optional(),
+});
+
+export const HlsRenditionPreferencesSchema = z.object({
+  audio_languages: z.array(z.string().min(1)).optional(),
+  subtitle_languages: z.array(z.string().min(1)).optional(),
+  subtitle_sidecar: z.boolean().optional(),
+  subtitle_format: z.enum(['vtt', 'srt']).optional(),
+});
+
+export const LiveRecordingProgressSchema = z.object({
+  elapsed_secs: z.number().nonnegative(),
+  recorded_secs: z.number().nonnegative(),
+  segments: z.number().int().nonnegative(),
+});
+
+export const SegmentGapSchema = z.object({
+  index: z.number().int().nonnegative(),
+  start_secs: z.number().nonnegative(),
+  end_secs: z.number().nonnegative(),
+  error: z.string(),
+});
+
+export const SidecarFileSchema = z.object({
+  kind: z.enum(['subtitle', 'thumbnail', 'description', 'info_json']),
+  path: z.string().min(1),
+  language: z.string().nullable().optional(),
 });
 
 export const VideoTaskBaseSchema = z.object({
   id: z.string().min(1, '任务ID不能为空'),
   url: z.string().url('请输入有效的URL'),
+  mirrors: z.array(z.string().url()).optional(),
+  rate_limit: z.number().int().positive().nullable().optional(),
+  variant_policy: HlsVariantPolicySchema.nullable().optional(),
+  ytdlp_format: YoutubeDownloadFormatSchema.nullable().optional(),
+  audio_extraction: AudioExtractionSchema.nullable().optional(),
+  hls_variant: HlsVariantSchema.nullable().optional(),
+  recording: LiveRecordingProgressSchema.nullable().optional(),
+  segment_gaps: z.array(SegmentGapSchema).optional(),
+  sidecar_files: z.array(SidecarFileSchema).optional(),
   title: z.string().min(1, '标题不能为空'),
   output_path: z.string().min(1, '输出路径不能为空'),
   resolved_path: z.string().optional(),
@@ -87,6 +175,16 @@ export const applyVideoTaskValidations = <T extends z.ZodTypeAny>(schema: T) =>
 
 export const VideoTaskSchema = applyVideoTaskValidations(VideoTaskBaseSchema);
 
+export const MirrorStatsSchema = z.object({
+  url: z.string(),
+  downloaded_bytes: z.number().nonnegative(),
+  speed_bps: z.number().nonnegative(),
+  chunks_completed: z.number().nonnegative(),
+  active_chunks: z.number().nonnegative(),
+  disabled: z.boolean(),
+  last_error: z.string().nullable().optional(),
+});
+
 export const ProgressUpdateSchema = z.object({
   task_id: z.string().min(1, '任务ID不能为空'),
   downloaded_size: z.number().nonnegative(),
@@ -95,6 +193,8 @@ export const ProgressUpdateSchema = z.object({
   display_speed_bps: z.number().nonnegative().optional().default(0),
   eta: z.number().nullable().optional(),
   progress: z.number().min(0).max(1.01).optional(),
+  sources: z.array(MirrorStatsSchema).optional(),
+  recording: LiveRecordingProgressSchema.optional(),
 });
 
 export type VideoInfo = z.infer<typeof VideoInfoSchema>;
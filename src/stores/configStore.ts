@@ -65,12 +65,18 @@ export const defaultConfig: AppConfig = {
   youtube: {
     default_quality: '720p',
     default_format: 'mp4',
+    default_video_codec: null,
+    default_audio_quality: null,
     extract_audio: false,
     audio_format: 'mp3',
+    audio_bitrate_kbps: null,
     download_subtitles: false,
     subtitle_languages: ['zh-CN', 'en'],
-    download_thumbnail: true,
-    download_description: true,
+    auto_subtitles: false,
+    subtitle_format: null,
+    download_thumbnail: false,
+    download_description: false,
+    write_info_json: false,
     playlist_reverse: false,
     playlist_max_items: null,
   },
@@ -90,6 +96,7 @@ export const defaultConfig: AppConfig = {
     enable_statistics: true,
     statistics_retention_days: 30,
   },
+  platforms: [],
 };
 
 const mergeConfigWithDefaults = (config?: Partial<AppConfig>): AppConfig => {
@@ -129,6 +136,7 @@ const mergeConfigWithDefaults = (config?: Partial<AppConfig>): AppConfig => {
         ...(incomingAdvanced.custom_user_agents ?? {}),
       },
     },
+    platforms: config?.platforms ?? defaultConfig.platforms,
   };
 };
 
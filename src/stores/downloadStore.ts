@@ -5,6 +5,7 @@ import { handleError } from '../utils/errorHandler';
 import { validateState, syncStates, shouldValidate } from '../utils/stateValidator';
 import { normalizeTaskData, createValidationStats } from '../utils/dataValidator';
 import type { VideoTask, TaskStatus, DownloadConfig, DownloadStats } from '../schemas';
+import type { AudioExtraction, HlsVariantPolicy, PlaylistExpansion } from '../types';
 import {
   createDefaultDownloadStats,
   ensureDownloadStats,
@@ -21,13 +22,23 @@ import {
   cancelDownloadCommand,
   startAllDownloadsCommand,
   pauseAllDownloadsCommand,
+  clearRateLimitOverrideCommand,
 } from '../features/downloads/api/downloadCommands';
 import {
   removeTasksCommand,
   clearCompletedTasksCommand,
   updateTaskOutputPathsCommand,
+  setTaskVariantPolicyCommand,
+  setTaskAudioExtractionCommand,
 } from '../features/downloads/api/taskMutations';
-import { addDownloadTasksCommand } from '../features/downloads/api/taskCreation';
+import {
+  addDownloadTasksCommand,
+  addPlaylistTasksCommand,
+} from '../features/downloads/api/taskCreation';
+import {
+  parseRateLimitChangedPayload,
+  type RateLimitChangedPayload,
+} from '../features/downloads/model/contracts';
 import { importRawFileCommand } from '../features/downloads/api/importCommands';
 import {
   DEFAULT_DOWNLOAD_VIEW_STATE,
@@ -66,6 +77,7 @@ import {
 import {
   getDownloadStatsCommand,
   getDownloadTasksCommand,
+  getRateLimitStatusCommand,
 } from '../features/downloads/api/runtimeQueries';
 import { runDataIntegrityCheckFor } from '../features/downloads/state/validationHelpers';
 import {
@@ -114,6 +126,9 @@ interface DownloadState {
 
   stats: DownloadStats;
 
+  // 当前生效的全局限速（由 rate_limit.changed 事件更新）
+  rateLimitStatus: RateLimitChangedPayload | null;
+
   // UI \u72b6\u6001
 
   isImporting: boolean;
@@ -147,6 +162,17 @@ interface DownloadState {
     tasks: VideoTask[] | Omit<VideoTask, 'id' | 'status' | 'created_at' | 'updated_at'>[]
   ) => Promise<VideoTask[]>;
 
+  // 单个任务的 HLS 档位策略，null 时恢复使用配置中的默认策略
+  setTaskVariantPolicy: (taskId: string, policy: HlsVariantPolicy | null) => Promise<void>;
+  setTaskAudioExtraction: (taskId: string, extraction: AudioExtraction | null) => Promise<void>;
+
+  // 为展开后的播放列表中选中的条目（为空时全部）各建一个任务
+  addPlaylistTasks: (
+    playlist: PlaylistExpansion,
+    selected: number[] | null,
+    outputPath: string
+  ) => Promise<VideoTask[]>;
+
   addTask: (
     task: VideoTask | Omit<VideoTask, 'id' | 'status' | 'created_at' | 'updated_at'>
   ) => Promise<VideoTask | undefined>;
@@ -202,6 +228,10 @@ interface DownloadState {
 
   refreshStats: () => Promise<void>;
 
+  refreshRateLimitStatus: () => Promise<void>;
+
+  resumeRateLimitSchedule: () => Promise<void>;
+
   syncRuntimeState: (reason?: string) => Promise<void>;
 
   // Actions - \u72b6\u6001\u9a8c\u8bc1\u548c\u540c\u6b65
@@ -257,6 +287,8 @@ export const useDownloadStore = create<DownloadState>()(
 
     stats: createDefaultDownloadStats(),
 
+    rateLimitStatus: null,
+
     // \u6570\u636e\u9a8c\u8bc1\u72b6\u6001\u521d\u59cb\u5316
 
     validationStats: createValidationStats(),
@@ -290,6 +322,28 @@ export const useDownloadStore = create<DownloadState>()(
       set({ stats: ensureDownloadStats(stats) });
     },
 
+    refreshRateLimitStatus: async () => {
+      try {
+        const parsed = parseRateLimitChangedPayload(await getRateLimitStatusCommand());
+        if (parsed.success) {
+          set({ rateLimitStatus: parsed.data });
+        }
+      } catch (error) {
+        reportFrontendDiagnosticIfEnabled('warn', 'download_store:rate_limit_status:failed', error);
+      }
+    },
+
+    resumeRateLimitSchedule: async () => {
+      try {
+        const parsed = parseRateLimitChangedPayload(await clearRateLimitOverrideCommand());
+        if (parsed.success) {
+          set({ rateLimitStatus: parsed.data });
+        }
+      } catch (error) {
+        handleError('恢复时段限速', error);
+      }
+    },
+
     syncRuntimeState: async reason => {
       try {
         if (reason) {
@@ -309,6 +363,47 @@ export const useDownloadStore = create<DownloadState>()(
 
     // 任务管理 - 增强版本带Zod验证
 
+    setTaskVariantPolicy: async (taskId, policy) => {
+      try {
+        const updated = normalizeBackendTask(await setTaskVariantPolicyCommand(taskId, policy));
+        set(state => ({
+          tasks: state.tasks.map(task => (task.id === updated.id ? updated : task)),
+        }));
+      } catch (error) {
+        handleError('设置清晰度档位', error);
+      }
+    },
+
+    setTaskAudioExtraction: async (taskId, extraction) => {
+      try {
+        const updated = normalizeBackendTask(
+          await setTaskAudioExtractionCommand(taskId, extraction)
+        );
+        set(state => ({
+          tasks: state.tasks.map(task => (task.id === updated.id ? updated : task)),
+        }));
+      } catch (error) {
+        handleError('设置仅音频模式', error);
+      }
+    },
+
+    addPlaylistTasks: async (playlist, selected, outputPath) => {
+      try {
+        const created = await addPlaylistTasksCommand(playlist, selected, outputPath);
+        const addedTasks = created.map(normalizeBackendTask);
+        set(state => {
+          const knownIds = new Set(state.tasks.map(task => task.id));
+          return {
+            tasks: [...state.tasks, ...addedTasks.filter(task => !knownIds.has(task.id))],
+          };
+        });
+        return addedTasks;
+      } catch (error) {
+        handleError('添加播放列表任务', error);
+        throw error;
+      }
+    },
+
     addTasks: async newTasks => {
       const validationStartTime = performance.now();
 
@@ -660,6 +755,7 @@ export const useDownloadStore = create<DownloadState>()(
           applyFailurePatch: patch => set(patch),
         });
         set({ recoveredSessionTaskIds: result.validatedTasks.map(task => task.id) });
+        await get().refreshRateLimitStatus();
       } catch (error) {
         handleError('初始化下载管理器', error);
       }
@@ -9,9 +9,30 @@ export type TaskStatus =
   | 'cancelled';
 
 // 下载器类型
-export type DownloaderType = 'http' | 'm3u8' | 'ytdlp';
-
-export type SourcePlatform = 'youtube' | 'tiktok' | 'instagram' | 'facebook' | 'generic';
+export type DownloaderType = 'http' | 'm3u8' | 'dash' | 'ytdlp';
+
+// 内置平台之外还可能是配置里自定义平台的 id（如 'bilibili'）
+export type SourcePlatform =
+  | 'youtube'
+  | 'tiktok'
+  | 'instagram'
+  | 'facebook'
+  | 'generic'
+  | (string & {});
+
+// 平台规则强制使用的下载路径
+export type PlatformRoute = 'ytdlp' | 'http' | 'm3u8';
+
+// 平台规则：按域名后缀识别，自定义规则优先于内置规则
+export interface PlatformRule {
+  id: string;
+  display_name: string;
+  hosts: string[];
+  route: PlatformRoute;
+  ytdlp_args: string[];
+  default_quality?: string | null;
+  max_concurrent?: number | null;
+}
 
 export interface ExternalVideoInfo {
   source_platform: SourcePlatform;
@@ -23,12 +44,121 @@ export interface ExternalVideoInfo {
   format_id?: string;
   format_note?: string;
   requires_auth?: boolean;
+  playlist_id?: string | null; // 从播放列表/频道展开时所属的列表
+  playlist_title?: string | null;
+  playlist_index?: number | null; // 在列表中的位置（从 1 开始）
+}
+
+// yt-dlp 画质：low/medium/high 分别对应 480p/720p/1080p 上限
+export type YtDlpVideoQuality = 'low' | 'medium' | 'high' | 'best' | 'worst' | { height: number };
+export type YtDlpVideoCodec = 'avc1' | 'vp9' | 'av01' | 'any';
+export type YtDlpAudioQuality = 'low' | 'medium' | 'high' | 'best' | 'worst';
+export type YtDlpAudioCodec = 'aac' | 'mp3' | 'opus' | 'flac' | 'any';
+
+// yt-dlp 格式选择，任务上为空时使用 YouTube 配置中的默认值
+export type YoutubeDownloadFormat =
+  | {
+      kind: 'complete_video';
+      video_quality: YtDlpVideoQuality;
+      video_codec: YtDlpVideoCodec;
+      audio_quality: YtDlpAudioQuality;
+      audio_codec: YtDlpAudioCodec;
+    }
+  | { kind: 'video_only'; quality: YtDlpVideoQuality; codec: YtDlpVideoCodec }
+  | { kind: 'audio_only'; quality: YtDlpAudioQuality; codec: YtDlpAudioCodec }
+  | { kind: 'best_available' }
+  | { kind: 'specific_format'; format_id: string };
+
+// yt-dlp --flat-playlist 列出的一项
+export interface PlaylistEntry {
+  index: number;
+  url: string;
+  id?: string | null;
+  title?: string | null;
+  duration_seconds?: number | null;
+  thumbnail?: string | null;
+}
+
+// 展开后的播放列表/频道/课程，entries 已按下载顺序排列
+export interface PlaylistExpansion {
+  source_platform: SourcePlatform;
+  webpage_url: string;
+  playlist_id?: string | null;
+  title?: string | null;
+  extractor?: string | null;
+  entries: PlaylistEntry[];
+}
+
+// HLS master playlist 档位选择策略（bandwidth 单位 bits/s）
+export type HlsVariantPolicy =
+  | { kind: 'highest' }
+  | { kind: 'lowest' }
+  | { kind: 'closest_height'; height: number }
+  | { kind: 'max_bandwidth'; bandwidth: number };
+
+export interface HlsVariant {
+  uri: string;
+  bandwidth: number;
+  average_bandwidth?: number | null;
+  width?: number | null;
+  height?: number | null;
+  codecs?: string | null;
+  frame_rate?: number | null;
+  audio_group?: string | null;
+  subtitles_group?: string | null;
+}
+
+// HLS 独立音轨/字幕（EXT-X-MEDIA）挑选偏好；语言按前缀匹配，如 "en" 匹配 "en-US"
+export interface HlsRenditionPreferences {
+  audio_languages?: string[]; // 为空时取默认音轨
+  subtitle_languages?: string[]; // 为空时不下载字幕
+  subtitle_sidecar?: boolean; // 字幕写成视频旁的独立文件
+  subtitle_format?: 'vtt' | 'srt';
+}
+
+// 直播录制进度：没有总大小，以已录制时长代替百分比
+export interface LiveRecordingProgress {
+  elapsed_secs: number; // 录制已进行的时间
+  recorded_secs: number; // 已录制的媒体时长（EXTINF 累计）
+  segments: number;
+}
+
+// 重试后仍失败、被跳过的 HLS 片段
+export interface SegmentGap {
+  index: number;
+  start_secs: number; // 缺失的媒体时间段（秒）
+  end_secs: number;
+  error: string;
+}
+
+// yt-dlp 写在视频旁的附属文件
+export interface SidecarFile {
+  kind: 'subtitle' | 'thumbnail' | 'description' | 'info_json';
+  path: string;
+  language?: string | null; // 字幕语言，如 "en"、"zh-Hans"
+}
+
+// 仅音频模式：只保留音轨并转成指定格式
+export type AudioFormat = 'mp3' | 'm4a' | 'opus' | 'flac' | 'wav';
+
+export interface AudioExtraction {
+  format: AudioFormat;
+  bitrate_kbps?: number | null; // 目标码率，为空时使用编码器默认；无损格式忽略
 }
 
 // 视频任务接口
 export interface VideoTask {
   id: string;
   url: string;
+  mirrors?: string[]; // 备用镜像地址，按优先级排序
+  rate_limit?: number | null; // 单任务限速（字节/秒），为空时使用每任务默认上限
+  variant_policy?: HlsVariantPolicy | null; // HLS 档位策略，为空时使用配置中的默认策略
+  ytdlp_format?: YoutubeDownloadFormat | null; // yt-dlp 格式选择，为空时使用 YouTube 配置
+  audio_extraction?: AudioExtraction | null; // 仅音频模式，为空时保留视频（yt-dlp 任务沿用 YouTube 配置）
+  hls_variant?: HlsVariant | null; // 最近一次从 master playlist 选中的档位
+  recording?: LiveRecordingProgress | null; // 直播录制进度，仅直播 HLS 任务有值
+  segment_gaps?: SegmentGap[]; // 最近一次下载按缺失容忍策略跳过的片段
+  sidecar_files?: SidecarFile[]; // 字幕、缩略图、简介等附属文件
   title: string;
   output_path: string;
   resolved_path?: string;
@@ -72,6 +202,19 @@ export interface ProgressUpdate {
   display_speed_bps?: number;
   eta?: number;
   progress?: number;
+  sources?: MirrorStats[];
+  recording?: LiveRecordingProgress;
+}
+
+// 单个镜像的下载统计
+export interface MirrorStats {
+  url: string;
+  downloaded_bytes: number;
+  speed_bps: number;
+  chunks_completed: number;
+  active_chunks: number;
+  disabled: boolean;
+  last_error?: string | null;
 }
 
 // 导入的数据接口 - 与Go版本Video结构保持一致
@@ -90,6 +233,58 @@ export interface ImportedData {
   course_name?: string; // 兼容旧版本
 }
 
+// 按主机匹配的请求头覆盖规则
+export interface HostHeaderRule {
+  host: string;
+  user_agent?: string | null;
+  referer?: string | null;
+  origin?: string | null;
+  headers?: Record<string, string>;
+}
+
+// 按时段的全局限速规则，时间为本地 HH:MM，结束早于开始时跨越午夜
+export interface BandwidthRule {
+  start: string;
+  end: string;
+  bytes_per_second?: number | null;
+  label?: string | null;
+}
+
+// 按主机（含子域名）共享的限速上限
+export interface HostRateLimit {
+  host: string;
+  bytes_per_second: number;
+}
+
+export type AuthScheme = 'basic' | 'digest' | 'bearer' | 'video_password';
+
+// 凭据库条目（后端不返回密码/令牌）
+export interface CredentialSummary {
+  id: string;
+  pattern: string;
+  scheme: AuthScheme;
+  username?: string | null;
+  label?: string | null;
+  updated_at: string;
+}
+
+// 保存凭据；编辑时 secret 留空表示沿用原密码
+export interface CredentialInput {
+  id?: string | null;
+  pattern: string;
+  scheme: AuthScheme;
+  username?: string | null;
+  secret: string;
+  label?: string | null;
+}
+
+// cookie jar 中每个域名的概况（不含 cookie 值）
+export interface CookieDomainSummary {
+  domain: string;
+  cookies: number;
+  expires_at?: string | null;
+}
+
 // 下载配置接口
 export interface DownloadConfig {
   concurrent_downloads: number;
@@ -97,7 +292,17 @@ export interface DownloadConfig {
   timeout_seconds: number;
   user_agent: string;
   proxy?: string;
+  no_proxy?: string[];
   headers: Record<string, string>;
+  host_headers?: HostHeaderRule[];
+  bandwidth_schedule?: BandwidthRule[];
+  per_task_rate_limit?: number | null;
+  host_rate_limits?: HostRateLimit[];
+  hls_variant_policy?: HlsVariantPolicy;
+  live_max_duration_secs?: number | null; // 直播录制时长上限（秒）
+  live_max_bytes?: number | null; // 直播录制大小上限（字节）
+  hls_renditions?: HlsRenditionPreferences;
+  hls_max_missing_segments?: number; // 片段重试耗尽后最多跳过的个数（0 表示直接失败）
   output_directory: string;
   auto_verify_integrity: boolean;
   integrity_algorithm?: string | null;
@@ -111,6 +316,7 @@ export interface AppConfig {
   system: SystemConfig;
   youtube: YoutubeConfig;
   advanced: AdvancedConfig;
+  platforms?: PlatformRule[];
 }
 
 export interface UIConfig {
@@ -139,13 +345,19 @@ export interface SystemConfig {
 
 export interface YoutubeConfig {
   default_quality?: string | null;
-  default_format?: string | null;
+  default_format?: string | null; // 同时决定 yt-dlp 合并音视频的容器
+  default_video_codec?: string | null; // 'any' | 'avc1' | 'vp9' | 'av01'
+  default_audio_quality?: string | null; // 'best' | 'high' | 'medium' | 'low' | 'worst'
   extract_audio: boolean;
-  audio_format?: string | null;
+  audio_format?: string | null; // 'mp3' | 'm4a' | 'opus' | 'flac' | 'wav'
+  audio_bitrate_kbps?: number | null; // 为空时使用编码器默认
   download_subtitles: boolean;
   subtitle_languages: string[];
-  download_thumbnail: boolean;
+  auto_subtitles?: boolean; // 没有上传字幕时使用自动字幕
+  subtitle_format?: 'srt' | 'vtt' | 'ass' | 'lrc' | null; // 为空时保留原始格式
+  download_thumbnail: boolean; // 缩略图转为 jpg
   download_description: boolean;
+  write_info_json?: boolean;
   playlist_reverse: boolean;
   playlist_max_items?: number | null;
 }
@@ -156,7 +368,7 @@ export interface AdvancedConfig {
   max_log_files: number;
   cleanup_on_exit: boolean;
   enable_proxy: boolean;
-  proxy_type: 'http' | 'https' | 'socks4' | 'socks5';
+  proxy_type: 'http' | 'https' | 'socks4' | 'socks5' | 'socks5h';
   proxy_host?: string;
   proxy_port?: number;
   proxy_username?: string;
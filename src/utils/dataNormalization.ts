@@ -27,7 +27,7 @@ const normalizeDownloaderType = (value: unknown): VideoTask['downloader_type'] =
   }
   const normalized = value.toLowerCase();
   if (normalized === 'youtube' || normalized === 'ytdlp') return 'ytdlp';
-  if (normalized === 'http' || normalized === 'm3u8') return normalized;
+  if (normalized === 'http' || normalized === 'm3u8' || normalized === 'dash') return normalized;
   return undefined;
 };
 